@@ -0,0 +1,18 @@
+use midpoint_editor::helpers::utilities::get_backend_address;
+use midpoint_editor::stand_in::start_stand_in_server;
+
+// Runs the stand-in backend on its own, for integration tests or a second editor instance
+#[tokio::main]
+async fn main() {
+    let address = get_backend_address();
+
+    start_stand_in_server(&address)
+        .await
+        .expect("Couldn't start stand-in backend");
+
+    tokio::signal::ctrl_c()
+        .await
+        .expect("Couldn't listen for shutdown signal");
+
+    println!("Stand-in backend stopped");
+}
@@ -0,0 +1,666 @@
+use std::collections::HashMap;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use directories::ProjectDirs;
+
+use midpoint_engine::helpers::saved_data::{
+    ComponentData, ComponentKind, GenericProperties, LandscapeProperties, SavedState,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use subtle::ConstantTimeEq;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::timeout;
+use uuid::Uuid;
+
+use crate::editor_state::{StateHelper, UIMessage};
+use crate::helpers::asset_store::{store_asset, AssetKind};
+use crate::helpers::collaboration::{apply_to_saved_state, SceneOperation, TransformProperty};
+use crate::helpers::imports::{register_asset, store_and_register};
+use crate::helpers::projects::get_projects;
+
+type ControlError = Box<dyn std::error::Error + Send + Sync>;
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:4200";
+
+// requests are small JSON documents, assets are imported by path rather than uploaded
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+// a client gets this long to send its whole request
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+// served at GET /schema, so tools can check requests against the running editor
+const SCHEMA: &str = include_str!("../docs/control-api.schema.json");
+
+/// Where the control server is listening and the token it expects,
+/// written to `control.json` in the user's local config folder so tools can find it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlInfo {
+    pub address: String,
+    pub token: String,
+}
+
+struct ControlResponse {
+    status: &'static str,
+    body: Value,
+}
+
+impl ControlResponse {
+    fn ok(body: Value) -> Self {
+        Self {
+            status: "200 OK",
+            body,
+        }
+    }
+
+    fn error(status: &'static str, message: &str) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenProjectRequest {
+    project_id: String,
+}
+
+#[derive(Deserialize)]
+struct ImportAssetRequest {
+    // an absolute path on this machine
+    path: String,
+    // concept, texture or model
+    kind: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddComponentRequest {
+    asset_id: String,
+    name: Option<String>,
+    position: Option<[f32; 3]>,
+    rotation: Option<[f32; 3]>,
+    scale: Option<[f32; 3]>,
+}
+
+/// The address to listen on, from `MIDPOINT_CONTROL_ADDRESS`, refusing anything but loopback
+pub fn control_address() -> Result<SocketAddr, String> {
+    let address =
+        std::env::var("MIDPOINT_CONTROL_ADDRESS").unwrap_or_else(|_| DEFAULT_ADDRESS.to_string());
+    let address: SocketAddr = address
+        .parse()
+        .map_err(|_| format!("Invalid control address {}", address))?;
+
+    if !address.ip().is_loopback() {
+        return Err(format!(
+            "Control address {} isn't a loopback address",
+            address
+        ));
+    }
+
+    Ok(address)
+}
+
+/// Kept out of the synced CommonOS folder, so the token never leaves this machine or user
+pub fn control_info_path() -> Option<PathBuf> {
+    ProjectDirs::from("com", "CommonOS", "Midpoint")
+        .map(|dirs| dirs.config_local_dir().join("control.json"))
+}
+
+fn save_control_info(info: &ControlInfo) -> Result<(), String> {
+    let path = control_info_path().ok_or("Couldn't find a config directory".to_string())?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Couldn't create {}: {}", parent.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(info)
+        .map_err(|e| format!("Couldn't serialize control info: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Couldn't write control info: {}", e))?;
+
+    // only this user may read the token
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Couldn't restrict control info: {}", e))?;
+    }
+
+    Ok(())
+}
+
+fn token_matches(authorization: &str, token: &str) -> bool {
+    match authorization.strip_prefix("Bearer ") {
+        Some(provided) => provided.as_bytes().ct_eq(token.as_bytes()).into(),
+        None => false,
+    }
+}
+
+fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn level_components(saved_state: &SavedState) -> Vec<ComponentData> {
+    saved_state
+        .levels
+        .as_ref()
+        .and_then(|levels| levels.get(0))
+        .and_then(|level| level.components.clone())
+        .unwrap_or_default()
+}
+
+fn parse_body<'a, T: Deserialize<'a>>(body: &'a [u8]) -> Result<T, ControlResponse> {
+    serde_json::from_slice(body)
+        .map_err(|e| ControlResponse::error("400 Bad Request", &format!("Invalid request: {}", e)))
+}
+
+fn list_projects(state_helper: &Arc<Mutex<StateHelper>>) -> ControlResponse {
+    let projects = match get_projects() {
+        Ok(projects) => projects,
+        Err(e) => return ControlResponse::error("500 Internal Server Error", &e.to_string()),
+    };
+    let open_project = state_helper.lock().unwrap().selected_project_id();
+
+    ControlResponse::ok(json!({
+        "projects": projects
+            .iter()
+            .map(|project| json!({
+                "projectId": project.name,
+                "modified": project.modified.to_rfc3339(),
+            }))
+            .collect::<Vec<Value>>(),
+        "openProject": open_project,
+    }))
+}
+
+fn open_project(state_helper: &Arc<Mutex<StateHelper>>, body: &[u8]) -> ControlResponse {
+    let request: OpenProjectRequest = match parse_body(body) {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+
+    let known = get_projects()
+        .map(|projects| projects.iter().any(|p| p.name == request.project_id))
+        .unwrap_or(false);
+    if !known {
+        return ControlResponse::error("404 Not Found", "No project with that id");
+    }
+
+    // opening touches the GPU and signals, so the UI thread does it
+    state_helper.lock().unwrap().send_ui_message(
+        "control",
+        UIMessage::OpenProject(request.project_id.clone()),
+    );
+
+    ControlResponse {
+        status: "202 Accepted",
+        body: json!({ "projectId": request.project_id }),
+    }
+}
+
+fn import_asset(state_helper: &Arc<Mutex<StateHelper>>, body: &[u8]) -> ControlResponse {
+    let request: ImportAssetRequest = match parse_body(body) {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+
+    let kind = match request.kind.as_str() {
+        "concept" => AssetKind::Concept,
+        "texture" => AssetKind::Texture,
+        "model" => AssetKind::Model,
+        _ => {
+            return ControlResponse::error(
+                "400 Bad Request",
+                "kind must be concept, texture or model",
+            )
+        }
+    };
+
+    let source = PathBuf::from(&request.path);
+    let file_name = match source.file_name().and_then(|n| n.to_str()) {
+        Some(file_name) => file_name.to_string(),
+        None => return ControlResponse::error("400 Bad Request", "path has no file name"),
+    };
+    let bytes = match fs::read(&source) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return ControlResponse::error(
+                "400 Bad Request",
+                &format!("Couldn't read {}: {}", request.path, e),
+            )
+        }
+    };
+
+    let project_id = match state_helper.lock().unwrap().selected_project_id() {
+        Some(project_id) => project_id,
+        None => return ControlResponse::error("409 Conflict", "No project is open"),
+    };
+
+    let registered = store_and_register(
+        state_helper,
+        || store_asset(&project_id, &kind, &file_name, &bytes),
+        |state_helper, stored| {
+            // the project may have been closed while the file was written
+            if state_helper.selected_project_id().as_deref() != Some(project_id.as_str()) {
+                return Err("The project was closed during the import".to_string());
+            }
+            Ok(register_asset(state_helper, &kind, stored))
+        },
+    );
+
+    match registered {
+        Ok((file, created)) => ControlResponse {
+            status: if created { "201 Created" } else { "200 OK" },
+            body: json!({ "assetId": file.id, "file": file }),
+        },
+        Err(e) => ControlResponse::error("400 Bad Request", &e),
+    }
+}
+
+fn add_component(state_helper: &Arc<Mutex<StateHelper>>, body: &[u8]) -> ControlResponse {
+    let request: AddComponentRequest = match parse_body(body) {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+
+    let mut state_helper = state_helper.lock().unwrap();
+    if state_helper.selected_project_id().is_none() {
+        return ControlResponse::error("409 Conflict", "No project is open");
+    }
+
+    let saved_state = Arc::clone(
+        state_helper
+            .saved_state
+            .as_ref()
+            .expect("Couldn't get saved state"),
+    );
+    let mut saved_state = saved_state.lock().unwrap();
+
+    let is_model = saved_state.models.iter().any(|m| m.id == request.asset_id);
+    let is_landscape = saved_state
+        .landscapes
+        .as_ref()
+        .map(|landscapes| landscapes.iter().any(|l| l.id == request.asset_id))
+        .unwrap_or(false);
+
+    let (kind, default_name, landscape_properties) = if is_model {
+        (ComponentKind::Model, "New Model Component", None)
+    } else if is_landscape {
+        (
+            ComponentKind::Landscape,
+            "New Landscape Component",
+            Some(LandscapeProperties {
+                primary_texture_id: None,
+                rockmap_texture_id: None,
+                soil_texture_id: None,
+            }),
+        )
+    } else {
+        return ControlResponse::error("404 Not Found", "No model or landscape with that id");
+    };
+
+    if saved_state.levels.as_ref().map_or(true, |l| l.is_empty()) {
+        return ControlResponse::error("409 Conflict", "The project has no level");
+    }
+
+    let component = ComponentData {
+        id: Uuid::new_v4().to_string(),
+        kind: Some(kind),
+        asset_id: request.asset_id,
+        generic_properties: GenericProperties {
+            name: request.name.unwrap_or_else(|| default_name.to_string()),
+            position: request.position.unwrap_or([0.0, 0.0, 0.0]),
+            rotation: request.rotation.unwrap_or([0.0, 0.0, 0.0]),
+            scale: request.scale.unwrap_or([1.0, 1.0, 1.0]),
+        },
+        landscape_properties,
+        model_properties: None,
+    };
+
+    let operation = SceneOperation::Add {
+        component: component.clone(),
+    };
+    apply_to_saved_state(&mut saved_state, &operation);
+    let components = level_components(&saved_state);
+    drop(saved_state);
+
+    state_helper.save_current_saved_state();
+    state_helper.broadcast_scene_operation(operation.clone());
+
+    // adding only places the component, so rotation and scale follow as their own edits
+    state_helper.send_ui_message("scene", UIMessage::SceneOperation(operation));
+    for (property, value) in [
+        (
+            TransformProperty::Rotation,
+            component.generic_properties.rotation,
+        ),
+        (TransformProperty::Scale, component.generic_properties.scale),
+    ] {
+        state_helper.send_ui_message(
+            "scene",
+            UIMessage::SceneOperation(SceneOperation::Transform {
+                component_id: component.id.clone(),
+                property,
+                value,
+            }),
+        );
+    }
+    state_helper.send_ui_message("component_browser", UIMessage::UpdateComponents(components));
+
+    ControlResponse {
+        status: "201 Created",
+        body: json!({ "componentId": component.id, "component": component }),
+    }
+}
+
+fn get_scene(state_helper: &Arc<Mutex<StateHelper>>) -> ControlResponse {
+    let state_helper = state_helper.lock().unwrap();
+    let project_id = match state_helper.selected_project_id() {
+        Some(project_id) => project_id,
+        None => return ControlResponse::error("409 Conflict", "No project is open"),
+    };
+    let saved_state = state_helper
+        .saved_state
+        .as_ref()
+        .expect("Couldn't get saved state")
+        .lock()
+        .unwrap();
+
+    ControlResponse::ok(json!({
+        "projectId": project_id,
+        "components": level_components(&saved_state),
+        "assets": {
+            "concepts": saved_state.concepts,
+            "textures": saved_state.textures.clone().unwrap_or_default(),
+            "models": saved_state.models,
+            "landscapes": saved_state
+                .landscapes
+                .as_ref()
+                .map(|landscapes| landscapes.iter().map(|l| l.id.clone()).collect::<Vec<String>>())
+                .unwrap_or_default(),
+        },
+    }))
+}
+
+fn save_project(state_helper: &Arc<Mutex<StateHelper>>) -> ControlResponse {
+    let mut state_helper = state_helper.lock().unwrap();
+    let project_id = match state_helper.selected_project_id() {
+        Some(project_id) => project_id,
+        None => return ControlResponse::error("409 Conflict", "No project is open"),
+    };
+
+    state_helper.save_current_saved_state();
+
+    ControlResponse::ok(json!({ "projectId": project_id, "saved": true }))
+}
+
+fn route(
+    state_helper: &Arc<Mutex<StateHelper>>,
+    method: &str,
+    path: &str,
+    body: &[u8],
+) -> ControlResponse {
+    match (method, path) {
+        ("GET", "/schema") => match serde_json::from_str(SCHEMA) {
+            Ok(schema) => ControlResponse::ok(schema),
+            Err(e) => ControlResponse::error("500 Internal Server Error", &e.to_string()),
+        },
+        ("GET", "/projects") => list_projects(state_helper),
+        ("POST", "/projects/open") => open_project(state_helper, body),
+        ("POST", "/assets/import") => import_asset(state_helper, body),
+        ("POST", "/components") => add_component(state_helper, body),
+        ("GET", "/scene") => get_scene(state_helper),
+        ("POST", "/save") => save_project(state_helper),
+        _ => ControlResponse::error("404 Not Found", "Not found"),
+    }
+}
+
+async fn write_response(
+    stream: &mut TcpStream,
+    response: ControlResponse,
+) -> Result<(), ControlError> {
+    let body = response.body.to_string();
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// A request read off the socket, or the error to answer it with
+struct ControlRequest {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<Option<ControlRequest>, ControlResponse> {
+    let io_error = |e: std::io::Error| ControlResponse::error("400 Bad Request", &e.to_string());
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    let header_end = loop {
+        let read = stream.read(&mut chunk).await.map_err(io_error)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        // the terminator can straddle two reads
+        let search_from = buffer.len().saturating_sub(3);
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(position) = find_subsequence(&buffer[search_from..], b"\r\n\r\n") {
+            break search_from + position + 4;
+        }
+        if buffer.len() > MAX_HEAD_BYTES {
+            return Err(ControlResponse::error(
+                "431 Request Header Fields Too Large",
+                "Request head is too large",
+            ));
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.lines();
+    let request_line = lines.next().unwrap_or_default();
+    let mut request_parts = request_line.split_whitespace();
+    let method = request_parts.next().unwrap_or_default().to_string();
+    let path = request_parts.next().unwrap_or_default().to_string();
+
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let content_length = match headers.get("content-length") {
+        Some(length) => length
+            .parse::<usize>()
+            .map_err(|_| ControlResponse::error("400 Bad Request", "Invalid Content-Length"))?,
+        None => 0,
+    };
+    if content_length > MAX_BODY_BYTES {
+        return Err(ControlResponse::error(
+            "413 Payload Too Large",
+            "Request body is too large",
+        ));
+    }
+
+    while buffer.len() < header_end + content_length {
+        let read = stream.read(&mut chunk).await.map_err(io_error)?;
+        if read == 0 {
+            return Err(ControlResponse::error(
+                "400 Bad Request",
+                "Request body is shorter than its Content-Length",
+            ));
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    Ok(Some(ControlRequest {
+        method,
+        path,
+        headers,
+        body: buffer[header_end..header_end + content_length].to_vec(),
+    }))
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    state_helper: Arc<Mutex<StateHelper>>,
+    token: Arc<String>,
+) -> Result<(), ControlError> {
+    let request = match timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(Some(request))) => request,
+        Ok(Ok(None)) => return Ok(()),
+        Ok(Err(response)) => return write_response(&mut stream, response).await,
+        Err(_) => {
+            return write_response(
+                &mut stream,
+                ControlResponse::error("408 Request Timeout", "Request took too long to arrive"),
+            )
+            .await
+        }
+    };
+
+    let authorization = request
+        .headers
+        .get("authorization")
+        .cloned()
+        .unwrap_or_default();
+    if !token_matches(&authorization, &token) {
+        return write_response(
+            &mut stream,
+            ControlResponse::error("401 Unauthorized", "Unauthorized"),
+        )
+        .await;
+    }
+
+    let response = route(&state_helper, &request.method, &request.path, &request.body);
+
+    write_response(&mut stream, response).await
+}
+
+/// Serves the control API on the listener, for tools running on this machine.
+/// The token comes from `MIDPOINT_CONTROL_TOKEN`, or is generated for this run.
+pub fn start_control_server(
+    listener: TcpListener,
+    state_helper: Arc<Mutex<StateHelper>>,
+) -> std::io::Result<ControlInfo> {
+    let address = listener.local_addr()?;
+    let token = std::env::var("MIDPOINT_CONTROL_TOKEN")
+        .ok()
+        .filter(|token| !token.trim().is_empty())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let info = ControlInfo {
+        address: address.to_string(),
+        token: token.clone(),
+    };
+    if let Err(e) = save_control_info(&info) {
+        println!("{}", e);
+    }
+
+    println!("Control API listening on {}", address);
+
+    let token = Arc::new(token);
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let state_helper = Arc::clone(&state_helper);
+                    let token = Arc::clone(&token);
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_connection(stream, state_helper, token).await {
+                            println!("Control connection error: {:?}", e);
+                        }
+                    });
+                }
+                Err(e) => println!("Control accept error: {:?}", e),
+            }
+        }
+    });
+
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sends raw bytes to a fresh connection and reads the request back off the other end
+    async fn read_raw(raw: Vec<u8>) -> Result<Option<ControlRequest>, ControlResponse> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(address).await.unwrap();
+            stream.write_all(&raw).await.unwrap();
+            stream.shutdown().await.unwrap();
+        });
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let request = read_request(&mut stream).await;
+        client.await.unwrap();
+        request
+    }
+
+    #[test]
+    fn tokens_must_match_exactly() {
+        assert!(token_matches("Bearer secret", "secret"));
+        assert!(!token_matches("Bearer secre", "secret"));
+        assert!(!token_matches("Bearer secrets", "secret"));
+        assert!(!token_matches("secret", "secret"));
+        assert!(!token_matches("", "secret"));
+    }
+
+    #[tokio::test]
+    async fn requests_are_read_up_to_their_content_length() {
+        let raw =
+            b"POST /save HTTP/1.1\r\nAuthorization: Bearer t\r\nContent-Length: 2\r\n\r\n{}extra";
+        let request = read_raw(raw.to_vec()).await.ok().flatten().unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/save");
+        assert_eq!(request.headers["authorization"], "Bearer t");
+        assert_eq!(request.body, b"{}");
+    }
+
+    #[tokio::test]
+    async fn oversized_requests_are_refused() {
+        let raw = format!(
+            "POST /components HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_BYTES + 1
+        );
+        let response = read_raw(raw.into_bytes()).await.err().unwrap();
+        assert_eq!(response.status, "413 Payload Too Large");
+
+        let mut raw = b"GET /scene HTTP/1.1\r\nX-Padding: ".to_vec();
+        raw.extend(std::iter::repeat(b'a').take(MAX_HEAD_BYTES + 1));
+        let response = read_raw(raw).await.err().unwrap();
+        assert_eq!(response.status, "431 Request Header Fields Too Large");
+    }
+
+    #[tokio::test]
+    async fn bodies_shorter_than_their_length_are_refused() {
+        let raw = b"POST /save HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}";
+        let response = read_raw(raw.to_vec()).await.err().unwrap();
+        assert_eq!(response.status, "400 Bad Request");
+
+        let raw = b"POST /save HTTP/1.1\r\nContent-Length: lots\r\n\r\n";
+        let response = read_raw(raw.to_vec()).await.err().unwrap();
+        assert_eq!(response.body["error"], "Invalid Content-Length");
+    }
+}
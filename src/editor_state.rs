@@ -22,7 +22,14 @@ use undo::Edit;
 use undo::Record;
 use uuid::Uuid;
 
+use crate::helpers::auth::AuthStatus;
+use crate::helpers::collaboration::{local_operation, SceneOperation};
+use crate::helpers::outbox::OutboxEntry;
+use crate::helpers::pipeline::{PipelineJob, Placement};
+use crate::helpers::presence::{display_name, presence_message, PresencePeer};
 use crate::helpers::utilities::get_common_os_dir;
+use crate::helpers::watcher::{record_own_save, ExternalChange};
+use crate::helpers::websocket::WebSocketManager;
 
 #[derive(Debug)]
 pub struct ObjectEdit {
@@ -172,16 +179,27 @@ pub struct NamedSignals {
     pub concept_browser: Option<RwSignal<Vec<File>>>,
 }
 
+pub type FileSignals = Arc<Mutex<HashMap<String, Arc<UnboundedSender<UIMessage>>>>>;
+
+/// Sends to the view registered under `name`, if it's open
+pub fn send_ui_message(file_signals: &FileSignals, name: &str, message: UIMessage) {
+    if let Some(tx) = file_signals.lock().unwrap().get(name).cloned() {
+        let _ = tx.send(message);
+    }
+}
+
 pub struct StateHelper {
     pub renderer_state: Option<Arc<Mutex<RendererState>>>,
     pub saved_state: Option<Arc<Mutex<SavedState>>>,
     pub last_saved_state: Option<SavedState>, // for comparisons
     pub project_selected_signal: Option<RwSignal<Uuid>>,
     pub auth_token: String,
-    pub file_signals: Arc<Mutex<HashMap<String, Arc<UnboundedSender<UIMessage>>>>>,
+    pub auth_status: AuthStatus,
+    pub file_signals: FileSignals,
     pub object_selected_signal: Option<RwSignal<bool>>,
     pub selected_object_id_signal: Option<RwSignal<Uuid>>,
     pub selected_object_data_signal: Option<RwSignal<ComponentData>>,
+    pub websocket: Option<Arc<WebSocketManager>>,
 }
 
 #[derive(Clone, Debug)]
@@ -192,24 +210,48 @@ pub enum UIMessage {
     AddConcept(File),
     UpdateModels(Vec<File>),
     AddModel(File),
+    UpdateLandscapes(Vec<LandscapeData>),
+    AddLandscape(LandscapeData),
+    LandscapeFailed(String),
+    MasksRegenerated(LandscapeData),
+    ImportFinished(File),
+    ImportFailed(String),
     UpdateParts(Vec<SkeletonPart>),
     AddPart(SkeletonPart),
     UpdateSkeletons(Vec<SkeletonAssemblyConfig>),
     AddSkeleton(SkeletonAssemblyConfig),
+    UpdateOutbox(Vec<OutboxEntry>),
+    GenerationFailed(String),
+    AuthRequired(AuthStatus),
+    AuthUpdated(String),
+    SceneOperation(SceneOperation),
+    UpdateComponents(Vec<ComponentData>),
+    UpdatePresence(Vec<PresencePeer>),
+    UpdatePipelines(Vec<PipelineJob>),
+    UpdateExternalChanges(Vec<ExternalChange>),
+    OpenProject(String),
+    ReloadScene(String),
+    PlacePipelineModel {
+        job_id: String,
+        model: File,
+        placement: Placement,
+    },
 }
 
 impl StateHelper {
-    pub fn new(auth_token: String) -> Self {
+    pub fn new(auth_token: String, auth_status: AuthStatus) -> Self {
         Self {
             renderer_state: None,
             saved_state: None,
             last_saved_state: None,
             project_selected_signal: None,
             auth_token,
+            auth_status,
             file_signals: Arc::new(Mutex::new(HashMap::new())),
             object_selected_signal: None,
             selected_object_id_signal: None,
             selected_object_data_signal: None,
+            websocket: None,
         }
     }
 
@@ -219,6 +261,58 @@ impl StateHelper {
         signals.insert(name, signal);
     }
 
+    pub fn send_ui_message(&self, name: &str, message: UIMessage) {
+        send_ui_message(&self.file_signals, name, message);
+    }
+
+    pub fn selected_project_id(&self) -> Option<String> {
+        self.renderer_state
+            .as_ref()?
+            .lock()
+            .unwrap()
+            .project_selected
+            .as_ref()
+            .map(|id| id.to_string())
+    }
+
+    /// For views that are only shown with a project open
+    pub fn get_project_id(&self) -> String {
+        self.selected_project_id()
+            .expect("Couldn't get current project")
+    }
+
+    /// Sends a scene edit to everyone else in the project group
+    pub fn broadcast_scene_operation(&self, operation: SceneOperation) {
+        let message = local_operation(operation);
+
+        if let Some(websocket) = &self.websocket {
+            websocket.send_message(message);
+        }
+    }
+
+    /// Tells the project group who we are and what we have selected
+    pub fn broadcast_presence(&self) {
+        let renderer_state = match self.renderer_state.as_ref() {
+            Some(renderer_state) => renderer_state.lock().unwrap(),
+            None => return,
+        };
+
+        if renderer_state.project_selected.is_none() {
+            return;
+        }
+
+        let selected_component = renderer_state.object_selected.map(|id| id.to_string());
+
+        drop(renderer_state);
+
+        if let Some(websocket) = &self.websocket {
+            websocket.send_message(presence_message(
+                display_name(&self.auth_token),
+                selected_component,
+            ));
+        }
+    }
+
     // pub fn register_part_signal(&mut self, name: String, signal: Arc<UnboundedSender<UIMessage>>) {
     //     let mut signals = self.part_signals.lock().unwrap();
     //     signals.insert(name, signal);
@@ -254,7 +348,8 @@ impl StateHelper {
 
         println!("Saving saved state... {}", save_path.display());
 
-        fs::write(&save_path, json).expect("Couldn't write saved state");
+        fs::write(&save_path, &json).expect("Couldn't write saved state");
+        record_own_save(&save_path, json.as_bytes());
 
         drop(saved_state);
         println!("Saved!");
@@ -0,0 +1,27 @@
+use reqwest_graphql::Client;
+use serde::Deserialize;
+
+use crate::helpers::utilities::get_graphql_endpoint;
+
+#[derive(Deserialize)]
+pub struct Data {
+    pub backendVersion: String,
+}
+
+pub async fn backend_version() -> Result<Data, Box<dyn std::error::Error>> {
+    let endpoint = get_graphql_endpoint();
+    let query = r#"
+        query BackendVersion {
+            backendVersion
+        }
+   "#;
+
+    let client = Client::new(&endpoint);
+
+    let data = client
+        .query::<Data>(query)
+        .await
+        .map_err(|e| format!("backendVersion request failed: {:?}", e))?;
+
+    Ok(data)
+}
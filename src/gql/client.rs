@@ -0,0 +1,124 @@
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::helpers::utilities::get_graphql_endpoint;
+
+/// Why a GraphQL call failed, kept apart so a dropped connection can be told from a refusal
+#[derive(Debug, Clone, PartialEq)]
+pub enum GqlError {
+    // no answer from the backend: offline, refused, timed out or a gateway in front of it is down
+    Unreachable(String),
+    // the backend answered, with a failing status or GraphQL errors
+    Rejected {
+        status: u16,
+        // `extensions.code` of the first GraphQL error, like UNAUTHENTICATED
+        code: Option<String>,
+        message: String,
+    },
+}
+
+impl GqlError {
+    pub fn is_unreachable(&self) -> bool {
+        matches!(self, GqlError::Unreachable(_))
+    }
+
+    pub fn is_unauthorized(&self) -> bool {
+        match self {
+            GqlError::Rejected { status, code, .. } => {
+                *status == 401 || code.as_deref() == Some("UNAUTHENTICATED")
+            }
+            GqlError::Unreachable(_) => false,
+        }
+    }
+
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            GqlError::Rejected { code, .. } => code.as_deref(),
+            GqlError::Unreachable(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for GqlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GqlError::Unreachable(message) => write!(f, "Backend unreachable: {}", message),
+            GqlError::Rejected { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for GqlError {}
+
+/// Posts a query and returns its `data`, keeping the HTTP status and error code on failure
+pub async fn post_graphql<V: Serialize, D: DeserializeOwned>(
+    auth_token: Option<&str>,
+    query: &str,
+    vars: V,
+) -> Result<D, GqlError> {
+    post_graphql_charged(auth_token, query, vars)
+        .await
+        .map(|(data, _)| data)
+}
+
+/// Like `post_graphql`, also returning the credits the backend reports in `extensions.credits`.
+/// The cost travels with the response it belongs to, so concurrent calls can't mix them up.
+pub async fn post_graphql_charged<V: Serialize, D: DeserializeOwned>(
+    auth_token: Option<&str>,
+    query: &str,
+    vars: V,
+) -> Result<(D, Option<f64>), GqlError> {
+    let mut request = reqwest::Client::new()
+        .post(get_graphql_endpoint())
+        .json(&json!({ "query": query, "variables": vars }));
+    if let Some(auth_token) = auth_token {
+        request = request.bearer_auth(auth_token);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| GqlError::Unreachable(e.to_string()))?;
+    let status = response.status().as_u16();
+
+    // bad gateway, unavailable and gateway timeout come from whatever sits in front of the backend
+    if matches!(status, 502..=504) {
+        return Err(GqlError::Unreachable(format!("HTTP {}", status)));
+    }
+
+    let rejected = |code: Option<String>, message: String| GqlError::Rejected {
+        status,
+        code,
+        message,
+    };
+
+    let body: Value = match response.json().await {
+        Ok(body) => body,
+        Err(e) if (200..300).contains(&status) => {
+            return Err(rejected(None, format!("Couldn't read response: {}", e)))
+        }
+        Err(_) => return Err(rejected(None, format!("HTTP {}", status))),
+    };
+
+    if let Some(error) = body["errors"].as_array().and_then(|errors| errors.first()) {
+        return Err(rejected(
+            error["extensions"]["code"].as_str().map(|c| c.to_string()),
+            error["message"]
+                .as_str()
+                .unwrap_or("Unknown GraphQL error")
+                .to_string(),
+        ));
+    }
+    if !(200..300).contains(&status) {
+        return Err(rejected(None, format!("HTTP {}", status)));
+    }
+
+    let data = serde_json::from_value(body["data"].clone())
+        .map_err(|e| rejected(None, format!("Unexpected response: {}", e)))?;
+
+    // older servers don't report credits, which leaves the cost unknown
+    Ok((data, body["extensions"]["credits"].as_f64()))
+}
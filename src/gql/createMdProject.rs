@@ -2,6 +2,8 @@ use reqwest_graphql::Client;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fmt::format};
 
+use crate::helpers::utilities::get_graphql_endpoint;
+
 #[derive(Deserialize)]
 pub struct MdProject {
     pub id: String,
@@ -27,7 +29,7 @@ pub struct Vars {
 }
 
 pub async fn create_md_project(auth_token: String) -> Result<Data, Box<dyn std::error::Error>> {
-    let endpoint = "http://localhost:4000/graphql";
+    let endpoint = get_graphql_endpoint();
     let query = r#"
         mutation CreateMdProject {
             createMdProject {
@@ -42,7 +44,7 @@ pub async fn create_md_project(auth_token: String) -> Result<Data, Box<dyn std::
     let mut headers = HashMap::new();
     headers.insert("Authorization", auth_header_str);
 
-    let client = Client::new_with_headers(endpoint, headers);
+    let client = Client::new_with_headers(&endpoint, headers);
 
     let vars = Vars {};
     let data = client
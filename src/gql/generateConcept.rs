@@ -1,41 +1,68 @@
-use reqwest_graphql::Client;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt::format};
+
+use super::client::{post_graphql_charged, GqlError};
+
+use crate::helpers::generation::GenerationOptions;
 
 #[derive(Deserialize)]
 pub struct Data {
+    // a single data URL, variations are requested one at a time
     pub generateConcept: String,
+    // reported alongside the response, not part of it
+    #[serde(skip)]
+    pub credits: Option<f64>,
 }
 
 #[derive(Serialize)]
 pub struct Vars {
     prompt: String,
+    negativePrompt: Option<String>,
+    seed: Option<i32>,
+    resolution: Option<i32>,
+    aspectRatio: Option<String>,
+    stylePreset: Option<String>,
+    sourceImageUrl: Option<String>,
 }
 
 pub async fn generate_concept(
     auth_token: String,
     prompt: String,
-) -> Result<Data, Box<dyn std::error::Error>> {
-    let endpoint = "http://localhost:4000/graphql";
+    options: GenerationOptions,
+) -> Result<Data, GqlError> {
     let query = r#"
-        mutation GenerateConcept($prompt: String!) {
-            generateConcept(prompt: $prompt)
+        mutation GenerateConcept(
+            $prompt: String!
+            $negativePrompt: String
+            $seed: Int
+            $resolution: Int
+            $aspectRatio: String
+            $stylePreset: String
+            $sourceImageUrl: String
+        ) {
+            generateConcept(
+                prompt: $prompt
+                negativePrompt: $negativePrompt
+                seed: $seed
+                resolution: $resolution
+                aspectRatio: $aspectRatio
+                stylePreset: $stylePreset
+                sourceImageUrl: $sourceImageUrl
+            )
         }
    "#;
 
-    let auth_header = "Bearer ".to_owned() + &auth_token.clone();
-    let auth_header_str: &str = &auth_header[..];
-
-    let mut headers = HashMap::new();
-    headers.insert("Authorization", auth_header_str);
-
-    let client = Client::new_with_headers(endpoint, headers);
-
-    let vars = Vars { prompt };
-    let data = client
-        .query_with_vars::<Data, Vars>(query, vars)
-        .await
-        .unwrap();
+    let vars = Vars {
+        prompt,
+        negativePrompt: options.negative_prompt,
+        seed: options.seed,
+        resolution: options.resolution,
+        aspectRatio: options.aspect_ratio,
+        stylePreset: options.style_preset,
+        sourceImageUrl: options.source_image_url,
+    };
+    let (mut data, credits) =
+        post_graphql_charged::<Vars, Data>(Some(&auth_token), query, vars).await?;
+    data.credits = credits;
 
     Ok(data)
 }
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+use super::client::{post_graphql_charged, GqlError};
+
+use crate::helpers::landscapes::LandscapeParams;
+
+#[derive(Deserialize)]
+pub struct GeneratedMaps {
+    // each a base64 PNG, with or without a data URL prefix
+    pub heightmap: String,
+    pub rockmap: String,
+    pub soil: String,
+}
+
+#[derive(Deserialize)]
+pub struct Data {
+    pub generateLandscape: GeneratedMaps,
+    // reported alongside the response, not part of it
+    #[serde(skip)]
+    pub credits: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct Vars {
+    // the editor's id for the landscape, sent back as the parent of each uploaded map
+    landscapeId: String,
+    size: i32,
+    biome: String,
+    roughness: f32,
+    seed: Option<i32>,
+}
+
+pub async fn generate_landscape(
+    auth_token: String,
+    landscape_id: String,
+    params: LandscapeParams,
+) -> Result<Data, GqlError> {
+    let query = r#"
+        mutation GenerateLandscape(
+            $landscapeId: String!
+            $size: Int!
+            $biome: String!
+            $roughness: Float!
+            $seed: Int
+        ) {
+            generateLandscape(
+                landscapeId: $landscapeId
+                size: $size
+                biome: $biome
+                roughness: $roughness
+                seed: $seed
+            ) {
+                heightmap
+                rockmap
+                soil
+            }
+        }
+   "#;
+
+    println!("Making gql call...");
+
+    let vars = Vars {
+        landscapeId: landscape_id,
+        size: params.size,
+        biome: params.biome,
+        roughness: params.roughness,
+        seed: params.seed,
+    };
+    let (mut data, credits) =
+        post_graphql_charged::<Vars, Data>(Some(&auth_token), query, vars).await?;
+    data.credits = credits;
+
+    println!("Gql call complete!");
+
+    Ok(data)
+}
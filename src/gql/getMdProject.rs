@@ -2,6 +2,8 @@ use reqwest_graphql::Client;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fmt::format};
 
+use crate::helpers::utilities::get_graphql_endpoint;
+
 use crate::contexts::saved::SavedState;
 
 #[derive(Deserialize)]
@@ -28,7 +30,7 @@ pub async fn get_md_project(
     auth_token: String,
     projectId: String,
 ) -> Result<Data, Box<dyn std::error::Error>> {
-    let endpoint = "http://localhost:4000/graphql";
+    let endpoint = get_graphql_endpoint();
     let query = r#"
         query GetMdProject($projectId: String!) {
             getMdProject(projectId: $projectId) {
@@ -47,7 +49,7 @@ pub async fn get_md_project(
     let mut headers = HashMap::new();
     headers.insert("Authorization", auth_header_str);
 
-    let client = Client::new_with_headers(endpoint, headers);
+    let client = Client::new_with_headers(&endpoint, headers);
 
     let vars = Vars { projectId };
     let data = client
@@ -2,6 +2,8 @@ use reqwest_graphql::Client;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fmt::format};
 
+use crate::helpers::utilities::get_graphql_endpoint;
+
 use crate::contexts::{local::MdProject, saved::SavedState};
 
 // #[derive(Deserialize)]
@@ -22,7 +24,7 @@ pub struct Data {
 pub struct Vars {}
 
 pub async fn get_md_projects(auth_token: String) -> Result<Data, Box<dyn std::error::Error>> {
-    let endpoint = "http://localhost:4000/graphql";
+    let endpoint = get_graphql_endpoint();
     let query = r#"
         query GetMdProjects {
             getMdProjects {
@@ -40,7 +42,7 @@ pub async fn get_md_projects(auth_token: String) -> Result<Data, Box<dyn std::er
     let mut headers = HashMap::new();
     headers.insert("Authorization", auth_header_str);
 
-    let client = Client::new_with_headers(endpoint, headers);
+    let client = Client::new_with_headers(&endpoint, headers);
 
     let vars = Vars {};
     let data = client
@@ -1,8 +1,13 @@
+pub mod backendVersion;
+pub mod client;
 // pub mod createMdProject;
 // pub mod deleteMdProject;
 pub mod generateConcept;
+pub mod generateLandscape;
 pub mod generateModel;
 pub mod generateTexture;
+pub mod refreshToken;
+pub mod signIn;
 // pub mod getMdProject;
 // pub mod getMdProjects;
 // pub mod updateMdProject;
@@ -0,0 +1,40 @@
+use reqwest_graphql::Client;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::format};
+
+use crate::helpers::utilities::get_graphql_endpoint;
+
+use super::signIn::AuthPayload;
+
+#[derive(Deserialize)]
+pub struct Data {
+    pub refreshToken: AuthPayload,
+}
+
+#[derive(Serialize)]
+pub struct Vars {
+    refreshToken: String,
+}
+
+pub async fn refresh_token(refreshToken: String) -> Result<Data, Box<dyn std::error::Error>> {
+    let endpoint = get_graphql_endpoint();
+    let query = r#"
+        mutation RefreshToken($refreshToken: String!) {
+            refreshToken(refreshToken: $refreshToken) {
+                token
+                refreshToken
+                expiresAt
+            }
+        }
+   "#;
+
+    let client = Client::new(&endpoint);
+
+    let vars = Vars { refreshToken };
+    let data = client
+        .query_with_vars::<Data, Vars>(query, vars)
+        .await
+        .map_err(|e| format!("refreshToken request failed: {:?}", e))?;
+
+    Ok(data)
+}
@@ -0,0 +1,46 @@
+use reqwest_graphql::Client;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::format};
+
+use crate::helpers::utilities::get_graphql_endpoint;
+
+#[derive(Deserialize)]
+pub struct AuthPayload {
+    pub token: String,
+    pub refreshToken: Option<String>,
+    pub expiresAt: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct Data {
+    pub signIn: AuthPayload,
+}
+
+#[derive(Serialize)]
+pub struct Vars {
+    email: String,
+    password: String,
+}
+
+pub async fn sign_in(email: String, password: String) -> Result<Data, Box<dyn std::error::Error>> {
+    let endpoint = get_graphql_endpoint();
+    let query = r#"
+        mutation SignIn($email: String!, $password: String!) {
+            signIn(email: $email, password: $password) {
+                token
+                refreshToken
+                expiresAt
+            }
+        }
+   "#;
+
+    let client = Client::new(&endpoint);
+
+    let vars = Vars { email, password };
+    let data = client
+        .query_with_vars::<Data, Vars>(query, vars)
+        .await
+        .map_err(|e| format!("signIn request failed: {:?}", e))?;
+
+    Ok(data)
+}
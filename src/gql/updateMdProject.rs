@@ -2,6 +2,8 @@ use reqwest_graphql::Client;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fmt::format};
 
+use crate::helpers::utilities::get_graphql_endpoint;
+
 use crate::contexts::local::LocalState;
 
 #[derive(Deserialize)]
@@ -37,7 +39,7 @@ pub async fn update_md_project(
     title: String,
     context: &LocalState,
 ) -> Result<Data, Box<dyn std::error::Error>> {
-    let endpoint = "http://localhost:4000/graphql";
+    let endpoint = get_graphql_endpoint();
     let query = r#"
         mutation UpdateMdProject($projectId: String!, $title: String, $context: String) {
             updateMdProject(projectId: $projectId, title: $title, context: $context) {
@@ -52,7 +54,7 @@ pub async fn update_md_project(
     let mut headers = HashMap::new();
     headers.insert("Authorization", auth_header_str);
 
-    let client = Client::new_with_headers(endpoint, headers);
+    let client = Client::new_with_headers(&endpoint, headers);
 
     let context = serde_json::to_string(&*context).expect("Failed to serialize");
 
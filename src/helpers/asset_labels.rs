@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::utilities::get_project_dir;
+
+/// Tags and a collection the user gave an asset, to find it again in the browsers
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AssetLabel {
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub collection: Option<String>,
+}
+
+impl AssetLabel {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.collection.is_none()
+    }
+}
+
+pub fn get_asset_labels_path(project_id: &str) -> PathBuf {
+    get_project_dir(project_id).join("asset_labels.json")
+}
+
+// keyed by "{kind directory}/{asset id}", ids survive a file being renamed
+pub fn asset_label_key(kind_dir: &str, asset_id: &str) -> String {
+    format!("{}/{}", kind_dir, asset_id)
+}
+
+pub fn load_asset_labels(project_id: &str) -> HashMap<String, AssetLabel> {
+    match fs::read_to_string(get_asset_labels_path(project_id)) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            println!("Couldn't parse asset labels: {:?}", e);
+            HashMap::new()
+        }),
+        Err(_) => HashMap::new(),
+    }
+}
+
+/// Replaces the asset's tags and collection, forgetting it when both are empty
+pub fn save_asset_label(
+    project_id: &str,
+    kind_dir: &str,
+    asset_id: &str,
+    label: AssetLabel,
+) -> Result<(), String> {
+    let mut labels = load_asset_labels(project_id);
+    let key = asset_label_key(kind_dir, asset_id);
+
+    if label.is_empty() {
+        labels.remove(&key);
+    } else {
+        labels.insert(key, label);
+    }
+
+    let json = serde_json::to_string_pretty(&labels)
+        .map_err(|e| format!("Couldn't serialize asset labels: {}", e))?;
+    fs::write(get_asset_labels_path(project_id), json)
+        .map_err(|e| format!("Couldn't write asset labels: {}", e))
+}
+
+/// Splits comma separated tags, lowercased, without blanks or repeats
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+
+    for tag in text.split(',') {
+        let tag = tag.trim().trim_start_matches('#').to_lowercase();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    tags
+}
@@ -0,0 +1,376 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use base64::decode;
+use midpoint_engine::helpers::saved_data::File;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::utilities::{get_project_dir, sha256_file, sha256_hex};
+
+// the index is read, changed and written back by whichever thread stores next
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Which folder of the project an asset belongs in
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssetKind {
+    Concept,
+    Texture,
+    Model,
+    // heightmaps, rockmaps or soils of one landscape
+    LandscapeMap { landscape_id: String, map: String },
+}
+
+impl AssetKind {
+    pub fn folder(&self) -> String {
+        match self {
+            AssetKind::Concept => "concepts".to_string(),
+            AssetKind::Texture => "textures".to_string(),
+            AssetKind::Model => "models".to_string(),
+            AssetKind::LandscapeMap { landscape_id, map } => {
+                format!("landscapes/{}/{}", landscape_id, map)
+            }
+        }
+    }
+
+    fn accepts(&self, mime: &str) -> bool {
+        match self {
+            AssetKind::Model => mime == "model/gltf-binary",
+            _ => mime.starts_with("image/"),
+        }
+    }
+}
+
+/// A payload in the project folder, ready to be added to the saved state
+#[derive(Debug, Clone)]
+pub struct StoredAsset {
+    pub file: File,
+    pub mime: String,
+    pub hash: String,
+    // the same bytes were already stored in this folder, under `file.fileName`
+    pub reused: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoreLocation {
+    folder: String,
+    file_name: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct StoredContent {
+    mime: String,
+    locations: Vec<StoreLocation>,
+}
+
+/// Everything stored in a project, keyed by the SHA-256 of its bytes
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoreIndex {
+    contents: BTreeMap<String, StoredContent>,
+}
+
+impl StoreIndex {
+    /// Names the content was stored under in the folder
+    fn file_names(&self, hash: &str, folder: &str) -> Vec<String> {
+        self.contents
+            .get(hash)
+            .map(|content| {
+                content
+                    .locations
+                    .iter()
+                    .filter(|location| location.folder == folder)
+                    .map(|location| location.file_name.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn record(&mut self, hash: &str, mime: &str, folder: &str, file_name: &str) {
+        // whatever was at that path before has been overwritten
+        self.forget(folder, file_name);
+
+        let content = self.contents.entry(hash.to_string()).or_default();
+        content.mime = mime.to_string();
+        content.locations.push(StoreLocation {
+            folder: folder.to_string(),
+            file_name: file_name.to_string(),
+        });
+    }
+
+    fn rename(&mut self, folder: &str, file_name: &str, new_name: &str) {
+        for content in self.contents.values_mut() {
+            for location in content.locations.iter_mut() {
+                if location.folder == folder && location.file_name == file_name {
+                    location.file_name = new_name.to_string();
+                }
+            }
+        }
+    }
+
+    fn forget(&mut self, folder: &str, file_name: &str) {
+        for content in self.contents.values_mut() {
+            content
+                .locations
+                .retain(|location| !(location.folder == folder && location.file_name == file_name));
+        }
+        self.contents
+            .retain(|_, content| !content.locations.is_empty());
+    }
+}
+
+fn get_store_dir(project_id: &str) -> PathBuf {
+    get_project_dir(project_id).join("store")
+}
+
+fn get_index_path(project_id: &str) -> PathBuf {
+    get_store_dir(project_id).join("index.json")
+}
+
+fn load_index(project_id: &str) -> StoreIndex {
+    match fs::read_to_string(get_index_path(project_id)) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_default(),
+        Err(_) => StoreIndex::default(),
+    }
+}
+
+// replaced in one rename, so a crash mid-write can't leave a truncated index behind
+fn save_index(project_id: &str, index: &StoreIndex) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(index)
+        .map_err(|e| format!("Couldn't serialize asset index: {}", e))?;
+
+    let tmp_path = get_store_dir(project_id).join(format!("index-{}.tmp", Uuid::new_v4()));
+    fs::write(&tmp_path, json).map_err(|e| format!("Couldn't write asset index: {}", e))?;
+    fs::rename(&tmp_path, get_index_path(project_id)).map_err(|e| {
+        let _ = fs::remove_file(&tmp_path);
+        format!("Couldn't move asset index into place: {}", e)
+    })
+}
+
+/// The content type and file extension of a payload, from its first bytes
+pub fn detect_mime(bytes: &[u8]) -> Option<(&'static str, &'static str)> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(("image/png", "png"))
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some(("image/jpeg", "jpg"))
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some(("image/webp", "webp"))
+    } else if bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*") {
+        Some(("image/tiff", "tiff"))
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some(("image/gif", "gif"))
+    } else if bytes.starts_with(b"glTF") {
+        Some(("model/gltf-binary", "glb"))
+    } else {
+        None
+    }
+}
+
+fn file_stem(name: &str) -> &str {
+    match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => name,
+    }
+}
+
+/// Writes the payload into the kind's folder, named after `name` with the extension its bytes call for.
+/// Identical bytes already in that folder are reused rather than written again.
+pub fn store_asset(
+    project_id: &str,
+    kind: &AssetKind,
+    name: &str,
+    bytes: &[u8],
+) -> Result<StoredAsset, String> {
+    let (mime, extension) =
+        detect_mime(bytes).ok_or(format!("Unrecognized file contents for {}", name))?;
+    if !kind.accepts(mime) {
+        return Err(format!(
+            "{} is {}, which can't be stored in {}",
+            name,
+            mime,
+            kind.folder()
+        ));
+    }
+
+    let hash = sha256_hex(bytes);
+    let folder = kind.folder();
+    let normal_file_path = format!("midpoint/projects/{}/{}", project_id, folder);
+    let folder_path = get_project_dir(project_id).join(&folder);
+
+    let _guard = STORE_LOCK.lock().unwrap();
+    let mut index = load_index(project_id);
+
+    // only trusted while the file on disk still has that content
+    let existing = index
+        .file_names(&hash, &folder)
+        .into_iter()
+        .find(|file_name| {
+            sha256_file(&folder_path.join(file_name))
+                .map(|actual| actual == hash)
+                .unwrap_or(false)
+        });
+
+    if let Some(file_name) = existing {
+        return Ok(StoredAsset {
+            file: File {
+                id: Uuid::new_v4().to_string(),
+                cloudfrontUrl: String::new(),
+                fileName: file_name,
+                normalFilePath: normal_file_path,
+            },
+            mime: mime.to_string(),
+            hash,
+            reused: true,
+        });
+    }
+
+    let stem = file_stem(name);
+    let mut file_name = format!("{}.{}", stem, extension);
+    if folder_path.join(&file_name).exists() {
+        file_name = format!("{}-{}.{}", stem, &hash[..8], extension);
+    }
+
+    fs::create_dir_all(&folder_path)
+        .map_err(|e| format!("Couldn't create {} directory: {}", folder, e))?;
+    fs::create_dir_all(get_store_dir(project_id))
+        .map_err(|e| format!("Couldn't create store directory: {}", e))?;
+
+    // written beside the index then moved into place, so a half-written file is never seen
+    let tmp_path = get_store_dir(project_id).join(format!("{}.tmp", Uuid::new_v4()));
+    fs::write(&tmp_path, bytes).map_err(|e| format!("Couldn't write {}: {}", file_name, e))?;
+    if let Err(e) = fs::rename(&tmp_path, folder_path.join(&file_name)) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(format!("Couldn't move {} into place: {}", file_name, e));
+    }
+
+    index.record(&hash, mime, &folder, &file_name);
+    save_index(project_id, &index)?;
+
+    Ok(StoredAsset {
+        file: File {
+            id: Uuid::new_v4().to_string(),
+            // filled in once the file has been uploaded
+            cloudfrontUrl: String::new(),
+            fileName: file_name,
+            normalFilePath: normal_file_path,
+        },
+        mime: mime.to_string(),
+        hash,
+        reused: false,
+    })
+}
+
+/// Stores a base64 payload, with or without a data URL prefix of any type
+pub fn store_base64(
+    project_id: &str,
+    kind: &AssetKind,
+    name: &str,
+    data: &str,
+) -> Result<StoredAsset, String> {
+    let bytes = decode_base64(name, data)?;
+
+    store_asset(project_id, kind, name, &bytes)
+}
+
+/// Decodes a base64 string, with or without its data URL prefix
+pub fn decode_base64(name: &str, data: &str) -> Result<Vec<u8>, String> {
+    let base64_data = match data.split_once(";base64,") {
+        Some((_, base64_data)) => base64_data,
+        None => data,
+    };
+
+    decode(base64_data.trim())
+        .map_err(|e| format!("Couldn't decode base64 string for {}: {}", name, e))
+}
+
+/// Follows a stored file that was renamed, or forgets it when `new_name` is None
+pub fn rename_stored(
+    project_id: &str,
+    kind: &AssetKind,
+    file_name: &str,
+    new_name: Option<&str>,
+) -> Result<(), String> {
+    let folder = kind.folder();
+
+    let _guard = STORE_LOCK.lock().unwrap();
+    // projects from before the store have no index to keep up to date
+    if !get_index_path(project_id).exists() {
+        return Ok(());
+    }
+    let mut index = load_index(project_id);
+
+    match new_name {
+        Some(new_name) => index.rename(&folder, file_name, new_name),
+        None => index.forget(&folder, file_name),
+    }
+
+    save_index(project_id, &index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payloads_are_recognized_by_their_first_bytes() {
+        assert_eq!(
+            detect_mime(b"\x89PNG\r\n\x1a\n...."),
+            Some(("image/png", "png"))
+        );
+        assert_eq!(
+            detect_mime(&[0xFF, 0xD8, 0xFF, 0xE0]),
+            Some(("image/jpeg", "jpg"))
+        );
+        assert_eq!(
+            detect_mime(b"RIFF\0\0\0\0WEBPVP8 "),
+            Some(("image/webp", "webp"))
+        );
+        assert_eq!(detect_mime(b"II*\0...."), Some(("image/tiff", "tiff")));
+        assert_eq!(detect_mime(b"MM\0*...."), Some(("image/tiff", "tiff")));
+        assert_eq!(detect_mime(b"GIF89a..."), Some(("image/gif", "gif")));
+        assert_eq!(
+            detect_mime(b"glTF\x02\0\0\0"),
+            Some(("model/gltf-binary", "glb"))
+        );
+    }
+
+    #[test]
+    fn unknown_and_truncated_payloads_are_not_guessed() {
+        assert_eq!(detect_mime(b""), None);
+        assert_eq!(detect_mime(b"{\"asset\": {}}"), None);
+        // a RIFF header too short to say what it holds
+        assert_eq!(detect_mime(b"RIFF\0\0\0\0WEB"), None);
+        assert_eq!(detect_mime(b"\x89PNG"), None);
+    }
+
+    #[test]
+    fn only_models_accept_model_payloads() {
+        assert!(AssetKind::Model.accepts("model/gltf-binary"));
+        assert!(!AssetKind::Model.accepts("image/png"));
+        assert!(AssetKind::Texture.accepts("image/png"));
+        assert!(!AssetKind::Concept.accepts("model/gltf-binary"));
+    }
+
+    #[test]
+    fn the_index_follows_content_through_renames_and_overwrites() {
+        let mut index = StoreIndex::default();
+        index.record("aaa", "image/png", "textures", "brick.png");
+        index.record("aaa", "image/png", "concepts", "brick.png");
+        assert_eq!(index.file_names("aaa", "textures"), vec!["brick.png"]);
+
+        index.rename("textures", "brick.png", "wall.png");
+        assert_eq!(index.file_names("aaa", "textures"), vec!["wall.png"]);
+        assert_eq!(index.file_names("aaa", "concepts"), vec!["brick.png"]);
+
+        // new bytes written over a path take it from the old content
+        index.record("bbb", "image/png", "textures", "wall.png");
+        assert!(index.file_names("aaa", "textures").is_empty());
+        assert_eq!(index.file_names("bbb", "textures"), vec!["wall.png"]);
+
+        index.forget("concepts", "brick.png");
+        index.forget("textures", "wall.png");
+        assert!(index.contents.is_empty());
+    }
+}
@@ -0,0 +1,595 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use midpoint_engine::helpers::saved_data::{ComponentData, File, SavedState};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::editor_state::{StateHelper, UIMessage};
+
+use super::asset_labels::{save_asset_label, AssetLabel};
+use super::asset_store::{rename_stored, AssetKind};
+use super::collaboration::{SceneOperation, TransformProperty};
+use super::downloads::get_asset_path;
+use super::generation::rename_generation;
+use super::utilities::get_project_dir;
+
+/// What holds a reference to an asset
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReferenceOwner {
+    // the component places the asset in a level
+    Component,
+    // one of a landscape component's texture slots
+    LandscapeTexture,
+    Skeleton,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetReference {
+    pub owner: ReferenceOwner,
+    pub owner_id: String,
+    pub owner_name: String,
+    // where in the owner the id sits, ex. "asset" or "Soil"
+    pub field: String,
+}
+
+impl AssetReference {
+    pub fn description(&self) -> String {
+        match self.owner {
+            ReferenceOwner::Component if self.field == "asset" => {
+                format!("Component {}", self.owner_name)
+            }
+            ReferenceOwner::Component => {
+                format!("{} of component {}", self.field, self.owner_name)
+            }
+            ReferenceOwner::LandscapeTexture => {
+                format!("{} texture of landscape {}", self.field, self.owner_name)
+            }
+            ReferenceOwner::Skeleton => format!("Skeleton {}", self.owner_name),
+        }
+    }
+}
+
+/// What happens to the references of an asset that's deleted
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReferenceAction {
+    Remove,
+    // the id of another asset of the same kind
+    Replace(String),
+}
+
+fn browser_name(kind_dir: &str) -> &'static str {
+    match kind_dir {
+        "concepts" => "concept_browser",
+        "textures" => "texture_browser",
+        "models" => "model_browser",
+        "parts" => "part_browser",
+        "skeletons" => "skeleton_browser",
+        _ => "landscape_browser",
+    }
+}
+
+fn list_message(saved_state: &SavedState, kind_dir: &str) -> UIMessage {
+    match kind_dir {
+        "concepts" => UIMessage::UpdateConcepts(saved_state.concepts.clone()),
+        "textures" => UIMessage::UpdateTextures(saved_state.textures.clone().unwrap_or_default()),
+        "models" => UIMessage::UpdateModels(saved_state.models.clone()),
+        "parts" => UIMessage::UpdateParts(saved_state.skeleton_parts.clone()),
+        "skeletons" => UIMessage::UpdateSkeletons(saved_state.skeletons.clone()),
+        _ => UIMessage::UpdateLandscapes(saved_state.landscapes.clone().unwrap_or_default()),
+    }
+}
+
+fn file_kind(kind_dir: &str) -> Option<AssetKind> {
+    match kind_dir {
+        "concepts" => Some(AssetKind::Concept),
+        "textures" => Some(AssetKind::Texture),
+        "models" => Some(AssetKind::Model),
+        _ => None,
+    }
+}
+
+fn files_mut<'a>(saved_state: &'a mut SavedState, kind_dir: &str) -> Option<&'a mut Vec<File>> {
+    match kind_dir {
+        "concepts" => Some(&mut saved_state.concepts),
+        "textures" => saved_state.textures.as_mut(),
+        "models" => Some(&mut saved_state.models),
+        _ => None,
+    }
+}
+
+fn asset_exists(saved_state: &SavedState, kind_dir: &str, asset_id: &str) -> bool {
+    match kind_dir {
+        "concepts" => saved_state.concepts.iter().any(|f| f.id == asset_id),
+        "textures" => saved_state
+            .textures
+            .iter()
+            .flatten()
+            .any(|f| f.id == asset_id),
+        "models" => saved_state.models.iter().any(|f| f.id == asset_id),
+        "parts" => saved_state.skeleton_parts.iter().any(|p| p.id == asset_id),
+        "skeletons" => saved_state.skeletons.iter().any(|s| s.id == asset_id),
+        _ => saved_state
+            .landscapes
+            .iter()
+            .flatten()
+            .any(|l| l.id == asset_id),
+    }
+}
+
+fn components_mut(saved_state: &mut SavedState) -> impl Iterator<Item = &mut Vec<ComponentData>> {
+    saved_state
+        .levels
+        .iter_mut()
+        .flatten()
+        .filter_map(|level| level.components.as_mut())
+}
+
+fn first_level_components(saved_state: &SavedState) -> Vec<ComponentData> {
+    saved_state
+        .levels
+        .as_ref()
+        .and_then(|levels| levels.first())
+        .and_then(|level| level.components.clone())
+        .unwrap_or_default()
+}
+
+/// Dotted paths to every string in the value that equals the id
+fn reference_paths(value: &Value, id: &str, path: &str, found: &mut Vec<String>) {
+    match value {
+        Value::String(text) if text == id => found.push(path.to_string()),
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                reference_paths(item, id, &format!("{}[{}]", path, index), found);
+            }
+        }
+        Value::Object(fields) => {
+            for (name, field) in fields.iter() {
+                let field_path = if path.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}.{}", path, name)
+                };
+                reference_paths(field, id, &field_path, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn find_paths<T: Serialize>(item: &T, id: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    if let Ok(value) = serde_json::to_value(item) {
+        reference_paths(&value, id, "", &mut found);
+    }
+    // an item's own id isn't a reference to anything
+    found.retain(|path| path != "id");
+    found
+}
+
+fn replace_id(value: &mut Value, id: &str, new_id: &str) {
+    match value {
+        Value::String(text) if text == id => *text = new_id.to_string(),
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| replace_id(item, id, new_id)),
+        Value::Object(fields) => fields
+            .values_mut()
+            .for_each(|field| replace_id(field, id, new_id)),
+        _ => {}
+    }
+}
+
+// fields holding the id are cleared and list entries that mention it are dropped
+fn strip_id(value: &mut Value, id: &str) {
+    match value {
+        Value::Array(items) => items.retain(|item| {
+            let mut found = Vec::new();
+            reference_paths(item, id, "", &mut found);
+            found.is_empty()
+        }),
+        Value::Object(fields) => {
+            for field in fields.values_mut() {
+                if field.as_str() == Some(id) {
+                    *field = Value::Null;
+                } else {
+                    strip_id(field, id);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Removes or replaces the id anywhere in an item whose fields the editor doesn't know
+fn rewrite<T: Serialize + DeserializeOwned>(
+    item: &T,
+    id: &str,
+    action: &ReferenceAction,
+) -> Result<T, String> {
+    let mut value = serde_json::to_value(item).map_err(|e| e.to_string())?;
+
+    match action {
+        ReferenceAction::Remove => strip_id(&mut value, id),
+        ReferenceAction::Replace(new_id) => replace_id(&mut value, id, new_id),
+    }
+
+    serde_json::from_value(value)
+        .map_err(|e| format!("The reference can't be removed, replace it instead ({})", e))
+}
+
+fn landscape_slots(component: &ComponentData) -> Vec<(&'static str, Option<String>)> {
+    match component.landscape_properties.as_ref() {
+        Some(properties) => vec![
+            ("Primary", properties.primary_texture_id.clone()),
+            ("Rockmap", properties.rockmap_texture_id.clone()),
+            ("Soil", properties.soil_texture_id.clone()),
+        ],
+        None => Vec::new(),
+    }
+}
+
+/// The components, landscape texture slots and skeletons that point at the asset
+pub fn find_references(saved_state: &SavedState, asset_id: &str) -> Vec<AssetReference> {
+    let mut references = Vec::new();
+
+    let components = saved_state
+        .levels
+        .iter()
+        .flatten()
+        .flat_map(|level| level.components.iter().flatten());
+
+    for component in components {
+        let reference = |owner: ReferenceOwner, field: &str| AssetReference {
+            owner,
+            owner_id: component.id.clone(),
+            owner_name: component.generic_properties.name.clone(),
+            field: field.to_string(),
+        };
+
+        if component.asset_id == asset_id {
+            references.push(reference(ReferenceOwner::Component, "asset"));
+        }
+        for (slot, texture_id) in landscape_slots(component) {
+            if texture_id.as_deref() == Some(asset_id) {
+                references.push(reference(ReferenceOwner::LandscapeTexture, slot));
+            }
+        }
+        for path in find_paths(&component.model_properties, asset_id) {
+            references.push(reference(ReferenceOwner::Component, &path));
+        }
+    }
+
+    for skeleton in saved_state.skeletons.iter() {
+        for path in find_paths(skeleton, asset_id) {
+            references.push(AssetReference {
+                owner: ReferenceOwner::Skeleton,
+                owner_id: skeleton.id.clone(),
+                owner_name: skeleton.name.clone(),
+                field: path,
+            });
+        }
+    }
+
+    references
+}
+
+/// Applies the action to every reference, returning the scene edits the renderer and peers need
+fn update_references(
+    saved_state: &mut SavedState,
+    asset_id: &str,
+    action: &ReferenceAction,
+) -> Result<Vec<SceneOperation>, String> {
+    let mut operations = Vec::new();
+
+    for components in components_mut(saved_state) {
+        let mut removed: Vec<String> = Vec::new();
+
+        for component in components.iter_mut() {
+            if component.asset_id == asset_id {
+                match action {
+                    ReferenceAction::Remove => removed.push(component.id.clone()),
+                    ReferenceAction::Replace(new_id) => {
+                        component.asset_id = new_id.clone();
+
+                        // the renderer loads the new asset by adding the component again
+                        operations.push(SceneOperation::Remove {
+                            component_id: component.id.clone(),
+                        });
+                        operations.push(SceneOperation::Add {
+                            component: component.clone(),
+                        });
+                        operations.push(SceneOperation::Transform {
+                            component_id: component.id.clone(),
+                            property: TransformProperty::Rotation,
+                            value: component.generic_properties.rotation,
+                        });
+                        operations.push(SceneOperation::Transform {
+                            component_id: component.id.clone(),
+                            property: TransformProperty::Scale,
+                            value: component.generic_properties.scale,
+                        });
+                    }
+                }
+            }
+
+            if let Some(properties) = component.landscape_properties.as_mut() {
+                let replacement = match action {
+                    ReferenceAction::Remove => None,
+                    ReferenceAction::Replace(new_id) => Some(new_id.clone()),
+                };
+                for slot in [
+                    &mut properties.primary_texture_id,
+                    &mut properties.rockmap_texture_id,
+                    &mut properties.soil_texture_id,
+                ] {
+                    if slot.as_deref() == Some(asset_id) {
+                        *slot = replacement.clone();
+                    }
+                }
+            }
+
+            if !find_paths(&component.model_properties, asset_id).is_empty() {
+                component.model_properties =
+                    rewrite(&component.model_properties, asset_id, action)?;
+            }
+        }
+
+        components.retain(|component| !removed.contains(&component.id));
+        operations.extend(
+            removed
+                .into_iter()
+                .map(|component_id| SceneOperation::Remove { component_id }),
+        );
+    }
+
+    for skeleton in saved_state.skeletons.iter_mut() {
+        if !find_paths(skeleton, asset_id).is_empty() {
+            *skeleton = rewrite(skeleton, asset_id, action)?;
+        }
+    }
+
+    Ok(operations)
+}
+
+// keeps what the user typed, minus anything a file name can't hold
+fn clean_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c => c,
+        })
+        .collect()
+}
+
+/// Renames the file on disk, keeping its extension, along with everything keyed by its name
+fn rename_file(
+    project_id: &str,
+    kind_dir: &str,
+    kind: &AssetKind,
+    file: &mut File,
+    name: &str,
+) -> Result<String, String> {
+    let new_file_name = match Path::new(&file.fileName)
+        .extension()
+        .and_then(|e| e.to_str())
+    {
+        Some(extension) => format!("{}.{}", name, extension),
+        None => name.to_string(),
+    };
+    if new_file_name == file.fileName {
+        return Ok(new_file_name);
+    }
+    // the cloud copy is still served under the old name
+    if !file.cloudfrontUrl.is_empty() {
+        return Err(format!(
+            "{} is synced, so it can't be renamed here",
+            file.fileName
+        ));
+    }
+
+    let from = get_asset_path(&file.normalFilePath, &file.fileName);
+    let to = get_asset_path(&file.normalFilePath, &new_file_name);
+    if to.exists() {
+        return Err(format!("{} already exists", new_file_name));
+    }
+
+    // a file that hasn't downloaded yet is fetched under its new name
+    if from.exists() {
+        fs::rename(&from, &to).map_err(|e| format!("Couldn't rename {}: {}", file.fileName, e))?;
+    }
+
+    rename_stored(project_id, kind, &file.fileName, Some(&new_file_name))?;
+    rename_generation(project_id, kind_dir, &file.fileName, Some(&new_file_name));
+    file.fileName = new_file_name.clone();
+
+    Ok(new_file_name)
+}
+
+/// Renames an asset, returning its new name. Files keep their extension, and a landscape is
+/// renamed through its heightmap, which is what it's listed by.
+pub fn rename_asset(
+    state_helper: &mut StateHelper,
+    project_id: &str,
+    kind_dir: &str,
+    asset_id: &str,
+    name: &str,
+) -> Result<String, String> {
+    let name = clean_name(name);
+    if name.is_empty() {
+        return Err("Enter a name".to_string());
+    }
+
+    let saved_state = Arc::clone(
+        state_helper
+            .saved_state
+            .as_ref()
+            .ok_or("No project is open".to_string())?,
+    );
+    let mut saved_state = saved_state.lock().unwrap();
+    let missing = || format!("Couldn't find {} {}", kind_dir, asset_id);
+
+    let renamed = match kind_dir {
+        "parts" => {
+            let part = saved_state
+                .skeleton_parts
+                .iter_mut()
+                .find(|p| p.id == asset_id)
+                .ok_or_else(missing)?;
+            part.name = name.clone();
+            name
+        }
+        "skeletons" => {
+            let skeleton = saved_state
+                .skeletons
+                .iter_mut()
+                .find(|s| s.id == asset_id)
+                .ok_or_else(missing)?;
+            skeleton.name = name.clone();
+            name
+        }
+        "landscapes" => {
+            let heightmap = saved_state
+                .landscapes
+                .iter_mut()
+                .flatten()
+                .find(|l| l.id == asset_id)
+                .and_then(|l| l.heightmap.as_mut())
+                .ok_or_else(missing)?;
+            let kind = AssetKind::LandscapeMap {
+                landscape_id: asset_id.to_string(),
+                map: "heightmaps".to_string(),
+            };
+            rename_file(project_id, kind_dir, &kind, heightmap, &name)?
+        }
+        _ => {
+            let kind = file_kind(kind_dir).ok_or_else(missing)?;
+            let file = files_mut(&mut saved_state, kind_dir)
+                .and_then(|files| files.iter_mut().find(|f| f.id == asset_id))
+                .ok_or_else(missing)?;
+            rename_file(project_id, kind_dir, &kind, file, &name)?
+        }
+    };
+
+    let message = list_message(&saved_state, kind_dir);
+    drop(saved_state);
+
+    state_helper.save_current_saved_state();
+    state_helper.send_ui_message(browser_name(kind_dir), message);
+
+    Ok(renamed)
+}
+
+/// Deletes an asset and its files. If anything still uses it, `action` says whether those
+/// references are removed or pointed at another asset; without one nothing is deleted.
+pub fn delete_asset(
+    state_helper: &mut StateHelper,
+    project_id: &str,
+    kind_dir: &str,
+    asset_id: &str,
+    action: Option<ReferenceAction>,
+) -> Result<(), String> {
+    let saved_state = Arc::clone(
+        state_helper
+            .saved_state
+            .as_ref()
+            .ok_or("No project is open".to_string())?,
+    );
+    let mut current = saved_state.lock().unwrap();
+    // edits go to a copy, so a reference that can't be rewritten leaves the project as it was
+    let mut saved_state = current.clone();
+
+    let references = find_references(&saved_state, asset_id);
+    let operations = match (references.is_empty(), action) {
+        (true, _) => Vec::new(),
+        (false, None) => {
+            return Err(format!(
+                "Still used by {} reference(s), remove or replace them",
+                references.len()
+            ))
+        }
+        (false, Some(action)) => {
+            if let ReferenceAction::Replace(new_id) = &action {
+                if new_id == asset_id || !asset_exists(&saved_state, kind_dir, new_id) {
+                    return Err("Choose another asset of the same kind".to_string());
+                }
+            }
+            update_references(&mut saved_state, asset_id, &action)?
+        }
+    };
+
+    let mut paths: Vec<PathBuf> = Vec::new();
+
+    match kind_dir {
+        "parts" => saved_state.skeleton_parts.retain(|p| p.id != asset_id),
+        "skeletons" => saved_state.skeletons.retain(|s| s.id != asset_id),
+        "landscapes" => {
+            if let Some(landscapes) = saved_state.landscapes.as_mut() {
+                landscapes.retain(|l| l.id != asset_id);
+            }
+            paths.push(
+                get_project_dir(project_id)
+                    .join("landscapes")
+                    .join(asset_id),
+            );
+        }
+        _ => {
+            let kind = file_kind(kind_dir)
+                .ok_or_else(|| format!("Couldn't find {} {}", kind_dir, asset_id))?;
+            let files = files_mut(&mut saved_state, kind_dir)
+                .ok_or_else(|| format!("Couldn't find {} {}", kind_dir, asset_id))?;
+
+            if let Some(index) = files.iter().position(|f| f.id == asset_id) {
+                let file = files.remove(index);
+                let path = get_asset_path(&file.normalFilePath, &file.fileName);
+
+                rename_stored(project_id, &kind, &file.fileName, None)?;
+                rename_generation(project_id, kind_dir, &file.fileName, None);
+                paths.push(path);
+            }
+        }
+    }
+
+    let message = list_message(&saved_state, kind_dir);
+    let skeletons_changed = references
+        .iter()
+        .any(|r| r.owner == ReferenceOwner::Skeleton);
+    let skeletons = UIMessage::UpdateSkeletons(saved_state.skeletons.clone());
+    let components = first_level_components(&saved_state);
+    *current = saved_state;
+    drop(current);
+
+    // gone from the project before the files go, so the folder watcher doesn't offer them back
+    for path in paths {
+        let removed = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        if let Err(e) = removed {
+            if path.exists() {
+                println!("Couldn't delete {}: {}", path.display(), e);
+            }
+        }
+    }
+    if let Err(e) = save_asset_label(project_id, kind_dir, asset_id, AssetLabel::default()) {
+        println!("{}", e);
+    }
+
+    state_helper.save_current_saved_state();
+
+    for operation in operations {
+        state_helper.broadcast_scene_operation(operation.clone());
+        state_helper.send_ui_message("scene", UIMessage::SceneOperation(operation));
+    }
+    state_helper.send_ui_message(browser_name(kind_dir), message);
+    if skeletons_changed {
+        state_helper.send_ui_message("skeleton_browser", skeletons);
+    }
+    state_helper.send_ui_message("component_browser", UIMessage::UpdateComponents(components));
+
+    Ok(())
+}
@@ -1,44 +1,200 @@
-use directories::ProjectDirs;
+use std::fs;
+use std::path::PathBuf;
 
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::editor_state::{send_ui_message, FileSignals, UIMessage};
+use crate::gql::refreshToken::refresh_token;
+use crate::gql::signIn::{sign_in, AuthPayload};
 use crate::helpers::utilities::get_common_os_dir;
 
-pub fn read_token(// state: tauri::State<'_, AppState>
-) -> String {
-    // let handle = &state.handle;
-    // let config = handle.config();
-    // let package_info = handle.package_info();
-    // let env = handle.env();
+// treat tokens this close to expiry as expired, so requests don't fail mid-flight
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+// where the editor's own sign-in lives in the OS credential store
+const KEYRING_SERVICE: &str = "midpoint-editor";
+const KEYRING_USER: &str = "auth";
 
-    read_auth_token()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthStatus {
+    SignedIn,
+    Missing,
+    Expired,
+    Rejected,
 }
 
-pub fn read_auth_token(// config: &Arc<tauri::Config>
-) -> String {
-    println!("read_auth_token");
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredAuth {
+    pub token: String,
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<i64>,
+}
 
-    // let app_data_path = app_data_dir(config)
-    //     .ok_or("Failed to get AppData directory (1)")
-    //     .expect("Failed to get AppData directory (2)");
-    // let app_data_path = app_data_path
-    //     .parent()
-    //     .expect("Failed to get AppData directory (3)")
-    //     .join("com.common.commonosfiles");
+impl From<AuthPayload> for StoredAuth {
+    fn from(payload: AuthPayload) -> Self {
+        let expires_at = payload.expiresAt.or_else(|| token_expiry(&payload.token));
 
-    // if let Some(proj_dirs) = ProjectDirs::from("com", "common", "commonosfiles") {
-    // App data directory
-    // let data_dir = proj_dirs.data_dir();
+        StoredAuth {
+            token: payload.token,
+            refresh_token: payload.refreshToken,
+            expires_at,
+        }
+    }
+}
 
-    // println!("data_dir {:?}", data_dir);
+// written by the editor's own sign-in before it moved to the credential store
+fn get_legacy_auth_path() -> PathBuf {
     let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
-    let read_path = sync_dir.join("auth");
+    sync_dir.join("midpoint").join("auth.json")
+}
+
+// raw token shared by the CommonOS File Manager
+fn get_shared_auth_path() -> PathBuf {
+    let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
+    sync_dir.join("auth")
+}
+
+/// Decodes the claims when the token is a JWT, without verifying it
+pub fn token_claims(token: &str) -> Option<serde_json::Value> {
+    let payload = token.split('.').nth(1)?;
+    let decoded = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+
+    serde_json::from_slice(&decoded).ok()
+}
+
+/// Reads the `exp` claim when the token is a JWT
+pub fn token_expiry(token: &str) -> Option<i64> {
+    token_claims(token)?.get("exp").and_then(|exp| exp.as_i64())
+}
+
+fn keyring_entry() -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
+        .map_err(|e| format!("Couldn't open credential store: {}", e))
+}
+
+fn read_keyring_auth() -> Option<StoredAuth> {
+    let json = match keyring_entry().and_then(|entry| {
+        entry
+            .get_password()
+            .map_err(|e| format!("Couldn't read credential store: {}", e))
+    }) {
+        Ok(json) => json,
+        Err(e) => {
+            println!("{}", e);
+            return None;
+        }
+    };
+
+    serde_json::from_str::<StoredAuth>(&json)
+        .ok()
+        .filter(|stored| !stored.token.trim().is_empty())
+}
+
+/// Moves a sign-in left in the synced folder by older versions into the credential store
+fn migrate_legacy_auth() -> Option<StoredAuth> {
+    let legacy_path = get_legacy_auth_path();
+    let json = fs::read_to_string(&legacy_path).ok()?;
+    let stored = serde_json::from_str::<StoredAuth>(&json)
+        .ok()
+        .filter(|stored| !stored.token.trim().is_empty());
+
+    // only drop the file once the token is safely somewhere else
+    match stored.as_ref().map(save_stored_auth) {
+        Some(Err(e)) => println!("{}", e),
+        _ => {
+            let _ = fs::remove_file(&legacy_path);
+        }
+    }
+
+    stored
+}
+
+pub fn read_stored_auth() -> Option<StoredAuth> {
+    if let Some(stored) = read_keyring_auth().or_else(migrate_legacy_auth) {
+        return Some(stored);
+    }
+
+    let shared_token = fs::read_to_string(get_shared_auth_path()).ok()?;
+    let shared_token = shared_token.trim().to_string();
+
+    if shared_token.is_empty() {
+        return None;
+    }
+
+    Some(StoredAuth {
+        expires_at: token_expiry(&shared_token),
+        token: shared_token,
+        refresh_token: None,
+    })
+}
+
+/// Keeps the sign-in in the OS credential store, never in the synced folder
+pub fn save_stored_auth(stored: &StoredAuth) -> Result<(), String> {
+    let json = serde_json::to_string(stored).expect("Couldn't serialize auth");
+
+    keyring_entry()?
+        .set_password(&json)
+        .map_err(|e| format!("Couldn't save sign-in to credential store: {}", e))
+}
+
+pub fn clear_stored_auth() {
+    if let Ok(entry) = keyring_entry() {
+        let _ = entry.delete_password();
+    }
+    let _ = fs::remove_file(get_legacy_auth_path());
+}
+
+pub fn check_auth(stored: &Option<StoredAuth>) -> AuthStatus {
+    match stored {
+        None => AuthStatus::Missing,
+        Some(stored) => match stored.expires_at {
+            Some(expires_at) if expires_at - EXPIRY_MARGIN_SECS <= Utc::now().timestamp() => {
+                AuthStatus::Expired
+            }
+            _ => AuthStatus::SignedIn,
+        },
+    }
+}
+
+pub fn report_auth_failure(file_signals: &FileSignals, status: AuthStatus) {
+    send_ui_message(file_signals, "auth", UIMessage::AuthRequired(status));
+}
+
+pub async fn sign_in_with_password(email: String, password: String) -> Result<StoredAuth, String> {
+    let data = sign_in(email, password).await.map_err(|e| e.to_string())?;
+    let stored = StoredAuth::from(data.signIn);
+
+    // still signed in for this session if the credential store is unavailable
+    if let Err(e) = save_stored_auth(&stored) {
+        println!("{}", e);
+    }
+
+    Ok(stored)
+}
+
+/// Exchanges the stored refresh token for a new token, if the backend gave us one
+pub async fn refresh_stored_auth(stored: StoredAuth) -> Result<StoredAuth, String> {
+    let current_refresh_token = stored
+        .refresh_token
+        .clone()
+        .ok_or("No refresh token available".to_string())?;
+
+    let data = refresh_token(current_refresh_token)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut refreshed = StoredAuth::from(data.refreshToken);
+
+    // keep the old refresh token if the backend doesn't rotate it
+    if refreshed.refresh_token.is_none() {
+        refreshed.refresh_token = stored.refresh_token;
+    }
 
-    // pull String content from read_path
-    let auth_data =
-        String::from_utf8_lossy(&std::fs::read(read_path).unwrap_or_default()).to_string();
+    if let Err(e) = save_stored_auth(&refreshed) {
+        println!("{}", e);
+    }
 
-    auth_data
-    // } else {
-    //     println!("Couldn't find CommonOS File Manager data directory");
-    //     "".to_string()
-    // }
+    Ok(refreshed)
 }
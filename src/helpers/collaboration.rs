@@ -0,0 +1,624 @@
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+use midpoint_engine::core::RendererState::RendererState;
+use midpoint_engine::handlers::{handle_add_landscape, handle_add_model};
+use midpoint_engine::helpers::saved_data::{ComponentData, ComponentKind, SavedState};
+use nalgebra::{Isometry3, Vector3};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+// how many operation ids to remember for duplicate detection
+const SEEN_OPERATION_LIMIT: usize = 1024;
+
+/// Identifies this editor in the project group, so our own operations can be ignored when echoed
+pub static CLIENT_ID: Lazy<String> = Lazy::new(|| Uuid::new_v4().to_string());
+
+static COLLABORATION: Lazy<Mutex<CollaborationState>> =
+    Lazy::new(|| Mutex::new(CollaborationState::default()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransformProperty {
+    Position,
+    Rotation,
+    Scale,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum SceneOperation {
+    Add {
+        component: ComponentData,
+    },
+    Remove {
+        component_id: String,
+    },
+    Rename {
+        component_id: String,
+        name: String,
+    },
+    Transform {
+        component_id: String,
+        property: TransformProperty,
+        value: [f32; 3],
+    },
+}
+
+impl SceneOperation {
+    pub fn component_id(&self) -> &str {
+        match self {
+            SceneOperation::Add { component } => &component.id,
+            SceneOperation::Remove { component_id }
+            | SceneOperation::Rename { component_id, .. }
+            | SceneOperation::Transform { component_id, .. } => component_id,
+        }
+    }
+
+    // the field last-writer-wins is resolved on; adding and removing share one
+    fn field_key(&self) -> String {
+        let field = match self {
+            SceneOperation::Add { .. } | SceneOperation::Remove { .. } => "exists",
+            SceneOperation::Rename { .. } => "name",
+            SceneOperation::Transform { property, .. } => match property {
+                TransformProperty::Position => "position",
+                TransformProperty::Rotation => "rotation",
+                TransformProperty::Scale => "scale",
+            },
+        };
+
+        format!("{}/{}", self.component_id(), field)
+    }
+}
+
+/// A component's whole transform as the edits other editors apply, one per field
+pub fn transform_operations(component: &ComponentData) -> Vec<SceneOperation> {
+    let properties = &component.generic_properties;
+
+    [
+        (TransformProperty::Position, properties.position),
+        (TransformProperty::Rotation, properties.rotation),
+        (TransformProperty::Scale, properties.scale),
+    ]
+    .into_iter()
+    .map(|(property, value)| SceneOperation::Transform {
+        component_id: component.id.clone(),
+        property,
+        value,
+    })
+    .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneOperationPayload {
+    #[serde(rename = "opId")]
+    pub op_id: String,
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    // milliseconds since the epoch, ties are broken by client id
+    pub timestamp: i64,
+    pub operation: SceneOperation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Stamp {
+    timestamp: i64,
+    client_id: String,
+}
+
+#[derive(Default)]
+struct CollaborationState {
+    clocks: HashMap<String, Stamp>,
+    seen_operations: VecDeque<String>,
+}
+
+impl CollaborationState {
+    fn remember(&mut self, op_id: String) {
+        self.seen_operations.push_back(op_id);
+        if self.seen_operations.len() > SEEN_OPERATION_LIMIT {
+            self.seen_operations.pop_front();
+        }
+    }
+}
+
+/// Stamps a local edit and returns the message to send to the project group
+pub fn local_operation(operation: SceneOperation) -> String {
+    let payload = SceneOperationPayload {
+        op_id: Uuid::new_v4().to_string(),
+        client_id: CLIENT_ID.clone(),
+        timestamp: Utc::now().timestamp_millis(),
+        operation,
+    };
+
+    let mut collaboration = COLLABORATION.lock().unwrap();
+    collaboration.clocks.insert(
+        payload.operation.field_key(),
+        Stamp {
+            timestamp: payload.timestamp,
+            client_id: payload.client_id.clone(),
+        },
+    );
+    collaboration.remember(payload.op_id.clone());
+    drop(collaboration);
+
+    json!({
+        "event": "scene_operation",
+        "payload": payload
+    })
+    .to_string()
+}
+
+pub fn parse_scene_operation(text: &str) -> Option<SceneOperationPayload> {
+    let message: serde_json::Value = serde_json::from_str(text).ok()?;
+
+    if message.get("event").and_then(|e| e.as_str()) != Some("scene_operation") {
+        return None;
+    }
+
+    serde_json::from_value(message.get("payload")?.clone()).ok()
+}
+
+/// Decides whether a remote operation should be applied:
+/// our own echoes and repeats are dropped, and older writes to a field lose to newer ones
+pub fn accept_remote(payload: &SceneOperationPayload) -> bool {
+    if payload.client_id == *CLIENT_ID {
+        return false;
+    }
+
+    let mut collaboration = COLLABORATION.lock().unwrap();
+
+    if collaboration.seen_operations.contains(&payload.op_id) {
+        return false;
+    }
+    collaboration.remember(payload.op_id.clone());
+
+    let stamp = Stamp {
+        timestamp: payload.timestamp,
+        client_id: payload.client_id.clone(),
+    };
+    let field_key = payload.operation.field_key();
+
+    match collaboration.clocks.get(&field_key) {
+        Some(current) if *current >= stamp => false,
+        _ => {
+            collaboration.clocks.insert(field_key, stamp);
+            true
+        }
+    }
+}
+
+/// Applies the operation to the first level's components
+pub fn apply_to_saved_state(saved_state: &mut SavedState, operation: &SceneOperation) {
+    let components = match saved_state
+        .levels
+        .as_mut()
+        .and_then(|levels| levels.get_mut(0))
+    {
+        Some(level) => level.components.get_or_insert_with(Vec::new),
+        None => {
+            println!("No level to apply scene operation to");
+            return;
+        }
+    };
+
+    match operation {
+        SceneOperation::Add { component } => {
+            if !components.iter().any(|c| c.id == component.id) {
+                components.push(component.clone());
+            }
+        }
+        SceneOperation::Remove { component_id } => {
+            components.retain(|c| c.id != *component_id);
+        }
+        SceneOperation::Rename { component_id, name } => {
+            if let Some(component) = components.iter_mut().find(|c| c.id == *component_id) {
+                component.generic_properties.name = name.clone();
+            }
+        }
+        SceneOperation::Transform {
+            component_id,
+            property,
+            value,
+        } => {
+            if let Some(component) = components.iter_mut().find(|c| c.id == *component_id) {
+                match property {
+                    TransformProperty::Position => component.generic_properties.position = *value,
+                    TransformProperty::Rotation => component.generic_properties.rotation = *value,
+                    TransformProperty::Scale => component.generic_properties.scale = *value,
+                }
+            }
+        }
+    }
+}
+
+/// Drops a component's meshes and the physics bodies `add_collider` made for it,
+/// which carry the component id as their user data
+pub fn remove_from_renderer(renderer_state: &mut RendererState, component_id: &str) {
+    renderer_state.models.retain(|m| m.id != component_id);
+    renderer_state.landscapes.retain(|l| l.id != component_id);
+
+    if renderer_state
+        .object_selected
+        .map(|id| id.to_string())
+        .as_deref()
+        == Some(component_id)
+    {
+        renderer_state.object_selected = None;
+        renderer_state.object_selected_data = None;
+    }
+
+    let user_data = match Uuid::from_str(component_id) {
+        Ok(id) => id.as_u128(),
+        Err(_) => return,
+    };
+
+    let RendererState {
+        rigid_body_set,
+        collider_set,
+        island_manager,
+        impulse_joint_set,
+        multibody_joint_set,
+        ..
+    } = renderer_state;
+
+    let bodies: Vec<_> = rigid_body_set
+        .iter()
+        .filter(|(_, body)| body.user_data == user_data)
+        .map(|(handle, _)| handle)
+        .collect();
+    for handle in bodies {
+        rigid_body_set.remove(
+            handle,
+            island_manager,
+            collider_set,
+            impulse_joint_set,
+            multibody_joint_set,
+            true,
+        );
+    }
+
+    // colliders without a body of their own
+    let colliders: Vec<_> = collider_set
+        .iter()
+        .filter(|(_, collider)| collider.user_data == user_data)
+        .map(|(handle, _)| handle)
+        .collect();
+    for handle in colliders {
+        collider_set.remove(handle, island_manager, rigid_body_set, true);
+    }
+}
+
+/// Mirrors the operation in the renderer, loading newly added components onto the GPU
+pub fn apply_to_renderer(
+    renderer_state: Arc<Mutex<RendererState>>,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    saved_state: &SavedState,
+    operation: &SceneOperation,
+) {
+    match operation {
+        SceneOperation::Add { component } => {
+            let project_id = match renderer_state.lock().unwrap().project_selected {
+                Some(project_id) => project_id.to_string(),
+                None => {
+                    println!("Remote component arrived with no project open");
+                    return;
+                }
+            };
+            let position = component.generic_properties.position;
+            let kind = match component.kind.as_ref() {
+                Some(kind) => kind,
+                None => {
+                    println!("Remote component {} has no kind", component.id);
+                    return;
+                }
+            };
+
+            match kind {
+                ComponentKind::Model => {
+                    let model = match saved_state
+                        .models
+                        .iter()
+                        .find(|m| m.id == component.asset_id)
+                    {
+                        Some(model) => model,
+                        None => {
+                            println!("Remote component uses unknown model {}", component.asset_id);
+                            return;
+                        }
+                    };
+
+                    handle_add_model(
+                        renderer_state.clone(),
+                        device,
+                        queue,
+                        project_id,
+                        component.asset_id.clone(),
+                        component.id.clone(),
+                        model.fileName.clone(),
+                        Isometry3::new(
+                            Vector3::new(position[0], position[1], position[2]),
+                            Vector3::new(0.0, 0.0, 0.0),
+                        ),
+                    );
+
+                    let mut renderer_state = renderer_state.lock().unwrap();
+                    renderer_state.add_collider(component.id.clone(), ComponentKind::Model);
+
+                    // loading only places the model
+                    if let Some(model) = renderer_state
+                        .models
+                        .iter_mut()
+                        .find(|m| m.id == component.id)
+                    {
+                        model.meshes.iter_mut().for_each(|mesh| {
+                            mesh.transform
+                                .update_rotation(component.generic_properties.rotation);
+                            mesh.transform
+                                .update_scale(component.generic_properties.scale);
+                        });
+                    }
+                }
+                ComponentKind::Landscape => {
+                    let heightmap = saved_state
+                        .landscapes
+                        .as_ref()
+                        .and_then(|landscapes| {
+                            landscapes.iter().find(|l| l.id == component.asset_id)
+                        })
+                        .and_then(|landscape| landscape.heightmap.clone());
+
+                    let heightmap = match heightmap {
+                        Some(heightmap) => heightmap,
+                        None => {
+                            println!(
+                                "Remote component uses unknown landscape {}",
+                                component.asset_id
+                            );
+                            return;
+                        }
+                    };
+
+                    handle_add_landscape(
+                        renderer_state.clone(),
+                        device,
+                        queue,
+                        project_id,
+                        component.asset_id.clone(),
+                        component.id.clone(),
+                        heightmap.fileName,
+                        position,
+                    );
+
+                    if let Some(landscape) = renderer_state
+                        .lock()
+                        .unwrap()
+                        .landscapes
+                        .iter_mut()
+                        .find(|l| l.id == component.id)
+                    {
+                        landscape
+                            .transform
+                            .update_rotation(component.generic_properties.rotation);
+                        landscape
+                            .transform
+                            .update_scale(component.generic_properties.scale);
+                    }
+                }
+            }
+        }
+        SceneOperation::Remove { component_id } => {
+            remove_from_renderer(&mut renderer_state.lock().unwrap(), component_id);
+        }
+        SceneOperation::Rename { .. } => {
+            // names only live in the saved state
+        }
+        SceneOperation::Transform {
+            component_id,
+            property,
+            value,
+        } => {
+            let mut renderer_state = renderer_state.lock().unwrap();
+            let value = *value;
+
+            if let Some(model) = renderer_state
+                .models
+                .iter_mut()
+                .find(|m| m.id == *component_id)
+            {
+                model.meshes.iter_mut().for_each(|mesh| match property {
+                    TransformProperty::Position => mesh.transform.update_position(value),
+                    TransformProperty::Rotation => mesh.transform.update_rotation(value),
+                    TransformProperty::Scale => mesh.transform.update_scale(value),
+                });
+            }
+            if let Some(landscape) = renderer_state
+                .landscapes
+                .iter_mut()
+                .find(|l| l.id == *component_id)
+            {
+                match property {
+                    TransformProperty::Position => landscape.transform.update_position(value),
+                    TransformProperty::Rotation => landscape.transform.update_rotation(value),
+                    TransformProperty::Scale => landscape.transform.update_scale(value),
+                }
+            }
+
+            // the gizmo and colliders follow the selected object only
+            let is_selected = renderer_state.object_selected.map(|id| id.to_string())
+                == Some(component_id.clone());
+
+            if !is_selected {
+                return;
+            }
+
+            if let Some(data) = renderer_state.object_selected_data.as_mut() {
+                match property {
+                    TransformProperty::Position => data.generic_properties.position = value,
+                    TransformProperty::Rotation => data.generic_properties.rotation = value,
+                    TransformProperty::Scale => data.generic_properties.scale = value,
+                }
+            }
+
+            if *property == TransformProperty::Position {
+                renderer_state.gizmo.transform.update_position(value);
+                renderer_state.update_arrow_collider_position(value);
+
+                match renderer_state.object_selected_kind.clone() {
+                    Some(ComponentKind::Model) => {
+                        renderer_state.update_model_collider_position(value)
+                    }
+                    Some(ComponentKind::Landscape) => {
+                        renderer_state.update_landscape_collider_position(value)
+                    }
+                    None => println!("Selected object {} has no kind", component_id),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the clocks are shared across tests, so each test edits its own component
+    fn remote(client_id: &str, timestamp: i64, operation: SceneOperation) -> SceneOperationPayload {
+        SceneOperationPayload {
+            op_id: Uuid::new_v4().to_string(),
+            client_id: client_id.to_string(),
+            timestamp,
+            operation,
+        }
+    }
+
+    fn rename(component_id: &str, name: &str) -> SceneOperation {
+        SceneOperation::Rename {
+            component_id: component_id.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    fn position(component_id: &str, value: [f32; 3]) -> SceneOperation {
+        SceneOperation::Transform {
+            component_id: component_id.to_string(),
+            property: TransformProperty::Position,
+            value,
+        }
+    }
+
+    #[test]
+    fn newer_writes_win_and_older_ones_lose() {
+        let component_id = Uuid::new_v4().to_string();
+
+        assert!(accept_remote(&remote(
+            "a",
+            100,
+            rename(&component_id, "first")
+        )));
+        assert!(accept_remote(&remote(
+            "b",
+            200,
+            rename(&component_id, "second")
+        )));
+        assert!(!accept_remote(&remote(
+            "a",
+            150,
+            rename(&component_id, "stale")
+        )));
+    }
+
+    #[test]
+    fn equal_timestamps_are_broken_by_client_id() {
+        let component_id = Uuid::new_v4().to_string();
+
+        assert!(accept_remote(&remote("b", 100, rename(&component_id, "b"))));
+        assert!(!accept_remote(&remote(
+            "a",
+            100,
+            rename(&component_id, "a")
+        )));
+        assert!(accept_remote(&remote("c", 100, rename(&component_id, "c"))));
+    }
+
+    #[test]
+    fn repeated_operations_are_dropped() {
+        let component_id = Uuid::new_v4().to_string();
+        let payload = remote("a", 100, rename(&component_id, "once"));
+
+        assert!(accept_remote(&payload));
+        assert!(!accept_remote(&payload));
+    }
+
+    #[test]
+    fn own_echoes_are_dropped() {
+        let component_id = Uuid::new_v4().to_string();
+
+        assert!(!accept_remote(&remote(
+            &CLIENT_ID,
+            i64::MAX,
+            rename(&component_id, "echo")
+        )));
+    }
+
+    #[test]
+    fn fields_are_resolved_independently() {
+        let component_id = Uuid::new_v4().to_string();
+
+        assert!(accept_remote(&remote(
+            "a",
+            200,
+            rename(&component_id, "named")
+        )));
+        assert!(accept_remote(&remote(
+            "b",
+            100,
+            position(&component_id, [1.0, 2.0, 3.0])
+        )));
+        assert!(!accept_remote(&remote(
+            "b",
+            150,
+            rename(&component_id, "older")
+        )));
+    }
+
+    #[test]
+    fn adding_and_removing_share_a_clock() {
+        let component_id = Uuid::new_v4().to_string();
+        let remove = SceneOperation::Remove {
+            component_id: component_id.clone(),
+        };
+
+        assert!(accept_remote(&remote("a", 200, remove.clone())));
+        assert!(!accept_remote(&remote("b", 100, remove)));
+    }
+
+    #[test]
+    fn local_edits_beat_older_remote_ones() {
+        let component_id = Uuid::new_v4().to_string();
+        let message = local_operation(rename(&component_id, "local"));
+        let payload = parse_scene_operation(&message).expect("Couldn't parse own operation");
+
+        assert_eq!(payload.client_id, *CLIENT_ID);
+        assert!(!accept_remote(&remote(
+            "a",
+            payload.timestamp - 1,
+            rename(&component_id, "older")
+        )));
+        assert!(accept_remote(&remote(
+            "a",
+            payload.timestamp + 1,
+            rename(&component_id, "newer")
+        )));
+    }
+
+    #[test]
+    fn other_events_are_not_scene_operations() {
+        assert!(parse_scene_operation("{\"event\":\"chat\",\"payload\":{}}").is_none());
+        assert!(parse_scene_operation("not json").is_none());
+    }
+}
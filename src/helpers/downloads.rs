@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use directories::BaseDirs;
+use midpoint_engine::helpers::saved_data::{File, SavedState};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+use super::utilities::{get_common_os_dir, hex_digest, sha256_file};
+
+const MAX_CONCURRENT_DOWNLOADS: usize = 3;
+const MAX_ATTEMPTS: u32 = 3;
+// don't flood the UI with an update for every network chunk
+const PROGRESS_STEP_BYTES: u64 = 256 * 1024;
+
+static DOWNLOAD_SLOTS: Lazy<Arc<Semaphore>> =
+    Lazy::new(|| Arc::new(Semaphore::new(MAX_CONCURRENT_DOWNLOADS)));
+
+// keyed by the asset's path in the project folder
+static DOWNLOADS: Lazy<Mutex<HashMap<PathBuf, DownloadEntry>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// the cache index is shared by every download task
+static CACHE_INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadStatus {
+    Missing,
+    Queued,
+    Progress { received: u64, total: Option<u64> },
+    Ready,
+    Failed(String),
+}
+
+#[derive(Default)]
+struct DownloadEntry {
+    active: bool,
+    waiters: Vec<UnboundedSender<DownloadStatus>>,
+}
+
+/// Maps a download URL to the SHA-256 of its content, which names the file in the cache
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CacheIndex {
+    pub urls: HashMap<String, String>,
+}
+
+/// The platform's cache folder for midpoint, kept out of the synced CommonOS folder
+pub fn get_cache_dir() -> PathBuf {
+    let base_dirs = BaseDirs::new().expect("Couldn't find the user's directories");
+    base_dirs.cache_dir().join("midpoint")
+}
+
+fn get_cache_index_path() -> PathBuf {
+    get_cache_dir().join("index.json")
+}
+
+fn get_cached_asset_path(hash: &str) -> PathBuf {
+    get_cache_dir().join("assets").join(hash)
+}
+
+fn load_cache_index() -> CacheIndex {
+    match fs::read_to_string(get_cache_index_path()) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_default(),
+        Err(_) => CacheIndex::default(),
+    }
+}
+
+fn update_cache_index(url: &str, hash: Option<String>) -> Result<(), String> {
+    let _guard = CACHE_INDEX_LOCK.lock().unwrap();
+
+    let mut index = load_cache_index();
+    match hash {
+        Some(hash) => index.urls.insert(url.to_string(), hash),
+        None => index.urls.remove(url),
+    };
+
+    let json = serde_json::to_string_pretty(&index)
+        .map_err(|e| format!("Couldn't serialize cache index: {}", e))?;
+    fs::create_dir_all(get_cache_dir())
+        .map_err(|e| format!("Couldn't create cache directory: {}", e))?;
+    fs::write(get_cache_index_path(), json)
+        .map_err(|e| format!("Couldn't write cache index: {}", e))
+}
+
+pub fn get_asset_path(image_path: &str, filename: &str) -> PathBuf {
+    let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
+    sync_dir.join(image_path).join(filename)
+}
+
+pub fn get_download_status(asset_path: &Path) -> DownloadStatus {
+    if asset_path.exists() {
+        return DownloadStatus::Ready;
+    }
+
+    match DOWNLOADS.lock().unwrap().get(asset_path) {
+        Some(entry) if entry.active => DownloadStatus::Queued,
+        _ => DownloadStatus::Missing,
+    }
+}
+
+/// Sends status updates for the asset until it is ready or fails
+pub fn watch_asset(asset_path: PathBuf, tx: UnboundedSender<DownloadStatus>) {
+    DOWNLOADS
+        .lock()
+        .unwrap()
+        .entry(asset_path)
+        .or_default()
+        .waiters
+        .push(tx);
+}
+
+fn notify(asset_path: &Path, status: DownloadStatus) {
+    let mut downloads = DOWNLOADS.lock().unwrap();
+
+    if let Some(entry) = downloads.get_mut(asset_path) {
+        entry.waiters.retain(|tx| tx.send(status.clone()).is_ok());
+    }
+
+    // finished downloads keep their waiters, the file may be fetched again later
+    if matches!(status, DownloadStatus::Ready | DownloadStatus::Failed(_)) {
+        if let Some(entry) = downloads.get_mut(asset_path) {
+            entry.active = false;
+        }
+    }
+}
+
+async fn fetch_to_cache(url: &str, asset_path: &Path) -> Result<String, String> {
+    let mut response = reqwest::get(url)
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Download request failed: {}", e))?;
+
+    let total = response.content_length();
+
+    let tmp_dir = get_cache_dir().join("tmp");
+    fs::create_dir_all(&tmp_dir).map_err(|e| format!("Couldn't create cache directory: {}", e))?;
+    let tmp_path = tmp_dir.join(Uuid::new_v4().to_string());
+
+    let mut tmp_file =
+        fs::File::create(&tmp_path).map_err(|e| format!("Couldn't create cache file: {}", e))?;
+    let mut hasher = Sha256::new();
+    let mut received = 0u64;
+    let mut last_reported = 0u64;
+
+    loop {
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => {
+                let _ = fs::remove_file(&tmp_path);
+                return Err(format!("Download interrupted: {}", e));
+            }
+        };
+
+        hasher.update(&chunk);
+        tmp_file
+            .write_all(&chunk)
+            .map_err(|e| format!("Couldn't write cache file: {}", e))?;
+        received += chunk.len() as u64;
+
+        if received - last_reported >= PROGRESS_STEP_BYTES {
+            last_reported = received;
+            notify(asset_path, DownloadStatus::Progress { received, total });
+        }
+    }
+
+    drop(tmp_file);
+
+    if let Some(total) = total {
+        if received != total {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(format!(
+                "Download incomplete: {} of {} bytes",
+                received, total
+            ));
+        }
+    }
+
+    let hash = hex_digest(hasher);
+
+    let cached_path = get_cached_asset_path(&hash);
+    fs::create_dir_all(
+        cached_path
+            .parent()
+            .expect("Couldn't get cache assets directory"),
+    )
+    .map_err(|e| format!("Couldn't create cache directory: {}", e))?;
+
+    // the same content may already be cached under another URL
+    if cached_path.exists() {
+        let _ = fs::remove_file(&tmp_path);
+    } else {
+        fs::rename(&tmp_path, &cached_path)
+            .map_err(|e| format!("Couldn't move download into cache: {}", e))?;
+    }
+
+    update_cache_index(url, Some(hash.clone()))?;
+
+    Ok(hash)
+}
+
+/// Returns the cached copy of the URL, if there is one and its content still matches its hash
+fn cached_copy(url: &str) -> Option<PathBuf> {
+    let hash = load_cache_index().urls.get(url).cloned()?;
+    let cached_path = get_cached_asset_path(&hash);
+
+    match sha256_file(&cached_path) {
+        Ok(actual) if actual == hash => Some(cached_path),
+        _ => {
+            println!("Cached copy of {} is missing or corrupt", url);
+            let _ = fs::remove_file(&cached_path);
+            if let Err(e) = update_cache_index(url, None) {
+                println!("{}", e);
+            }
+            None
+        }
+    }
+}
+
+async fn download_asset(url: String, asset_path: PathBuf) -> Result<(), String> {
+    let _permit = DOWNLOAD_SLOTS
+        .clone()
+        .acquire_owned()
+        .await
+        .map_err(|e| format!("Download slots closed: {}", e))?;
+
+    let cached_path = match cached_copy(&url) {
+        Some(cached_path) => cached_path,
+        None => {
+            let mut attempt = 0;
+            loop {
+                attempt += 1;
+                match fetch_to_cache(&url, &asset_path).await {
+                    Ok(hash) => break get_cached_asset_path(&hash),
+                    Err(e) if attempt < MAX_ATTEMPTS => {
+                        println!("Download of {} failed (attempt {}): {}", url, attempt, e);
+                        tokio::time::sleep(Duration::from_secs(2u64.pow(attempt))).await;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    };
+
+    let asset_dir = asset_path.parent().expect("Couldn't get asset directory");
+    fs::create_dir_all(asset_dir).map_err(|e| format!("Couldn't create asset directory: {}", e))?;
+
+    // copied beside the asset then moved into place, so a half-copied file is never seen.
+    // The cache can be on another drive, where a rename from it wouldn't work
+    let tmp_path = asset_dir.join(format!(".{}.tmp", Uuid::new_v4()));
+    fs::copy(&cached_path, &tmp_path)
+        .map_err(|e| format!("Couldn't copy asset from cache: {}", e))?;
+    if let Err(e) = fs::rename(&tmp_path, &asset_path) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(format!("Couldn't move asset into place: {}", e));
+    }
+
+    Ok(())
+}
+
+/// Downloads the file from its `cloudfrontUrl` if it isn't in the project folder yet
+pub fn spawn_download(file: File) {
+    let asset_path = get_asset_path(&file.normalFilePath, &file.fileName);
+
+    if asset_path.exists() || file.cloudfrontUrl.is_empty() {
+        return;
+    }
+
+    {
+        let mut downloads = DOWNLOADS.lock().unwrap();
+        let entry = downloads.entry(asset_path.clone()).or_default();
+        if entry.active {
+            return;
+        }
+        entry.active = true;
+    }
+
+    notify(&asset_path, DownloadStatus::Queued);
+
+    tokio::runtime::Handle::current().spawn(async move {
+        match download_asset(file.cloudfrontUrl.clone(), asset_path.clone()).await {
+            Ok(()) => {
+                println!("Downloaded {}", file.fileName);
+                notify(&asset_path, DownloadStatus::Ready);
+            }
+            Err(e) => {
+                println!("Couldn't download {}: {}", file.fileName, e);
+                notify(&asset_path, DownloadStatus::Failed(e));
+            }
+        }
+    });
+}
+
+/// Fetches every asset the project references but doesn't have on disk
+pub fn queue_missing_downloads(saved_state: &SavedState) {
+    let mut files: Vec<&File> = Vec::new();
+
+    files.extend(saved_state.concepts.iter());
+    files.extend(saved_state.models.iter());
+    if let Some(textures) = saved_state.textures.as_ref() {
+        files.extend(textures.iter());
+    }
+    if let Some(landscapes) = saved_state.landscapes.as_ref() {
+        for landscape in landscapes.iter() {
+            files.extend(landscape.heightmap.iter());
+            files.extend(landscape.rockmap.iter());
+            files.extend(landscape.soil.iter());
+        }
+    }
+
+    for file in files {
+        spawn_download(file.clone());
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+use crate::gql::backendVersion::backend_version;
+
+use super::utilities::get_project_dir;
+
+pub const ASPECT_RATIOS: [&str; 5] = ["1:1", "4:3", "3:4", "16:9", "9:16"];
+pub const STYLE_PRESETS: [&str; 6] = [
+    "none",
+    "photographic",
+    "concept-art",
+    "anime",
+    "low-poly",
+    "pixel-art",
+];
+pub const DEFAULT_RESOLUTION: i32 = 1024;
+pub const MAX_VARIATIONS: i32 = 4;
+
+// asked until the backend answers, then kept for the session
+static BACKEND_VERSION: OnceCell<String> = OnceCell::const_new();
+
+/// Everything besides the prompt that shapes a concept or texture generation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationOptions {
+    pub negative_prompt: Option<String>,
+    pub seed: Option<i32>,
+    // length of the longest edge in pixels
+    pub resolution: Option<i32>,
+    pub aspect_ratio: Option<String>,
+    pub style_preset: Option<String>,
+    pub variations: Option<i32>,
+    // set when generating variations of an existing image
+    pub source_image_url: Option<String>,
+}
+
+impl GenerationOptions {
+    /// Fills in anything left to chance, so retries and reruns give the same output
+    pub fn resolved(&self) -> GenerationOptions {
+        GenerationOptions {
+            seed: Some(self.seed.unwrap_or_else(random_seed)),
+            resolution: Some(self.resolution.unwrap_or(DEFAULT_RESOLUTION)),
+            aspect_ratio: Some(
+                self.aspect_ratio
+                    .clone()
+                    .unwrap_or_else(|| ASPECT_RATIOS[0].to_string()),
+            ),
+            variations: Some(self.variations.unwrap_or(1).clamp(1, MAX_VARIATIONS)),
+            ..self.clone()
+        }
+    }
+
+    /// One set of single-result options per variation, each carrying the seed it's made with,
+    /// so what's sent for an output is exactly what's recorded to reproduce it
+    pub fn variations(&self) -> Vec<GenerationOptions> {
+        let resolved = self.resolved();
+        let seed = resolved.seed.unwrap_or_default();
+
+        (0..resolved.variations.unwrap_or(1))
+            .map(|index| GenerationOptions {
+                seed: Some(seed.wrapping_add(index)),
+                variations: Some(1),
+                ..resolved.clone()
+            })
+            .collect()
+    }
+
+    /// The variations after the first `done`, each keeping the seed it would have had,
+    /// so a request that stopped early can be finished later
+    pub fn remaining_variations(&self, done: usize) -> GenerationOptions {
+        let resolved = self.resolved();
+        let done = done as i32;
+
+        GenerationOptions {
+            seed: Some(resolved.seed.unwrap_or_default().wrapping_add(done)),
+            variations: Some((resolved.variations.unwrap_or(1) - done).max(1)),
+            ..resolved
+        }
+    }
+}
+
+/// Stored per generated file so the same output can be reproduced later, and searched by
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenerationRecord {
+    pub prompt: String,
+    pub options: GenerationOptions,
+    // when the file was saved
+    pub created: String,
+    // when the request was first made, earlier than created if it waited in the outbox
+    #[serde(default)]
+    pub requested: Option<String>,
+    // the concept a model was generated from
+    #[serde(default)]
+    pub source_concept: Option<String>,
+    #[serde(default)]
+    pub backend_version: Option<String>,
+    #[serde(default)]
+    pub editor_version: Option<String>,
+}
+
+impl GenerationRecord {
+    pub fn new(
+        prompt: &str,
+        options: GenerationOptions,
+        requested: &str,
+        backend_version: Option<String>,
+    ) -> GenerationRecord {
+        GenerationRecord {
+            prompt: prompt.to_string(),
+            options,
+            created: Local::now().to_rfc3339(),
+            requested: Some(requested.to_string()),
+            source_concept: None,
+            backend_version,
+            editor_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        }
+    }
+
+    /// Case-insensitive match against the prompts, style and source concept
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return true;
+        }
+
+        [
+            Some(&self.prompt),
+            self.options.negative_prompt.as_ref(),
+            self.options.style_preset.as_ref(),
+            self.source_concept.as_ref(),
+        ]
+        .iter()
+        .flatten()
+        .any(|text| text.to_lowercase().contains(&query))
+    }
+}
+
+pub fn random_seed() -> i32 {
+    (Uuid::new_v4().as_u128() as u32 >> 1) as i32
+}
+
+/// Width and height for a resolution (longest edge) and an aspect ratio like "16:9"
+pub fn output_dimensions(resolution: i32, aspect_ratio: &str) -> (u32, u32) {
+    let (w, h) = aspect_ratio
+        .split_once(':')
+        .and_then(|(w, h)| Some((w.trim().parse::<f32>().ok()?, h.trim().parse::<f32>().ok()?)))
+        .filter(|(w, h)| *w > 0.0 && *h > 0.0)
+        .unwrap_or((1.0, 1.0));
+
+    let resolution = resolution.max(1) as f32;
+
+    if w >= h {
+        (
+            resolution as u32,
+            (resolution * h / w).round().max(1.0) as u32,
+        )
+    } else {
+        (
+            (resolution * w / h).round().max(1.0) as u32,
+            resolution as u32,
+        )
+    }
+}
+
+pub fn get_generations_path(project_id: &str) -> PathBuf {
+    get_project_dir(project_id).join("generations.json")
+}
+
+// keyed by "{kind directory}/{file name}", since the file id only arrives later over the WebSocket
+fn generation_key(kind_dir: &str, file_name: &str) -> String {
+    format!("{}/{}", kind_dir, file_name)
+}
+
+pub fn load_generations(project_id: &str) -> HashMap<String, GenerationRecord> {
+    match fs::read_to_string(get_generations_path(project_id)) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            println!("Couldn't parse generations: {:?}", e);
+            HashMap::new()
+        }),
+        Err(_) => HashMap::new(),
+    }
+}
+
+pub fn record_generation(
+    project_id: &str,
+    kind_dir: &str,
+    file_name: &str,
+    record: GenerationRecord,
+) {
+    let mut generations = load_generations(project_id);
+
+    generations.insert(generation_key(kind_dir, file_name), record);
+
+    let json = serde_json::to_string_pretty(&generations).expect("Couldn't serialize generations");
+    fs::write(get_generations_path(project_id), json).expect("Couldn't write generations");
+}
+
+/// Moves the record along with a renamed file, or drops it when the file is deleted
+pub fn rename_generation(
+    project_id: &str,
+    kind_dir: &str,
+    file_name: &str,
+    new_name: Option<&str>,
+) {
+    let mut generations = load_generations(project_id);
+
+    let record = match generations.remove(&generation_key(kind_dir, file_name)) {
+        Some(record) => record,
+        None => return,
+    };
+    if let Some(new_name) = new_name {
+        generations.insert(generation_key(kind_dir, new_name), record);
+    }
+
+    let json = serde_json::to_string_pretty(&generations).expect("Couldn't serialize generations");
+    fs::write(get_generations_path(project_id), json).expect("Couldn't write generations");
+}
+
+pub fn find_generation(
+    project_id: &str,
+    kind_dir: &str,
+    file_name: &str,
+) -> Option<GenerationRecord> {
+    load_generations(project_id).remove(&generation_key(kind_dir, file_name))
+}
+
+/// Records matching the query, newest first, with their kind directory and file name
+pub fn search_generations(
+    project_id: &str,
+    query: &str,
+) -> Vec<(String, String, GenerationRecord)> {
+    let mut results: Vec<(String, String, GenerationRecord)> = load_generations(project_id)
+        .into_iter()
+        .filter(|(key, record)| {
+            record.matches(query) || key.to_lowercase().contains(&query.trim().to_lowercase())
+        })
+        .filter_map(|(key, record)| {
+            let (kind_dir, file_name) = key.split_once('/')?;
+            Some((kind_dir.to_string(), file_name.to_string(), record))
+        })
+        .collect();
+    results.sort_by(|a, b| b.2.created.cmp(&a.2.created));
+
+    results
+}
+
+/// Asks the backend which version it runs, once per session. A failed lookup isn't kept,
+/// so the next generation asks again.
+pub async fn get_backend_version() -> Option<String> {
+    let version = BACKEND_VERSION
+        .get_or_try_init(|| async { backend_version().await.map(|data| data.backendVersion) })
+        .await;
+
+    match version {
+        Ok(version) => Some(version.clone()),
+        Err(e) => {
+            println!("Couldn't get backend version: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remaining_variations_keep_their_seeds() {
+        let options = GenerationOptions {
+            seed: Some(40),
+            variations: Some(4),
+            ..Default::default()
+        };
+
+        let remaining = options.remaining_variations(1);
+        let seeds: Vec<Option<i32>> = remaining.variations().iter().map(|v| v.seed).collect();
+        let expected: Vec<Option<i32>> = options.variations()[1..].iter().map(|v| v.seed).collect();
+
+        assert_eq!(remaining.variations, Some(3));
+        assert_eq!(seeds, expected);
+    }
+}
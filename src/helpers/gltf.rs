@@ -0,0 +1,328 @@
+use nalgebra::{Matrix4, Quaternion, UnitQuaternion, Vector3};
+use serde_json::Value;
+
+// accessor component types
+pub const UNSIGNED_BYTE: u64 = 5121;
+pub const UNSIGNED_SHORT: u64 = 5123;
+pub const UNSIGNED_INT: u64 = 5125;
+pub const FLOAT: u64 = 5126;
+
+// primitive mode
+pub const TRIANGLES: u64 = 4;
+
+/// Splits a GLB into its JSON and binary chunks
+pub fn read_glb(bytes: &[u8]) -> Result<(Value, Option<Vec<u8>>), String> {
+    let read_u32 = |offset: usize| -> Result<usize, String> {
+        bytes
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .ok_or("GLB is truncated".to_string())
+    };
+
+    if !bytes.starts_with(b"glTF") || read_u32(4)? != 2 {
+        return Err("Only glTF 2.0 GLB files can be imported".to_string());
+    }
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+
+    while offset + 8 <= bytes.len() {
+        let length = read_u32(offset)?;
+        let chunk_type = &bytes[offset + 4..offset + 8];
+        let chunk = bytes
+            .get(offset + 8..offset + 8 + length)
+            .ok_or("GLB chunk is truncated".to_string())?;
+
+        match chunk_type {
+            b"JSON" => {
+                json = Some(
+                    serde_json::from_slice(chunk)
+                        .map_err(|e| format!("Couldn't parse GLB JSON: {}", e))?,
+                )
+            }
+            b"BIN\0" => bin = Some(chunk.to_vec()),
+            _ => {}
+        }
+
+        offset += 8 + length;
+    }
+
+    Ok((json.ok_or("GLB has no JSON chunk".to_string())?, bin))
+}
+
+pub fn write_glb(json: &Value, mut bin: Vec<u8>) -> Result<Vec<u8>, String> {
+    let mut json_bytes =
+        serde_json::to_vec(json).map_err(|e| format!("Couldn't serialize glTF: {}", e))?;
+    while json_bytes.len() % 4 != 0 {
+        json_bytes.push(b' ');
+    }
+    while bin.len() % 4 != 0 {
+        bin.push(0);
+    }
+
+    let total_length = 12 + 8 + json_bytes.len() + 8 + bin.len();
+
+    let mut glb: Vec<u8> = Vec::with_capacity(total_length);
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(total_length as u32).to_le_bytes());
+    glb.extend_from_slice(&(json_bytes.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json_bytes);
+    glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"BIN\0");
+    glb.extend_from_slice(&bin);
+
+    Ok(glb)
+}
+
+pub fn read_floats(value: &Value) -> Vec<f32> {
+    value
+        .as_array()
+        .map(|values| {
+            values
+                .iter()
+                .map(|v| v.as_f64().unwrap_or_default() as f32)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The node's transform relative to its parent
+pub fn node_matrix(node: &Value) -> Matrix4<f32> {
+    let matrix = read_floats(&node["matrix"]);
+    if matrix.len() == 16 {
+        return Matrix4::from_column_slice(&matrix);
+    }
+
+    let translation = match read_floats(&node["translation"]).as_slice() {
+        [x, y, z] => Vector3::new(*x, *y, *z),
+        _ => Vector3::zeros(),
+    };
+    let rotation = match read_floats(&node["rotation"]).as_slice() {
+        [x, y, z, w] => UnitQuaternion::new_normalize(Quaternion::new(*w, *x, *y, *z)),
+        _ => UnitQuaternion::identity(),
+    };
+    let scale = match read_floats(&node["scale"]).as_slice() {
+        [x, y, z] => Vector3::new(*x, *y, *z),
+        _ => Vector3::new(1.0, 1.0, 1.0),
+    };
+
+    Matrix4::new_translation(&translation)
+        * rotation.to_homogeneous()
+        * Matrix4::new_nonuniform_scaling(&scale)
+}
+
+/// The top-level nodes of the default scene
+pub fn scene_roots(json: &Value) -> Vec<usize> {
+    let scene = json.get("scene").and_then(|s| s.as_u64()).unwrap_or(0) as usize;
+
+    match json["scenes"][scene]["nodes"].as_array() {
+        Some(nodes) => nodes
+            .iter()
+            .filter_map(|n| n.as_u64())
+            .map(|n| n as usize)
+            .collect(),
+        // without scenes every node that isn't a child is drawn
+        None => {
+            let nodes = json["nodes"].as_array().cloned().unwrap_or_default();
+            let children: Vec<u64> = nodes
+                .iter()
+                .flat_map(|n| n["children"].as_array().cloned().unwrap_or_default())
+                .filter_map(|c| c.as_u64())
+                .collect();
+            (0..nodes.len())
+                .filter(|n| !children.contains(&(*n as u64)))
+                .collect()
+        }
+    }
+}
+
+/// Calls `visit` with every mesh of the default scene and the world transform it's drawn with
+pub fn visit_scene_meshes(
+    json: &Value,
+    mut visit: impl FnMut(usize, &Matrix4<f32>) -> Result<(), String>,
+) -> Result<(), String> {
+    fn visit_node(
+        json: &Value,
+        node: usize,
+        parent: &Matrix4<f32>,
+        depth: usize,
+        visit: &mut dyn FnMut(usize, &Matrix4<f32>) -> Result<(), String>,
+    ) -> Result<(), String> {
+        // a malformed file could have nodes that contain each other
+        if depth > 64 {
+            return Err("Model nodes are nested too deeply".to_string());
+        }
+
+        let node_json = &json["nodes"][node];
+        let world = parent * node_matrix(node_json);
+
+        if let Some(mesh) = node_json.get("mesh").and_then(|m| m.as_u64()) {
+            visit(mesh as usize, &world)?;
+        }
+        for child in node_json["children"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+        {
+            if let Some(child) = child.as_u64() {
+                visit_node(json, child as usize, &world, depth + 1, visit)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    for root in scene_roots(json) {
+        visit_node(json, root, &Matrix4::identity(), 0, &mut visit)?;
+    }
+
+    Ok(())
+}
+
+/// Where an accessor's elements sit in the binary chunk
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccessorLayout {
+    pub offset: usize,
+    pub stride: usize,
+    pub count: usize,
+    // bytes in one element, also the tightly packed stride
+    pub element_size: usize,
+    pub component_type: u64,
+}
+
+pub fn accessor_layout(
+    json: &Value,
+    accessor: usize,
+    bin: &[u8],
+) -> Result<AccessorLayout, String> {
+    let accessor = &json["accessors"][accessor];
+
+    if accessor.get("sparse").is_some() {
+        return Err("Models with sparse accessors aren't supported".to_string());
+    }
+    let view_index = accessor
+        .get("bufferView")
+        .and_then(|v| v.as_u64())
+        .ok_or("Compressed models aren't supported".to_string())?;
+    let view = &json["bufferViews"][view_index as usize];
+
+    if view.get("buffer").and_then(|b| b.as_u64()).unwrap_or(0) != 0 {
+        return Err("Only self-contained GLB models are supported".to_string());
+    }
+
+    let component_type = accessor
+        .get("componentType")
+        .and_then(|c| c.as_u64())
+        .unwrap_or(FLOAT);
+    let component_size = match component_type {
+        UNSIGNED_INT | FLOAT => 4,
+        UNSIGNED_SHORT | 5122 => 2,
+        _ => 1,
+    };
+    let components = match accessor.get("type").and_then(|t| t.as_str()) {
+        Some("SCALAR") => 1,
+        Some("VEC2") => 2,
+        Some("VEC4") | Some("MAT2") => 4,
+        Some("MAT3") => 9,
+        Some("MAT4") => 16,
+        _ => 3,
+    };
+    let element_size = component_size * components;
+
+    let offset = view.get("byteOffset").and_then(|o| o.as_u64()).unwrap_or(0)
+        + accessor
+            .get("byteOffset")
+            .and_then(|o| o.as_u64())
+            .unwrap_or(0);
+    let stride = view
+        .get("byteStride")
+        .and_then(|s| s.as_u64())
+        .unwrap_or(element_size as u64);
+    let count = accessor.get("count").and_then(|c| c.as_u64()).unwrap_or(0);
+
+    let layout = AccessorLayout {
+        offset: offset as usize,
+        stride: stride as usize,
+        count: count as usize,
+        element_size,
+        component_type,
+    };
+    if layout.count > 0
+        && layout.offset + layout.stride * (layout.count - 1) + layout.element_size > bin.len()
+    {
+        return Err("Model accessor runs past the end of its buffer".to_string());
+    }
+
+    Ok(layout)
+}
+
+pub fn read_vector(bin: &[u8], at: usize) -> [f32; 3] {
+    let read = |at: usize| f32::from_le_bytes([bin[at], bin[at + 1], bin[at + 2], bin[at + 3]]);
+    [read(at), read(at + 4), read(at + 8)]
+}
+
+pub fn write_vector(bin: &mut [u8], at: usize, value: [f32; 3]) {
+    for (axis, component) in value.iter().enumerate() {
+        bin[at + axis * 4..at + axis * 4 + 4].copy_from_slice(&component.to_le_bytes());
+    }
+}
+
+/// The float vectors of a POSITION, NORMAL or TANGENT accessor, only their first three components
+pub fn read_vectors(json: &Value, bin: &[u8], accessor: usize) -> Result<Vec<[f32; 3]>, String> {
+    let layout = accessor_layout(json, accessor, bin)?;
+    if layout.component_type != FLOAT || layout.element_size < 12 {
+        return Err("Models with quantized vertices aren't supported".to_string());
+    }
+
+    Ok((0..layout.count)
+        .map(|i| read_vector(bin, layout.offset + layout.stride * i))
+        .collect())
+}
+
+/// The coordinates of a TEXCOORD accessor, as floats whatever they were stored as
+pub fn read_texcoords(json: &Value, bin: &[u8], accessor: usize) -> Result<Vec<[f32; 2]>, String> {
+    let layout = accessor_layout(json, accessor, bin)?;
+
+    let read = |at: usize| -> f32 {
+        match layout.component_type {
+            FLOAT => f32::from_le_bytes([bin[at], bin[at + 1], bin[at + 2], bin[at + 3]]),
+            // integer coordinates are always normalized
+            UNSIGNED_SHORT => u16::from_le_bytes([bin[at], bin[at + 1]]) as f32 / 65535.0,
+            _ => bin[at] as f32 / 255.0,
+        }
+    };
+    let component_size = match layout.component_type {
+        FLOAT => 4,
+        UNSIGNED_SHORT => 2,
+        UNSIGNED_BYTE => 1,
+        _ => return Err("Model has texture coordinates of an unknown type".to_string()),
+    };
+
+    Ok((0..layout.count)
+        .map(|i| {
+            let at = layout.offset + layout.stride * i;
+            [read(at), read(at + component_size)]
+        })
+        .collect())
+}
+
+pub fn read_indices(json: &Value, bin: &[u8], accessor: usize) -> Result<Vec<usize>, String> {
+    let layout = accessor_layout(json, accessor, bin)?;
+    if ![UNSIGNED_BYTE, UNSIGNED_SHORT, UNSIGNED_INT].contains(&layout.component_type) {
+        return Err("Model has indices of an unknown type".to_string());
+    }
+
+    Ok((0..layout.count)
+        .map(|i| {
+            let at = layout.offset + layout.stride * i;
+            match layout.component_type {
+                UNSIGNED_BYTE => bin[at] as usize,
+                UNSIGNED_SHORT => u16::from_le_bytes([bin[at], bin[at + 1]]) as usize,
+                _ => u32::from_le_bytes([bin[at], bin[at + 1], bin[at + 2], bin[at + 3]]) as usize,
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,117 @@
+use std::io::Cursor;
+use std::path::Path;
+
+use image::{DynamicImage, GrayImage, ImageBuffer, ImageFormat, Luma};
+
+/// Heights in 0..1, row by row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightfield {
+    pub width: u32,
+    pub height: u32,
+    pub heights: Vec<f32>,
+}
+
+impl Heightfield {
+    // 8-bit images are widened, so both fill 0..1
+    pub fn from_image(image: &DynamicImage) -> Heightfield {
+        let image = image.to_luma16();
+
+        Heightfield {
+            width: image.width(),
+            height: image.height(),
+            heights: image.pixels().map(|p| p[0] as f32 / 65535.0).collect(),
+        }
+    }
+
+    /// Every nth height, so the longest edge is at most `max_size`
+    pub fn downsample(&self, max_size: u32) -> Heightfield {
+        let max_size = max_size.max(1);
+        let step = ((self.width.max(self.height) + max_size - 1) / max_size).max(1);
+        let (width, height) = (
+            (self.width + step - 1) / step,
+            (self.height + step - 1) / step,
+        );
+
+        Heightfield {
+            width,
+            height,
+            heights: (0..width * height)
+                .map(|i| {
+                    let (x, y) = ((i % width) * step, (i / width) * step);
+                    self.heights[(y * self.width + x) as usize]
+                })
+                .collect(),
+        }
+    }
+
+    /// Lowest point at 0 and highest at 1, so the whole range of the heightmap is used.
+    /// Returns the lowest and highest heights it had.
+    pub fn stretch(&mut self) -> [f32; 2] {
+        let (min, max) = self
+            .heights
+            .iter()
+            .filter(|h| h.is_finite())
+            .fold((f32::MAX, f32::MIN), |(min, max), h| {
+                (min.min(*h), max.max(*h))
+            });
+
+        for h in self.heights.iter_mut() {
+            *h = if h.is_finite() && max > min {
+                (*h - min) / (max - min)
+            } else {
+                0.0
+            };
+        }
+
+        if max >= min {
+            [min, max]
+        } else {
+            [0.0, 0.0]
+        }
+    }
+
+    // values outside 0..1 are cut off
+    pub fn clamp(&mut self) {
+        for h in self.heights.iter_mut() {
+            *h = if h.is_finite() {
+                h.clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+        }
+    }
+
+    /// Encoded as a 16-bit grayscale PNG, which keeps the detail 8 bits would band
+    pub fn to_png(&self) -> Result<Vec<u8>, String> {
+        let pixels: Vec<u16> = self
+            .heights
+            .iter()
+            .map(|h| (h.clamp(0.0, 1.0) * 65535.0).round() as u16)
+            .collect();
+        let image = ImageBuffer::<Luma<u16>, Vec<u16>>::from_raw(self.width, self.height, pixels)
+            .ok_or("Heightmap size doesn't match its heights".to_string())?;
+
+        let mut bytes = Vec::new();
+        DynamicImage::ImageLuma16(image)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .map_err(|e| format!("Couldn't encode heightmap: {}", e))?;
+
+        Ok(bytes)
+    }
+}
+
+/// Reads a heightmap the project already has, like the 16-bit PNGs imports are stored as
+pub fn load_heightfield(path: &Path) -> Result<Heightfield, String> {
+    let image =
+        image::open(path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+
+    Ok(Heightfield::from_image(&image))
+}
+
+pub fn encode_mask(mask: GrayImage) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    DynamicImage::ImageLuma8(mask)
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .map_err(|e| format!("Couldn't encode landscape mask: {}", e))?;
+    Ok(bytes)
+}
@@ -0,0 +1,361 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use midpoint_engine::helpers::saved_data::LandscapeData;
+use tiff::decoder::{Decoder, DecodingResult};
+
+use crate::editor_state::StateHelper;
+
+use super::asset_store::detect_mime;
+use super::heightfield::{encode_mask, Heightfield};
+use super::imports::store_and_register;
+use super::landscapes::{
+    add_landscape, landscape_masks, place_landscape, save_landscape_files, LandscapeScale,
+    MaskParams,
+};
+
+pub const NORMALIZATIONS: [&str; 2] = ["Stretch", "Keep"];
+pub const BYTE_ORDERS: [&str; 2] = ["Little Endian", "Big Endian"];
+
+const RAW_EXTENSIONS: [&str; 2] = ["raw", "r16"];
+
+/// How a heightmap is read and laid out
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeightmapImportOptions {
+    // RAW files have no header, a missing side is worked out from the file's length
+    pub raw_width: Option<u32>,
+    pub raw_height: Option<u32>,
+    pub big_endian: bool,
+    pub stretch: bool,
+    pub scale: LandscapeScale,
+}
+
+impl Default for HeightmapImportOptions {
+    fn default() -> Self {
+        HeightmapImportOptions {
+            raw_width: None,
+            raw_height: None,
+            big_endian: false,
+            stretch: true,
+            scale: LandscapeScale::default(),
+        }
+    }
+}
+
+impl HeightmapImportOptions {
+    /// Reads the import form, blank fields keep their defaults
+    pub fn from_choices(
+        raw_width: &str,
+        raw_height: &str,
+        byte_order: &str,
+        normalization: &str,
+        world_scale: &str,
+        vertical_scale: &str,
+    ) -> HeightmapImportOptions {
+        let positive = |text: &str| text.trim().parse::<f32>().ok().filter(|v| *v > 0.0);
+
+        HeightmapImportOptions {
+            raw_width: raw_width.trim().parse().ok().filter(|w| *w > 0),
+            raw_height: raw_height.trim().parse().ok().filter(|h| *h > 0),
+            big_endian: byte_order == "Big Endian",
+            stretch: normalization == "Stretch",
+            scale: LandscapeScale {
+                horizontal: positive(world_scale).unwrap_or(1.0),
+                vertical: positive(vertical_scale).unwrap_or(1.0),
+                range: None,
+            },
+        }
+    }
+}
+
+fn read_png(bytes: &[u8]) -> Result<Heightfield, String> {
+    let image = image::load_from_memory(bytes).map_err(|e| format!("Couldn't read PNG: {}", e))?;
+
+    Ok(Heightfield::from_image(&image))
+}
+
+// whether the samples are floats, which hold heights in the file's own units
+fn read_tiff(bytes: &[u8]) -> Result<(Heightfield, bool), String> {
+    let mut decoder =
+        Decoder::new(Cursor::new(bytes)).map_err(|e| format!("Couldn't read TIFF: {}", e))?;
+    let (width, height) = decoder
+        .dimensions()
+        .map_err(|e| format!("Couldn't read TIFF: {}", e))?;
+
+    let (samples, float): (Vec<f32>, bool) = match decoder
+        .read_image()
+        .map_err(|e| format!("Couldn't read TIFF: {}", e))?
+    {
+        DecodingResult::F32(samples) => (samples, true),
+        DecodingResult::F64(samples) => (samples.into_iter().map(|s| s as f32).collect(), true),
+        DecodingResult::U16(samples) => (
+            samples.into_iter().map(|s| s as f32 / 65535.0).collect(),
+            false,
+        ),
+        DecodingResult::U8(samples) => (
+            samples.into_iter().map(|s| s as f32 / 255.0).collect(),
+            false,
+        ),
+        _ => return Err("Only float, 16-bit and 8-bit TIFFs can be imported".to_string()),
+    };
+
+    // color TIFFs only give their first channel
+    let channels = samples.len() / (width as usize * height as usize).max(1);
+    if channels == 0 {
+        return Err("TIFF has fewer samples than pixels".to_string());
+    }
+
+    Ok((
+        Heightfield {
+            width,
+            height,
+            heights: samples.into_iter().step_by(channels).collect(),
+        },
+        float,
+    ))
+}
+
+fn read_raw(bytes: &[u8], options: &HeightmapImportOptions) -> Result<Heightfield, String> {
+    let count = (bytes.len() / 2) as u32;
+    let (width, height) = match (options.raw_width, options.raw_height) {
+        (Some(width), Some(height)) => (width, height),
+        (Some(width), None) => (width, count / width),
+        (None, Some(height)) => (count / height, height),
+        // most RAW heightmaps are square
+        (None, None) => {
+            let side = (count as f64).sqrt() as u32;
+            (side, side)
+        }
+    };
+
+    if width as usize * height as usize * 2 != bytes.len() {
+        return Err(format!(
+            "{} bytes aren't a {}×{} 16-bit heightmap, check its width and height",
+            bytes.len(),
+            width,
+            height
+        ));
+    }
+
+    let heights = bytes
+        .chunks_exact(2)
+        .map(|pair| {
+            let value = if options.big_endian {
+                u16::from_be_bytes([pair[0], pair[1]])
+            } else {
+                u16::from_le_bytes([pair[0], pair[1]])
+            };
+            value as f32 / 65535.0
+        })
+        .collect();
+
+    Ok(Heightfield {
+        width,
+        height,
+        heights,
+    })
+}
+
+/// Reads a grayscale PNG, a TIFF or a headerless 16-bit RAW/R16 into heights, along with the
+/// scale they're laid out at
+pub fn read_heightmap(
+    path: &Path,
+    bytes: &[u8],
+    options: &HeightmapImportOptions,
+) -> Result<(Heightfield, LandscapeScale), String> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let (mut heightfield, float) = match detect_mime(bytes).map(|(mime, _)| mime) {
+        Some("image/png") => (read_png(bytes)?, false),
+        Some("image/tiff") => read_tiff(bytes)?,
+        _ if RAW_EXTENSIONS.contains(&extension.as_str()) => (read_raw(bytes, options)?, false),
+        _ => return Err("Heightmaps can be PNG, TIFF, RAW or R16 files".to_string()),
+    };
+
+    if heightfield.width < 2 || heightfield.height < 2 {
+        return Err("Heightmap is too small to make a landscape".to_string());
+    }
+
+    let mut scale = options.scale;
+    if options.stretch {
+        heightfield.stretch();
+    } else if float {
+        // float heights can be anything, like meters above sea level, so they're fitted into
+        // 0..1 and the landscape is raised by the span they covered
+        scale.range = Some(heightfield.stretch());
+    } else {
+        heightfield.clamp();
+    }
+
+    Ok((heightfield, scale))
+}
+
+/// Makes a landscape from a heightmap on disk, with rockmap and soil masks worked out from its
+/// slopes, and places it in the scene at the scale it was imported with
+pub fn import_heightmap(
+    state_helper: &Arc<Mutex<StateHelper>>,
+    project_id: &str,
+    path: &str,
+    options: &HeightmapImportOptions,
+) -> Result<LandscapeData, String> {
+    let path = Path::new(path.trim());
+    let name = path
+        .file_stem()
+        .and_then(|n| n.to_str())
+        .ok_or("Enter the path of a heightmap".to_string())?;
+
+    let bytes = fs::read(path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+    let (heightfield, scale) = read_heightmap(path, &bytes, options)?;
+    let (rockmap, soil) = landscape_masks(
+        &heightfield.heights,
+        heightfield.width,
+        heightfield.height,
+        &MaskParams::default(),
+    );
+
+    let heightmap = heightfield.to_png()?;
+    let rockmap = encode_mask(rockmap)?;
+    let soil = encode_mask(soil)?;
+
+    store_and_register(
+        state_helper,
+        || save_landscape_files(project_id, name, &heightmap, &rockmap, &soil),
+        |state_helper, landscape| {
+            add_landscape(state_helper, landscape.clone());
+            if let Err(e) = place_landscape(state_helper, &landscape.id, scale.component_scale()) {
+                println!("Couldn't place {}: {}", name, e);
+            }
+            Ok(landscape)
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_bytes(values: &[u16], big_endian: bool) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|v| {
+                if big_endian {
+                    v.to_be_bytes()
+                } else {
+                    v.to_le_bytes()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn raw_heightmaps_are_assumed_square() {
+        let bytes = raw_bytes(&[0, 65535, 65535, 0], false);
+        let heightfield = read_raw(&bytes, &HeightmapImportOptions::default()).unwrap();
+
+        assert_eq!((heightfield.width, heightfield.height), (2, 2));
+        assert_eq!(heightfield.heights, vec![0.0, 1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn raw_heightmaps_work_out_a_missing_side() {
+        let bytes = raw_bytes(&[0; 6], false);
+        let options = HeightmapImportOptions {
+            raw_width: Some(3),
+            ..HeightmapImportOptions::default()
+        };
+        let heightfield = read_raw(&bytes, &options).unwrap();
+
+        assert_eq!((heightfield.width, heightfield.height), (3, 2));
+    }
+
+    #[test]
+    fn raw_heightmaps_read_either_byte_order() {
+        let little = read_raw(
+            &raw_bytes(&[256, 0, 0, 0], false),
+            &HeightmapImportOptions::default(),
+        )
+        .unwrap();
+        let big = read_raw(
+            &raw_bytes(&[256, 0, 0, 0], true),
+            &HeightmapImportOptions {
+                big_endian: true,
+                ..HeightmapImportOptions::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(little.heights, big.heights);
+        assert_eq!(little.heights[0], 256.0 / 65535.0);
+    }
+
+    #[test]
+    fn raw_heightmaps_must_match_their_size() {
+        let bytes = raw_bytes(&[0; 6], false);
+        let options = HeightmapImportOptions {
+            raw_width: Some(4),
+            raw_height: Some(4),
+            ..HeightmapImportOptions::default()
+        };
+
+        assert!(read_raw(&bytes, &options).is_err());
+        assert!(read_raw(&[0; 7], &HeightmapImportOptions::default()).is_err());
+    }
+
+    fn float_tiff(values: &[f32], width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        tiff::encoder::TiffEncoder::new(Cursor::new(&mut bytes))
+            .unwrap()
+            .write_image::<tiff::encoder::colortype::Gray32Float>(width, height, values)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn kept_float_heights_are_fitted_with_their_range() {
+        let bytes = float_tiff(&[100.0, 300.0, 200.0, 100.0], 2, 2);
+        let options = HeightmapImportOptions {
+            stretch: false,
+            ..HeightmapImportOptions::default()
+        };
+        let (heightfield, scale) =
+            read_heightmap(Path::new("valley.tif"), &bytes, &options).unwrap();
+
+        assert_eq!(heightfield.heights, vec![0.0, 1.0, 0.5, 0.0]);
+        assert_eq!(scale.range, Some([100.0, 300.0]));
+        assert_eq!(scale.component_scale(), [1.0, 200.0, 1.0]);
+    }
+
+    #[test]
+    fn stretched_heights_keep_the_chosen_scale() {
+        let bytes = float_tiff(&[100.0, 300.0, 200.0, 100.0], 2, 2);
+        let (heightfield, scale) = read_heightmap(
+            Path::new("valley.tif"),
+            &bytes,
+            &HeightmapImportOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(heightfield.heights, vec![0.0, 1.0, 0.5, 0.0]);
+        assert_eq!(scale, LandscapeScale::default());
+    }
+
+    #[test]
+    fn kept_integer_heights_are_left_as_they_are() {
+        let bytes = raw_bytes(&[0, 32768, 65535, 0], false);
+        let options = HeightmapImportOptions {
+            stretch: false,
+            ..HeightmapImportOptions::default()
+        };
+        let (heightfield, scale) =
+            read_heightmap(Path::new("valley.r16"), &bytes, &options).unwrap();
+
+        assert_eq!(heightfield.heights[1], 32768.0 / 65535.0);
+        assert_eq!(scale.range, None);
+    }
+}
@@ -0,0 +1,211 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use midpoint_engine::helpers::saved_data::File;
+
+use crate::editor_state::{StateHelper, UIMessage};
+
+use super::asset_store::{detect_mime, store_asset, AssetKind, StoredAsset};
+
+pub const IMAGE_SIZES: [&str; 4] = ["Original", "512", "1024", "2048"];
+pub const IMAGE_FORMATS: [&str; 3] = ["Keep", "PNG", "JPEG"];
+
+const IMPORTABLE_IMAGES: [&str; 4] = ["image/png", "image/jpeg", "image/webp", "image/tiff"];
+// offered by the file picker, the bytes still decide the type
+pub const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "webp", "tif", "tiff"];
+const JPEG_QUALITY: u8 = 90;
+
+/// How an image is changed on its way into the project
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ImageImportOptions {
+    // longest edge in pixels, larger images are scaled down
+    pub max_size: Option<u32>,
+    // None keeps the original bytes unless the image has to be resized
+    pub format: Option<ImageFormat>,
+}
+
+impl ImageImportOptions {
+    /// Reads the size and format choices from the import form
+    pub fn from_choices(size: &str, format: &str) -> ImageImportOptions {
+        ImageImportOptions {
+            max_size: size.parse::<u32>().ok().filter(|size| *size > 0),
+            format: match format {
+                "PNG" => Some(ImageFormat::Png),
+                "JPEG" => Some(ImageFormat::Jpeg),
+                _ => None,
+            },
+        }
+    }
+}
+
+fn encode_image(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+
+    match format {
+        ImageFormat::Jpeg => {
+            // JPEG has no alpha or 16-bit channels
+            let rgb = DynamicImage::ImageRgb8(image.to_rgb8());
+            rgb.write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY))
+                .map_err(|e| format!("Couldn't encode JPEG: {}", e))?;
+        }
+        _ => {
+            image
+                .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+                .map_err(|e| format!("Couldn't encode PNG: {}", e))?;
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Resizes and converts the image as asked, returning the bytes to store
+pub fn prepare_image(bytes: Vec<u8>, options: &ImageImportOptions) -> Result<Vec<u8>, String> {
+    let mime = detect_mime(&bytes)
+        .map(|(mime, _)| mime)
+        .unwrap_or_default();
+    if !IMPORTABLE_IMAGES.contains(&mime) {
+        return Err("Only PNG, JPEG, WebP and TIFF images can be imported".to_string());
+    }
+
+    let image =
+        image::load_from_memory(&bytes).map_err(|e| format!("Couldn't read image: {}", e))?;
+
+    let too_large = options
+        .max_size
+        .map(|max_size| image.width().max(image.height()) > max_size)
+        .unwrap_or(false);
+
+    if !too_large && options.format.is_none() {
+        return Ok(bytes);
+    }
+
+    let image = match options.max_size {
+        Some(max_size) if too_large => image.resize(max_size, max_size, FilterType::Lanczos3),
+        _ => image,
+    };
+
+    // resized WebP and TIFF images are written as PNG, which everything reads
+    let format = options.format.unwrap_or(match mime {
+        "image/jpeg" => ImageFormat::Jpeg,
+        _ => ImageFormat::Png,
+    });
+
+    encode_image(&image, format)
+}
+
+/// Adds a stored asset to the saved state and its browser, unless the same file is already there.
+/// Returns the project's entry for it and whether it was added.
+pub fn register_asset(
+    state_helper: &mut StateHelper,
+    kind: &AssetKind,
+    stored: StoredAsset,
+) -> (File, bool) {
+    let (file, created) = {
+        let mut saved_state = state_helper
+            .saved_state
+            .as_ref()
+            .expect("Couldn't get saved state")
+            .lock()
+            .unwrap();
+
+        let files = match kind {
+            AssetKind::Concept => &mut saved_state.concepts,
+            AssetKind::Texture => saved_state.textures.get_or_insert_with(Vec::new),
+            _ => &mut saved_state.models,
+        };
+
+        // importing the same bytes again gives back the file that's already there
+        match files
+            .iter()
+            .position(|f| stored.reused && f.fileName == stored.file.fileName)
+        {
+            Some(existing) => (files[existing].clone(), false),
+            None => {
+                files.push(stored.file.clone());
+                (stored.file, true)
+            }
+        }
+    };
+
+    if created {
+        state_helper.save_current_saved_state();
+
+        let (browser, message) = match kind {
+            AssetKind::Concept => ("concept_browser", UIMessage::AddConcept(file.clone())),
+            AssetKind::Texture => ("texture_browser", UIMessage::AddTexture(file.clone())),
+            _ => ("model_browser", UIMessage::AddModel(file.clone())),
+        };
+        state_helper.send_ui_message(browser, message);
+    }
+
+    (file, created)
+}
+
+// stores between writing their files and registering them, see store_and_register
+static STORING: AtomicUsize = AtomicUsize::new(0);
+
+struct Storing;
+
+impl Storing {
+    fn start() -> Self {
+        STORING.fetch_add(1, Ordering::SeqCst);
+        Storing
+    }
+}
+
+impl Drop for Storing {
+    fn drop(&mut self) {
+        STORING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Whether a store has written files it hasn't registered yet
+pub fn storing() -> bool {
+    STORING.load(Ordering::SeqCst) > 0
+}
+
+/// Writes new files into the project with `store`, then takes the state helper lock only to
+/// `register` them. Everything that writes new files into the project goes through here, and the
+/// folder watcher skips its check while a store is in between, so it never offers a file that's
+/// about to be registered.
+pub fn store_and_register<S, T>(
+    state_helper: &Arc<Mutex<StateHelper>>,
+    store: impl FnOnce() -> Result<S, String>,
+    register: impl FnOnce(&mut StateHelper, S) -> Result<T, String>,
+) -> Result<T, String> {
+    let _storing = Storing::start();
+    let stored = store()?;
+
+    let mut state_helper = state_helper.lock().unwrap();
+    register(&mut state_helper, stored)
+}
+
+/// Copies an image from disk into the open project as a texture or concept
+pub fn import_image(
+    state_helper: &Arc<Mutex<StateHelper>>,
+    project_id: &str,
+    kind: &AssetKind,
+    path: &str,
+    options: &ImageImportOptions,
+) -> Result<File, String> {
+    let path = Path::new(path.trim());
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or("Enter the path of an image file".to_string())?;
+
+    let bytes = fs::read(path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+    let bytes = prepare_image(bytes, options)?;
+
+    store_and_register(
+        state_helper,
+        || store_asset(project_id, kind, name, &bytes),
+        |state_helper, stored| Ok(register_asset(state_helper, kind, stored).0),
+    )
+}
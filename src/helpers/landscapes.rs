@@ -1,85 +1,544 @@
-use std::{fs, path::Path};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
-use base64::decode;
-use midpoint_engine::helpers::saved_data::{LandscapeTextureKinds, LevelData};
+use image::{GrayImage, Luma};
+use midpoint_engine::helpers::saved_data::{
+    ComponentData, ComponentKind, GenericProperties, LandscapeData, LandscapeProperties,
+    LandscapeTextureKinds, LevelData, SavedState,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::utilities::get_common_os_dir;
-
-pub fn save_landscape(
-    // state: tauri::State<'_, AppState>,
-    projectId: String,
-    landscapeBase64: String,
-    landscapeFilename: String,
-    rockmapFilename: String,
-    rockmapBase64: String,
-    soilFilename: String,
-    soilBase64: String,
-) -> String {
-    // let handle = &state.handle;
-    // let config = handle.config();
-    // let package_info = handle.package_info();
-    // let env = handle.env();
-
-    let landscape_id = Uuid::new_v4();
-
-    // let sync_dir = PathBuf::from("C:/Users/alext/CommonOSFiles");
-    let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
-
-    let heightmaps_dir = sync_dir.join(format!(
-        "midpoint/projects/{}/landscapes/{}/heightmaps",
-        projectId, landscape_id,
-    ));
-    let rockmaps_dir = sync_dir.join(format!(
-        "midpoint/projects/{}/landscapes/{}/rockmaps",
-        projectId, landscape_id
-    ));
-    let soils_dir = sync_dir.join(format!(
-        "midpoint/projects/{}/landscapes/{}/soils",
-        projectId, landscape_id
-    ));
-
-    // Check if the concepts directory exists, create if it doesn't
-    if !Path::new(&heightmaps_dir).exists() {
-        fs::create_dir_all(&heightmaps_dir).expect("Couldn't create heightmaps directory");
-    }
-    if !Path::new(&rockmaps_dir).exists() {
-        fs::create_dir_all(&rockmaps_dir).expect("Couldn't create rockmaps directory");
-    }
-    if !Path::new(&soils_dir).exists() {
-        fs::create_dir_all(&soils_dir).expect("Couldn't create soils directory");
-    }
-
-    let heightmap_path = heightmaps_dir.join(landscapeFilename);
-    let rockmap_path = rockmaps_dir.join(rockmapFilename);
-    let soil_path = soils_dir.join(soilFilename);
-
-    // prefix is pre-stripped on frontend
-    let base64_data = landscapeBase64;
-    let heightmap_data = decode(base64_data)
-        .map_err(|e| format!("Couldn't decode base64 string for heightmap: {}", e))
-        .expect("Couldn't decode base64 string for heightmap");
-    fs::write(heightmap_path, heightmap_data)
-        .map_err(|e| format!("Couldn't save heightmap file: {}", e))
-        .expect("Couldn't save heightmap file");
-
-    let base64_data = rockmapBase64;
-    let rockmap_data = decode(base64_data)
-        .map_err(|e| format!("Couldn't decode base64 string for rockmap: {}", e))
-        .expect("Couldn't decode base64 string for rockmap");
-    fs::write(rockmap_path, rockmap_data)
-        .map_err(|e| format!("Couldn't save rockmap file: {}", e))
-        .expect("Couldn't save rockmap file");
-
-    let base64_data = soilBase64;
-    let soil_data = decode(base64_data)
-        .map_err(|e| format!("Couldn't decode base64 string for soil: {}", e))
-        .expect("Couldn't decode base64 string for soil");
-    fs::write(soil_path, soil_data)
-        .map_err(|e| format!("Couldn't save soil file: {}", e))
-        .expect("Couldn't save soil file");
-
-    "success".to_string()
+use crate::editor_state::{StateHelper, UIMessage};
+
+use super::asset_store::{decode_base64, rename_stored, store_asset, AssetKind};
+use super::collaboration::{apply_to_saved_state, SceneOperation, TransformProperty};
+use super::downloads::get_asset_path;
+use super::generation::random_seed;
+use super::heightfield::{encode_mask, load_heightfield, Heightfield};
+use super::local_provider::smoothstep;
+use super::providers::{project_provider, GeneratedLandscape};
+use super::usage::{check_spending_limits, record_usage, UsageEntry};
+use super::utilities::{get_filename, get_project_dir};
+
+pub const BIOMES: [&str; 5] = ["Plains", "Hills", "Mountains", "Desert", "Islands"];
+pub const LANDSCAPE_SIZES: [&str; 3] = ["256", "512", "1024"];
+
+/// What a landscape is generated from, the same parameters always give the same maps
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LandscapeParams {
+    // width and height of the maps in pixels
+    pub size: i32,
+    pub biome: String,
+    // 0 is smooth, 1 is craggy
+    pub roughness: f32,
+    pub seed: Option<i32>,
+}
+
+impl Default for LandscapeParams {
+    fn default() -> Self {
+        LandscapeParams {
+            size: 256,
+            biome: BIOMES[1].to_string(),
+            roughness: 0.5,
+            seed: None,
+        }
+    }
+}
+
+impl LandscapeParams {
+    pub fn resolved(&self) -> LandscapeParams {
+        LandscapeParams {
+            size: self.size.clamp(64, 2048),
+            roughness: self.roughness.clamp(0.0, 1.0),
+            seed: Some(self.seed.unwrap_or_else(random_seed)),
+            ..self.clone()
+        }
+    }
+}
+
+/// How much larger than the engine's default a landscape is laid out
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LandscapeScale {
+    // across the ground, in both directions
+    pub horizontal: f32,
+    pub vertical: f32,
+    // the lowest and highest heights a float heightmap was fitted into 0..1 from,
+    // one unit of which is as tall as a full 16-bit heightmap
+    pub range: Option<[f32; 2]>,
+}
+
+impl Default for LandscapeScale {
+    fn default() -> Self {
+        LandscapeScale {
+            horizontal: 1.0,
+            vertical: 1.0,
+            range: None,
+        }
+    }
+}
+
+impl LandscapeScale {
+    /// The scale a landscape component is given when it's added to the scene
+    pub fn component_scale(&self) -> [f32; 3] {
+        let span = match self.range {
+            Some([min, max]) if max > min => max - min,
+            _ => 1.0,
+        };
+
+        [self.horizontal, self.vertical * span, self.horizontal]
+    }
+}
+
+/// Where rock and soil show through. Slopes are 0 on flat ground and about 1 on a cliff,
+/// heights run 0..1 from the lowest to the highest a heightmap can hold.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MaskParams {
+    // ground steeper than this turns to rock
+    pub rock_slope: f32,
+    // soil only lies on ground flatter and lower than these
+    pub soil_slope: f32,
+    pub soil_height: f32,
+    // box blur radius in pixels, softening where the masks meet
+    pub blur: u32,
+}
+
+impl Default for MaskParams {
+    fn default() -> Self {
+        MaskParams {
+            rock_slope: 0.35,
+            soil_slope: 0.25,
+            soil_height: 0.45,
+            blur: 2,
+        }
+    }
+}
+
+// how far past a threshold a mask takes to go from nothing to full
+const MASK_RAMP: f32 = 0.1;
+
+// masks are previewed this small, so they can follow every change to the thresholds
+pub const MASK_PREVIEW_SIZE: u32 = 128;
+
+// kept inside the landscape's folder, so they go when the landscape is deleted
+fn get_landscape_settings_path(project_id: &str, landscape_id: &str, file_name: &str) -> PathBuf {
+    get_project_dir(project_id)
+        .join("landscapes")
+        .join(landscape_id)
+        .join(file_name)
+}
+
+fn load_landscape_settings<T: DeserializeOwned + Default>(
+    project_id: &str,
+    landscape_id: &str,
+    file_name: &str,
+) -> T {
+    fs::read_to_string(get_landscape_settings_path(
+        project_id,
+        landscape_id,
+        file_name,
+    ))
+    .ok()
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default()
+}
+
+fn save_landscape_settings<T: Serialize>(
+    project_id: &str,
+    landscape_id: &str,
+    file_name: &str,
+    settings: &T,
+) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Couldn't serialize {}: {}", file_name, e))?;
+    fs::write(
+        get_landscape_settings_path(project_id, landscape_id, file_name),
+        json,
+    )
+    .map_err(|e| format!("Couldn't write {}: {}", file_name, e))
+}
+
+/// The thresholds the landscape's masks were last made with
+pub fn load_mask_params(project_id: &str, landscape_id: &str) -> MaskParams {
+    load_landscape_settings(project_id, landscape_id, "masks.json")
+}
+
+// 0 below the threshold and 1 above it, with a short ramp between
+fn ramp(value: f32, threshold: f32) -> f32 {
+    smoothstep(((value - threshold) / MASK_RAMP + 0.5).clamp(0.0, 1.0))
+}
+
+/// Averages every value with its neighbours up to `radius` away, across then down
+fn box_blur(values: Vec<f32>, width: u32, height: u32, radius: u32) -> Vec<f32> {
+    if radius == 0 {
+        return values;
+    }
+
+    let (width, height, radius) = (width as i64, height as i64, radius as i64);
+    let pass = |values: &[f32], step: (i64, i64)| -> Vec<f32> {
+        (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let total: f32 = (-radius..=radius)
+                    .map(|offset| {
+                        let sx = (x + offset * step.0).clamp(0, width - 1);
+                        let sy = (y + offset * step.1).clamp(0, height - 1);
+                        values[(sy * width + sx) as usize]
+                    })
+                    .sum();
+                total / (radius * 2 + 1) as f32
+            })
+            .collect()
+    };
+
+    let across = pass(&values, (1, 0));
+    pass(&across, (0, 1))
+}
+
+fn mask_image(values: Vec<f32>, width: u32, height: u32) -> GrayImage {
+    GrayImage::from_fn(width, height, |x, y| {
+        Luma([(values[(y * width + x) as usize].clamp(0.0, 1.0) * 255.0).round() as u8])
+    })
+}
+
+/// Rock where the ground is steep and soil on the gentle low ground, from heights in 0..1
+pub fn landscape_masks(
+    heights: &[f32],
+    width: u32,
+    height: u32,
+    params: &MaskParams,
+) -> (GrayImage, GrayImage) {
+    let height_at =
+        |x: u32, y: u32| heights[(y.min(height - 1) * width + x.min(width - 1)) as usize];
+    // measured across the whole map, so a preview's slopes match the full size
+    let slope_at = |x: u32, y: u32| {
+        let dx = height_at(x + 1, y) - height_at(x.saturating_sub(1), y);
+        let dy = height_at(x, y + 1) - height_at(x, y.saturating_sub(1));
+        (dx * dx + dy * dy).sqrt() * width.max(height) as f32 / 4.0
+    };
+
+    let mut rock = Vec::with_capacity((width * height) as usize);
+    let mut soil = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let slope = slope_at(x, y);
+            rock.push(ramp(slope, params.rock_slope));
+            soil.push(
+                (1.0 - ramp(slope, params.soil_slope))
+                    * (1.0 - ramp(height_at(x, y), params.soil_height)),
+            );
+        }
+    }
+
+    (
+        mask_image(box_blur(rock, width, height, params.blur), width, height),
+        mask_image(box_blur(soil, width, height, params.blur), width, height),
+    )
+}
+
+/// The masks of a heightmap drawn at preview size, with the blur shrunk to match
+pub fn preview_masks(heightfield: &Heightfield, params: &MaskParams) -> (GrayImage, GrayImage) {
+    let preview = heightfield.downsample(MASK_PREVIEW_SIZE);
+    let ratio = preview.width as f32 / heightfield.width as f32;
+    let params = MaskParams {
+        blur: (params.blur as f32 * ratio).round() as u32,
+        ..*params
+    };
+
+    landscape_masks(&preview.heights, preview.width, preview.height, &params)
+}
+
+/// Whether either mask has been synced, so replacing it locally leaves the cloud copy behind
+pub fn has_synced_masks(landscape: &LandscapeData) -> bool {
+    [&landscape.rockmap, &landscape.soil]
+        .into_iter()
+        .flatten()
+        .any(|mask| !mask.cloudfrontUrl.is_empty())
+}
+
+fn find_landscape(
+    state_helper: &Arc<Mutex<StateHelper>>,
+    landscape_id: &str,
+) -> Result<LandscapeData, String> {
+    let state_helper = state_helper.lock().unwrap();
+    let saved_state = state_helper
+        .saved_state
+        .as_ref()
+        .ok_or("No project is open".to_string())?
+        .lock()
+        .unwrap();
+
+    saved_state
+        .landscapes
+        .iter()
+        .flatten()
+        .find(|l| l.id == landscape_id)
+        .cloned()
+        .ok_or(format!("Couldn't find landscape {}", landscape_id))
+}
+
+/// Works the landscape's rockmap and soil masks out from its heightmap again, replacing the
+/// files the entry points at. Synced masks are only replaced with `replace_synced`.
+///
+/// The masks are made without holding the project, which is only locked to swap them in.
+pub fn regenerate_masks(
+    state_helper: &Arc<Mutex<StateHelper>>,
+    project_id: &str,
+    landscape_id: &str,
+    params: &MaskParams,
+    replace_synced: bool,
+) -> Result<LandscapeData, String> {
+    let landscape = find_landscape(state_helper, landscape_id)?;
+    if has_synced_masks(&landscape) && !replace_synced {
+        return Err("The masks are synced, confirm to replace them".to_string());
+    }
+    let heightmap = landscape
+        .heightmap
+        .as_ref()
+        .ok_or("Landscape has no heightmap".to_string())?;
+
+    let heightfield = load_heightfield(&get_asset_path(
+        &heightmap.normalFilePath,
+        &heightmap.fileName,
+    ))?;
+    let (rockmap, soil) = landscape_masks(
+        &heightfield.heights,
+        heightfield.width,
+        heightfield.height,
+        params,
+    );
+
+    let mut stored = Vec::new();
+    for (map, label, mask) in [("rockmaps", "rockmap", rockmap), ("soils", "soil", soil)] {
+        let kind = AssetKind::LandscapeMap {
+            landscape_id: landscape_id.to_string(),
+            map: map.to_string(),
+        };
+        let file = store_asset(
+            project_id,
+            &kind,
+            &get_filename(label.to_string()),
+            &encode_mask(mask)?,
+        )?
+        .file;
+        stored.push((kind, file));
+    }
+
+    let (landscape, landscapes, replaced) = {
+        let state_helper = state_helper.lock().unwrap();
+        let mut saved_state = state_helper
+            .saved_state
+            .as_ref()
+            .ok_or("No project is open".to_string())?
+            .lock()
+            .unwrap();
+        let entry = saved_state
+            .landscapes
+            .iter_mut()
+            .flatten()
+            .find(|l| l.id == landscape_id)
+            .ok_or(format!("Couldn't find landscape {}", landscape_id))?;
+
+        let mut replaced = Vec::new();
+        for ((kind, file), slot) in stored
+            .into_iter()
+            .zip([&mut entry.rockmap, &mut entry.soil])
+        {
+            match slot.take() {
+                // the same mask as before is stored under the name it already has
+                Some(old) if old.fileName == file.fileName => *slot = Some(old),
+                old => {
+                    replaced.extend(old.map(|old| (kind, old)));
+                    *slot = Some(file);
+                }
+            }
+        }
+
+        (
+            entry.clone(),
+            saved_state.landscapes.clone().unwrap_or_default(),
+            replaced,
+        )
+    };
+
+    for (kind, old) in replaced {
+        let _ = fs::remove_file(get_asset_path(&old.normalFilePath, &old.fileName));
+        rename_stored(project_id, &kind, &old.fileName, None)?;
+    }
+
+    save_landscape_settings(project_id, landscape_id, "masks.json", params)?;
+
+    let mut state_helper = state_helper.lock().unwrap();
+    state_helper.save_current_saved_state();
+    state_helper.send_ui_message("landscape_browser", UIMessage::UpdateLandscapes(landscapes));
+
+    Ok(landscape)
+}
+
+/// Stores the three encoded maps of a new landscape, returning the entry that points at them
+pub fn save_landscape_files(
+    project_id: &str,
+    name: &str,
+    heightmap: &[u8],
+    rockmap: &[u8],
+    soil: &[u8],
+) -> Result<LandscapeData, String> {
+    let landscape_id = Uuid::new_v4().to_string();
+    store_landscape_maps(project_id, &landscape_id, name, heightmap, rockmap, soil)
+}
+
+fn store_landscape_maps(
+    project_id: &str,
+    landscape_id: &str,
+    name: &str,
+    heightmap: &[u8],
+    rockmap: &[u8],
+    soil: &[u8],
+) -> Result<LandscapeData, String> {
+    let store_map = |map: &str, label: &str, bytes: &[u8]| {
+        let kind = AssetKind::LandscapeMap {
+            landscape_id: landscape_id.to_string(),
+            map: map.to_string(),
+        };
+        store_asset(
+            project_id,
+            &kind,
+            &get_filename(format!("{} {}", name, label)),
+            bytes,
+        )
+        .map(|stored| stored.file)
+    };
+
+    Ok(LandscapeData {
+        heightmap: Some(store_map("heightmaps", "heightmap", heightmap)?),
+        rockmap: Some(store_map("rockmaps", "rockmap", rockmap)?),
+        soil: Some(store_map("soils", "soil", soil)?),
+        id: landscape_id.to_string(),
+    })
+}
+
+/// Adds a saved landscape to the project and the landscape browser
+pub fn add_landscape(state_helper: &mut StateHelper, landscape: LandscapeData) {
+    let landscapes = {
+        let mut saved_state = state_helper
+            .saved_state
+            .as_ref()
+            .expect("Couldn't get saved state")
+            .lock()
+            .unwrap();
+        let landscapes = saved_state.landscapes.get_or_insert_with(Vec::new);
+        landscapes.push(landscape);
+        landscapes.clone()
+    };
+    state_helper.save_current_saved_state();
+
+    if let Some(tx) = state_helper
+        .file_signals
+        .lock()
+        .unwrap()
+        .get("landscape_browser")
+        .cloned()
+    {
+        let _ = tx.send(UIMessage::UpdateLandscapes(landscapes));
+    }
+}
+
+/// The scale the landscape is already placed at, so adding it again keeps the size it was
+/// imported with
+pub fn placed_landscape_scale(saved_state: &SavedState, landscape_id: &str) -> [f32; 3] {
+    saved_state
+        .levels
+        .iter()
+        .flatten()
+        .flat_map(|level| level.components.iter().flatten())
+        .find(|component| {
+            matches!(component.kind, Some(ComponentKind::Landscape))
+                && component.asset_id == landscape_id
+        })
+        .map(|component| component.generic_properties.scale)
+        .unwrap_or([1.0, 1.0, 1.0])
+}
+
+/// Places a landscape at the origin of the first level, for the scene and other editors
+pub fn place_landscape(
+    state_helper: &mut StateHelper,
+    landscape_id: &str,
+    scale: [f32; 3],
+) -> Result<ComponentData, String> {
+    let component = ComponentData {
+        id: Uuid::new_v4().to_string(),
+        kind: Some(ComponentKind::Landscape),
+        asset_id: landscape_id.to_string(),
+        generic_properties: GenericProperties {
+            name: "New Landscape Component".to_string(),
+            position: [0.0, 0.0, 0.0],
+            rotation: [0.0, 0.0, 0.0],
+            scale,
+        },
+        landscape_properties: Some(LandscapeProperties {
+            primary_texture_id: None,
+            rockmap_texture_id: None,
+            soil_texture_id: None,
+        }),
+        model_properties: None,
+    };
+    let operation = SceneOperation::Add {
+        component: component.clone(),
+    };
+
+    let components = {
+        let mut saved_state = state_helper
+            .saved_state
+            .as_ref()
+            .ok_or("No project is open".to_string())?
+            .lock()
+            .unwrap();
+        if saved_state.levels.as_ref().map_or(true, |l| l.is_empty()) {
+            return Err("The project has no level".to_string());
+        }
+        apply_to_saved_state(&mut saved_state, &operation);
+        saved_state
+            .levels
+            .as_ref()
+            .and_then(|levels| levels.first())
+            .and_then(|level| level.components.clone())
+            .unwrap_or_default()
+    };
+    state_helper.save_current_saved_state();
+    state_helper.broadcast_scene_operation(operation.clone());
+
+    // adding only places the component, so its scale follows as its own edit
+    state_helper.send_ui_message("scene", UIMessage::SceneOperation(operation));
+    state_helper.send_ui_message(
+        "scene",
+        UIMessage::SceneOperation(SceneOperation::Transform {
+            component_id: component.id.clone(),
+            property: TransformProperty::Scale,
+            value: scale,
+        }),
+    );
+    state_helper.send_ui_message("component_browser", UIMessage::UpdateComponents(components));
+
+    Ok(component)
+}
+
+/// Writes the generated maps into the project folder, returning the entry that points at them
+pub fn save_generated_landscape(
+    project_id: &str,
+    landscape_id: &str,
+    params: &LandscapeParams,
+    landscape: GeneratedLandscape,
+) -> Result<LandscapeData, String> {
+    let name = format!("{} {}", params.biome, params.seed.unwrap_or_default());
+
+    // all three are decoded first, so a bad map doesn't leave the others behind
+    let heightmap = decode_base64(&name, &landscape.heightmap)?;
+    let rockmap = decode_base64(&name, &landscape.rockmap)?;
+    let soil = decode_base64(&name, &landscape.soil)?;
+
+    store_landscape_maps(project_id, landscape_id, &name, &heightmap, &rockmap, &soil)
 }
 
 pub fn save_landscape_texture(
@@ -116,3 +575,165 @@ pub fn save_landscape_texture(
         }
     }
 }
+
+/// Generates a landscape with the project's provider and adds it to the saved state
+pub async fn request_landscape(
+    state_helper: Arc<Mutex<StateHelper>>,
+    project_id: String,
+    params: LandscapeParams,
+) -> Result<LandscapeData, String> {
+    check_spending_limits(&project_id).map_err(|e| e.to_string())?;
+
+    let auth_token = state_helper.lock().unwrap().auth_token.clone();
+
+    let landscape_id = Uuid::new_v4().to_string();
+    let provider = project_provider(&project_id);
+    let maps = provider
+        .generate_landscape(&auth_token, &landscape_id, &params)
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Err(e) = record_usage(
+        &project_id,
+        UsageEntry::new(
+            "landscapes",
+            &format!("{} landscape", params.biome),
+            &auth_token,
+            1,
+            maps.credits,
+            provider.version().await,
+        ),
+    ) {
+        println!("{}", e);
+    }
+    let landscape = save_generated_landscape(&project_id, &landscape_id, &params, maps.output)?;
+
+    let mut state_helper = state_helper.lock().unwrap();
+    state_helper
+        .saved_state
+        .as_ref()
+        .expect("Couldn't get saved state")
+        .lock()
+        .unwrap()
+        .landscapes
+        .get_or_insert_with(Vec::new)
+        .push(landscape.clone());
+    state_helper.save_current_saved_state();
+
+    Ok(landscape)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use midpoint_engine::helpers::saved_data::File;
+
+    const UNBLURRED: MaskParams = MaskParams {
+        rock_slope: 0.35,
+        soil_slope: 0.25,
+        soil_height: 0.45,
+        blur: 0,
+    };
+
+    // rises from 0 to 1 across the map
+    fn ramp_heights(width: u32, height: u32) -> Vec<f32> {
+        (0..width * height)
+            .map(|i| (i % width) as f32 / (width - 1) as f32)
+            .collect()
+    }
+
+    #[test]
+    fn flat_low_ground_is_soil() {
+        let (rock, soil) = landscape_masks(&vec![0.0; 16], 4, 4, &UNBLURRED);
+
+        assert!(rock.pixels().all(|p| p[0] == 0));
+        assert!(soil.pixels().all(|p| p[0] == 255));
+    }
+
+    #[test]
+    fn flat_high_ground_is_bare() {
+        let (rock, soil) = landscape_masks(&vec![1.0; 16], 4, 4, &UNBLURRED);
+
+        assert!(rock.pixels().all(|p| p[0] == 0));
+        assert!(soil.pixels().all(|p| p[0] == 0));
+    }
+
+    #[test]
+    fn steep_ground_is_rock() {
+        let (rock, soil) = landscape_masks(&ramp_heights(8, 8), 8, 8, &UNBLURRED);
+
+        // the edges only see half the slope
+        for x in 1..7 {
+            assert_eq!(rock.get_pixel(x, 4)[0], 255);
+            assert_eq!(soil.get_pixel(x, 4)[0], 0);
+        }
+    }
+
+    #[test]
+    fn blur_softens_where_masks_meet() {
+        let mut heights = vec![0.0; 64];
+        heights[..32].iter_mut().for_each(|h| *h = 1.0);
+        let params = MaskParams {
+            blur: 2,
+            ..UNBLURRED
+        };
+
+        let (_, sharp) = landscape_masks(&heights, 8, 8, &UNBLURRED);
+        let (_, soft) = landscape_masks(&heights, 8, 8, &params);
+
+        assert_eq!(sharp.get_pixel(4, 3)[0], 0);
+        assert!(soft.get_pixel(4, 3)[0] > 0);
+    }
+
+    #[test]
+    fn box_blur_keeps_even_values() {
+        assert_eq!(box_blur(vec![0.5; 25], 5, 5, 2), vec![0.5; 25]);
+    }
+
+    #[test]
+    fn previews_are_drawn_small() {
+        let heightfield = Heightfield {
+            width: 512,
+            height: 256,
+            heights: ramp_heights(512, 256),
+        };
+        let (rock, soil) = preview_masks(&heightfield, &MaskParams::default());
+
+        assert_eq!(
+            rock.dimensions(),
+            (MASK_PREVIEW_SIZE, MASK_PREVIEW_SIZE / 2)
+        );
+        assert_eq!(soil.dimensions(), rock.dimensions());
+    }
+
+    #[test]
+    fn synced_masks_are_noticed() {
+        let mask = |cloudfront_url: &str| File {
+            id: Uuid::new_v4().to_string(),
+            cloudfrontUrl: cloudfront_url.to_string(),
+            fileName: "soil.png".to_string(),
+            normalFilePath: "soils".to_string(),
+        };
+        let mut landscape = LandscapeData {
+            id: Uuid::new_v4().to_string(),
+            heightmap: None,
+            rockmap: Some(mask("")),
+            soil: None,
+        };
+
+        assert!(!has_synced_masks(&landscape));
+        landscape.soil = Some(mask("https://example.com/soil.png"));
+        assert!(has_synced_masks(&landscape));
+    }
+
+    #[test]
+    fn float_ranges_raise_the_landscape() {
+        let scale = LandscapeScale {
+            horizontal: 2.0,
+            vertical: 0.5,
+            range: Some([-10.0, 30.0]),
+        };
+
+        assert_eq!(scale.component_scale(), [2.0, 20.0, 2.0]);
+        assert_eq!(LandscapeScale::default().component_scale(), [1.0, 1.0, 1.0]);
+    }
+}
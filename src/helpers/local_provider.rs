@@ -0,0 +1,631 @@
+use std::f32::consts::PI;
+use std::fs;
+use std::io::Cursor;
+
+use async_trait::async_trait;
+use base64::{decode, encode};
+use image::{DynamicImage, GrayImage, ImageFormat, Luma, Rgba, RgbaImage};
+use serde_json::json;
+
+use crate::stand_in::assets::{cube_glb, encode_png, mesh_glb};
+
+use super::generation::{output_dimensions, GenerationOptions, DEFAULT_RESOLUTION};
+use super::gltf::{read_glb, write_glb};
+use super::landscapes::{landscape_masks, LandscapeParams, MaskParams};
+use super::providers::{Generated, GeneratedLandscape, GenerationError, GenerationProvider};
+
+// prompts with these words get a tiled texture rather than plain noise
+const TILE_WORDS: [&str; 7] = ["brick", "tile", "floor", "wall", "stone", "cobble", "plank"];
+
+// longest edge of the concept image a local model carries as its texture
+const MODEL_TEXTURE_SIZE: u32 = 512;
+
+// everything is made on this machine, so nothing is charged
+const LOCAL_CREDITS: Option<f64> = Some(0.0);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Primitive {
+    Cube,
+    Sphere,
+    Cylinder,
+    Plane,
+}
+
+impl Primitive {
+    /// Picks a shape from words in the concept's name, so a blockout roughly matches
+    pub fn for_name(name: &str) -> Primitive {
+        let name = name.to_lowercase();
+        let has_any = |words: &[&str]| words.iter().any(|word| name.contains(word));
+
+        if has_any(&["ball", "sphere", "orb", "planet", "rock", "boulder"]) {
+            Primitive::Sphere
+        } else if has_any(&["barrel", "pillar", "column", "tree", "can", "tower", "pipe"]) {
+            Primitive::Cylinder
+        } else if has_any(&["ground", "floor", "plane", "road", "water"]) {
+            Primitive::Plane
+        } else {
+            Primitive::Cube
+        }
+    }
+}
+
+/// Generates everything on this machine from the prompt and seed, the same way every time
+pub struct LocalProvider;
+
+fn hash(seed: u64, x: i32, y: i32) -> f32 {
+    let mut h = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xC4CE_B9FE_1A85_EC53);
+    h ^= h >> 33;
+
+    (h >> 40) as f32 / (1u64 << 24) as f32
+}
+
+pub fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Smoothly interpolated noise in 0..1 with a new value at every whole coordinate
+pub fn value_noise(seed: u64, x: f32, y: f32) -> f32 {
+    let (x0, y0) = (x.floor() as i32, y.floor() as i32);
+    let (tx, ty) = (smoothstep(x - x0 as f32), smoothstep(y - y0 as f32));
+
+    let top = hash(seed, x0, y0) * (1.0 - tx) + hash(seed, x0 + 1, y0) * tx;
+    let bottom = hash(seed, x0, y0 + 1) * (1.0 - tx) + hash(seed, x0 + 1, y0 + 1) * tx;
+
+    top * (1.0 - ty) + bottom * ty
+}
+
+/// Fractal noise in 0..1, each octave twice the frequency and half the weight of the last
+pub fn fbm(seed: u64, x: f32, y: f32, octaves: u32) -> f32 {
+    shaped_fbm(seed, x, y, octaves, |noise| noise)
+}
+
+/// Fractal noise with every octave passed through `shape`, which maps 0..1 into 0..1
+pub fn shaped_fbm(seed: u64, x: f32, y: f32, octaves: u32, shape: impl Fn(f32) -> f32) -> f32 {
+    let mut total = 0.0;
+    let mut weight = 0.5;
+    let mut frequency = 1.0;
+    let mut max = 0.0;
+
+    for octave in 0..octaves {
+        total += shape(value_noise(
+            seed.wrapping_add(octave as u64),
+            x * frequency,
+            y * frequency,
+        )) * weight;
+        max += weight;
+        weight *= 0.5;
+        frequency *= 2.0;
+    }
+
+    total / max
+}
+
+fn seed_color(seed: u64, shift: u32) -> [f32; 3] {
+    let bits = seed.rotate_left(shift);
+    [
+        (bits & 0xff) as f32,
+        ((bits >> 8) & 0xff) as f32,
+        ((bits >> 16) & 0xff) as f32,
+    ]
+}
+
+fn mix(from: [f32; 3], to: [f32; 3], t: f32) -> Rgba<u8> {
+    let t = t.clamp(0.0, 1.0);
+    Rgba([
+        (from[0] * (1.0 - t) + to[0] * t) as u8,
+        (from[1] * (1.0 - t) + to[1] * t) as u8,
+        (from[2] * (1.0 - t) + to[2] * t) as u8,
+        255,
+    ])
+}
+
+pub fn noise_texture(seed: u64, width: u32, height: u32) -> RgbaImage {
+    let (from, to) = (seed_color(seed, 0), seed_color(seed, 24));
+    // about eight noise cells across the longest edge
+    let scale = 8.0 / width.max(height) as f32;
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        mix(from, to, fbm(seed, x as f32 * scale, y as f32 * scale, 5))
+    })
+}
+
+/// Running bond bricks with mortar lines and a little variation between tiles
+pub fn tile_texture(seed: u64, width: u32, height: u32) -> RgbaImage {
+    let (from, to) = (seed_color(seed, 0), seed_color(seed, 24));
+    let mortar = [200.0, 200.0, 195.0];
+    let tile_width = (width / 6).max(4);
+    let tile_height = (tile_width / 2).max(2);
+    let mortar_width = (tile_width / 16).max(1);
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let row = y / tile_height;
+        let offset = if row % 2 == 0 { 0 } else { tile_width / 2 };
+        let column = (x + offset) / tile_width;
+
+        let in_mortar = (x + offset) % tile_width < mortar_width || y % tile_height < mortar_width;
+        if in_mortar {
+            return mix(mortar, mortar, 0.0);
+        }
+
+        let tile_shade = hash(seed, column as i32, row as i32);
+        let grain = fbm(seed, x as f32 / 12.0, y as f32 / 12.0, 3);
+
+        mix(from, to, tile_shade * 0.7 + grain * 0.3)
+    })
+}
+
+/// A noisy blob on a plain background, standing in for an object on a concept sheet
+pub fn concept_image(seed: u64, width: u32, height: u32) -> RgbaImage {
+    let (object_from, object_to) = (seed_color(seed, 0), seed_color(seed, 24));
+    let background = [235.0, 235.0, 235.0];
+    let size = width.min(height) as f32;
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let dx = (x as f32 - width as f32 / 2.0) / size;
+        let dy = (y as f32 - height as f32 / 2.0) / size;
+        let shade = fbm(seed, x as f32 * 6.0 / size, y as f32 * 6.0 / size, 4);
+        let edge = 0.3 + (shade - 0.5) * 0.15;
+
+        if (dx * dx + dy * dy).sqrt() < edge {
+            mix(object_from, object_to, shade)
+        } else {
+            mix(background, background, 0.0)
+        }
+    })
+}
+
+fn png_data_url(image: RgbaImage) -> String {
+    format!("data:image/png;base64,{}", encode(encode_png(image)))
+}
+
+fn gray_base64(image: GrayImage) -> String {
+    let mut bytes: Vec<u8> = Vec::new();
+    DynamicImage::ImageLuma8(image)
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .expect("Couldn't encode landscape map");
+    encode(bytes)
+}
+
+fn uv_sphere(rings: u16, segments: u16) -> Vec<u8> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut texcoords: Vec<[f32; 2]> = Vec::new();
+    let mut indices: Vec<u16> = Vec::new();
+
+    for ring in 0..=rings {
+        let v = ring as f32 / rings as f32;
+        let theta = v * PI;
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let phi = u * 2.0 * PI;
+            let normal = [
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            ];
+
+            positions.push([normal[0] * 0.5, normal[1] * 0.5, normal[2] * 0.5]);
+            normals.push(normal);
+            texcoords.push([u, v]);
+        }
+    }
+
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let a = ring * (segments + 1) + segment;
+            let b = a + segments + 1;
+            indices.extend_from_slice(&[a, a + 1, b, a + 1, b + 1, b]);
+        }
+    }
+
+    mesh_glb("LocalSphere", &positions, &normals, &texcoords, &indices)
+}
+
+fn cylinder(segments: u16) -> Vec<u8> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut texcoords: Vec<[f32; 2]> = Vec::new();
+    let mut indices: Vec<u16> = Vec::new();
+
+    // the side, as bottom and top vertex pairs
+    for segment in 0..=segments {
+        let u = segment as f32 / segments as f32;
+        let (sin, cos) = (u * 2.0 * PI).sin_cos();
+        for y in [-0.5, 0.5] {
+            positions.push([cos * 0.5, y, sin * 0.5]);
+            normals.push([cos, 0.0, sin]);
+            texcoords.push([u, 0.5 - y]);
+        }
+    }
+    for segment in 0..segments {
+        let (bottom, top) = (segment * 2, segment * 2 + 1);
+        indices.extend_from_slice(&[bottom, top, bottom + 2, bottom + 2, top, top + 2]);
+    }
+
+    // the caps, as a centre vertex and a ring each
+    for (y, normal_y) in [(0.5, 1.0), (-0.5, -1.0)] {
+        let centre = positions.len() as u16;
+        positions.push([0.0, y, 0.0]);
+        normals.push([0.0, normal_y, 0.0]);
+        texcoords.push([0.5, 0.5]);
+
+        for segment in 0..=segments {
+            let (sin, cos) = (segment as f32 / segments as f32 * 2.0 * PI).sin_cos();
+            positions.push([cos * 0.5, y, sin * 0.5]);
+            normals.push([0.0, normal_y, 0.0]);
+            texcoords.push([0.5 + cos * 0.5, 0.5 + sin * 0.5]);
+        }
+        for segment in 0..segments {
+            let current = centre + 1 + segment;
+            if normal_y > 0.0 {
+                indices.extend_from_slice(&[centre, current + 1, current]);
+            } else {
+                indices.extend_from_slice(&[centre, current, current + 1]);
+            }
+        }
+    }
+
+    mesh_glb("LocalCylinder", &positions, &normals, &texcoords, &indices)
+}
+
+fn plane() -> Vec<u8> {
+    let positions = [
+        [-0.5, 0.0, -0.5],
+        [0.5, 0.0, -0.5],
+        [0.5, 0.0, 0.5],
+        [-0.5, 0.0, 0.5],
+    ];
+    let normals = [[0.0, 1.0, 0.0]; 4];
+    let texcoords = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+    let indices = [0, 3, 2, 0, 2, 1];
+
+    mesh_glb("LocalPlane", &positions, &normals, &texcoords, &indices)
+}
+
+pub fn primitive_glb(primitive: Primitive) -> Vec<u8> {
+    match primitive {
+        Primitive::Cube => cube_glb(),
+        Primitive::Sphere => uv_sphere(16, 24),
+        Primitive::Cylinder => cylinder(24),
+        Primitive::Plane => plane(),
+    }
+}
+
+/// Reads the image a model is made from: a data URL, a web URL or a file path
+async fn load_source_image(image_url: &str) -> Result<DynamicImage, String> {
+    let bytes = if let Some((_, data)) = image_url.split_once(";base64,") {
+        decode(data.trim()).map_err(|e| format!("Couldn't decode source image: {}", e))?
+    } else if image_url.starts_with("http://") || image_url.starts_with("https://") {
+        reqwest::get(image_url)
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Couldn't download source image: {}", e))?
+            .bytes()
+            .await
+            .map_err(|e| format!("Couldn't download source image: {}", e))?
+            .to_vec()
+    } else {
+        let path = image_url.trim_start_matches("file://");
+        fs::read(path).map_err(|e| format!("Couldn't read source image {}: {}", path, e))?
+    };
+
+    image::load_from_memory(&bytes).map_err(|e| format!("Couldn't read source image: {}", e))
+}
+
+/// The primitive wrapped in the concept image and stretched to its proportions
+pub fn textured_glb(primitive: Primitive, image: &DynamicImage) -> Result<Vec<u8>, String> {
+    let (mut json, bin) = read_glb(&primitive_glb(primitive))?;
+    let mut bin = bin.unwrap_or_default();
+    while bin.len() % 4 != 0 {
+        bin.push(0);
+    }
+
+    let png = encode_png(
+        image
+            .thumbnail(MODEL_TEXTURE_SIZE, MODEL_TEXTURE_SIZE)
+            .to_rgba8(),
+    );
+    let views = json["bufferViews"]
+        .as_array_mut()
+        .ok_or("Primitive has no buffer views".to_string())?;
+    let image_view = views.len();
+    views.push(json!({ "buffer": 0, "byteOffset": bin.len(), "byteLength": png.len() }));
+    bin.extend_from_slice(&png);
+    json["buffers"][0]["byteLength"] = json!(bin.len());
+
+    json["images"] = json!([{ "bufferView": image_view, "mimeType": "image/png" }]);
+    json["samplers"] = json!([{}]);
+    json["textures"] = json!([{ "source": 0, "sampler": 0 }]);
+    json["materials"][0]["pbrMetallicRoughness"]["baseColorTexture"] = json!({ "index": 0 });
+    json["materials"][0]["pbrMetallicRoughness"]["baseColorFactor"] = json!([1.0, 1.0, 1.0, 1.0]);
+
+    // a tall concept makes a tall model, planes stay flat
+    if primitive != Primitive::Plane {
+        let proportion = (image.height() as f32 / image.width().max(1) as f32).clamp(0.25, 4.0);
+        json["nodes"][0]["scale"] = json!([1.0, proportion, 1.0]);
+    }
+
+    write_glb(&json, bin)
+}
+
+/// Shapes raw noise into the biome's kind of terrain, `x` and `y` being 0..1 across the map
+fn biome_height(biome: &str, noise: f32, x: f32, y: f32) -> f32 {
+    match biome {
+        "Plains" => 0.1 + noise * 0.25,
+        // ridges where the noise crosses its midpoint
+        "Mountains" => (1.0 - (noise * 2.0 - 1.0).abs()).powf(1.5),
+        "Desert" => 0.2 + noise * 0.3 + ((x + noise * 0.2) * 40.0).sin() * 0.03,
+        "Islands" => {
+            let distance = ((x - 0.5).powi(2) + (y - 0.5).powi(2)).sqrt() * 2.0;
+            noise * (1.0 - distance).clamp(0.0, 1.0)
+        }
+        _ => noise,
+    }
+}
+
+/// Heightmap from fractal noise, with rock on the steep parts and soil on the gentle low ground
+pub fn procedural_landscape(params: &LandscapeParams) -> (GrayImage, GrayImage, GrayImage) {
+    let seed = params.seed.unwrap_or_default() as u64;
+    let size = params.size.max(2) as u32;
+    // rougher terrain keeps more of the fine octaves
+    let octaves = 2 + (params.roughness.clamp(0.0, 1.0) * 6.0).round() as u32;
+
+    let heights: Vec<f32> = (0..size * size)
+        .map(|i| {
+            let (x, y) = (
+                (i % size) as f32 / size as f32,
+                (i / size) as f32 / size as f32,
+            );
+            let noise = fbm(seed, x * 6.0, y * 6.0, octaves);
+            biome_height(&params.biome, noise, x, y).clamp(0.0, 1.0)
+        })
+        .collect();
+    let heightmap = GrayImage::from_fn(size, size, |x, y| {
+        Luma([(heights[(y * size + x) as usize] * 255.0) as u8])
+    });
+    let (rockmap, soil) = landscape_masks(&heights, size, size, &MaskParams::default());
+
+    (heightmap, rockmap, soil)
+}
+
+/// The three maps as base64 PNGs, as providers return them
+pub fn landscape_maps(params: &LandscapeParams) -> GeneratedLandscape {
+    let (heightmap, rockmap, soil) = procedural_landscape(params);
+
+    GeneratedLandscape {
+        heightmap: gray_base64(heightmap),
+        rockmap: gray_base64(rockmap),
+        soil: gray_base64(soil),
+    }
+}
+
+/// The seed and size each variation of a request is made with
+fn image_requests(options: &GenerationOptions) -> Vec<(u64, u32, u32)> {
+    options
+        .variations()
+        .into_iter()
+        .map(|variation| {
+            let (width, height) = output_dimensions(
+                variation.resolution.unwrap_or(DEFAULT_RESOLUTION),
+                variation.aspect_ratio.as_deref().unwrap_or("1:1"),
+            );
+            (variation.seed.unwrap_or_default() as u64, width, height)
+        })
+        .collect()
+}
+
+#[async_trait]
+impl GenerationProvider for LocalProvider {
+    async fn version(&self) -> Option<String> {
+        Some(format!("local {}", env!("CARGO_PKG_VERSION")))
+    }
+
+    // nothing is uploaded, so nothing is announced
+    fn syncs_outputs(&self) -> bool {
+        false
+    }
+
+    async fn generate_concepts(
+        &self,
+        _auth_token: &str,
+        prompt: &str,
+        options: &GenerationOptions,
+    ) -> Result<Generated<Vec<String>>, GenerationError> {
+        let images = image_requests(options)
+            .into_iter()
+            .map(|(seed, width, height)| png_data_url(concept_image(seed, width, height)))
+            .collect();
+
+        Ok(Generated::new(images, LOCAL_CREDITS))
+    }
+
+    async fn generate_textures(
+        &self,
+        _auth_token: &str,
+        prompt: &str,
+        options: &GenerationOptions,
+    ) -> Result<Generated<Vec<String>>, GenerationError> {
+        let prompt_lower = prompt.to_lowercase();
+        let tiled = TILE_WORDS.iter().any(|word| prompt_lower.contains(word));
+
+        let images = image_requests(options)
+            .into_iter()
+            .map(|(seed, width, height)| {
+                if tiled {
+                    png_data_url(tile_texture(seed, width, height))
+                } else {
+                    png_data_url(noise_texture(seed, width, height))
+                }
+            })
+            .collect();
+
+        Ok(Generated::new(images, LOCAL_CREDITS))
+    }
+
+    async fn generate_model(
+        &self,
+        _auth_token: &str,
+        image_url: &str,
+        source_name: &str,
+    ) -> Result<Generated<String>, GenerationError> {
+        let image = load_source_image(image_url).await?;
+        let glb = textured_glb(Primitive::for_name(source_name), &image)?;
+
+        Ok(Generated::new(
+            format!("data:model/gltf-binary;base64,{}", encode(glb)),
+            LOCAL_CREDITS,
+        ))
+    }
+
+    async fn generate_landscape(
+        &self,
+        _auth_token: &str,
+        _landscape_id: &str,
+        params: &LandscapeParams,
+    ) -> Result<Generated<GeneratedLandscape>, GenerationError> {
+        Ok(Generated::new(
+            landscape_maps(&params.resolved()),
+            LOCAL_CREDITS,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(seed: i32, variations: i32) -> GenerationOptions {
+        GenerationOptions {
+            seed: Some(seed),
+            resolution: Some(64),
+            variations: Some(variations),
+            ..GenerationOptions::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn concepts_are_reproduced_from_their_recorded_options() {
+        let provider = LocalProvider;
+        let concepts = provider
+            .generate_concepts("", "a red barn", &options(7, 3))
+            .await
+            .unwrap();
+        assert_eq!(concepts.credits, Some(0.0));
+        let concepts = concepts.output;
+
+        assert_eq!(concepts.len(), 3);
+        assert_eq!(
+            concepts,
+            provider
+                .generate_concepts("", "a red barn", &options(7, 3))
+                .await
+                .unwrap()
+                .output
+        );
+
+        // each output comes back from the single-result options recorded for it
+        for (concept, variation) in concepts.iter().zip(options(7, 3).variations()) {
+            let single = provider
+                .generate_concepts("", "a red barn", &variation)
+                .await
+                .unwrap()
+                .output;
+            assert_eq!(single, vec![concept.clone()]);
+        }
+    }
+
+    #[tokio::test]
+    async fn textures_change_with_the_seed() {
+        let provider = LocalProvider;
+        let first = provider
+            .generate_textures("", "brick wall", &options(1, 1))
+            .await
+            .unwrap()
+            .output;
+        let second = provider
+            .generate_textures("", "brick wall", &options(2, 1))
+            .await
+            .unwrap()
+            .output;
+
+        assert_ne!(first, second);
+    }
+
+    #[tokio::test]
+    async fn models_are_wrapped_in_their_source_image() {
+        let source = png_data_url(concept_image(3, 32, 64));
+        let model = LocalProvider
+            .generate_model("", &source, "barrel.png")
+            .await
+            .unwrap()
+            .output;
+        assert_eq!(
+            model,
+            LocalProvider
+                .generate_model("", &source, "barrel.png")
+                .await
+                .unwrap()
+                .output
+        );
+
+        let bytes = decode(model.split_once(";base64,").unwrap().1).unwrap();
+        let (json, bin) = read_glb(&bytes).unwrap();
+        let bin = bin.unwrap();
+
+        assert_eq!(
+            json["materials"][0]["pbrMetallicRoughness"]["baseColorTexture"]["index"],
+            0
+        );
+        // twice as tall as it is wide, like the concept
+        assert_eq!(json["nodes"][0]["scale"][1], 2.0);
+
+        let view = &json["bufferViews"][json["images"][0]["bufferView"].as_u64().unwrap() as usize];
+        let offset = view["byteOffset"].as_u64().unwrap() as usize;
+        let length = view["byteLength"].as_u64().unwrap() as usize;
+        let texture = image::load_from_memory(&bin[offset..offset + length]).unwrap();
+        assert_eq!((texture.width(), texture.height()), (32, 64));
+    }
+
+    #[tokio::test]
+    async fn models_need_a_readable_source_image() {
+        let result = LocalProvider
+            .generate_model("", "/no/such/concept.png", "barrel.png")
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn landscapes_are_reproduced_from_their_seed() {
+        let params = LandscapeParams {
+            size: 64,
+            seed: Some(11),
+            ..LandscapeParams::default()
+        };
+        let first = LocalProvider
+            .generate_landscape("", "landscape", &params)
+            .await
+            .unwrap()
+            .output;
+        let second = LocalProvider
+            .generate_landscape("", "landscape", &params)
+            .await
+            .unwrap()
+            .output;
+
+        assert_eq!(first.heightmap, second.heightmap);
+        assert_eq!(first.rockmap, second.rockmap);
+        assert_eq!(first.soil, second.soil);
+    }
+
+    #[test]
+    fn local_outputs_are_registered_by_the_editor() {
+        assert!(!LocalProvider.syncs_outputs());
+    }
+}
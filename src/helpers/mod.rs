@@ -1,9 +1,29 @@
+pub mod asset_labels;
+pub mod asset_store;
+pub mod asset_usage;
 pub mod auth;
-pub mod concepts;
+pub mod collaboration;
+pub mod downloads;
+pub mod generation;
+pub mod gltf;
+pub mod heightfield;
+pub mod heightmap_import;
+pub mod imports;
 pub mod landscapes;
-pub mod models;
+pub mod local_provider;
+pub mod model_import;
+pub mod model_previews;
 pub mod nodes;
+pub mod outbox;
+pub mod pipeline;
+pub mod presence;
+pub mod project_settings;
 pub mod projects;
-pub mod textures;
+pub mod providers;
+pub mod terrain;
+pub mod thumbnails;
+pub mod transfers;
+pub mod usage;
 pub mod utilities;
+pub mod watcher;
 pub mod websocket;
@@ -0,0 +1,878 @@
+use std::collections::{BTreeSet, HashMap};
+use std::f32::consts::FRAC_PI_2;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use base64::decode;
+use midpoint_engine::helpers::saved_data::File;
+use nalgebra::{Matrix4, Point3, Quaternion, UnitQuaternion, Vector3};
+use serde_json::{json, Value};
+
+use crate::editor_state::StateHelper;
+
+use super::asset_store::{detect_mime, store_asset, AssetKind};
+use super::gltf::{
+    accessor_layout, read_floats, read_glb, read_vector, read_vectors, scene_roots,
+    visit_scene_meshes, write_glb, write_vector, FLOAT, UNSIGNED_INT,
+};
+use super::imports::{register_asset, store_and_register};
+
+// choices in the import form, see ModelImportOptions::from_choices
+pub const MODEL_UNITS: [&str; 4] = ["Meters", "Centimeters", "Millimeters", "Inches"];
+pub const UP_AXES: [&str; 2] = ["Y Up", "Z Up"];
+pub const PIVOTS: [&str; 3] = ["Keep", "Center", "Base"];
+// offered by the file picker
+pub const MODEL_EXTENSIONS: [&str; 3] = ["glb", "gltf", "obj"];
+
+const ARRAY_BUFFER: u64 = 34962;
+const ELEMENT_ARRAY_BUFFER: u64 = 34963;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UpAxis {
+    Y,
+    // Blender and most CAD tools
+    Z,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pivot {
+    Keep,
+    // the middle of the model's bounds
+    Center,
+    // the middle of the bottom of its bounds, so it sits on the ground
+    Base,
+}
+
+/// How a model's vertices are changed on their way into the project
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelImportOptions {
+    pub unit_scale: f32,
+    pub up_axis: UpAxis,
+    pub pivot: Pivot,
+}
+
+impl Default for ModelImportOptions {
+    fn default() -> Self {
+        ModelImportOptions {
+            unit_scale: 1.0,
+            up_axis: UpAxis::Y,
+            pivot: Pivot::Keep,
+        }
+    }
+}
+
+impl ModelImportOptions {
+    /// Reads the unit, axis and pivot choices from the import form
+    pub fn from_choices(unit: &str, up_axis: &str, pivot: &str) -> ModelImportOptions {
+        ModelImportOptions {
+            unit_scale: match unit {
+                "Centimeters" => 0.01,
+                "Millimeters" => 0.001,
+                "Inches" => 0.0254,
+                _ => 1.0,
+            },
+            up_axis: match up_axis {
+                "Z Up" => UpAxis::Z,
+                _ => UpAxis::Y,
+            },
+            pivot: match pivot {
+                "Center" => Pivot::Center,
+                "Base" => Pivot::Base,
+                _ => Pivot::Keep,
+            },
+        }
+    }
+
+    fn is_identity(&self) -> bool {
+        *self == ModelImportOptions::default()
+    }
+
+    fn axis_rotation(&self) -> UnitQuaternion<f32> {
+        match self.up_axis {
+            UpAxis::Y => UnitQuaternion::identity(),
+            // turns +Z into +Y
+            UpAxis::Z => UnitQuaternion::from_axis_angle(&Vector3::x_axis(), -FRAC_PI_2),
+        }
+    }
+
+    // scaling and axis conversion, without the pivot
+    fn orient(&self, value: [f32; 3], scale: bool) -> [f32; 3] {
+        let factor = if scale { self.unit_scale } else { 1.0 };
+        let [x, y, z] = value;
+        match self.up_axis {
+            UpAxis::Y => [x * factor, y * factor, z * factor],
+            UpAxis::Z => [x * factor, z * factor, -y * factor],
+        }
+    }
+}
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = if bytes[i] == b'%' && i + 2 < bytes.len() {
+            std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// The bytes behind a buffer or image uri, embedded or next to the model file
+fn read_uri(uri: &str, base_dir: &Path) -> Result<Vec<u8>, String> {
+    if uri.starts_with("data:") {
+        let (_, data) = uri
+            .split_once(";base64,")
+            .ok_or("Unsupported data uri in model".to_string())?;
+        return decode(data).map_err(|e| format!("Couldn't decode embedded data: {}", e));
+    }
+
+    // a model may only pull in files from its own folder
+    let relative = percent_decode(uri);
+    if Path::new(&relative).is_absolute() || relative.contains("://") {
+        return Err(format!("Model points outside its folder: {}", uri));
+    }
+
+    let path = base_dir.join(&relative);
+    let read_error = |e: std::io::Error| format!("Couldn't read {}: {}", path.display(), e);
+    let canonical = path.canonicalize().map_err(read_error)?;
+    let base_dir = base_dir
+        .canonicalize()
+        .map_err(|e| format!("Couldn't read {}: {}", base_dir.display(), e))?;
+    if !canonical.starts_with(&base_dir) {
+        return Err(format!("Model points outside its folder: {}", uri));
+    }
+
+    fs::read(&canonical).map_err(read_error)
+}
+
+/// The contents of every buffer in the model, in the order they're listed
+fn load_buffers(
+    json: &Value,
+    glb_bin: Option<Vec<u8>>,
+    base_dir: &Path,
+) -> Result<Vec<Vec<u8>>, String> {
+    let mut glb_bin = glb_bin;
+
+    json.get("buffers")
+        .and_then(|b| b.as_array())
+        .cloned()
+        .unwrap_or_default()
+        .iter()
+        .map(|buffer| match buffer.get("uri").and_then(|u| u.as_str()) {
+            Some(uri) => read_uri(uri, base_dir),
+            // only a GLB's first buffer may leave out its uri
+            None => glb_bin
+                .take()
+                .ok_or("Model has a buffer without any data".to_string()),
+        })
+        .collect()
+}
+
+/// Moves images that point at files or data uris into buffers of their own
+fn embed_images(
+    json: &mut Value,
+    buffers: &mut Vec<Vec<u8>>,
+    base_dir: &Path,
+) -> Result<(), String> {
+    let image_count = json
+        .get("images")
+        .and_then(|i| i.as_array())
+        .map(|i| i.len())
+        .unwrap_or(0);
+
+    for index in 0..image_count {
+        let uri = match json["images"][index].get("uri").and_then(|u| u.as_str()) {
+            Some(uri) => uri.to_string(),
+            None => continue,
+        };
+
+        let bytes = read_uri(&uri, base_dir)?;
+        let mime = detect_mime(&bytes)
+            .map(|(mime, _)| mime.to_string())
+            .unwrap_or_else(|| "image/png".to_string());
+
+        let buffer_index = buffers.len();
+        let byte_length = bytes.len();
+        buffers.push(bytes);
+
+        let buffers_json = json["buffers"]
+            .as_array_mut()
+            .ok_or("Model has images but no buffers".to_string())?;
+        buffers_json.push(json!({ "byteLength": byte_length }));
+
+        if json.get("bufferViews").is_none() {
+            json["bufferViews"] = json!([]);
+        }
+        let views = json["bufferViews"]
+            .as_array_mut()
+            .expect("Couldn't get bufferViews");
+        views.push(json!({ "buffer": buffer_index, "byteOffset": 0, "byteLength": byte_length }));
+        let view_index = views.len() - 1;
+
+        let image = json["images"][index]
+            .as_object_mut()
+            .expect("Couldn't get image");
+        image.remove("uri");
+        image.insert("bufferView".to_string(), json!(view_index));
+        image.insert("mimeType".to_string(), json!(mime));
+    }
+
+    Ok(())
+}
+
+/// Joins every buffer into the single binary chunk of a GLB
+fn merge_buffers(json: &mut Value, buffers: Vec<Vec<u8>>) -> Vec<u8> {
+    let mut bin: Vec<u8> = Vec::new();
+    let mut offsets = Vec::with_capacity(buffers.len());
+
+    for buffer in buffers.iter() {
+        offsets.push(bin.len() as u64);
+        bin.extend_from_slice(buffer);
+        while bin.len() % 4 != 0 {
+            bin.push(0);
+        }
+    }
+
+    if let Some(views) = json.get_mut("bufferViews").and_then(|v| v.as_array_mut()) {
+        for view in views.iter_mut() {
+            let buffer = view.get("buffer").and_then(|b| b.as_u64()).unwrap_or(0) as usize;
+            let offset = view.get("byteOffset").and_then(|o| o.as_u64()).unwrap_or(0);
+            view["buffer"] = json!(0);
+            view["byteOffset"] = json!(offset + offsets.get(buffer).copied().unwrap_or(0));
+        }
+    }
+
+    json["buffers"] = json!([{ "byteLength": bin.len() }]);
+
+    bin
+}
+
+/// Reads an OBJ into a single mesh, triangulating faces and making flat normals where none are given
+fn obj_to_gltf(text: &str) -> Result<(Value, Vec<u8>), String> {
+    let mut obj_positions: Vec<[f32; 3]> = Vec::new();
+    let mut obj_texcoords: Vec<[f32; 2]> = Vec::new();
+    let mut obj_normals: Vec<[f32; 3]> = Vec::new();
+
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut texcoords: Vec<[f32; 2]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    // (position, texcoord, normal) to vertex, flat normals are keyed by the negated face number
+    let mut vertices: HashMap<(usize, Option<usize>, i64), u32> = HashMap::new();
+    let mut face_count: i64 = 0;
+
+    let parse_floats = |parts: &[&str], line_number: usize| -> Result<Vec<f32>, String> {
+        parts
+            .iter()
+            .map(|p| {
+                p.parse::<f32>()
+                    .map_err(|_| format!("Bad number on OBJ line {}", line_number))
+            })
+            .collect()
+    };
+
+    // OBJ indices start at 1, and negative ones count back from the end
+    let resolve = |index: &str, len: usize, line_number: usize| -> Result<usize, String> {
+        let index: i64 = index
+            .parse()
+            .map_err(|_| format!("Bad face index on OBJ line {}", line_number))?;
+        let resolved = if index < 0 {
+            len as i64 + index
+        } else {
+            index - 1
+        };
+        if resolved < 0 || resolved as usize >= len {
+            return Err(format!(
+                "Face index out of range on OBJ line {}",
+                line_number
+            ));
+        }
+        Ok(resolved as usize)
+    };
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line_number = line_index + 1;
+        let parts: Vec<&str> = line.split_whitespace().collect();
+
+        match parts.first().copied() {
+            Some("v") if parts.len() >= 4 => {
+                let v = parse_floats(&parts[1..4], line_number)?;
+                obj_positions.push([v[0], v[1], v[2]]);
+            }
+            Some("vt") if parts.len() >= 3 => {
+                let v = parse_floats(&parts[1..3], line_number)?;
+                // OBJ's v runs up the image, glTF's runs down
+                obj_texcoords.push([v[0], 1.0 - v[1]]);
+            }
+            Some("vn") if parts.len() >= 4 => {
+                let v = parse_floats(&parts[1..4], line_number)?;
+                obj_normals.push([v[0], v[1], v[2]]);
+            }
+            Some("f") if parts.len() >= 4 => {
+                let mut corners: Vec<(usize, Option<usize>, Option<usize>)> = Vec::new();
+                for corner in parts[1..].iter() {
+                    let mut refs = corner.split('/');
+                    let position = resolve(
+                        refs.next().unwrap_or_default(),
+                        obj_positions.len(),
+                        line_number,
+                    )?;
+                    let texcoord = match refs.next() {
+                        Some(t) if !t.is_empty() => {
+                            Some(resolve(t, obj_texcoords.len(), line_number)?)
+                        }
+                        _ => None,
+                    };
+                    let normal = match refs.next() {
+                        Some(n) if !n.is_empty() => {
+                            Some(resolve(n, obj_normals.len(), line_number)?)
+                        }
+                        _ => None,
+                    };
+                    corners.push((position, texcoord, normal));
+                }
+
+                let flat_normal = {
+                    let a = obj_positions[corners[0].0];
+                    let b = obj_positions[corners[1].0];
+                    let c = obj_positions[corners[2].0];
+                    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+                    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+                    let n = [
+                        u[1] * v[2] - u[2] * v[1],
+                        u[2] * v[0] - u[0] * v[2],
+                        u[0] * v[1] - u[1] * v[0],
+                    ];
+                    let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+                    if length > 0.0 {
+                        [n[0] / length, n[1] / length, n[2] / length]
+                    } else {
+                        [0.0, 1.0, 0.0]
+                    }
+                };
+
+                face_count += 1;
+                let mut face: Vec<u32> = Vec::with_capacity(corners.len());
+                for (position, texcoord, normal) in corners {
+                    let (normal_key, normal_value) = match normal {
+                        Some(normal) => (normal as i64, obj_normals[normal]),
+                        None => (-face_count, flat_normal),
+                    };
+
+                    let vertex = *vertices
+                        .entry((position, texcoord, normal_key))
+                        .or_insert_with(|| {
+                            positions.push(obj_positions[position]);
+                            normals.push(normal_value);
+                            texcoords
+                                .push(texcoord.map(|t| obj_texcoords[t]).unwrap_or([0.0, 0.0]));
+                            (positions.len() - 1) as u32
+                        });
+                    face.push(vertex);
+                }
+
+                for i in 1..face.len() - 1 {
+                    indices.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if indices.is_empty() {
+        return Err("OBJ has no faces".to_string());
+    }
+
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for position in positions.iter() {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(position[axis]);
+            max[axis] = max[axis].max(position[axis]);
+        }
+    }
+
+    let mut bin: Vec<u8> = Vec::new();
+    bin.extend_from_slice(bytemuck::cast_slice(&positions));
+    let normals_offset = bin.len();
+    bin.extend_from_slice(bytemuck::cast_slice(&normals));
+    let texcoords_offset = bin.len();
+    bin.extend_from_slice(bytemuck::cast_slice(&texcoords));
+    let indices_offset = bin.len();
+    bin.extend_from_slice(bytemuck::cast_slice(&indices));
+
+    let json = json!({
+        "asset": { "version": "2.0", "generator": "midpoint OBJ import" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0 }],
+        "meshes": [{
+            "primitives": [{
+                "attributes": { "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2 },
+                "indices": 3,
+                "material": 0
+            }]
+        }],
+        "materials": [{
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [0.8, 0.8, 0.8, 1.0],
+                "metallicFactor": 0.0,
+                "roughnessFactor": 1.0
+            }
+        }],
+        "buffers": [{ "byteLength": bin.len() }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": normals_offset, "target": ARRAY_BUFFER },
+            { "buffer": 0, "byteOffset": normals_offset, "byteLength": texcoords_offset - normals_offset, "target": ARRAY_BUFFER },
+            { "buffer": 0, "byteOffset": texcoords_offset, "byteLength": indices_offset - texcoords_offset, "target": ARRAY_BUFFER },
+            { "buffer": 0, "byteOffset": indices_offset, "byteLength": indices.len() * 4, "target": ELEMENT_ARRAY_BUFFER }
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": FLOAT, "count": positions.len(), "type": "VEC3",
+              "min": min, "max": max },
+            { "bufferView": 1, "componentType": FLOAT, "count": normals.len(), "type": "VEC3" },
+            { "bufferView": 2, "componentType": FLOAT, "count": texcoords.len(), "type": "VEC2" },
+            { "bufferView": 3, "componentType": UNSIGNED_INT, "count": indices.len(), "type": "SCALAR" }
+        ]
+    });
+
+    Ok((json, bin))
+}
+
+/// Rewrites the first three components of every vector in the accessor, returning their new bounds
+fn map_vectors(
+    json: &Value,
+    bin: &mut [u8],
+    accessor: usize,
+    map: impl Fn([f32; 3]) -> [f32; 3],
+) -> Result<([f32; 3], [f32; 3]), String> {
+    let layout = accessor_layout(json, accessor, bin)?;
+    if layout.component_type != FLOAT || layout.element_size < 12 {
+        return Err("Models with quantized vertices can't be scaled or re-oriented".to_string());
+    }
+
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+
+    for i in 0..layout.count {
+        let at = layout.offset + layout.stride * i;
+        let value = map(read_vector(bin, at));
+        write_vector(bin, at, value);
+        for axis in 0..3 {
+            min[axis] = min[axis].min(value[axis]);
+            max[axis] = max[axis].max(value[axis]);
+        }
+    }
+
+    Ok((min, max))
+}
+
+fn set_bounds(json: &mut Value, accessor: usize, min: [f32; 3], max: [f32; 3]) {
+    if json["accessors"][accessor].get("min").is_some() {
+        json["accessors"][accessor]["min"] = json!(min);
+        json["accessors"][accessor]["max"] = json!(max);
+    }
+}
+
+/// Re-expresses node transforms in the converted space, so nested parts stay where they were
+fn transform_nodes(json: &mut Value, options: &ModelImportOptions) {
+    let rotation = options.axis_rotation();
+    let basis = Matrix4::new_scaling(options.unit_scale) * rotation.to_homogeneous();
+    let inverse_basis = basis.try_inverse().unwrap_or_else(Matrix4::identity);
+
+    let nodes = match json.get_mut("nodes").and_then(|n| n.as_array_mut()) {
+        Some(nodes) => nodes,
+        None => return,
+    };
+
+    for node in nodes.iter_mut() {
+        let matrix = read_floats(&node["matrix"]);
+        if matrix.len() == 16 {
+            let converted = basis * Matrix4::from_column_slice(&matrix) * inverse_basis;
+            node["matrix"] = json!(converted.as_slice());
+            continue;
+        }
+
+        let translation = read_floats(&node["translation"]);
+        if translation.len() == 3 {
+            node["translation"] =
+                json!(options.orient([translation[0], translation[1], translation[2]], true));
+        }
+
+        let node_rotation = read_floats(&node["rotation"]);
+        if node_rotation.len() == 4 {
+            let [x, y, z, w] = [
+                node_rotation[0],
+                node_rotation[1],
+                node_rotation[2],
+                node_rotation[3],
+            ];
+            let converted = rotation
+                * UnitQuaternion::new_normalize(Quaternion::new(w, x, y, z))
+                * rotation.inverse();
+            node["rotation"] = json!([converted.i, converted.j, converted.k, converted.w]);
+        }
+
+        let scale = read_floats(&node["scale"]);
+        if scale.len() == 3 && options.up_axis == UpAxis::Z {
+            node["scale"] = json!([scale[0], scale[2], scale[1]]);
+        }
+    }
+}
+
+/// Bounds of the default scene as it's drawn, with every node's transform applied
+fn world_bounds(json: &Value, bin: &[u8]) -> Result<Option<([f32; 3], [f32; 3])>, String> {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    let mut any = false;
+
+    visit_scene_meshes(json, |mesh, world| {
+        for primitive in json["meshes"][mesh]["primitives"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+        {
+            let accessor = match primitive["attributes"]["POSITION"].as_u64() {
+                Some(accessor) => accessor as usize,
+                None => continue,
+            };
+            for [x, y, z] in read_vectors(json, bin, accessor)? {
+                let point = world.transform_point(&Point3::new(x, y, z));
+                for axis in 0..3 {
+                    min[axis] = min[axis].min(point[axis]);
+                    max[axis] = max[axis].max(point[axis]);
+                }
+                any = true;
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(any.then_some((min, max)))
+}
+
+/// Moves the scene's top-level nodes, which carries every mesh under them along
+fn shift_roots(json: &mut Value, shift: [f32; 3]) {
+    let offset = Matrix4::new_translation(&Vector3::new(-shift[0], -shift[1], -shift[2]));
+
+    for root in scene_roots(json) {
+        let node = &mut json["nodes"][root];
+        let matrix = read_floats(&node["matrix"]);
+        if matrix.len() == 16 {
+            let shifted = offset * Matrix4::from_column_slice(&matrix);
+            node["matrix"] = json!(shifted.as_slice());
+            continue;
+        }
+
+        // translation is applied last, so moving the node is only a change to it
+        let translation = match read_floats(&node["translation"]).as_slice() {
+            [x, y, z] => [*x, *y, *z],
+            _ => [0.0, 0.0, 0.0],
+        };
+        node["translation"] = json!([
+            translation[0] - shift[0],
+            translation[1] - shift[1],
+            translation[2] - shift[2]
+        ]);
+    }
+}
+
+/// Bakes the unit scale and up axis into the vertices of every mesh, then moves the pivot
+fn transform_meshes(
+    json: &mut Value,
+    bin: &mut [u8],
+    options: &ModelImportOptions,
+) -> Result<(), String> {
+    if options.is_identity() {
+        return Ok(());
+    }
+    let has_any = |name: &str| {
+        json.get(name)
+            .and_then(|s| s.as_array())
+            .map_or(false, |s| !s.is_empty())
+    };
+    if has_any("skins") || has_any("animations") {
+        return Err(
+            "Skinned and animated models can only be imported in meters, Y up, with their pivot kept"
+                .to_string(),
+        );
+    }
+
+    transform_nodes(json, options);
+
+    // accessors can be shared between primitives, so each is changed once
+    let mut positions = BTreeSet::new();
+    let mut offsets = BTreeSet::new();
+    let mut directions = BTreeSet::new();
+
+    let accessor_of = |attributes: &Value, name: &str| {
+        attributes
+            .get(name)
+            .and_then(|a| a.as_u64())
+            .map(|a| a as usize)
+    };
+
+    for mesh in json["meshes"].as_array().cloned().unwrap_or_default() {
+        for primitive in mesh["primitives"].as_array().cloned().unwrap_or_default() {
+            let attributes = &primitive["attributes"];
+            positions.extend(accessor_of(attributes, "POSITION"));
+            directions.extend(accessor_of(attributes, "NORMAL"));
+            directions.extend(accessor_of(attributes, "TANGENT"));
+
+            // morph targets hold offsets from the base mesh
+            for target in primitive["targets"].as_array().cloned().unwrap_or_default() {
+                offsets.extend(accessor_of(&target, "POSITION"));
+                directions.extend(accessor_of(&target, "NORMAL"));
+                directions.extend(accessor_of(&target, "TANGENT"));
+            }
+        }
+    }
+
+    for accessor in positions.iter() {
+        let (accessor_min, accessor_max) =
+            map_vectors(json, bin, *accessor, |v| options.orient(v, true))?;
+        set_bounds(json, *accessor, accessor_min, accessor_max);
+    }
+    for accessor in offsets.iter() {
+        let (offset_min, offset_max) =
+            map_vectors(json, bin, *accessor, |v| options.orient(v, true))?;
+        set_bounds(json, *accessor, offset_min, offset_max);
+    }
+    for accessor in directions.iter() {
+        map_vectors(json, bin, *accessor, |v| options.orient(v, false))?;
+    }
+
+    // measured as drawn, since nodes can move, turn and scale the meshes under them
+    let (min, max) = match world_bounds(json, bin)? {
+        Some(bounds) if options.pivot != Pivot::Keep => bounds,
+        _ => return Ok(()),
+    };
+    let center = [
+        (min[0] + max[0]) * 0.5,
+        (min[1] + max[1]) * 0.5,
+        (min[2] + max[2]) * 0.5,
+    ];
+    let shift = match options.pivot {
+        Pivot::Keep => [0.0, 0.0, 0.0],
+        Pivot::Center => center,
+        Pivot::Base => [center[0], min[1], center[2]],
+    };
+    shift_roots(json, shift);
+
+    Ok(())
+}
+
+/// Reads a GLB, glTF or OBJ into a self-contained GLB with the import options applied
+pub fn prepare_model(path: &Path, options: &ModelImportOptions) -> Result<Vec<u8>, String> {
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    let read_error = |e: std::io::Error| format!("Couldn't read {}: {}", path.display(), e);
+
+    let (mut json, buffers) = match extension.as_str() {
+        "glb" => {
+            let (json, bin) = read_glb(&fs::read(path).map_err(read_error)?)?;
+            let buffers = load_buffers(&json, bin, base_dir)?;
+            (json, buffers)
+        }
+        "gltf" => {
+            let json: Value = serde_json::from_slice(&fs::read(path).map_err(read_error)?)
+                .map_err(|e| format!("Couldn't parse glTF: {}", e))?;
+            let buffers = load_buffers(&json, None, base_dir)?;
+            (json, buffers)
+        }
+        "obj" => {
+            let (json, bin) = obj_to_gltf(&fs::read_to_string(path).map_err(read_error)?)?;
+            (json, vec![bin])
+        }
+        _ => return Err("Only GLB, glTF and OBJ models can be imported".to_string()),
+    };
+
+    if json.get("buffers").is_none() {
+        json["buffers"] = json!([]);
+    }
+    let mut buffers = buffers;
+    embed_images(&mut json, &mut buffers, base_dir)?;
+    let mut bin = merge_buffers(&mut json, buffers);
+    transform_meshes(&mut json, &mut bin, options)?;
+
+    write_glb(&json, bin)
+}
+
+/// Converts a model from disk and adds it to the open project's models
+pub fn import_model(
+    state_helper: &Arc<Mutex<StateHelper>>,
+    project_id: &str,
+    path: &str,
+    options: &ModelImportOptions,
+) -> Result<File, String> {
+    let path = Path::new(path.trim());
+    let stem = path
+        .file_stem()
+        .and_then(|n| n.to_str())
+        .ok_or("Enter the path of a model file".to_string())?;
+
+    let glb = prepare_model(path, options)?;
+
+    store_and_register(
+        state_helper,
+        || {
+            store_asset(
+                project_id,
+                &AssetKind::Model,
+                &format!("{}.glb", stem),
+                &glb,
+            )
+        },
+        |state_helper, stored| Ok(register_asset(state_helper, &AssetKind::Model, stored).0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::helpers::gltf::read_indices;
+
+    const QUAD: &str = "
+# a unit quad facing up
+v 0 0 0
+v 1 0 0
+v 1 0 1
+v 0 0 1
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+f 1/1 4/4 3/3 2/2
+";
+
+    fn positions(json: &Value, bin: &[u8]) -> Vec<[f32; 3]> {
+        read_vectors(json, bin, 0).unwrap()
+    }
+
+    #[test]
+    fn faces_are_triangulated_with_flat_normals() {
+        let (json, bin) = obj_to_gltf(QUAD).unwrap();
+
+        assert_eq!(positions(&json, &bin).len(), 4);
+        assert_eq!(
+            read_indices(&json, &bin, 3).unwrap(),
+            vec![0, 1, 2, 0, 2, 3]
+        );
+        for normal in read_vectors(&json, &bin, 1).unwrap() {
+            assert_eq!(normal, [0.0, 1.0, 0.0]);
+        }
+        assert_eq!(json["accessors"][0]["min"], json!([0.0, 0.0, 0.0]));
+        assert_eq!(json["accessors"][0]["max"], json!([1.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn texture_coordinates_are_flipped_to_run_down_the_image() {
+        let (json, bin) = obj_to_gltf(QUAD).unwrap();
+        let layout = accessor_layout(&json, 2, &bin).unwrap();
+        let read = |at: usize| f32::from_le_bytes([bin[at], bin[at + 1], bin[at + 2], bin[at + 3]]);
+
+        // the first corner is vt 0 0
+        assert_eq!((read(layout.offset), read(layout.offset + 4)), (0.0, 1.0));
+    }
+
+    #[test]
+    fn given_normals_and_negative_indices_are_used() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 -1\nf -3//1 -2//1 -1//1\n";
+        let (json, bin) = obj_to_gltf(obj).unwrap();
+
+        assert_eq!(
+            positions(&json, &bin),
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+        );
+        assert_eq!(read_vectors(&json, &bin, 1).unwrap()[0], [0.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn uris_are_read_only_from_the_models_folder() {
+        let root = std::env::temp_dir().join(format!("midpoint-{}", uuid::Uuid::new_v4()));
+        let base_dir = root.join("model");
+        fs::create_dir_all(base_dir.join("textures")).unwrap();
+        fs::write(base_dir.join("textures").join("wood grain.png"), b"wood").unwrap();
+        fs::write(root.join("secret.txt"), b"secret").unwrap();
+
+        assert_eq!(
+            read_uri("textures/wood%20grain.png", &base_dir).unwrap(),
+            b"wood".to_vec()
+        );
+
+        let outside = root.join("secret.txt").to_string_lossy().to_string();
+        for uri in [
+            "../secret.txt",
+            "textures/../../secret.txt",
+            outside.as_str(),
+        ] {
+            assert_eq!(
+                read_uri(uri, &base_dir).unwrap_err(),
+                format!("Model points outside its folder: {}", uri)
+            );
+        }
+        assert!(read_uri("missing.bin", &base_dir).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn broken_objs_are_reported() {
+        assert_eq!(
+            obj_to_gltf("v 0 0 0\n").unwrap_err(),
+            "OBJ has no faces".to_string()
+        );
+        assert_eq!(
+            obj_to_gltf("v 0 0 0\nv 1 0 0\nf 1 2 3\n").unwrap_err(),
+            "Face index out of range on OBJ line 3".to_string()
+        );
+        assert_eq!(
+            obj_to_gltf("v 0 zero 0\n").unwrap_err(),
+            "Bad number on OBJ line 1".to_string()
+        );
+    }
+
+    #[test]
+    fn pivots_come_from_the_bounds_as_drawn() {
+        let (mut json, mut bin) = obj_to_gltf(QUAD).unwrap();
+        // the node lifts and doubles the quad, which the mesh's own bounds don't show
+        json["nodes"][0]["translation"] = json!([0.0, 5.0, 0.0]);
+        json["nodes"][0]["scale"] = json!([2.0, 2.0, 2.0]);
+
+        let options = ModelImportOptions {
+            pivot: Pivot::Base,
+            ..ModelImportOptions::default()
+        };
+        transform_meshes(&mut json, &mut bin, &options).unwrap();
+
+        let (min, max) = world_bounds(&json, &bin).unwrap().unwrap();
+        assert_eq!(min, [-1.0, 0.0, -1.0]);
+        assert_eq!(max, [1.0, 0.0, 1.0]);
+        // the vertices themselves are left alone
+        assert_eq!(positions(&json, &bin)[2], [1.0, 0.0, 1.0]);
+    }
+}
@@ -0,0 +1,613 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+use nalgebra::{Matrix4, Point3, Vector3};
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+use super::gltf::{
+    read_floats, read_glb, read_indices, read_texcoords, read_vectors, visit_scene_meshes,
+    TRIANGLES,
+};
+use super::thumbnails::cached_file_hash;
+
+// pixels on the longest edge, twice the browser tile so previews stay sharp
+pub const PREVIEW_SIZE: u32 = 240;
+pub const TURNTABLE_FRAMES: usize = 24;
+
+// rendered this many times larger, then scaled down to smooth the edges
+const SUPERSAMPLE: u32 = 2;
+const FIELD_OF_VIEW: f32 = 30.0;
+// the camera looks down on the model a little, from the front right
+const CAMERA_PITCH: f32 = 25.0;
+const CAMERA_YAW: f32 = 35.0;
+const TURNTABLE_FRAME_TIME: Duration = Duration::from_millis(80);
+const MAX_CONCURRENT_PREVIEWS: usize = 2;
+// textures are shrunk to this before sampling, plenty for a preview this size
+const TEXTURE_SAMPLE_SIZE: u32 = 256;
+
+static PREVIEW_SLOTS: Lazy<Arc<Semaphore>> =
+    Lazy::new(|| Arc::new(Semaphore::new(MAX_CONCURRENT_PREVIEWS)));
+
+struct PreviewTriangle {
+    corners: [Vector3<f32>; 3],
+    color: [f32; 3],
+    // the base color texture, by its place in `PreviewMesh::textures`, and where each corner samples it
+    texture: Option<(usize, [[f32; 2]; 3])>,
+}
+
+/// Every triangle of a model's default scene in world space, ready to be drawn from any angle
+pub struct PreviewMesh {
+    triangles: Vec<PreviewTriangle>,
+    textures: Vec<RgbaImage>,
+    center: Vector3<f32>,
+    radius: f32,
+}
+
+/// A material's base color factor, and its base color texture with the coordinate set it's read by
+fn material_color(json: &Value, primitive: &Value) -> ([f32; 3], Option<(usize, usize)>) {
+    let pbr = match primitive.get("material").and_then(|m| m.as_u64()) {
+        Some(material) => &json["materials"][material as usize]["pbrMetallicRoughness"],
+        // glTF's default material is plain white, drawn light gray so shading shows
+        None => return ([0.8, 0.8, 0.8], None),
+    };
+
+    let color = match read_floats(&pbr["baseColorFactor"]).as_slice() {
+        [r, g, b, ..] => [*r, *g, *b],
+        _ => [1.0, 1.0, 1.0],
+    };
+    let texture = pbr["baseColorTexture"]["index"].as_u64().map(|texture| {
+        let tex_coord = pbr["baseColorTexture"]["texCoord"].as_u64().unwrap_or(0);
+        (texture as usize, tex_coord as usize)
+    });
+
+    (color, texture)
+}
+
+/// Decodes the image behind a glTF texture, if it's embedded in the binary chunk
+fn decode_texture(json: &Value, bin: &[u8], texture: usize) -> Option<RgbaImage> {
+    let image = json["textures"][texture]["source"].as_u64()?;
+    let view =
+        &json["bufferViews"][json["images"][image as usize]["bufferView"].as_u64()? as usize];
+    let offset = view.get("byteOffset").and_then(|o| o.as_u64()).unwrap_or(0) as usize;
+    let length = view.get("byteLength").and_then(|l| l.as_u64())? as usize;
+
+    let decoded = image::load_from_memory(bin.get(offset..offset + length)?).ok()?;
+    Some(
+        decoded
+            .thumbnail(TEXTURE_SAMPLE_SIZE, TEXTURE_SAMPLE_SIZE)
+            .to_rgba8(),
+    )
+}
+
+/// Reads textures as primitives ask for them, each decoded once
+struct TextureCache<'a> {
+    json: &'a Value,
+    bin: &'a [u8],
+    // glTF texture index to its place in `textures`, None if it couldn't be decoded
+    loaded: HashMap<usize, Option<usize>>,
+    textures: Vec<RgbaImage>,
+}
+
+impl<'a> TextureCache<'a> {
+    fn get(&mut self, texture: usize) -> Option<usize> {
+        if let Some(loaded) = self.loaded.get(&texture) {
+            return *loaded;
+        }
+
+        let loaded = decode_texture(self.json, self.bin, texture).map(|image| {
+            self.textures.push(image);
+            self.textures.len() - 1
+        });
+        self.loaded.insert(texture, loaded);
+        loaded
+    }
+}
+
+/// Repeats outside 0..1, like the wrap mode glTF defaults to
+fn sample_texture(texture: &RgbaImage, [u, v]: [f32; 2]) -> [f32; 3] {
+    let x = (u.rem_euclid(1.0) * texture.width() as f32) as u32;
+    let y = (v.rem_euclid(1.0) * texture.height() as f32) as u32;
+    let pixel = texture.get_pixel(x.min(texture.width() - 1), y.min(texture.height() - 1));
+
+    [
+        pixel[0] as f32 / 255.0,
+        pixel[1] as f32 / 255.0,
+        pixel[2] as f32 / 255.0,
+    ]
+}
+
+fn add_mesh(
+    json: &Value,
+    bin: &[u8],
+    mesh: usize,
+    world: &Matrix4<f32>,
+    textures: &mut TextureCache,
+    triangles: &mut Vec<PreviewTriangle>,
+) -> Result<(), String> {
+    for primitive in json["meshes"][mesh]["primitives"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+    {
+        // points and lines don't show up in a preview
+        if primitive
+            .get("mode")
+            .and_then(|m| m.as_u64())
+            .unwrap_or(TRIANGLES)
+            != TRIANGLES
+        {
+            continue;
+        }
+        let position_accessor = match primitive["attributes"]["POSITION"].as_u64() {
+            Some(accessor) => accessor as usize,
+            None => continue,
+        };
+
+        let positions: Vec<Vector3<f32>> = read_vectors(json, bin, position_accessor)?
+            .iter()
+            .map(|p| world.transform_point(&Point3::new(p[0], p[1], p[2])).coords)
+            .collect();
+        let indices = match primitive.get("indices").and_then(|i| i.as_u64()) {
+            Some(accessor) => read_indices(json, bin, accessor as usize)?,
+            None => (0..positions.len()).collect(),
+        };
+        let (color, texture) = material_color(json, &primitive);
+
+        // a texture without its coordinates, or that can't be read, leaves the base color
+        let texture = texture.and_then(|(texture, tex_coord)| {
+            let accessor =
+                primitive["attributes"][format!("TEXCOORD_{}", tex_coord)].as_u64()? as usize;
+            let coordinates = read_texcoords(json, bin, accessor).ok()?;
+            if coordinates.len() < positions.len() {
+                return None;
+            }
+            Some((textures.get(texture)?, coordinates))
+        });
+
+        for corners in indices.chunks_exact(3) {
+            if corners.iter().any(|i| *i >= positions.len()) {
+                return Err("Model has an index past the end of its vertices".to_string());
+            }
+            triangles.push(PreviewTriangle {
+                corners: [
+                    positions[corners[0]],
+                    positions[corners[1]],
+                    positions[corners[2]],
+                ],
+                color,
+                texture: texture.as_ref().map(|(texture, coordinates)| {
+                    (
+                        *texture,
+                        [
+                            coordinates[corners[0]],
+                            coordinates[corners[1]],
+                            coordinates[corners[2]],
+                        ],
+                    )
+                }),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the triangles of a GLB's default scene
+pub fn load_preview_mesh(glb: &[u8]) -> Result<PreviewMesh, String> {
+    let (json, bin) = read_glb(glb)?;
+    let bin = bin.unwrap_or_default();
+
+    let mut triangles = Vec::new();
+    let mut textures = TextureCache {
+        json: &json,
+        bin: &bin,
+        loaded: HashMap::new(),
+        textures: Vec::new(),
+    };
+    visit_scene_meshes(&json, |mesh, world| {
+        add_mesh(&json, &bin, mesh, world, &mut textures, &mut triangles)
+    })?;
+    let textures = textures.textures;
+
+    if triangles.is_empty() {
+        return Err("Model has no triangles to preview".to_string());
+    }
+
+    let mut min = Vector3::repeat(f32::MAX);
+    let mut max = Vector3::repeat(f32::MIN);
+    for corner in triangles.iter().flat_map(|t| t.corners.iter()) {
+        min = min.inf(corner);
+        max = max.sup(corner);
+    }
+
+    Ok(PreviewMesh {
+        triangles,
+        textures,
+        center: (min + max) * 0.5,
+        radius: ((max - min).norm() * 0.5).max(0.0001),
+    })
+}
+
+/// Draws the mesh with a neutral camera and light, turned `yaw` degrees around its vertical axis.
+/// The background is left transparent.
+pub fn render_preview(mesh: &PreviewMesh, size: u32, yaw: f32) -> RgbaImage {
+    let render_size = size * SUPERSAMPLE;
+    let extent = render_size as f32;
+
+    let yaw = (CAMERA_YAW + yaw).to_radians();
+    let pitch = CAMERA_PITCH.to_radians();
+    let direction = Vector3::new(
+        pitch.cos() * yaw.sin(),
+        pitch.sin(),
+        pitch.cos() * yaw.cos(),
+    );
+    // far enough back that the model's bounding sphere fills the frame
+    let distance = mesh.radius / (FIELD_OF_VIEW.to_radians() * 0.5).sin();
+    let eye = Point3::from(mesh.center + direction * distance);
+
+    let view = Matrix4::look_at_rh(&eye, &Point3::from(mesh.center), &Vector3::y());
+    let projection = Matrix4::new_perspective(
+        1.0,
+        FIELD_OF_VIEW.to_radians(),
+        (distance - mesh.radius).max(distance * 0.01),
+        distance + mesh.radius,
+    );
+    let view_projection = projection * view;
+
+    // the light comes from over the camera's shoulder
+    let light = (direction + Vector3::new(0.0, 0.6, 0.0)).normalize();
+
+    let mut color = RgbaImage::new(render_size, render_size);
+    let mut depth = vec![f32::MAX; (render_size * render_size) as usize];
+
+    for triangle in mesh.triangles.iter() {
+        let [a, b, c] = triangle.corners;
+        let normal = (b - a).cross(&(c - a));
+        if normal.norm() == 0.0 {
+            continue;
+        }
+
+        // lit from both sides, since not every model winds its faces the same way
+        let shade = 0.35 + 0.65 * normal.normalize().dot(&light).abs();
+        let pixel = |base: [f32; 3]| {
+            Rgba([
+                (base[0] * triangle.color[0] * shade * 255.0).clamp(0.0, 255.0) as u8,
+                (base[1] * triangle.color[1] * shade * 255.0).clamp(0.0, 255.0) as u8,
+                (base[2] * triangle.color[2] * shade * 255.0).clamp(0.0, 255.0) as u8,
+                255,
+            ])
+        };
+        let flat = pixel([1.0, 1.0, 1.0]);
+
+        // 1 / w of each corner, to interpolate texture coordinates with perspective
+        let mut inverse_w = [1.0f32; 3];
+        let screen: Vec<Vector3<f32>> = triangle
+            .corners
+            .iter()
+            .enumerate()
+            .map(|(i, corner)| {
+                let clip = view_projection * corner.push(1.0);
+                inverse_w[i] = 1.0 / clip.w;
+                let ndc = clip.xyz() / clip.w;
+                Vector3::new(
+                    (ndc.x * 0.5 + 0.5) * extent,
+                    (0.5 - ndc.y * 0.5) * extent,
+                    ndc.z,
+                )
+            })
+            .collect();
+        let [p0, p1, p2] = [screen[0], screen[1], screen[2]];
+
+        let area = (p1.x - p0.x) * (p2.y - p0.y) - (p2.x - p0.x) * (p1.y - p0.y);
+        if area.abs() < f32::EPSILON {
+            continue;
+        }
+
+        let min_x = p0.x.min(p1.x).min(p2.x).floor().max(0.0) as u32;
+        let max_x = p0.x.max(p1.x).max(p2.x).ceil().min(extent - 1.0).max(0.0) as u32;
+        let min_y = p0.y.min(p1.y).min(p2.y).floor().max(0.0) as u32;
+        let max_y = p0.y.max(p1.y).max(p2.y).ceil().min(extent - 1.0).max(0.0) as u32;
+
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let w0 = ((p1.x - px) * (p2.y - py) - (p2.x - px) * (p1.y - py)) / area;
+                let w1 = ((p2.x - px) * (p0.y - py) - (p0.x - px) * (p2.y - py)) / area;
+                let w2 = 1.0 - w0 - w1;
+                if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                    continue;
+                }
+
+                let z = w0 * p0.z + w1 * p1.z + w2 * p2.z;
+                let index = (y * render_size + x) as usize;
+                if z < depth[index] {
+                    depth[index] = z;
+
+                    let shaded = match &triangle.texture {
+                        Some((texture, [t0, t1, t2])) => {
+                            let weights = [w0 * inverse_w[0], w1 * inverse_w[1], w2 * inverse_w[2]];
+                            let total = weights[0] + weights[1] + weights[2];
+                            let uv = [
+                                (weights[0] * t0[0] + weights[1] * t1[0] + weights[2] * t2[0])
+                                    / total,
+                                (weights[0] * t0[1] + weights[1] * t1[1] + weights[2] * t2[1])
+                                    / total,
+                            ];
+                            pixel(sample_texture(&mesh.textures[*texture], uv))
+                        }
+                        None => flat,
+                    };
+                    color.put_pixel(x, y, shaded);
+                }
+            }
+        }
+    }
+
+    image::imageops::resize(&color, size, size, FilterType::Triangle)
+}
+
+/// Previews live beside the model in a hidden `.previews` folder, named after the model and its
+/// SHA-256, so a changed model gets a new one
+fn get_preview_path(model_path: &Path, model_hash: &str) -> PathBuf {
+    preview_dir(model_path).join(format!("{}-{}.png", file_name(model_path), model_hash))
+}
+
+fn preview_dir(model_path: &Path) -> PathBuf {
+    model_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(".previews")
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Deletes the model's previews of contents it no longer has
+fn remove_stale_previews(model_path: &Path, preview_path: &Path) {
+    let prefix = format!("{}-", file_name(model_path));
+    let entries = match fs::read_dir(preview_dir(model_path)) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // only this model's previews, not those of a model whose name starts the same way
+        let hash = match name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".png"))
+        {
+            Some(hash) => hash,
+            None => continue,
+        };
+        let is_hash = hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit());
+
+        if is_hash && entry.path() != preview_path {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+fn cache_preview(preview: &RgbaImage, preview_path: &Path) -> Result<(), String> {
+    let dir = preview_path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+
+    // written beside the preview then moved into place, so a half-written one is never read
+    let tmp_path = dir.join(format!("{}.tmp", Uuid::new_v4()));
+    let result = preview
+        .save_with_format(&tmp_path, ImageFormat::Png)
+        .map_err(|e| e.to_string())
+        .and_then(|_| fs::rename(&tmp_path, preview_path).map_err(|e| e.to_string()));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+
+    result
+}
+
+/// Loads the model's cached preview, rendering it if there isn't one for its contents yet
+pub fn load_model_preview(model_path: &Path) -> Result<DynamicImage, String> {
+    // only hashed again when the model's size or modified time changes
+    let preview_path = get_preview_path(model_path, &cached_file_hash(model_path)?);
+
+    if let Ok(preview) = image::open(&preview_path) {
+        return Ok(preview);
+    }
+
+    let glb = fs::read(model_path)
+        .map_err(|e| format!("Couldn't read {}: {}", model_path.display(), e))?;
+    let preview = render_preview(&load_preview_mesh(&glb)?, PREVIEW_SIZE, 0.0);
+
+    // a failed write only costs rendering the preview again next time
+    match cache_preview(&preview, &preview_path) {
+        Ok(()) => remove_stale_previews(model_path, &preview_path),
+        Err(e) => println!("Couldn't cache preview of {}: {}", model_path.display(), e),
+    }
+
+    Ok(DynamicImage::ImageRgba8(preview))
+}
+
+/// Renders or loads the preview off the UI thread, sending None if the model can't be drawn
+pub fn request_model_preview(model_path: PathBuf, tx: UnboundedSender<Option<DynamicImage>>) {
+    tokio::runtime::Handle::current().spawn(async move {
+        let _permit = PREVIEW_SLOTS
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("Couldn't get preview slot");
+
+        let result = tokio::task::spawn_blocking(move || {
+            let result = load_model_preview(&model_path);
+            if let Err(e) = &result {
+                println!("Couldn't preview {}: {}", model_path.display(), e);
+            }
+            result.ok()
+        })
+        .await
+        .unwrap_or(None);
+
+        let _ = tx.send(result);
+    });
+}
+
+/// Sends frames of the model turning on the spot until `playing` is cleared.
+/// Frames are rendered once, on the first turn, each taking a preview slot while it's drawn.
+pub fn spawn_turntable(
+    model_path: PathBuf,
+    playing: Arc<AtomicBool>,
+    tx: UnboundedSender<Option<DynamicImage>>,
+) {
+    tokio::runtime::Handle::current().spawn(async move {
+        let mesh = {
+            let _permit = PREVIEW_SLOTS
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("Couldn't get preview slot");
+            let path = model_path.clone();
+            tokio::task::spawn_blocking(move || {
+                fs::read(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|glb| load_preview_mesh(&glb))
+            })
+            .await
+            .unwrap_or_else(|e| Err(e.to_string()))
+        };
+        let mesh = match mesh {
+            Ok(mesh) => Arc::new(mesh),
+            Err(e) => {
+                println!("Couldn't preview {}: {}", model_path.display(), e);
+                return;
+            }
+        };
+
+        let mut frames: Vec<DynamicImage> = Vec::with_capacity(TURNTABLE_FRAMES);
+        let mut frame = 0;
+
+        while playing.load(Ordering::Relaxed) {
+            if frames.len() <= frame {
+                let _permit = PREVIEW_SLOTS
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .expect("Couldn't get preview slot");
+                // the pointer may have left while waiting for the slot
+                if !playing.load(Ordering::Relaxed) {
+                    return;
+                }
+
+                let mesh = Arc::clone(&mesh);
+                let yaw = 360.0 * frame as f32 / TURNTABLE_FRAMES as f32;
+                let rendered =
+                    tokio::task::spawn_blocking(move || render_preview(&mesh, PREVIEW_SIZE, yaw))
+                        .await;
+                match rendered {
+                    Ok(rendered) => frames.push(DynamicImage::ImageRgba8(rendered)),
+                    Err(_) => return,
+                }
+            }
+
+            if tx.send(Some(frames[frame].clone())).is_err() {
+                return;
+            }
+
+            frame = (frame + 1) % TURNTABLE_FRAMES;
+            tokio::time::sleep(TURNTABLE_FRAME_TIME).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::helpers::local_provider::{textured_glb, Primitive};
+
+    fn solid(color: [u8; 3]) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            8,
+            8,
+            Rgba([color[0], color[1], color[2], 255]),
+        ))
+    }
+
+    fn center_pixel(mesh: &PreviewMesh) -> Rgba<u8> {
+        *render_preview(mesh, 32, 0.0).get_pixel(16, 16)
+    }
+
+    #[test]
+    fn previews_of_older_contents_are_removed() {
+        let model_dir = std::env::temp_dir().join(format!("midpoint-{}", Uuid::new_v4()));
+        let model_path = model_dir.join("tree.glb");
+        let (old_hash, new_hash) = ("a".repeat(64), "b".repeat(64));
+        fs::create_dir_all(preview_dir(&model_path)).unwrap();
+
+        let old_preview = get_preview_path(&model_path, &old_hash);
+        let new_preview = get_preview_path(&model_path, &new_hash);
+        let other_model = get_preview_path(&model_dir.join("tree.glb-2.glb"), &old_hash);
+        for path in [&old_preview, &new_preview, &other_model] {
+            fs::write(path, b"png").unwrap();
+        }
+        assert_eq!(
+            new_preview,
+            model_dir
+                .join(".previews")
+                .join(format!("tree.glb-{}.png", new_hash))
+        );
+
+        remove_stale_previews(&model_path, &new_preview);
+        assert!(!old_preview.exists());
+        assert!(new_preview.exists());
+        assert!(other_model.exists());
+
+        fs::remove_dir_all(&model_dir).unwrap();
+    }
+
+    #[test]
+    fn base_color_textures_are_sampled() {
+        let glb = textured_glb(Primitive::Cube, &solid([220, 20, 20])).unwrap();
+        let mesh = load_preview_mesh(&glb).unwrap();
+        assert_eq!(mesh.textures.len(), 1);
+
+        let pixel = center_pixel(&mesh);
+        assert_eq!(pixel[3], 255);
+        assert!(pixel[0] > pixel[1] * 3 && pixel[0] > pixel[2] * 3);
+    }
+
+    #[test]
+    fn textures_are_wrapped_past_the_edges() {
+        let texture = RgbaImage::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 255, 255])
+            }
+        });
+
+        assert_eq!(sample_texture(&texture, [0.25, 0.5]), [1.0, 0.0, 0.0]);
+        assert_eq!(sample_texture(&texture, [1.75, 0.5]), [0.0, 0.0, 1.0]);
+        assert_eq!(sample_texture(&texture, [-0.75, 0.5]), [1.0, 0.0, 0.0]);
+        assert_eq!(sample_texture(&texture, [1.0, 1.0]), [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn the_background_stays_transparent() {
+        let glb = textured_glb(Primitive::Cube, &solid([255, 255, 255])).unwrap();
+        let preview = render_preview(&load_preview_mesh(&glb).unwrap(), 32, 0.0);
+
+        assert_eq!(preview.get_pixel(0, 0)[3], 0);
+    }
+}
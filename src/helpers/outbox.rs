@@ -0,0 +1,528 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use chrono::Local;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::editor_state::{send_ui_message, FileSignals, StateHelper, UIMessage};
+
+use super::asset_store::{store_base64, AssetKind};
+use super::auth::{report_auth_failure, AuthStatus};
+use super::generation::{find_generation, record_generation, GenerationOptions, GenerationRecord};
+use super::imports::{register_asset, store_and_register};
+use super::providers::{project_provider, GenerationError};
+use super::usage::{check_spending_limits, record_usage, UsageEntry};
+use super::utilities::{change_extension_to_glb, get_filename, get_project_dir};
+
+// outbox.json is read and rewritten from the UI, the WebSocket thread and flushes, one lock per project
+static OUTBOX_LOCKS: Lazy<Mutex<HashMap<String, Arc<Mutex<()>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// projects whose outbox is being replayed, so a project's entries are only sent once at a time
+static FLUSHING: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GenerationRequest {
+    Concept {
+        prompt: String,
+        #[serde(default)]
+        options: GenerationOptions,
+    },
+    Texture {
+        prompt: String,
+        #[serde(default)]
+        options: GenerationOptions,
+    },
+    Model {
+        image_url: String,
+        filename: String,
+    },
+}
+
+impl GenerationRequest {
+    pub fn kind_label(&self) -> &'static str {
+        match self {
+            GenerationRequest::Concept { .. } => "Concept",
+            GenerationRequest::Texture { .. } => "Texture",
+            GenerationRequest::Model { .. } => "Model",
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        match self {
+            GenerationRequest::Concept { prompt, .. } => prompt.clone(),
+            GenerationRequest::Texture { prompt, .. } => prompt.clone(),
+            GenerationRequest::Model { filename, .. } => filename.clone(),
+        }
+    }
+
+    /// Settles the seed and defaults up front, so a queued request reruns identically
+    pub fn resolved(self) -> GenerationRequest {
+        match self {
+            GenerationRequest::Concept { prompt, options } => GenerationRequest::Concept {
+                prompt,
+                options: options.resolved(),
+            },
+            GenerationRequest::Texture { prompt, options } => GenerationRequest::Texture {
+                prompt,
+                options: options.resolved(),
+            },
+            request => request,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub id: String,
+    pub request: GenerationRequest,
+    pub created: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    // failed in a way another attempt won't fix, kept until it's discarded
+    #[serde(default)]
+    pub failed: bool,
+}
+
+pub fn get_outbox_path(project_id: &str) -> PathBuf {
+    get_project_dir(project_id).join("outbox.json")
+}
+
+fn outbox_lock(project_id: &str) -> Arc<Mutex<()>> {
+    let mut locks = OUTBOX_LOCKS.lock().unwrap();
+    Arc::clone(locks.entry(project_id.to_string()).or_default())
+}
+
+pub fn load_outbox(project_id: &str) -> Vec<OutboxEntry> {
+    let outbox_path = get_outbox_path(project_id);
+
+    match fs::read_to_string(&outbox_path) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            println!("Couldn't parse outbox: {:?}", e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+// replaced in one rename, so a crash mid-write can't leave a truncated outbox behind
+fn save_outbox(project_id: &str, entries: &[OutboxEntry]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(entries)
+        .map_err(|e| format!("Couldn't serialize outbox: {}", e))?;
+
+    let tmp_path = get_project_dir(project_id).join(format!("outbox-{}.tmp", Uuid::new_v4()));
+    fs::write(&tmp_path, json).map_err(|e| format!("Couldn't write outbox: {}", e))?;
+    fs::rename(&tmp_path, get_outbox_path(project_id)).map_err(|e| {
+        let _ = fs::remove_file(&tmp_path);
+        format!("Couldn't move outbox into place: {}", e)
+    })
+}
+
+/// Loads the outbox, lets `change` edit it and writes it back, all under the project's lock
+fn update_outbox(
+    project_id: &str,
+    change: impl FnOnce(&mut Vec<OutboxEntry>),
+) -> Result<Vec<OutboxEntry>, String> {
+    let lock = outbox_lock(project_id);
+    let _guard = lock.lock().unwrap();
+
+    let mut entries = load_outbox(project_id);
+    change(&mut entries);
+    save_outbox(project_id, &entries)?;
+
+    Ok(entries)
+}
+
+pub fn enqueue_request(
+    project_id: &str,
+    request: GenerationRequest,
+    error: String,
+) -> Result<Vec<OutboxEntry>, String> {
+    update_outbox(project_id, |entries| {
+        entries.push(OutboxEntry {
+            id: Uuid::new_v4().to_string(),
+            request,
+            created: Local::now().to_rfc3339(),
+            attempts: 1,
+            last_error: Some(error),
+            failed: false,
+        });
+    })
+}
+
+pub fn discard_request(project_id: &str, entry_id: &str) -> Result<Vec<OutboxEntry>, String> {
+    update_outbox(project_id, |entries| entries.retain(|e| e.id != entry_id))
+}
+
+pub fn notify_outbox(file_signals: &FileSignals, entries: Vec<OutboxEntry>) {
+    send_ui_message(file_signals, "outbox", UIMessage::UpdateOutbox(entries));
+}
+
+/// Saves one output with its generation record. Outputs of providers that don't sync are
+/// registered straight away, the others once the backend announces the upload. Bytes the
+/// project already has aren't uploaded again, so they're registered against the existing file.
+fn save_output(
+    state_helper: &Arc<Mutex<StateHelper>>,
+    project_id: &str,
+    kind: &AssetKind,
+    name: &str,
+    data: &str,
+    record: GenerationRecord,
+    register: bool,
+) -> Result<String, String> {
+    store_and_register(
+        state_helper,
+        || {
+            let stored = store_base64(project_id, kind, name, data)?;
+            record_generation(project_id, &kind.folder(), &stored.file.fileName, record);
+            Ok(stored)
+        },
+        |state_helper, stored| {
+            let file_name = stored.file.fileName.clone();
+            if register || stored.reused {
+                register_asset(state_helper, kind, stored);
+            }
+            Ok(file_name)
+        },
+    )
+}
+
+// the outputs are already paid for, so a ledger that can't be written doesn't discard them
+fn log_usage(project_id: &str, entry: UsageEntry) {
+    if let Err(e) = record_usage(project_id, entry) {
+        println!("{}", e);
+    }
+}
+
+/// What a request saved, and what's left of it if a later variation failed after earlier ones arrived
+#[derive(Debug, Clone)]
+pub struct RequestOutcome {
+    pub saved_files: Vec<String>,
+    pub unfinished: Option<(GenerationError, GenerationRequest)>,
+}
+
+/// Sends a generation request to the project's provider and saves the result into the project folder
+pub async fn run_request(
+    state_helper: &Arc<Mutex<StateHelper>>,
+    auth_token: String,
+    project_id: String,
+    request: &GenerationRequest,
+    requested: &str,
+) -> Result<RequestOutcome, GenerationError> {
+    check_spending_limits(&project_id)?;
+
+    let provider = project_provider(&project_id);
+    let backend_version = provider.version().await;
+    let register = !provider.syncs_outputs();
+    let mut saved_files = Vec::new();
+    let mut unfinished = None;
+
+    match request {
+        GenerationRequest::Concept { prompt, options } => {
+            let concepts = provider
+                .generate_concepts(&auth_token, prompt, options)
+                .await?;
+            log_usage(
+                &project_id,
+                UsageEntry::new(
+                    "concepts",
+                    prompt,
+                    &auth_token,
+                    concepts.output.len(),
+                    concepts.credits,
+                    backend_version.clone(),
+                ),
+            );
+            if let Some(error) = concepts.failure {
+                let rest = GenerationRequest::Concept {
+                    prompt: prompt.clone(),
+                    options: options.remaining_variations(concepts.output.len()),
+                };
+                unfinished = Some((error, rest));
+            }
+
+            for (concept, variation) in concepts.output.into_iter().zip(options.variations()) {
+                saved_files.push(save_output(
+                    state_helper,
+                    &project_id,
+                    &AssetKind::Concept,
+                    &get_filename(prompt.clone()),
+                    &concept,
+                    GenerationRecord::new(prompt, variation, requested, backend_version.clone()),
+                    register,
+                )?);
+            }
+        }
+        GenerationRequest::Texture { prompt, options } => {
+            let textures = provider
+                .generate_textures(&auth_token, prompt, options)
+                .await?;
+            log_usage(
+                &project_id,
+                UsageEntry::new(
+                    "textures",
+                    prompt,
+                    &auth_token,
+                    textures.output.len(),
+                    textures.credits,
+                    backend_version.clone(),
+                ),
+            );
+            if let Some(error) = textures.failure {
+                let rest = GenerationRequest::Texture {
+                    prompt: prompt.clone(),
+                    options: options.remaining_variations(textures.output.len()),
+                };
+                unfinished = Some((error, rest));
+            }
+
+            for (texture, variation) in textures.output.into_iter().zip(options.variations()) {
+                saved_files.push(save_output(
+                    state_helper,
+                    &project_id,
+                    &AssetKind::Texture,
+                    &get_filename(prompt.clone()),
+                    &texture,
+                    GenerationRecord::new(prompt, variation, requested, backend_version.clone()),
+                    register,
+                )?);
+            }
+        }
+        GenerationRequest::Model {
+            image_url,
+            filename,
+        } => {
+            // concepts that were never uploaded are read from the project folder
+            let image_url = if image_url.is_empty() && register {
+                get_project_dir(&project_id)
+                    .join("concepts")
+                    .join(filename)
+                    .to_string_lossy()
+                    .to_string()
+            } else {
+                image_url.clone()
+            };
+            let model = provider
+                .generate_model(&auth_token, &image_url, filename)
+                .await?;
+
+            // models carry the prompt of the concept they came from
+            let prompt = find_generation(&project_id, "concepts", filename)
+                .map(|record| record.prompt)
+                .unwrap_or_else(|| filename.clone());
+            log_usage(
+                &project_id,
+                UsageEntry::new(
+                    "models",
+                    &prompt,
+                    &auth_token,
+                    1,
+                    model.credits,
+                    backend_version.clone(),
+                ),
+            );
+            saved_files.push(save_output(
+                state_helper,
+                &project_id,
+                &AssetKind::Model,
+                &change_extension_to_glb(filename),
+                &model.output,
+                GenerationRecord {
+                    source_concept: Some(filename.clone()),
+                    ..GenerationRecord::new(
+                        &prompt,
+                        GenerationOptions::default(),
+                        requested,
+                        backend_version,
+                    )
+                },
+                register,
+            )?);
+        }
+    }
+
+    Ok(RequestOutcome {
+        saved_files,
+        unfinished,
+    })
+}
+
+/// Shows a failure that queueing wouldn't fix, signing in again first if the token was refused
+fn report_failure(file_signals: &FileSignals, error: &GenerationError) {
+    if error.is_unauthorized() {
+        report_auth_failure(file_signals, AuthStatus::Rejected);
+    }
+    send_ui_message(
+        file_signals,
+        "outbox",
+        UIMessage::GenerationFailed(error.to_string()),
+    );
+}
+
+/// Queues what's left of a request if the backend couldn't be reached, otherwise shows the failure
+fn queue_or_report(
+    file_signals: &FileSignals,
+    project_id: &str,
+    request: GenerationRequest,
+    error: GenerationError,
+) {
+    match error {
+        GenerationError::Unreachable(e) => {
+            println!("Backend unreachable, adding to outbox: {}", e);
+            match enqueue_request(project_id, request, e) {
+                Ok(entries) => notify_outbox(file_signals, entries),
+                Err(e) => {
+                    println!("{}", e);
+                    send_ui_message(file_signals, "outbox", UIMessage::GenerationFailed(e));
+                }
+            }
+        }
+        e => {
+            println!("Generation failed: {}", e);
+            report_failure(file_signals, &e);
+        }
+    }
+}
+
+/// Runs the request, placing it in the outbox if the backend can't be reached. If only some
+/// variations arrived, they're kept and just the missing ones are queued.
+/// Any other failure, a spent credit limit included, is shown rather than queued. Returns true if the request completed.
+pub async fn submit_request(
+    state_helper: Arc<Mutex<StateHelper>>,
+    auth_token: String,
+    project_id: String,
+    request: GenerationRequest,
+) -> bool {
+    let request = request.resolved();
+    let requested = Local::now().to_rfc3339();
+    let result = run_request(
+        &state_helper,
+        auth_token,
+        project_id.clone(),
+        &request,
+        &requested,
+    )
+    .await;
+    let file_signals = Arc::clone(&state_helper.lock().unwrap().file_signals);
+
+    match result.map(|outcome| outcome.unfinished) {
+        Ok(None) => true,
+        Ok(Some((e, rest))) => {
+            queue_or_report(&file_signals, &project_id, rest, e);
+            false
+        }
+        Err(e) => {
+            queue_or_report(&file_signals, &project_id, request, e);
+            false
+        }
+    }
+}
+
+// a queued request that didn't finish when the outbox was replayed
+struct EntryFailure {
+    id: String,
+    error: String,
+    // failed in a way another attempt won't fix
+    failed: bool,
+    // what's left to send, if some of its variations arrived
+    rest: Option<GenerationRequest>,
+}
+
+/// Replays every queued request in order, keeping the ones that still fail.
+/// A spent credit limit stops the flush, as every later entry would hit it too.
+pub async fn flush_outbox(
+    state_helper: Arc<Mutex<StateHelper>>,
+    auth_token: String,
+    project_id: String,
+) {
+    if !FLUSHING.lock().unwrap().insert(project_id.clone()) {
+        println!("Outbox flush already in progress");
+        return;
+    }
+
+    let file_signals = Arc::clone(&state_helper.lock().unwrap().file_signals);
+    let pending: Vec<OutboxEntry> = load_outbox(&project_id)
+        .into_iter()
+        .filter(|e| !e.failed)
+        .collect();
+
+    if !pending.is_empty() {
+        println!("Flushing {} outbox entries...", pending.len());
+    }
+
+    let mut sent_ids = Vec::new();
+    let mut failures = Vec::new();
+
+    for entry in pending.iter() {
+        let result = run_request(
+            &state_helper,
+            auth_token.clone(),
+            project_id.clone(),
+            &entry.request,
+            &entry.created,
+        )
+        .await;
+
+        // variations that arrived are saved, only the rest stay queued
+        let (error, rest) = match result.map(|outcome| outcome.unfinished) {
+            Ok(None) => {
+                sent_ids.push(entry.id.clone());
+                continue;
+            }
+            Ok(Some((error, rest))) => (error, Some(rest)),
+            Err(error) => (error, None),
+        };
+        let limit_reached = matches!(error, GenerationError::LimitReached(_));
+
+        if !error.is_unreachable() {
+            report_failure(&file_signals, &error);
+        }
+        failures.push(EntryFailure {
+            id: entry.id.clone(),
+            error: error.to_string(),
+            failed: !error.is_unreachable() && !limit_reached,
+            rest,
+        });
+
+        if limit_reached {
+            break;
+        }
+    }
+
+    // reloaded under the lock, so entries queued during the flush are kept
+    let updated = update_outbox(&project_id, |entries| {
+        entries.retain(|e| !sent_ids.contains(&e.id));
+
+        for entry in entries.iter_mut() {
+            if let Some(failure) = failures.iter().find(|f| f.id == entry.id) {
+                entry.attempts += 1;
+                entry.last_error = Some(failure.error.clone());
+                entry.failed = failure.failed;
+                if let Some(rest) = failure.rest.clone() {
+                    entry.request = rest;
+                }
+            }
+        }
+    });
+
+    match updated {
+        Ok(entries) => notify_outbox(&file_signals, entries),
+        Err(e) => println!("{}", e),
+    }
+
+    FLUSHING.lock().unwrap().remove(&project_id);
+}
+
+pub fn spawn_outbox_flush(
+    state_helper: Arc<Mutex<StateHelper>>,
+    auth_token: String,
+    project_id: String,
+) {
+    tokio::runtime::Handle::current().spawn(async move {
+        flush_outbox(state_helper, auth_token, project_id).await;
+    });
+}
@@ -0,0 +1,572 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::Local;
+use midpoint_engine::floem::reactive::SignalGet;
+use midpoint_engine::handlers::{get_camera, handle_add_model};
+use midpoint_engine::helpers::saved_data::{ComponentData, ComponentKind, File, GenericProperties};
+use nalgebra::{Isometry3, Vector3};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::editor_state::{send_ui_message, FileSignals, StateHelper, UIMessage};
+
+use super::collaboration::SceneOperation;
+use super::generation::GenerationOptions;
+use super::outbox::{run_request, GenerationRequest};
+use super::providers::project_provider;
+use super::utilities::get_project_dir;
+
+// how far in front of the camera a component lands when placed at the viewport centre
+const PLACEMENT_DISTANCE: f32 = 10.0;
+
+// pipelines.json is read and rewritten from both the UI and the WebSocket thread
+static PIPELINES_LOCK: Mutex<()> = Mutex::new(());
+
+// jobs with a task currently advancing them, so an upload notice can't start a stage twice
+static ADVANCING: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PipelineStage {
+    Concept,
+    // several concepts came back, waiting for the user to pick one
+    Choose,
+    Model,
+    Place,
+    Done,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PipelineStatus {
+    Running,
+    Waiting(String),
+    Failed(String),
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Placement {
+    ViewportCentre,
+    Position([f32; 3]),
+}
+
+/// Takes a prompt all the way to a placed component, one stage at a time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineJob {
+    pub id: String,
+    pub prompt: String,
+    pub options: GenerationOptions,
+    pub placement: Placement,
+    pub stage: PipelineStage,
+    pub status: PipelineStatus,
+    pub concept_files: Vec<String>,
+    pub chosen_concept: Option<String>,
+    pub model_file: Option<String>,
+    pub component_id: Option<String>,
+    pub created: String,
+}
+
+impl PipelineJob {
+    pub fn stage_label(&self) -> &'static str {
+        match self.stage {
+            PipelineStage::Concept => "Concept",
+            PipelineStage::Choose => "Choose Concept",
+            PipelineStage::Model => "Model",
+            PipelineStage::Place => "Place",
+            PipelineStage::Done => "Done",
+        }
+    }
+
+    pub fn status_text(&self) -> String {
+        match &self.status {
+            PipelineStatus::Running => format!("{}: running", self.stage_label()),
+            PipelineStatus::Waiting(reason) => format!("{}: {}", self.stage_label(), reason),
+            PipelineStatus::Failed(error) => format!("{} failed: {}", self.stage_label(), error),
+            PipelineStatus::Done => "Placed in scene".to_string(),
+        }
+    }
+}
+
+enum StageOutcome {
+    Advanced,
+    Waiting,
+}
+
+pub fn get_pipelines_path(project_id: &str) -> PathBuf {
+    get_project_dir(project_id).join("pipelines.json")
+}
+
+fn read_pipelines(project_id: &str) -> Vec<PipelineJob> {
+    match fs::read_to_string(get_pipelines_path(project_id)) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            println!("Couldn't parse pipelines: {:?}", e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn write_pipelines(project_id: &str, jobs: &[PipelineJob]) {
+    let json = serde_json::to_string_pretty(jobs).expect("Couldn't serialize pipelines");
+    fs::write(get_pipelines_path(project_id), json).expect("Couldn't write pipelines");
+}
+
+pub fn load_pipelines(project_id: &str) -> Vec<PipelineJob> {
+    let _guard = PIPELINES_LOCK.lock().unwrap();
+    read_pipelines(project_id)
+}
+
+fn find_job(project_id: &str, job_id: &str) -> Option<PipelineJob> {
+    load_pipelines(project_id)
+        .into_iter()
+        .find(|job| job.id == job_id)
+}
+
+/// Changes one job and returns every job, for the UI
+fn update_job(
+    project_id: &str,
+    job_id: &str,
+    change: impl FnOnce(&mut PipelineJob),
+) -> Vec<PipelineJob> {
+    let _guard = PIPELINES_LOCK.lock().unwrap();
+
+    let mut jobs = read_pipelines(project_id);
+    if let Some(job) = jobs.iter_mut().find(|job| job.id == job_id) {
+        change(job);
+    }
+    write_pipelines(project_id, &jobs);
+
+    jobs
+}
+
+pub fn notify_pipelines(file_signals: &FileSignals, jobs: Vec<PipelineJob>) {
+    send_ui_message(file_signals, "pipeline", UIMessage::UpdatePipelines(jobs));
+}
+
+fn set_status(project_id: &str, job_id: &str, status: PipelineStatus, file_signals: &FileSignals) {
+    let jobs = update_job(project_id, job_id, |job| job.status = status);
+    notify_pipelines(file_signals, jobs);
+}
+
+pub fn create_job(
+    project_id: &str,
+    prompt: String,
+    options: GenerationOptions,
+    placement: Placement,
+) -> PipelineJob {
+    let job = PipelineJob {
+        id: Uuid::new_v4().to_string(),
+        prompt,
+        // settled now so a retried concept stage gives the same concepts
+        options: options.resolved(),
+        placement,
+        stage: PipelineStage::Concept,
+        status: PipelineStatus::Running,
+        concept_files: Vec::new(),
+        chosen_concept: None,
+        model_file: None,
+        component_id: None,
+        created: Local::now().to_rfc3339(),
+    };
+
+    let _guard = PIPELINES_LOCK.lock().unwrap();
+    let mut jobs = read_pipelines(project_id);
+    jobs.push(job.clone());
+    write_pipelines(project_id, &jobs);
+
+    job
+}
+
+pub fn choose_concept(project_id: &str, job_id: &str, file_name: &str) -> Vec<PipelineJob> {
+    update_job(project_id, job_id, |job| {
+        job.chosen_concept = Some(file_name.to_string());
+        job.stage = PipelineStage::Model;
+        job.status = PipelineStatus::Running;
+    })
+}
+
+pub fn dismiss_job(project_id: &str, job_id: &str) -> Vec<PipelineJob> {
+    let _guard = PIPELINES_LOCK.lock().unwrap();
+
+    let mut jobs = read_pipelines(project_id);
+    jobs.retain(|job| job.id != job_id);
+    write_pipelines(project_id, &jobs);
+
+    jobs
+}
+
+async fn run_concept_stage(
+    state_helper: &Arc<Mutex<StateHelper>>,
+    auth_token: String,
+    project_id: &str,
+    job: &PipelineJob,
+    file_signals: &FileSignals,
+) -> Result<StageOutcome, String> {
+    set_status(project_id, &job.id, PipelineStatus::Running, file_signals);
+
+    let request = GenerationRequest::Concept {
+        prompt: job.prompt.clone(),
+        options: job.options.clone(),
+    };
+    let outcome = run_request(
+        state_helper,
+        auth_token,
+        project_id.to_string(),
+        &request,
+        &job.created,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    // the concepts that did arrive are enough to carry on with
+    if let Some((e, _)) = outcome.unfinished {
+        println!("Some concepts weren't generated: {}", e);
+    }
+    let concept_files = outcome.saved_files;
+
+    if concept_files.is_empty() {
+        return Err("No concepts were generated".to_string());
+    }
+
+    let jobs = update_job(project_id, &job.id, |job| {
+        if concept_files.len() == 1 {
+            job.chosen_concept = Some(concept_files[0].clone());
+            job.stage = PipelineStage::Model;
+            job.status = PipelineStatus::Running;
+        } else {
+            job.stage = PipelineStage::Choose;
+            job.status = PipelineStatus::Waiting("pick a concept".to_string());
+        }
+        job.concept_files = concept_files;
+    });
+    notify_pipelines(file_signals, jobs);
+
+    Ok(StageOutcome::Advanced)
+}
+
+async fn run_model_stage(
+    state_helper: &Arc<Mutex<StateHelper>>,
+    auth_token: String,
+    project_id: &str,
+    job: &PipelineJob,
+    concept: Option<File>,
+    file_signals: &FileSignals,
+) -> Result<StageOutcome, String> {
+    let chosen_concept = job
+        .chosen_concept
+        .clone()
+        .ok_or("No concept was chosen".to_string())?;
+
+    // models are generated from the uploaded image, which arrives with `add_concept`,
+    // or from the file itself when the provider doesn't upload
+    let concept = match concept {
+        Some(concept) => concept,
+        None => {
+            set_status(
+                project_id,
+                &job.id,
+                PipelineStatus::Waiting("waiting for concept upload".to_string()),
+                file_signals,
+            );
+            return Ok(StageOutcome::Waiting);
+        }
+    };
+
+    set_status(project_id, &job.id, PipelineStatus::Running, file_signals);
+
+    let request = GenerationRequest::Model {
+        image_url: concept.cloudfrontUrl,
+        filename: chosen_concept,
+    };
+    let model_file = run_request(
+        state_helper,
+        auth_token,
+        project_id.to_string(),
+        &request,
+        &job.created,
+    )
+    .await
+    .map_err(|e| e.to_string())?
+    .saved_files
+    .into_iter()
+    .next()
+    .ok_or("No model was generated".to_string())?;
+
+    let jobs = update_job(project_id, &job.id, |job| {
+        job.model_file = Some(model_file);
+        job.stage = PipelineStage::Place;
+        job.status = PipelineStatus::Running;
+    });
+    notify_pipelines(file_signals, jobs);
+
+    Ok(StageOutcome::Advanced)
+}
+
+fn request_placement(
+    project_id: &str,
+    job: &PipelineJob,
+    model: Option<File>,
+    file_signals: &FileSignals,
+) -> Result<StageOutcome, String> {
+    // the model needs its asset id from `add_model` before it can be a component
+    let model = match model {
+        Some(model) => model,
+        None => {
+            set_status(
+                project_id,
+                &job.id,
+                PipelineStatus::Waiting("waiting for model upload".to_string()),
+                file_signals,
+            );
+            return Ok(StageOutcome::Waiting);
+        }
+    };
+
+    // loading onto the GPU happens in the project view
+    let scene_signal = file_signals
+        .lock()
+        .unwrap()
+        .get("scene")
+        .cloned()
+        .ok_or("The project view isn't open".to_string())?;
+
+    set_status(project_id, &job.id, PipelineStatus::Running, file_signals);
+
+    scene_signal
+        .send(UIMessage::PlacePipelineModel {
+            job_id: job.id.clone(),
+            model,
+            placement: job.placement,
+        })
+        .map_err(|e| format!("Couldn't reach the project view: {}", e))?;
+
+    Ok(StageOutcome::Waiting)
+}
+
+/// Runs the job's stages until it finishes, fails or has to wait
+async fn advance_pipeline(
+    state_helper: Arc<Mutex<StateHelper>>,
+    project_id: String,
+    job_id: String,
+) {
+    loop {
+        let job = match find_job(&project_id, &job_id) {
+            Some(job) => job,
+            None => return,
+        };
+
+        let syncs_outputs = project_provider(&project_id).syncs_outputs();
+
+        let (auth_token, file_signals, concept, model) = {
+            let state_helper = state_helper.lock().unwrap();
+            let saved_state = state_helper
+                .saved_state
+                .as_ref()
+                .expect("Couldn't get saved state")
+                .lock()
+                .unwrap();
+
+            let concept = saved_state
+                .concepts
+                .iter()
+                .find(|c| {
+                    Some(&c.fileName) == job.chosen_concept.as_ref()
+                        && (!c.cloudfrontUrl.is_empty() || !syncs_outputs)
+                })
+                .cloned();
+            let model = saved_state
+                .models
+                .iter()
+                .find(|m| Some(&m.fileName) == job.model_file.as_ref())
+                .cloned();
+
+            (
+                state_helper.auth_token.clone(),
+                Arc::clone(&state_helper.file_signals),
+                concept,
+                model,
+            )
+        };
+
+        let outcome = match job.stage {
+            PipelineStage::Concept => {
+                run_concept_stage(&state_helper, auth_token, &project_id, &job, &file_signals).await
+            }
+            PipelineStage::Model => {
+                run_model_stage(
+                    &state_helper,
+                    auth_token,
+                    &project_id,
+                    &job,
+                    concept,
+                    &file_signals,
+                )
+                .await
+            }
+            PipelineStage::Place => request_placement(&project_id, &job, model, &file_signals),
+            PipelineStage::Choose | PipelineStage::Done => Ok(StageOutcome::Waiting),
+        };
+
+        match outcome {
+            Ok(StageOutcome::Advanced) => continue,
+            Ok(StageOutcome::Waiting) => return,
+            Err(e) => {
+                println!("Pipeline {} failed: {}", job_id, e);
+                // the stage is kept, so retrying picks up from here
+                set_status(
+                    &project_id,
+                    &job_id,
+                    PipelineStatus::Failed(e),
+                    &file_signals,
+                );
+                return;
+            }
+        }
+    }
+}
+
+/// Starts or resumes a job, unless a task is already advancing it
+pub fn spawn_pipeline(state_helper: Arc<Mutex<StateHelper>>, project_id: String, job_id: String) {
+    if !ADVANCING.lock().unwrap().insert(job_id.clone()) {
+        return;
+    }
+
+    tokio::runtime::Handle::current().spawn(async move {
+        advance_pipeline(state_helper, project_id, job_id.clone()).await;
+        ADVANCING.lock().unwrap().remove(&job_id);
+    });
+}
+
+/// Nudges jobs that were waiting on an upload, called as concepts and models arrive
+pub fn resume_waiting_pipelines(state_helper: Arc<Mutex<StateHelper>>, project_id: String) {
+    for job in load_pipelines(&project_id) {
+        let waiting_for_upload = matches!(job.status, PipelineStatus::Waiting(_))
+            && matches!(job.stage, PipelineStage::Model | PipelineStage::Place);
+
+        if waiting_for_upload {
+            spawn_pipeline(state_helper.clone(), project_id.clone(), job.id);
+        }
+    }
+}
+
+/// Run when a project opens: jobs left running by a previous session are marked for retry
+pub fn resume_pipelines(state_helper: Arc<Mutex<StateHelper>>, project_id: String) {
+    {
+        let _guard = PIPELINES_LOCK.lock().unwrap();
+
+        let mut jobs = read_pipelines(&project_id);
+        for job in jobs.iter_mut() {
+            if job.status == PipelineStatus::Running {
+                job.status = PipelineStatus::Failed("interrupted".to_string());
+            }
+        }
+        write_pipelines(&project_id, &jobs);
+    }
+
+    resume_waiting_pipelines(state_helper, project_id);
+}
+
+fn viewport_centre_position() -> [f32; 3] {
+    let camera = get_camera();
+    let forward = camera.forward_vector();
+
+    [
+        camera.position.x + forward.x * PLACEMENT_DISTANCE,
+        camera.position.y + forward.y * PLACEMENT_DISTANCE,
+        camera.position.z + forward.z * PLACEMENT_DISTANCE,
+    ]
+}
+
+/// Adds the job's model to the scene, must run on the UI thread
+pub fn place_pipeline_model(
+    state_helper: MutexGuard<StateHelper>,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    job_id: &str,
+    model: File,
+    placement: Placement,
+) {
+    let renderer_state = state_helper
+        .renderer_state
+        .as_ref()
+        .expect("Couldn't get RendererState");
+    let project_id = state_helper
+        .project_selected_signal
+        .expect("Couldn't get project signal")
+        .get_untracked();
+
+    let position = match placement {
+        Placement::ViewportCentre => viewport_centre_position(),
+        Placement::Position(position) => position,
+    };
+
+    // different than the asset id, this is the component instance id
+    let component_id = Uuid::new_v4().to_string();
+
+    handle_add_model(
+        renderer_state.clone(),
+        device,
+        queue,
+        project_id.to_string(),
+        model.id.clone(),
+        component_id.clone(),
+        model.fileName.clone(),
+        Isometry3::new(
+            Vector3::new(position[0], position[1], position[2]),
+            Vector3::new(0.0, 0.0, 0.0),
+        ),
+    );
+
+    renderer_state
+        .lock()
+        .unwrap()
+        .add_collider(component_id.clone(), ComponentKind::Model);
+
+    let job = find_job(&project_id.to_string(), job_id);
+    let model_component = ComponentData {
+        id: component_id.clone(),
+        kind: Some(ComponentKind::Model),
+        asset_id: model.id.clone(),
+        generic_properties: GenericProperties {
+            name: job
+                .map(|job| job.prompt)
+                .unwrap_or_else(|| "New Model Component".to_string()),
+            position,
+            rotation: [0.0, 0.0, 0.0],
+            scale: [1.0, 1.0, 1.0],
+        },
+        landscape_properties: None,
+        model_properties: None,
+    };
+
+    let mut saved_state = state_helper
+        .saved_state
+        .as_ref()
+        .expect("Couldn't get saved state")
+        .lock()
+        .unwrap();
+    saved_state
+        .levels
+        .as_mut()
+        .expect("Couldn't get levels")
+        .get_mut(0)
+        .expect("Couldn't get first level")
+        .components
+        .get_or_insert_with(Vec::new)
+        .push(model_component.clone());
+
+    state_helper.save_saved_state(project_id, saved_state);
+
+    state_helper.broadcast_scene_operation(SceneOperation::Add {
+        component: model_component,
+    });
+
+    let jobs = update_job(&project_id.to_string(), job_id, |job| {
+        job.component_id = Some(component_id);
+        job.stage = PipelineStage::Done;
+        job.status = PipelineStatus::Done;
+    });
+    notify_pipelines(&state_helper.file_signals, jobs);
+}
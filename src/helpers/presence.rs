@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::editor_state::{FileSignals, StateHelper, UIMessage};
+
+use super::auth::token_claims;
+use super::collaboration::CLIENT_ID;
+
+pub const PRESENCE_INTERVAL: Duration = Duration::from_secs(5);
+// a few missed heartbeats means the client has gone
+pub const PRESENCE_TIMEOUT: Duration = Duration::from_secs(15);
+
+const PEER_COLORS: [(u8, u8, u8); 6] = [
+    (230, 90, 80),
+    (70, 150, 230),
+    (90, 190, 110),
+    (230, 170, 60),
+    (170, 100, 220),
+    (60, 190, 190),
+];
+
+static PEERS: Lazy<Mutex<HashMap<String, (PresencePeer, Instant)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PresencePeer {
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    pub name: String,
+    #[serde(rename = "selectedComponent")]
+    pub selected_component: Option<String>,
+}
+
+impl PresencePeer {
+    /// Each collaborator keeps the same colour for the whole session
+    pub fn color(&self) -> (u8, u8, u8) {
+        let hash = self.client_id.bytes().fold(0usize, |acc, b| {
+            acc.wrapping_mul(31).wrapping_add(b as usize)
+        });
+
+        PEER_COLORS[hash % PEER_COLORS.len()]
+    }
+}
+
+/// The name shown to collaborators, taken from the token when it has one
+pub fn display_name(auth_token: &str) -> String {
+    let claims = token_claims(auth_token);
+    let claimed_name = claims.as_ref().and_then(|claims| {
+        ["name", "email"]
+            .iter()
+            .find_map(|key| claims.get(*key).and_then(|v| v.as_str()))
+            .map(|name| name.to_string())
+    });
+
+    claimed_name.unwrap_or_else(|| format!("Guest {}", &CLIENT_ID[..4]))
+}
+
+pub fn presence_message(name: String, selected_component: Option<String>) -> String {
+    json!({
+        "event": "presence",
+        "payload": PresencePeer {
+            client_id: CLIENT_ID.clone(),
+            name,
+            selected_component,
+        }
+    })
+    .to_string()
+}
+
+pub fn presence_leave_message() -> String {
+    json!({
+        "event": "presence_leave",
+        "payload": {
+            "clientId": CLIENT_ID.clone()
+        }
+    })
+    .to_string()
+}
+
+/// Records a presence or leave message, returning true if it was one
+pub fn receive_presence(text: &str) -> bool {
+    let message: serde_json::Value = match serde_json::from_str(text) {
+        Ok(message) => message,
+        Err(_) => return false,
+    };
+    let payload = match message.get("payload") {
+        Some(payload) => payload.clone(),
+        None => return false,
+    };
+
+    match message.get("event").and_then(|e| e.as_str()) {
+        Some("presence") => {
+            if let Ok(peer) = serde_json::from_value::<PresencePeer>(payload) {
+                if peer.client_id != *CLIENT_ID {
+                    PEERS
+                        .lock()
+                        .unwrap()
+                        .insert(peer.client_id.clone(), (peer, Instant::now()));
+                }
+            }
+            true
+        }
+        Some("presence_leave") => {
+            if let Some(client_id) = payload.get("clientId").and_then(|c| c.as_str()) {
+                PEERS.lock().unwrap().remove(client_id);
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Drops collaborators we haven't heard from within the timeout
+pub fn expire_peers() {
+    PEERS
+        .lock()
+        .unwrap()
+        .retain(|_, (_, last_seen)| last_seen.elapsed() < PRESENCE_TIMEOUT);
+}
+
+pub fn clear_peers() {
+    PEERS.lock().unwrap().clear();
+}
+
+pub fn current_peers() -> Vec<PresencePeer> {
+    let mut peers: Vec<PresencePeer> = PEERS
+        .lock()
+        .unwrap()
+        .values()
+        .map(|(peer, _)| peer.clone())
+        .collect();
+    peers.sort_by(|a, b| a.name.cmp(&b.name));
+
+    peers
+}
+
+pub fn notify_presence(file_signals: &FileSignals) {
+    let peers = current_peers();
+    let signals = file_signals.lock().unwrap();
+
+    for name in ["presence", "component_browser"] {
+        if let Some(tx) = signals.get(name) {
+            let _ = tx.send(UIMessage::UpdatePresence(peers.clone()));
+        }
+    }
+}
+
+/// Announces this client every few seconds while a project is open, and expires silent peers
+pub fn spawn_presence_heartbeat(state_helper: Arc<Mutex<StateHelper>>) {
+    tokio::runtime::Handle::current().spawn(async move {
+        let mut interval = tokio::time::interval(PRESENCE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let state_helper = state_helper.lock().unwrap();
+            state_helper.broadcast_presence();
+
+            expire_peers();
+            notify_presence(&state_helper.file_signals);
+        }
+    });
+}
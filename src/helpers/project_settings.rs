@@ -0,0 +1,58 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::utilities::get_project_dir;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ProviderKind {
+    // the CommonOS GraphQL server
+    #[default]
+    CommonOS,
+    // procedural output made on this machine, works offline
+    Local,
+}
+
+impl ProviderKind {
+    pub const ALL: [ProviderKind; 2] = [ProviderKind::CommonOS, ProviderKind::Local];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProviderKind::CommonOS => "CommonOS",
+            ProviderKind::Local => "Local (procedural)",
+        }
+    }
+}
+
+/// Per-project editor settings, kept next to the saved state
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectSettings {
+    #[serde(default)]
+    pub generation_provider: ProviderKind,
+    // generation stops once the project's ledger reaches these, none means no limit
+    #[serde(default)]
+    pub credit_limit: Option<f64>,
+    #[serde(default)]
+    pub daily_credit_limit: Option<f64>,
+}
+
+pub fn get_project_settings_path(project_id: &str) -> PathBuf {
+    get_project_dir(project_id).join("settings.json")
+}
+
+pub fn load_project_settings(project_id: &str) -> ProjectSettings {
+    match fs::read_to_string(get_project_settings_path(project_id)) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            println!("Couldn't parse project settings: {:?}", e);
+            ProjectSettings::default()
+        }),
+        Err(_) => ProjectSettings::default(),
+    }
+}
+
+pub fn save_project_settings(project_id: &str, settings: &ProjectSettings) {
+    let json = serde_json::to_string_pretty(settings).expect("Couldn't serialize project settings");
+    fs::write(get_project_settings_path(project_id), json)
+        .expect("Couldn't write project settings");
+}
@@ -0,0 +1,260 @@
+use std::fmt;
+
+use async_trait::async_trait;
+
+use crate::gql::client::GqlError;
+use crate::gql::generateConcept::generate_concept;
+use crate::gql::generateLandscape::generate_landscape;
+use crate::gql::generateModel::generate_model;
+use crate::gql::generateTexture::generate_texture;
+
+use super::generation::{get_backend_version, GenerationOptions};
+use super::landscapes::LandscapeParams;
+use super::local_provider::LocalProvider;
+use super::project_settings::{load_project_settings, ProviderKind};
+
+/// How a generation failed, which decides whether it's worth sending again later
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenerationError {
+    // the backend couldn't be reached, the request can wait in the outbox
+    Unreachable(String),
+    // the token was refused, the user has to sign in again
+    Unauthorized(String),
+    // the project's credit limit is spent, nothing is sent until it's raised
+    LimitReached(String),
+    // anything else, sending it again won't help
+    Failed(String),
+}
+
+impl GenerationError {
+    pub fn is_unreachable(&self) -> bool {
+        matches!(self, GenerationError::Unreachable(_))
+    }
+
+    pub fn is_unauthorized(&self) -> bool {
+        matches!(self, GenerationError::Unauthorized(_))
+    }
+}
+
+impl fmt::Display for GenerationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenerationError::Unreachable(message)
+            | GenerationError::Unauthorized(message)
+            | GenerationError::LimitReached(message)
+            | GenerationError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for GenerationError {
+    fn from(message: String) -> Self {
+        GenerationError::Failed(message)
+    }
+}
+
+impl From<GqlError> for GenerationError {
+    fn from(error: GqlError) -> Self {
+        if error.is_unreachable() {
+            GenerationError::Unreachable(error.to_string())
+        } else if error.is_unauthorized() {
+            GenerationError::Unauthorized(error.to_string())
+        } else {
+            GenerationError::Failed(error.to_string())
+        }
+    }
+}
+
+/// What a generation call produced, with the credits it cost if the provider reports them
+#[derive(Debug, Clone)]
+pub struct Generated<T> {
+    pub output: T,
+    pub credits: Option<f64>,
+    // why a call with several outputs stopped early, the outputs before it are kept
+    pub failure: Option<GenerationError>,
+}
+
+impl<T> Generated<T> {
+    pub fn new(output: T, credits: Option<f64>) -> Self {
+        Generated {
+            output,
+            credits,
+            failure: None,
+        }
+    }
+}
+
+/// The three maps that make up a landscape, as base64 images with or without a data URL prefix
+#[derive(Debug, Clone)]
+pub struct GeneratedLandscape {
+    pub heightmap: String,
+    pub rockmap: String,
+    pub soil: String,
+}
+
+/// Where concepts, textures, models and landscapes come from.
+/// Images are returned as PNG data URLs and models as GLB data URLs, ready for the asset store.
+#[async_trait]
+pub trait GenerationProvider: Send + Sync {
+    /// Recorded with each generated file
+    async fn version(&self) -> Option<String>;
+
+    /// Whether saved outputs reach the project through the backend's `add_*` WebSocket commands.
+    /// Outputs of providers that don't sync are registered as soon as they're saved.
+    fn syncs_outputs(&self) -> bool {
+        true
+    }
+
+    /// One image per entry of `options.variations()`, in the same order. If a variation fails
+    /// after earlier ones arrived, those are returned with the failure rather than an error.
+    async fn generate_concepts(
+        &self,
+        auth_token: &str,
+        prompt: &str,
+        options: &GenerationOptions,
+    ) -> Result<Generated<Vec<String>>, GenerationError>;
+
+    /// One image per entry of `options.variations()`, in the same order, failing like `generate_concepts`
+    async fn generate_textures(
+        &self,
+        auth_token: &str,
+        prompt: &str,
+        options: &GenerationOptions,
+    ) -> Result<Generated<Vec<String>>, GenerationError>;
+
+    // the image is a URL, a data URL or a path in the project folder, and the source name
+    // is the concept's file name, which local providers may use as a hint
+    async fn generate_model(
+        &self,
+        auth_token: &str,
+        image_url: &str,
+        source_name: &str,
+    ) -> Result<Generated<String>, GenerationError>;
+
+    // the id is the one the landscape is saved under, so synced maps can find their way back to it
+    async fn generate_landscape(
+        &self,
+        auth_token: &str,
+        landscape_id: &str,
+        params: &LandscapeParams,
+    ) -> Result<Generated<GeneratedLandscape>, GenerationError>;
+}
+
+/// Generation through the CommonOS GraphQL server
+pub struct CommonOSProvider;
+
+#[async_trait]
+impl GenerationProvider for CommonOSProvider {
+    async fn version(&self) -> Option<String> {
+        get_backend_version().await
+    }
+
+    async fn generate_concepts(
+        &self,
+        auth_token: &str,
+        prompt: &str,
+        options: &GenerationOptions,
+    ) -> Result<Generated<Vec<String>>, GenerationError> {
+        let mut images = Vec::new();
+        let mut credits = Vec::new();
+        let mut failure = None;
+        for variation in options.variations() {
+            match generate_concept(auth_token.to_string(), prompt.to_string(), variation).await {
+                Ok(data) => {
+                    images.push(data.generateConcept);
+                    credits.push(data.credits);
+                }
+                // the variations that arrived are paid for, so they're kept
+                Err(e) if !images.is_empty() => {
+                    failure = Some(GenerationError::from(e));
+                    break;
+                }
+                Err(e) => return Err(GenerationError::from(e)),
+            }
+        }
+
+        // one unreported call leaves the total unknown
+        Ok(Generated {
+            failure,
+            ..Generated::new(images, credits.into_iter().sum())
+        })
+    }
+
+    async fn generate_textures(
+        &self,
+        auth_token: &str,
+        prompt: &str,
+        options: &GenerationOptions,
+    ) -> Result<Generated<Vec<String>>, GenerationError> {
+        let mut images = Vec::new();
+        let mut credits = Vec::new();
+        let mut failure = None;
+        for variation in options.variations() {
+            match generate_texture(auth_token.to_string(), prompt.to_string(), variation).await {
+                Ok(data) => {
+                    images.push(data.generateTexture);
+                    credits.push(data.credits);
+                }
+                Err(e) if !images.is_empty() => {
+                    failure = Some(GenerationError::from(e));
+                    break;
+                }
+                Err(e) => return Err(GenerationError::from(e)),
+            }
+        }
+
+        Ok(Generated {
+            failure,
+            ..Generated::new(images, credits.into_iter().sum())
+        })
+    }
+
+    async fn generate_model(
+        &self,
+        auth_token: &str,
+        image_url: &str,
+        _source_name: &str,
+    ) -> Result<Generated<String>, GenerationError> {
+        generate_model(auth_token.to_string(), image_url.to_string())
+            .await
+            .map(|data| Generated::new(data.generateModel, data.credits))
+            .map_err(GenerationError::from)
+    }
+
+    async fn generate_landscape(
+        &self,
+        auth_token: &str,
+        landscape_id: &str,
+        params: &LandscapeParams,
+    ) -> Result<Generated<GeneratedLandscape>, GenerationError> {
+        let data = generate_landscape(
+            auth_token.to_string(),
+            landscape_id.to_string(),
+            params.clone(),
+        )
+        .await
+        .map_err(GenerationError::from)?;
+        let maps = data.generateLandscape;
+
+        Ok(Generated::new(
+            GeneratedLandscape {
+                heightmap: maps.heightmap,
+                rockmap: maps.rockmap,
+                soil: maps.soil,
+            },
+            data.credits,
+        ))
+    }
+}
+
+pub fn provider_for(kind: ProviderKind) -> Box<dyn GenerationProvider> {
+    match kind {
+        ProviderKind::CommonOS => Box::new(CommonOSProvider),
+        ProviderKind::Local => Box::new(LocalProvider),
+    }
+}
+
+/// The provider chosen in the project's settings
+pub fn project_provider(project_id: &str) -> Box<dyn GenerationProvider> {
+    provider_for(load_project_settings(project_id).generation_provider)
+}
@@ -0,0 +1,203 @@
+use std::sync::{Arc, Mutex};
+
+use midpoint_engine::helpers::saved_data::LandscapeData;
+use serde::{Deserialize, Serialize};
+
+use crate::editor_state::StateHelper;
+
+use super::heightfield::{encode_mask, Heightfield};
+use super::imports::store_and_register;
+use super::landscapes::{add_landscape, landscape_masks, save_landscape_files, MaskParams};
+use super::local_provider::{fbm, shaped_fbm, smoothstep};
+
+pub const NOISE_KINDS: [&str; 3] = ["fBm", "Ridged", "Billow"];
+
+// small enough to draw again on every change to the form
+pub const TERRAIN_PREVIEW_SIZE: u32 = 96;
+
+/// One noise field summed into the terrain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoiseLayer {
+    // one of NOISE_KINDS
+    pub kind: String,
+    pub octaves: u32,
+    // noise cells across the map for the first octave
+    pub frequency: f32,
+    // share of the full height range the layer can reach
+    pub amplitude: f32,
+}
+
+/// Everything a terrain is generated from, the same parameters always give the same heights
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TerrainParams {
+    pub seed: i32,
+    pub layers: Vec<NoiseLayer>,
+    // number of flat steps, 0 leaves the slopes smooth
+    pub terraces: u32,
+    // 0 leaves the edges as they are, 1 sinks them to the bottom
+    pub island_falloff: f32,
+}
+
+impl Default for TerrainParams {
+    fn default() -> Self {
+        TerrainParams {
+            seed: 0,
+            layers: vec![
+                NoiseLayer {
+                    kind: NOISE_KINDS[0].to_string(),
+                    octaves: 5,
+                    frequency: 3.0,
+                    amplitude: 0.8,
+                },
+                NoiseLayer {
+                    kind: NOISE_KINDS[1].to_string(),
+                    octaves: 4,
+                    frequency: 8.0,
+                    amplitude: 0.2,
+                },
+            ],
+            terraces: 0,
+            island_falloff: 0.0,
+        }
+    }
+}
+
+/// Octaves of value noise in 0..1, shaped by the kind: ridged folds each octave into sharp
+/// crests and billow into rounded lumps
+fn layer_noise(layer: &NoiseLayer, seed: u64, x: f32, y: f32) -> f32 {
+    let (x, y) = (x * layer.frequency, y * layer.frequency);
+    let octaves = layer.octaves.clamp(1, 10);
+
+    match layer.kind.as_str() {
+        "Ridged" => shaped_fbm(seed, x, y, octaves, |noise| {
+            (1.0 - (noise * 2.0 - 1.0).abs()).powi(2)
+        }),
+        "Billow" => shaped_fbm(seed, x, y, octaves, |noise| (noise * 2.0 - 1.0).abs()),
+        _ => fbm(seed, x, y, octaves),
+    }
+}
+
+// flat shelves joined by short slopes
+fn terrace(height: f32, terraces: u32) -> f32 {
+    if terraces == 0 {
+        return height;
+    }
+
+    let steps = height * terraces as f32;
+    let shelf = steps.floor();
+    (shelf + smoothstep(smoothstep(steps - shelf))) / terraces as f32
+}
+
+/// Heights of a `size` by `size` terrain. Coordinates run 0..1 across the map whatever its size,
+/// so a small preview has the same shape as the full map.
+pub fn generate_terrain(params: &TerrainParams, size: u32) -> Heightfield {
+    let size = size.max(2);
+    let falloff = params.island_falloff.clamp(0.0, 1.0);
+
+    let heights = (0..size * size)
+        .map(|i| {
+            let (x, y) = (
+                (i % size) as f32 / (size - 1) as f32,
+                (i / size) as f32 / (size - 1) as f32,
+            );
+
+            let mut height: f32 = params
+                .layers
+                .iter()
+                .enumerate()
+                .map(|(index, layer)| {
+                    // each layer gets its own noise, even with the same settings
+                    let seed = (params.seed as u64).wrapping_add(index as u64 * 1013);
+                    layer_noise(layer, seed, x, y) * layer.amplitude
+                })
+                .sum();
+
+            if falloff > 0.0 {
+                let distance = ((x - 0.5).powi(2) + (y - 0.5).powi(2)).sqrt() * 2.0;
+                height *= 1.0 - falloff * smoothstep(distance.clamp(0.0, 1.0));
+            }
+
+            terrace(height.clamp(0.0, 1.0), params.terraces)
+        })
+        .collect();
+
+    Heightfield {
+        width: size,
+        height: size,
+        heights,
+    }
+}
+
+/// Generates the terrain at full size and adds it to the project as a new landscape,
+/// with rockmap and soil masks worked out from its slopes
+pub fn save_terrain(
+    state_helper: &Arc<Mutex<StateHelper>>,
+    project_id: &str,
+    params: &TerrainParams,
+    size: u32,
+) -> Result<LandscapeData, String> {
+    let heightfield = generate_terrain(params, size);
+    let (rockmap, soil) = landscape_masks(&heightfield.heights, size, size, &MaskParams::default());
+
+    let heightmap = heightfield.to_png()?;
+    let rockmap = encode_mask(rockmap)?;
+    let soil = encode_mask(soil)?;
+
+    store_and_register(
+        state_helper,
+        || {
+            save_landscape_files(
+                project_id,
+                &format!("Terrain {}", params.seed),
+                &heightmap,
+                &rockmap,
+                &soil,
+            )
+        },
+        |state_helper, landscape| {
+            add_landscape(state_helper, landscape.clone());
+            Ok(landscape)
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_params_give_the_same_terrain() {
+        let params = TerrainParams {
+            seed: 7,
+            ..TerrainParams::default()
+        };
+
+        assert_eq!(generate_terrain(&params, 32), generate_terrain(&params, 32));
+    }
+
+    #[test]
+    fn layers_stay_in_range() {
+        for kind in NOISE_KINDS {
+            let layer = NoiseLayer {
+                kind: kind.to_string(),
+                octaves: 4,
+                frequency: 3.0,
+                amplitude: 1.0,
+            };
+
+            for i in 0..100 {
+                let (x, y) = (i as f32 / 10.0, i as f32 / 7.0);
+                let noise = layer_noise(&layer, 11, x, y);
+                assert!((0.0..=1.0).contains(&noise), "{} gave {}", kind, noise);
+            }
+        }
+    }
+
+    #[test]
+    fn terraces_flatten_into_steps() {
+        assert_eq!(terrace(0.55, 0), 0.55);
+        assert_eq!(terrace(0.5, 2), 0.5);
+        // just above a step stays close to its shelf
+        assert!(terrace(0.55, 2) < 0.51);
+    }
+}
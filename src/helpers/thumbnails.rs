@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use image::{DynamicImage, ImageFormat};
+use once_cell::sync::Lazy;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+use super::downloads::get_cache_dir;
+use super::utilities::sha256_file;
+
+// decoding is CPU bound, leave the rest of the machine to the UI and the renderer
+const MAX_CONCURRENT_THUMBNAILS: usize = 2;
+
+static THUMBNAIL_SLOTS: Lazy<Arc<Semaphore>> =
+    Lazy::new(|| Arc::new(Semaphore::new(MAX_CONCURRENT_THUMBNAILS)));
+
+// keyed by the asset's path, so files that haven't changed aren't hashed again
+static FINGERPRINTS: Lazy<Mutex<HashMap<PathBuf, Fingerprint>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, PartialEq)]
+struct Fingerprint {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: String,
+}
+
+pub fn get_thumbnail_dir() -> PathBuf {
+    get_cache_dir().join("thumbnails")
+}
+
+fn get_thumbnail_path(hash: &str, size: u32) -> PathBuf {
+    get_thumbnail_dir().join(format!("{}-{}.png", hash, size))
+}
+
+/// Deletes every size of a thumbnail no other known file still points at
+fn remove_thumbnails(hash: &str, fingerprints: &HashMap<PathBuf, Fingerprint>) {
+    if fingerprints.values().any(|f| f.hash == hash) {
+        return;
+    }
+
+    let prefix = format!("{}-", hash);
+    if let Ok(entries) = fs::read_dir(get_thumbnail_dir()) {
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+/// The SHA-256 of the file, only recomputed when its size or modified time changes
+pub fn cached_file_hash(path: &Path) -> Result<String, String> {
+    let metadata =
+        fs::metadata(path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+    let modified = metadata.modified().ok();
+    let len = metadata.len();
+
+    if let Some(known) = FINGERPRINTS.lock().unwrap().get(path) {
+        if known.modified == modified && known.len == len {
+            return Ok(known.hash.clone());
+        }
+    }
+
+    let hash = sha256_file(path).map_err(|e| format!("Couldn't hash {}: {}", path.display(), e))?;
+
+    let mut fingerprints = FINGERPRINTS.lock().unwrap();
+    let previous = fingerprints.insert(
+        path.to_path_buf(),
+        Fingerprint {
+            modified,
+            len,
+            hash: hash.clone(),
+        },
+    );
+
+    // the file changed, its old thumbnails won't be asked for again
+    if let Some(previous) = previous.filter(|previous| previous.hash != hash) {
+        remove_thumbnails(&previous.hash, &fingerprints);
+    }
+
+    Ok(hash)
+}
+
+fn cache_thumbnail(thumbnail: &DynamicImage, thumbnail_path: &Path) -> Result<(), String> {
+    fs::create_dir_all(get_thumbnail_dir()).map_err(|e| e.to_string())?;
+
+    // written beside the cache then moved into place, so a half-written thumbnail is never read
+    let tmp_path = get_thumbnail_dir().join(format!("{}.tmp", Uuid::new_v4()));
+    let result = thumbnail
+        .save_with_format(&tmp_path, ImageFormat::Png)
+        .map_err(|e| e.to_string())
+        .and_then(|_| fs::rename(&tmp_path, thumbnail_path).map_err(|e| e.to_string()));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+
+    result
+}
+
+/// Loads the cached thumbnail of the image, making and caching it first if needed.
+/// `size` is the longest edge in pixels.
+pub fn load_thumbnail(path: &Path, size: u32) -> Result<DynamicImage, String> {
+    let hash = cached_file_hash(path)?;
+    let thumbnail_path = get_thumbnail_path(&hash, size);
+
+    if let Ok(thumbnail) = image::open(&thumbnail_path) {
+        return Ok(thumbnail);
+    }
+
+    let image =
+        image::open(path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+    let thumbnail = if image.width().max(image.height()) > size {
+        image.thumbnail(size, size)
+    } else {
+        image
+    };
+
+    // a failed write only costs decoding the image again next time
+    if let Err(e) = cache_thumbnail(&thumbnail, &thumbnail_path) {
+        println!("Couldn't cache thumbnail of {}: {}", path.display(), e);
+    }
+
+    Ok(thumbnail)
+}
+
+/// Makes the thumbnail off the UI thread, sending None if the image can't be read
+pub fn request_thumbnail(path: PathBuf, size: u32, tx: UnboundedSender<Option<DynamicImage>>) {
+    tokio::runtime::Handle::current().spawn(async move {
+        let _permit = THUMBNAIL_SLOTS
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("Couldn't get thumbnail slot");
+
+        let result = tokio::task::spawn_blocking(move || {
+            let result = load_thumbnail(&path, size);
+            if let Err(e) = &result {
+                println!("Couldn't make thumbnail: {}", e);
+            }
+            result.ok()
+        })
+        .await
+        .unwrap_or(None);
+
+        let _ = tx.send(result);
+    });
+}
@@ -0,0 +1,264 @@
+use std::fs::{self, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use midpoint_engine::helpers::saved_data::File;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::utilities::{get_common_os_dir, get_project_dir, sha256_file};
+
+// every asset frame starts with this, so other binary traffic on the group can be ignored
+pub const TRANSFER_MAGIC: &[u8; 4] = b"MPAT";
+
+/// Describes the asset a binary frame belongs to and where its payload sits in the file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferHeader {
+    #[serde(rename = "assetId")]
+    pub asset_id: String,
+    pub kind: String,
+    #[serde(rename = "fileName")]
+    pub filename: String,
+    #[serde(rename = "totalSize")]
+    pub total_size: u64,
+    // hex encoded SHA-256 of the whole file
+    pub checksum: String,
+    pub offset: u64,
+    #[serde(rename = "cloudfrontUrl", default)]
+    pub cloudfront_url: String,
+}
+
+/// Written next to the partial file so a transfer can pick up where it left off
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferProgress {
+    pub header: TransferHeader,
+    pub received: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChunkOutcome {
+    InProgress(u64),
+    // the chunk didn't follow on from what we have, ask the sender to restart from here
+    Resume(u64),
+    Complete(File),
+    ChecksumMismatch,
+}
+
+/// The project folder each transferable kind is saved into
+pub fn get_kind_dir(kind: &str) -> Option<&'static str> {
+    match kind {
+        "concept" => Some("concepts"),
+        "texture" => Some("textures"),
+        "model" => Some("models"),
+        _ => None,
+    }
+}
+
+pub fn get_transfers_dir(project_id: &str) -> PathBuf {
+    get_project_dir(project_id).join("transfers")
+}
+
+fn get_partial_path(project_id: &str, asset_id: &str) -> PathBuf {
+    get_transfers_dir(project_id).join(format!("{}.part", asset_id))
+}
+
+fn get_progress_path(project_id: &str, asset_id: &str) -> PathBuf {
+    get_transfers_dir(project_id).join(format!("{}.json", asset_id))
+}
+
+// asset ids and file names come off the wire, so keep them inside the project folder
+fn is_safe_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains('/') && !name.contains('\\')
+}
+
+pub fn encode_transfer_frame(header: &TransferHeader, payload: &[u8]) -> Vec<u8> {
+    let header_json = serde_json::to_vec(header).expect("Couldn't serialize transfer header");
+
+    let mut frame = Vec::with_capacity(8 + header_json.len() + payload.len());
+    frame.extend_from_slice(TRANSFER_MAGIC);
+    frame.extend_from_slice(&(header_json.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&header_json);
+    frame.extend_from_slice(payload);
+
+    frame
+}
+
+/// Splits a frame into its header and payload.
+/// Layout: magic (4 bytes), header length (u32 LE), header JSON, payload.
+pub fn parse_transfer_frame(bytes: &[u8]) -> Result<(TransferHeader, &[u8]), String> {
+    if bytes.len() < 8 || &bytes[0..4] != TRANSFER_MAGIC {
+        return Err("Not an asset transfer frame".to_string());
+    }
+
+    let header_len = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    let header_end = 8 + header_len;
+
+    if bytes.len() < header_end {
+        return Err("Transfer frame is shorter than its header".to_string());
+    }
+
+    let header: TransferHeader = serde_json::from_slice(&bytes[8..header_end])
+        .map_err(|e| format!("Couldn't parse transfer header: {}", e))?;
+
+    if get_kind_dir(&header.kind).is_none() {
+        return Err(format!("Unsupported transfer kind {:?}", header.kind));
+    }
+    if !is_safe_name(&header.asset_id) || !is_safe_name(&header.filename) {
+        return Err("Transfer header contains an invalid name".to_string());
+    }
+
+    Ok((header, &bytes[header_end..]))
+}
+
+/// Asks the sender to continue a transfer from the given offset
+pub fn resume_message(asset_id: &str, offset: u64) -> String {
+    json!({
+        "event": "resume_transfer",
+        "payload": {
+            "assetId": asset_id,
+            "offset": offset
+        }
+    })
+    .to_string()
+}
+
+fn load_progress(project_id: &str, asset_id: &str) -> Option<TransferProgress> {
+    let json = fs::read_to_string(get_progress_path(project_id, asset_id)).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+fn save_progress(project_id: &str, progress: &TransferProgress) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(progress)
+        .map_err(|e| format!("Couldn't serialize transfer progress: {}", e))?;
+
+    fs::write(
+        get_progress_path(project_id, &progress.header.asset_id),
+        json,
+    )
+    .map_err(|e| format!("Couldn't write transfer progress: {}", e))
+}
+
+fn discard_transfer(project_id: &str, asset_id: &str) {
+    let _ = fs::remove_file(get_partial_path(project_id, asset_id));
+    let _ = fs::remove_file(get_progress_path(project_id, asset_id));
+}
+
+/// Transfers that were interrupted, e.g. by a disconnect
+pub fn pending_transfers(project_id: &str) -> Vec<TransferProgress> {
+    let entries = match fs::read_dir(get_transfers_dir(project_id)) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().and_then(|e| e.to_str()) == Some("json"))
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|json| serde_json::from_str::<TransferProgress>(&json).ok())
+        .collect()
+}
+
+/// Appends one chunk to the partial file, moving it into the project once the checksum verifies
+pub fn write_chunk(
+    project_id: &str,
+    header: &TransferHeader,
+    payload: &[u8],
+) -> Result<ChunkOutcome, String> {
+    fs::create_dir_all(get_transfers_dir(project_id))
+        .map_err(|e| format!("Couldn't create transfers directory: {}", e))?;
+
+    let asset_id = header.asset_id.as_str();
+
+    let mut progress = match load_progress(project_id, asset_id) {
+        // the same id with a different file means the sender started over
+        Some(progress)
+            if progress.header.checksum == header.checksum
+                && progress.header.total_size == header.total_size =>
+        {
+            progress
+        }
+        _ => {
+            discard_transfer(project_id, asset_id);
+            let progress = TransferProgress {
+                header: TransferHeader {
+                    offset: 0,
+                    ..header.clone()
+                },
+                received: 0,
+            };
+            save_progress(project_id, &progress)?;
+            progress
+        }
+    };
+
+    let chunk_end = header.offset + payload.len() as u64;
+
+    if header.offset > progress.received {
+        return Ok(ChunkOutcome::Resume(progress.received));
+    }
+    if chunk_end > header.total_size {
+        discard_transfer(project_id, asset_id);
+        return Err(format!("Chunk overruns the size of asset {}", asset_id));
+    }
+
+    // skip anything we already have from an earlier attempt
+    if chunk_end > progress.received {
+        let skip = (progress.received - header.offset) as usize;
+
+        let mut partial = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(get_partial_path(project_id, asset_id))
+            .map_err(|e| format!("Couldn't open partial file: {}", e))?;
+        partial
+            .seek(SeekFrom::Start(progress.received))
+            .map_err(|e| format!("Couldn't seek partial file: {}", e))?;
+        partial
+            .write_all(&payload[skip..])
+            .map_err(|e| format!("Couldn't write partial file: {}", e))?;
+
+        progress.received = chunk_end;
+        save_progress(project_id, &progress)?;
+    }
+
+    if progress.received < header.total_size {
+        return Ok(ChunkOutcome::InProgress(progress.received));
+    }
+
+    finish_transfer(project_id, &progress.header)
+}
+
+fn finish_transfer(project_id: &str, header: &TransferHeader) -> Result<ChunkOutcome, String> {
+    let partial_path = get_partial_path(project_id, &header.asset_id);
+
+    let checksum =
+        sha256_file(&partial_path).map_err(|e| format!("Couldn't hash partial file: {}", e))?;
+
+    if !checksum.eq_ignore_ascii_case(&header.checksum) {
+        println!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            header.asset_id, header.checksum, checksum
+        );
+        discard_transfer(project_id, &header.asset_id);
+        return Ok(ChunkOutcome::ChecksumMismatch);
+    }
+
+    let kind_dir = get_kind_dir(&header.kind).expect("Couldn't get transfer kind directory");
+    let normal_file_path = format!("midpoint/projects/{}/{}", project_id, kind_dir);
+
+    let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
+    let target_dir = sync_dir.join(&normal_file_path);
+
+    fs::create_dir_all(&target_dir)
+        .map_err(|e| format!("Couldn't create {} directory: {}", kind_dir, e))?;
+    fs::rename(&partial_path, target_dir.join(&header.filename))
+        .map_err(|e| format!("Couldn't move transferred file: {}", e))?;
+    let _ = fs::remove_file(get_progress_path(project_id, &header.asset_id));
+
+    Ok(ChunkOutcome::Complete(File {
+        id: header.asset_id.clone(),
+        cloudfrontUrl: header.cloudfront_url.clone(),
+        fileName: header.filename.clone(),
+        normalFilePath: normal_file_path,
+    }))
+}
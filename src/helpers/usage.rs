@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use super::presence::display_name;
+use super::project_settings::load_project_settings;
+use super::providers::GenerationError;
+use super::utilities::get_project_dir;
+
+// what an output is assumed to cost before the provider has reported any price for its kind
+const UNREPORTED_CREDITS_PER_OUTPUT: f64 = 1.0;
+
+/// One generation call, kept whether or not the provider reported its cost
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageEntry {
+    // concepts, textures, models or landscapes
+    pub kind: String,
+    pub prompt: String,
+    pub user: String,
+    pub created: String,
+    pub outputs: usize,
+    pub credits: Option<f64>,
+    pub provider_version: Option<String>,
+}
+
+impl UsageEntry {
+    pub fn new(
+        kind: &str,
+        prompt: &str,
+        auth_token: &str,
+        outputs: usize,
+        credits: Option<f64>,
+        provider_version: Option<String>,
+    ) -> Self {
+        UsageEntry {
+            kind: kind.to_string(),
+            prompt: prompt.to_string(),
+            user: display_name(auth_token),
+            created: Local::now().to_rfc3339(),
+            outputs,
+            credits,
+            provider_version,
+        }
+    }
+
+    // rfc3339 starts with the local date
+    pub fn day(&self) -> String {
+        self.created.chars().take(10).collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageTotal {
+    pub calls: usize,
+    pub outputs: usize,
+    pub credits: f64,
+    // calls the provider didn't report a cost for
+    pub unreported: usize,
+}
+
+impl UsageTotal {
+    fn add(&mut self, entry: &UsageEntry) {
+        self.calls += 1;
+        self.outputs += entry.outputs;
+        match entry.credits {
+            Some(credits) => self.credits += credits,
+            None => self.unreported += 1,
+        }
+    }
+}
+
+pub fn get_usage_path(project_id: &str) -> PathBuf {
+    get_project_dir(project_id).join("usage.json")
+}
+
+pub fn load_usage(project_id: &str) -> Vec<UsageEntry> {
+    match fs::read_to_string(get_usage_path(project_id)) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            println!("Couldn't parse usage ledger: {:?}", e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+pub fn record_usage(project_id: &str, entry: UsageEntry) -> Result<(), String> {
+    let mut entries = load_usage(project_id);
+    entries.push(entry);
+
+    let json = serde_json::to_string_pretty(&entries)
+        .map_err(|e| format!("Couldn't serialize usage ledger: {}", e))?;
+    fs::write(get_usage_path(project_id), json)
+        .map_err(|e| format!("Couldn't write usage ledger: {}", e))
+}
+
+/// Totals grouped by the given key, in key order
+pub fn usage_totals(
+    entries: &[UsageEntry],
+    key: impl Fn(&UsageEntry) -> String,
+) -> Vec<(String, UsageTotal)> {
+    let mut totals: BTreeMap<String, UsageTotal> = BTreeMap::new();
+
+    for entry in entries {
+        totals.entry(key(entry)).or_default().add(entry);
+    }
+
+    totals.into_iter().collect()
+}
+
+pub fn total_credits(entries: &[UsageEntry]) -> f64 {
+    entries.iter().filter_map(|entry| entry.credits).sum()
+}
+
+/// Credits counted against the limits. Calls without a reported cost are priced at the
+/// dearest reported output of the same kind, so a silent provider can't spend past a limit.
+pub fn spent_credits(entries: &[UsageEntry]) -> f64 {
+    let price_per_output = |kind: &str| {
+        entries
+            .iter()
+            .filter(|entry| entry.kind == kind && entry.outputs > 0)
+            .filter_map(|entry| entry.credits.map(|credits| credits / entry.outputs as f64))
+            .reduce(f64::max)
+            .unwrap_or(UNREPORTED_CREDITS_PER_OUTPUT)
+    };
+
+    entries
+        .iter()
+        .map(|entry| match entry.credits {
+            Some(credits) => credits,
+            None => price_per_output(&entry.kind) * entry.outputs.max(1) as f64,
+        })
+        .sum()
+}
+
+/// Errs once the project has spent its total or daily credits
+pub fn check_spending_limits(project_id: &str) -> Result<(), GenerationError> {
+    let settings = load_project_settings(project_id);
+    if settings.credit_limit.is_none() && settings.daily_credit_limit.is_none() {
+        return Ok(());
+    }
+
+    let entries = load_usage(project_id);
+
+    if let Some(limit) = settings.credit_limit {
+        let spent = spent_credits(&entries);
+        if spent >= limit {
+            return Err(GenerationError::LimitReached(format!(
+                "Spending limit reached: {:.1} of {:.1} credits used on this project",
+                spent, limit
+            )));
+        }
+    }
+
+    if let Some(limit) = settings.daily_credit_limit {
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        let todays_entries: Vec<UsageEntry> = entries
+            .into_iter()
+            .filter(|entry| entry.day() == today)
+            .collect();
+        let spent = spent_credits(&todays_entries);
+        if spent >= limit {
+            return Err(GenerationError::LimitReached(format!(
+                "Spending limit reached: {:.1} of {:.1} credits used today",
+                spent, limit
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(
+        kind: &str,
+        user: &str,
+        created: &str,
+        outputs: usize,
+        credits: Option<f64>,
+    ) -> UsageEntry {
+        UsageEntry {
+            kind: kind.to_string(),
+            prompt: "a red barn".to_string(),
+            user: user.to_string(),
+            created: created.to_string(),
+            outputs,
+            credits,
+            provider_version: None,
+        }
+    }
+
+    fn ledger() -> Vec<UsageEntry> {
+        vec![
+            entry("concepts", "ana", "2024-05-02T10:00:00+00:00", 4, Some(4.0)),
+            entry("models", "ben", "2024-05-01T09:00:00+00:00", 1, Some(4.0)),
+            entry("concepts", "ben", "2024-05-01T11:00:00+00:00", 2, None),
+        ]
+    }
+
+    #[test]
+    fn totals_are_grouped_in_key_order() {
+        let totals = usage_totals(&ledger(), |entry| entry.day());
+
+        assert_eq!(
+            totals,
+            vec![
+                (
+                    "2024-05-01".to_string(),
+                    UsageTotal {
+                        calls: 2,
+                        outputs: 3,
+                        credits: 4.0,
+                        unreported: 1,
+                    }
+                ),
+                (
+                    "2024-05-02".to_string(),
+                    UsageTotal {
+                        calls: 1,
+                        outputs: 4,
+                        credits: 4.0,
+                        unreported: 0,
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn totals_by_kind_and_user_cover_every_entry() {
+        let by_kind = usage_totals(&ledger(), |entry| entry.kind.clone());
+        let keys: Vec<&str> = by_kind.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["concepts", "models"]);
+        assert_eq!(by_kind[0].1.calls, 2);
+        assert_eq!(by_kind[0].1.unreported, 1);
+
+        let by_user = usage_totals(&ledger(), |entry| entry.user.clone());
+        let calls: usize = by_user.iter().map(|(_, total)| total.calls).sum();
+        assert_eq!(calls, 3);
+        assert_eq!(by_user[1].1.credits, 4.0);
+    }
+
+    #[test]
+    fn empty_ledgers_have_no_totals() {
+        assert!(usage_totals(&[], |entry| entry.day()).is_empty());
+        assert_eq!(spent_credits(&[]), 0.0);
+    }
+
+    #[test]
+    fn unreported_calls_count_toward_limits() {
+        // the unreported pair of concepts is priced like the reported ones, a credit each
+        assert_eq!(total_credits(&ledger()), 8.0);
+        assert_eq!(spent_credits(&ledger()), 10.0);
+
+        // with nothing reported for the kind, the fallback price is used
+        let silent = vec![entry(
+            "textures",
+            "ana",
+            "2024-05-01T09:00:00+00:00",
+            3,
+            None,
+        )];
+        assert_eq!(spent_credits(&silent), 3.0 * UNREPORTED_CREDITS_PER_OUTPUT);
+    }
+}
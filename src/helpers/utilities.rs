@@ -1,7 +1,9 @@
-use std::{fs, path::PathBuf};
+use std::io::Read;
+use std::{fs, path::Path, path::PathBuf};
 
 use directories::{BaseDirs, UserDirs};
 use regex::Regex;
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
 pub fn get_common_os_dir() -> Option<PathBuf> {
@@ -17,6 +19,57 @@ pub fn get_common_os_dir() -> Option<PathBuf> {
     })
 }
 
+/// Folder a project's midpoint.json, assets and bookkeeping files are synced in
+pub fn get_project_dir(project_id: &str) -> PathBuf {
+    let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
+    sync_dir.join("midpoint").join("projects").join(project_id)
+}
+
+/// Lowercase hex of a finished SHA-256, the form every stored and transferred hash takes
+pub fn hex_digest(hasher: Sha256) -> String {
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    hex_digest(hasher)
+}
+
+/// Hashes a file in chunks, so large models aren't read into memory at once
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hex_digest(hasher))
+}
+
+/// Host and port of the CommonOS backend, overridable to point at the stand-in server
+pub fn get_backend_address() -> String {
+    std::env::var("MIDPOINT_BACKEND").unwrap_or_else(|_| "localhost:4000".to_string())
+}
+
+pub fn get_graphql_endpoint() -> String {
+    format!("http://{}/graphql", get_backend_address())
+}
+
+pub fn get_websocket_endpoint() -> String {
+    format!("ws://{}", get_backend_address())
+}
+
 pub fn get_filename(concept_prompt_str: String) -> String {
     let concept_filename: String = concept_prompt_str.chars().skip(0).take(20).collect();
 
@@ -0,0 +1,877 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use midpoint_engine::helpers::saved_data::{File, LandscapeData, SavedState};
+use midpoint_engine::helpers::utilities::load_project_state;
+use once_cell::sync::Lazy;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::editor_state::{send_ui_message, FileSignals, StateHelper, UIMessage};
+
+use super::downloads::queue_missing_downloads;
+use super::generation::find_generation;
+use super::imports::storing;
+use super::providers::project_provider;
+use super::utilities::{get_common_os_dir, get_project_dir, sha256_file, sha256_hex};
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+const ASSET_KINDS: [&str; 3] = ["concepts", "textures", "models"];
+const LANDSCAPE_MAPS: [&str; 3] = ["heightmaps", "rockmaps", "soils"];
+
+// the open project's watcher task, stopped when a project is opened again
+static WATCHER: Mutex<Option<(String, JoinHandle<()>)>> = Mutex::new(None);
+
+static PENDING_CHANGES: Lazy<Mutex<Vec<ExternalChange>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+// hash of what the editor last wrote to each project file, to tell its saves from anyone else's
+static OWN_SAVES: Lazy<Mutex<HashMap<PathBuf, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// Something another program did to the open project's folder
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalChange {
+    // concepts, textures, models, landscapes or project
+    pub kind: String,
+    // the file name, or the landscape id for landscapes
+    pub name: String,
+    pub change: ChangeKind,
+}
+
+impl ExternalChange {
+    pub fn key(&self) -> String {
+        format!("{}/{}/{:?}", self.kind, self.name, self.change)
+    }
+
+    pub fn description(&self) -> String {
+        match (self.kind.as_str(), self.change) {
+            ("project", _) => "midpoint.json was changed outside the editor".to_string(),
+            ("landscapes", ChangeKind::Added) => format!("New landscape {}", self.name),
+            ("landscapes", ChangeKind::Removed) => format!("Landscape {} was removed", self.name),
+            ("landscapes", ChangeKind::Modified) => format!("Landscape {} changed", self.name),
+            (kind, ChangeKind::Added) => format!("New file in {}: {}", kind, self.name),
+            (kind, ChangeKind::Removed) => format!("Removed from {}: {}", kind, self.name),
+            (kind, ChangeKind::Modified) => format!("Changed in {}: {}", kind, self.name),
+        }
+    }
+
+    pub fn action_label(&self) -> &'static str {
+        match (self.kind.as_str(), self.change) {
+            ("project", _) => "Reload",
+            (_, ChangeKind::Added) => "Register",
+            (_, ChangeKind::Removed) => "Forget",
+            (_, ChangeKind::Modified) => "Reload",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+// dot files are the editor's own partial writes, moved into place once they're complete
+fn list_files(dir: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && !file_name(path).starts_with('.'))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn list_dirs(dir: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Remembers what the editor just wrote to a watched file
+pub fn record_own_save(path: &Path, contents: &[u8]) {
+    OWN_SAVES
+        .lock()
+        .unwrap()
+        .insert(path.to_path_buf(), sha256_hex(contents));
+}
+
+// a file that still holds what the editor last wrote to it
+fn is_own_save(path: &Path) -> bool {
+    let own_hash = match OWN_SAVES.lock().unwrap().get(path) {
+        Some(hash) => hash.clone(),
+        None => return false,
+    };
+
+    sha256_file(path).map_or(false, |hash| hash == own_hash)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Every watched file in the project with its size and modified time
+fn scan_project(project_dir: &Path) -> HashMap<PathBuf, FileStamp> {
+    let mut files = Vec::new();
+
+    for kind in ASSET_KINDS {
+        files.extend(list_files(&project_dir.join(kind)));
+    }
+    for landscape_dir in list_dirs(&project_dir.join("landscapes")) {
+        for map in LANDSCAPE_MAPS {
+            files.extend(list_files(&landscape_dir.join(map)));
+        }
+    }
+    files.push(project_dir.join("midpoint.json"));
+
+    files
+        .into_iter()
+        .filter_map(|path| {
+            let metadata = fs::metadata(&path).ok()?;
+            let stamp = FileStamp {
+                modified: metadata.modified().ok(),
+                len: metadata.len(),
+            };
+            Some((path, stamp))
+        })
+        .collect()
+}
+
+fn kind_files<'a>(saved_state: &'a SavedState, kind: &str) -> Vec<&'a File> {
+    match kind {
+        "concepts" => saved_state.concepts.iter().collect(),
+        "models" => saved_state.models.iter().collect(),
+        "textures" => saved_state.textures.iter().flatten().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn is_registered(saved_state: &SavedState, kind: &str, name: &str) -> bool {
+    match kind {
+        "landscapes" => saved_state
+            .landscapes
+            .iter()
+            .flatten()
+            .any(|landscape| landscape.id == name),
+        _ => kind_files(saved_state, kind)
+            .iter()
+            .any(|file| file.fileName == name),
+    }
+}
+
+// a landscape is only usable once all three of its maps are there
+fn landscape_complete(project_dir: &Path, landscape_id: &str) -> bool {
+    LANDSCAPE_MAPS.iter().all(|map| {
+        !list_files(&project_dir.join("landscapes").join(landscape_id).join(map)).is_empty()
+    })
+}
+
+/// What a file path in the project folder belongs to, as a kind and name
+fn classify(project_dir: &Path, path: &Path) -> Option<(String, String)> {
+    let relative = path.strip_prefix(project_dir).ok()?;
+    let parts: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+
+    match parts.as_slice() {
+        [name] if name == "midpoint.json" => Some(("project".to_string(), name.clone())),
+        [kind, name] if ASSET_KINDS.contains(&kind.as_str()) => Some((kind.clone(), name.clone())),
+        [landscapes, landscape_id, _, _] if landscapes == "landscapes" => {
+            Some(("landscapes".to_string(), landscape_id.clone()))
+        }
+        _ => None,
+    }
+}
+
+/// What the disk says about a scan's new and changed files, read before any state is locked
+#[derive(Debug, Default)]
+struct DiskFacts {
+    // new files that are our own generations, waiting for their sync to register them
+    awaiting_sync: HashSet<PathBuf>,
+    // new landscapes with all three maps in place
+    complete_landscapes: HashSet<String>,
+    // midpoint.json still holds what the editor last wrote
+    own_save: bool,
+}
+
+fn gather_disk_facts(
+    project_id: &str,
+    project_dir: &Path,
+    previous: &HashMap<PathBuf, FileStamp>,
+    current: &HashMap<PathBuf, FileStamp>,
+) -> DiskFacts {
+    let syncs_outputs = project_provider(project_id).syncs_outputs();
+    let mut facts = DiskFacts::default();
+
+    for (path, stamp) in current.iter() {
+        let (kind, name) = match classify(project_dir, path) {
+            Some(classified) => classified,
+            None => continue,
+        };
+
+        match (kind.as_str(), previous.get(path)) {
+            ("project", Some(previous_stamp)) if previous_stamp != stamp => {
+                facts.own_save = is_own_save(path);
+            }
+            ("project", _) => {}
+            ("landscapes", None) => {
+                if landscape_complete(project_dir, &name) {
+                    facts.complete_landscapes.insert(name);
+                }
+            }
+            (_, None) => {
+                if syncs_outputs && find_generation(project_id, &kind, &name).is_some() {
+                    facts.awaiting_sync.insert(path.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    facts
+}
+
+/// Compares two scans and keeps the changes the editor didn't make itself
+fn detect_changes(
+    project_dir: &Path,
+    previous: &HashMap<PathBuf, FileStamp>,
+    current: &HashMap<PathBuf, FileStamp>,
+    saved_state: &SavedState,
+    facts: &DiskFacts,
+) -> Vec<ExternalChange> {
+    let mut changes: Vec<ExternalChange> = Vec::new();
+    let mut push = |change: ExternalChange| {
+        if !changes.contains(&change) {
+            changes.push(change);
+        }
+    };
+
+    for (path, stamp) in current.iter() {
+        let (kind, name) = match classify(project_dir, path) {
+            Some(classified) => classified,
+            None => continue,
+        };
+        let registered = is_registered(saved_state, &kind, &name);
+
+        match previous.get(path) {
+            None if kind == "project" => {}
+            None if kind == "landscapes" => {
+                if !registered && facts.complete_landscapes.contains(&name) {
+                    push(ExternalChange {
+                        kind,
+                        name,
+                        change: ChangeKind::Added,
+                    });
+                }
+            }
+            // our own generations are registered once they've synced, local ones as they're saved
+            None => {
+                if !registered && !facts.awaiting_sync.contains(path) {
+                    push(ExternalChange {
+                        kind,
+                        name,
+                        change: ChangeKind::Added,
+                    });
+                }
+            }
+            Some(previous_stamp) if previous_stamp != stamp => {
+                if kind == "project" {
+                    if !facts.own_save {
+                        push(ExternalChange {
+                            kind,
+                            name,
+                            change: ChangeKind::Modified,
+                        });
+                    }
+                } else if registered {
+                    push(ExternalChange {
+                        kind,
+                        name,
+                        change: ChangeKind::Modified,
+                    });
+                }
+            }
+            Some(_) => {}
+        }
+    }
+
+    for path in previous.keys() {
+        if current.contains_key(path) {
+            continue;
+        }
+        let (kind, name) = match classify(project_dir, path) {
+            Some(classified) => classified,
+            None => continue,
+        };
+
+        if kind != "project" && is_registered(saved_state, &kind, &name) {
+            push(ExternalChange {
+                kind,
+                name,
+                change: ChangeKind::Removed,
+            });
+        }
+    }
+
+    changes
+}
+
+pub fn pending_changes() -> Vec<ExternalChange> {
+    PENDING_CHANGES.lock().unwrap().clone()
+}
+
+pub fn notify_external_changes(file_signals: &FileSignals) {
+    let signal = file_signals
+        .lock()
+        .unwrap()
+        .get("external_changes")
+        .cloned();
+
+    if let Some(tx) = signal {
+        let _ = tx.send(UIMessage::UpdateExternalChanges(pending_changes()));
+    }
+}
+
+fn add_pending(changes: Vec<ExternalChange>, saved_state: &SavedState) -> bool {
+    let mut pending = PENDING_CHANGES.lock().unwrap();
+    let count = pending.len();
+
+    // files that have since been registered by a sync don't need offering any more
+    pending.retain(|change| {
+        change.change != ChangeKind::Added
+            || !is_registered(saved_state, &change.kind, &change.name)
+    });
+    let mut updated = pending.len() != count;
+
+    for change in changes {
+        if !pending.contains(&change) {
+            pending.push(change);
+            updated = true;
+        }
+    }
+
+    updated
+}
+
+fn check_for_changes(
+    state_helper: &Arc<Mutex<StateHelper>>,
+    project_id: &str,
+    project_dir: &Path,
+    previous: &HashMap<PathBuf, FileStamp>,
+    current: &HashMap<PathBuf, FileStamp>,
+) -> bool {
+    let facts = gather_disk_facts(project_id, project_dir, previous, current);
+
+    let state_helper = state_helper.lock().unwrap();
+    // a file written by a store that hasn't registered it yet would look like an outside one
+    if storing() {
+        return false;
+    }
+    let saved_state = match state_helper.saved_state.as_ref() {
+        Some(saved_state) => saved_state.lock().unwrap(),
+        None => return true,
+    };
+
+    let changes = detect_changes(project_dir, previous, current, &saved_state, &facts);
+    let updated = add_pending(changes, &saved_state);
+    drop(saved_state);
+
+    if updated {
+        notify_external_changes(&state_helper.file_signals);
+    }
+
+    true
+}
+
+pub fn dismiss_change(file_signals: &FileSignals, change: &ExternalChange) {
+    PENDING_CHANGES
+        .lock()
+        .unwrap()
+        .retain(|pending| pending != change);
+    notify_external_changes(file_signals);
+}
+
+/// Polls the project folder while it's open, collecting changes made by other programs
+pub fn spawn_project_watcher(state_helper: Arc<Mutex<StateHelper>>, project_id: String) {
+    let mut watcher = WATCHER.lock().unwrap();
+    if let Some((watched_id, task)) = watcher.take() {
+        println!("Stopping the watcher for project {}", watched_id);
+        task.abort();
+    }
+    PENDING_CHANGES.lock().unwrap().clear();
+
+    let watched_id = project_id.clone();
+    let task = tokio::runtime::Handle::current().spawn(async move {
+        let project_dir = get_project_dir(&project_id);
+        // anything already there counts as added, so files from before the editor opened show up
+        let mut previous: HashMap<PathBuf, FileStamp> = HashMap::new();
+        let mut interval = tokio::time::interval(WATCH_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let current = scan_project(&project_dir);
+            // a skipped check compares against the same scan next time
+            if check_for_changes(
+                &state_helper,
+                &project_id,
+                &project_dir,
+                &previous,
+                &current,
+            ) {
+                previous = current;
+            }
+        }
+    });
+    *watcher = Some((watched_id, task));
+}
+
+fn browser_name(kind: &str) -> &'static str {
+    match kind {
+        "concepts" => "concept_browser",
+        "textures" => "texture_browser",
+        "models" => "model_browser",
+        _ => "landscape_browser",
+    }
+}
+
+fn list_message(saved_state: &SavedState, kind: &str) -> UIMessage {
+    let files: Vec<File> = kind_files(saved_state, kind).into_iter().cloned().collect();
+
+    match kind {
+        "concepts" => UIMessage::UpdateConcepts(files),
+        "textures" => UIMessage::UpdateTextures(files),
+        "models" => UIMessage::UpdateModels(files),
+        _ => UIMessage::UpdateLandscapes(saved_state.landscapes.clone().unwrap_or_default()),
+    }
+}
+
+fn empty_list_message(kind: &str) -> UIMessage {
+    match kind {
+        "concepts" => UIMessage::UpdateConcepts(Vec::new()),
+        "textures" => UIMessage::UpdateTextures(Vec::new()),
+        "models" => UIMessage::UpdateModels(Vec::new()),
+        _ => UIMessage::UpdateLandscapes(Vec::new()),
+    }
+}
+
+fn landscape_map(project_id: &str, landscape_id: &str, map: &str) -> Option<File> {
+    let normal_file_path = format!(
+        "midpoint/projects/{}/landscapes/{}/{}",
+        project_id, landscape_id, map
+    );
+    let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
+    let path = list_files(&sync_dir.join(&normal_file_path))
+        .into_iter()
+        .next()?;
+
+    Some(File {
+        id: Uuid::new_v4().to_string(),
+        cloudfrontUrl: String::new(),
+        fileName: file_name(&path),
+        normalFilePath: normal_file_path,
+    })
+}
+
+fn register(saved_state: &mut SavedState, project_id: &str, change: &ExternalChange) {
+    if change.kind == "landscapes" {
+        let landscape = LandscapeData {
+            id: change.name.clone(),
+            heightmap: landscape_map(project_id, &change.name, "heightmaps"),
+            rockmap: landscape_map(project_id, &change.name, "rockmaps"),
+            soil: landscape_map(project_id, &change.name, "soils"),
+        };
+        saved_state
+            .landscapes
+            .get_or_insert_with(Vec::new)
+            .push(landscape);
+        return;
+    }
+
+    let new_file = File {
+        id: Uuid::new_v4().to_string(),
+        // no upload has been seen for it yet
+        cloudfrontUrl: String::new(),
+        fileName: change.name.clone(),
+        normalFilePath: format!("midpoint/projects/{}/{}", project_id, change.kind),
+    };
+
+    match change.kind.as_str() {
+        "concepts" => saved_state.concepts.push(new_file),
+        "textures" => saved_state
+            .textures
+            .get_or_insert_with(Vec::new)
+            .push(new_file),
+        "models" => saved_state.models.push(new_file),
+        _ => {}
+    }
+}
+
+fn forget(saved_state: &mut SavedState, change: &ExternalChange) {
+    let name = &change.name;
+
+    match change.kind.as_str() {
+        "concepts" => saved_state.concepts.retain(|f| f.fileName != *name),
+        "textures" => {
+            if let Some(textures) = saved_state.textures.as_mut() {
+                textures.retain(|f| f.fileName != *name);
+            }
+        }
+        "models" => saved_state.models.retain(|f| f.fileName != *name),
+        "landscapes" => {
+            if let Some(landscapes) = saved_state.landscapes.as_mut() {
+                landscapes.retain(|l| l.id != *name);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn reload_project_file(
+    state_helper: &mut MutexGuard<StateHelper>,
+    project_id: &str,
+) -> Result<(), String> {
+    let reloaded = load_project_state(project_id).map_err(|e| e.to_string())?;
+    queue_missing_downloads(&reloaded);
+
+    let file_signals = Arc::clone(&state_helper.file_signals);
+    let mut saved_state = state_helper
+        .saved_state
+        .as_ref()
+        .expect("Couldn't get saved state")
+        .lock()
+        .unwrap();
+    *saved_state = reloaded.clone();
+
+    for kind in ["concepts", "textures", "models", "landscapes"] {
+        send_ui_message(
+            &file_signals,
+            browser_name(kind),
+            list_message(&saved_state, kind),
+        );
+    }
+    let components = saved_state
+        .levels
+        .as_ref()
+        .and_then(|levels| levels.get(0))
+        .and_then(|level| level.components.clone())
+        .unwrap_or_default();
+    send_ui_message(
+        &file_signals,
+        "component_browser",
+        UIMessage::UpdateComponents(components),
+    );
+    drop(saved_state);
+
+    // the reloaded file is now what's on disk, and the scene has to show it
+    state_helper.last_saved_state = Some(reloaded);
+    send_ui_message(
+        &file_signals,
+        "scene",
+        UIMessage::ReloadScene(project_id.to_string()),
+    );
+
+    Ok(())
+}
+
+/// Registers, forgets or reloads whatever the change points at, then clears it
+pub fn apply_change(
+    state_helper: Arc<Mutex<StateHelper>>,
+    project_id: &str,
+    change: &ExternalChange,
+) -> Result<(), String> {
+    let mut state_helper = state_helper.lock().unwrap();
+    let file_signals = Arc::clone(&state_helper.file_signals);
+
+    if change.kind == "project" {
+        reload_project_file(&mut state_helper, project_id)?;
+        dismiss_change(&file_signals, change);
+        return Ok(());
+    }
+
+    let saved_state = Arc::clone(
+        state_helper
+            .saved_state
+            .as_ref()
+            .ok_or("No project is open".to_string())?,
+    );
+    let mut saved_state = saved_state.lock().unwrap();
+
+    match change.change {
+        ChangeKind::Added => {
+            if !is_registered(&saved_state, &change.kind, &change.name) {
+                register(&mut saved_state, project_id, change);
+            }
+        }
+        ChangeKind::Removed => forget(&mut saved_state, change),
+        ChangeKind::Modified => {}
+    }
+
+    let browser = browser_name(&change.kind);
+    if change.change == ChangeKind::Modified {
+        // an empty list first, so the browser rebuilds its items from the new file
+        send_ui_message(&file_signals, browser, empty_list_message(&change.kind));
+    }
+    send_ui_message(
+        &file_signals,
+        browser,
+        list_message(&saved_state, &change.kind),
+    );
+
+    drop(saved_state);
+
+    if change.change != ChangeKind::Modified {
+        state_helper.save_current_saved_state();
+    }
+
+    dismiss_change(&file_signals, change);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_editors_last_write_counts_as_its_own() {
+        let path = std::env::temp_dir().join(format!("midpoint-{}.json", Uuid::new_v4()));
+
+        // reformatting alone used to look like an outside change
+        fs::write(&path, "{\"levels\": []}").unwrap();
+        assert!(!is_own_save(&path));
+        record_own_save(&path, b"{\"levels\": []}");
+        assert!(is_own_save(&path));
+
+        fs::write(&path, "{\"levels\": null}").unwrap();
+        assert!(!is_own_save(&path));
+
+        fs::remove_file(&path).unwrap();
+        assert!(!is_own_save(&path));
+    }
+
+    fn stamp(len: u64) -> FileStamp {
+        FileStamp {
+            modified: None,
+            len,
+        }
+    }
+
+    fn state_with_concept(file_name: &str) -> SavedState {
+        serde_json::from_value(serde_json::json!({
+            "concepts": [{
+                "id": "concept",
+                "cloudfrontUrl": "",
+                "fileName": file_name,
+                "normalFilePath": "midpoint/projects/project/concepts",
+            }],
+            "models": [],
+            "textures": null,
+            "landscapes": null,
+            "levels": null,
+            "skeletons": [],
+            "skeleton_parts": [],
+        }))
+        .unwrap()
+    }
+
+    fn change(kind: &str, name: &str, change: ChangeKind) -> ExternalChange {
+        ExternalChange {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            change,
+        }
+    }
+
+    #[test]
+    fn paths_are_classified_by_their_folder() {
+        let project_dir = Path::new("/projects/project");
+
+        assert_eq!(
+            classify(project_dir, &project_dir.join("midpoint.json")),
+            Some(("project".to_string(), "midpoint.json".to_string()))
+        );
+        assert_eq!(
+            classify(project_dir, &project_dir.join("models").join("tree.glb")),
+            Some(("models".to_string(), "tree.glb".to_string()))
+        );
+        assert_eq!(
+            classify(
+                project_dir,
+                &project_dir.join("landscapes/hills/heightmaps/height.png")
+            ),
+            Some(("landscapes".to_string(), "hills".to_string()))
+        );
+        assert_eq!(classify(project_dir, &project_dir.join("notes.txt")), None);
+        assert_eq!(
+            classify(
+                Path::new("/projects/other"),
+                &project_dir.join("midpoint.json")
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn only_unregistered_files_that_arent_syncing_are_offered() {
+        let project_dir = Path::new("/projects/project");
+        let saved_state = state_with_concept("known.png");
+        let syncing = project_dir.join("concepts").join("syncing.png");
+
+        let current: HashMap<PathBuf, FileStamp> = [
+            project_dir.join("concepts").join("known.png"),
+            project_dir.join("concepts").join("dropped.png"),
+            syncing.clone(),
+        ]
+        .into_iter()
+        .map(|path| (path, stamp(1)))
+        .collect();
+        let facts = DiskFacts {
+            awaiting_sync: HashSet::from([syncing]),
+            ..Default::default()
+        };
+
+        let changes = detect_changes(project_dir, &HashMap::new(), &current, &saved_state, &facts);
+        assert_eq!(
+            changes,
+            vec![change("concepts", "dropped.png", ChangeKind::Added)]
+        );
+    }
+
+    #[test]
+    fn registered_files_that_change_or_go_are_offered() {
+        let project_dir = Path::new("/projects/project");
+        let saved_state = state_with_concept("known.png");
+        let known = project_dir.join("concepts").join("known.png");
+        let stray = project_dir.join("concepts").join("stray.png");
+        let previous = HashMap::from([(known.clone(), stamp(1)), (stray.clone(), stamp(1))]);
+
+        let modified = HashMap::from([(known.clone(), stamp(2)), (stray.clone(), stamp(2))]);
+        assert_eq!(
+            detect_changes(
+                project_dir,
+                &previous,
+                &modified,
+                &saved_state,
+                &DiskFacts::default()
+            ),
+            vec![change("concepts", "known.png", ChangeKind::Modified)]
+        );
+
+        assert_eq!(
+            detect_changes(
+                project_dir,
+                &previous,
+                &HashMap::new(),
+                &saved_state,
+                &DiskFacts::default()
+            ),
+            vec![change("concepts", "known.png", ChangeKind::Removed)]
+        );
+    }
+
+    #[test]
+    fn landscapes_are_offered_once_complete() {
+        let project_dir = Path::new("/projects/project");
+        let saved_state = state_with_concept("known.png");
+        let current = HashMap::from([(
+            project_dir.join("landscapes/hills/heightmaps/height.png"),
+            stamp(1),
+        )]);
+
+        let partial = detect_changes(
+            project_dir,
+            &HashMap::new(),
+            &current,
+            &saved_state,
+            &DiskFacts::default(),
+        );
+        assert!(partial.is_empty());
+
+        let facts = DiskFacts {
+            complete_landscapes: HashSet::from(["hills".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            detect_changes(project_dir, &HashMap::new(), &current, &saved_state, &facts),
+            vec![change("landscapes", "hills", ChangeKind::Added)]
+        );
+    }
+
+    #[test]
+    fn the_editors_own_project_saves_are_not_offered() {
+        let project_dir = Path::new("/projects/project");
+        let saved_state = state_with_concept("known.png");
+        let project_file = project_dir.join("midpoint.json");
+        let previous = HashMap::from([(project_file.clone(), stamp(1))]);
+        let current = HashMap::from([(project_file, stamp(2))]);
+
+        let own = DiskFacts {
+            own_save: true,
+            ..Default::default()
+        };
+        assert!(detect_changes(project_dir, &previous, &current, &saved_state, &own).is_empty());
+
+        assert_eq!(
+            detect_changes(
+                project_dir,
+                &previous,
+                &current,
+                &saved_state,
+                &DiskFacts::default()
+            ),
+            vec![change("project", "midpoint.json", ChangeKind::Modified)]
+        );
+    }
+
+    #[test]
+    fn disk_facts_find_complete_landscapes_and_own_saves() {
+        let project_dir = std::env::temp_dir().join(format!("midpoint-{}", Uuid::new_v4()));
+        let mut current = HashMap::new();
+        for (landscape_id, maps) in [
+            ("hills", &LANDSCAPE_MAPS[..]),
+            ("dunes", &LANDSCAPE_MAPS[..1]),
+        ] {
+            for map in maps {
+                let dir = project_dir.join("landscapes").join(landscape_id).join(map);
+                fs::create_dir_all(&dir).unwrap();
+                fs::write(dir.join("map.png"), b"map").unwrap();
+                current.insert(dir.join("map.png"), stamp(3));
+            }
+        }
+        let project_file = project_dir.join("midpoint.json");
+        fs::write(&project_file, "{}").unwrap();
+        record_own_save(&project_file, b"{}");
+        current.insert(project_file.clone(), stamp(2));
+        let previous = HashMap::from([(project_file, stamp(1))]);
+
+        let facts = gather_disk_facts("project", &project_dir, &previous, &current);
+        assert_eq!(
+            facts.complete_landscapes,
+            HashSet::from(["hills".to_string()])
+        );
+        assert!(facts.own_save);
+
+        fs::remove_dir_all(&project_dir).unwrap();
+    }
+}
@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use ezsockets::{ClientConfig, CloseCode, CloseFrame, Error};
 use midpoint_engine::floem::reactive::SignalUpdate;
-use midpoint_engine::helpers::saved_data::File;
+use midpoint_engine::helpers::saved_data::{File, LandscapeData, SavedState};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::sync::{Arc, Mutex};
@@ -12,7 +12,20 @@ use url::Url;
 // use midpoint_engine::floem::reactive::RUNTIME;
 
 use crate::editor_state::{StateHelper, UIMessage};
-use crate::helpers::utilities::parse_ws_command;
+use crate::helpers::auth::{report_auth_failure, AuthStatus};
+use crate::helpers::collaboration::{
+    accept_remote, apply_to_saved_state, parse_scene_operation, SceneOperation,
+};
+use crate::helpers::downloads::spawn_download;
+use crate::helpers::outbox::spawn_outbox_flush;
+use crate::helpers::pipeline::resume_waiting_pipelines;
+use crate::helpers::presence::{
+    clear_peers, notify_presence, presence_leave_message, receive_presence,
+};
+use crate::helpers::transfers::{
+    parse_transfer_frame, pending_transfers, resume_message, write_chunk, ChunkOutcome,
+};
+use crate::helpers::utilities::{get_websocket_endpoint, parse_ws_command};
 
 // Types for our messages
 #[derive(Debug, Serialize)]
@@ -48,6 +61,30 @@ impl ezsockets::ClientExt for WebSocketClient {
     async fn on_text(&mut self, text: String) -> Result<(), Error> {
         println!("Received message: {}", text);
 
+        // the backend answers a join made with a bad token with `join_rejected`
+        if let Ok(event) = serde_json::from_str::<serde_json::Value>(&text) {
+            if event.get("event").and_then(|e| e.as_str()) == Some("join_rejected") {
+                let state_helper = self.state_helper.lock().unwrap();
+                report_auth_failure(&state_helper.file_signals, AuthStatus::Rejected);
+                return Ok(());
+            }
+        }
+
+        // who else has the project open
+        if receive_presence(&text) {
+            let state_helper = self.state_helper.lock().unwrap();
+            notify_presence(&state_helper.file_signals);
+            return Ok(());
+        }
+
+        // scene edits from other designers in the project group
+        if let Some(scene_operation) = parse_scene_operation(&text) {
+            if accept_remote(&scene_operation) {
+                self.apply_remote_scene_operation(scene_operation.operation);
+            }
+            return Ok(());
+        }
+
         // Handle refresh state_helper command
         if let Ok(command_data) = parse_ws_command(&text) {
             // let command_data = parse_ws_command(&text).expect("Couldn't parse WebSocket command");
@@ -69,8 +106,11 @@ impl ezsockets::ClientExt for WebSocketClient {
                 normalFilePath: command_data.normal_file_path,
             };
 
+            // fetch the file ourselves rather than waiting for another app to sync it
+            spawn_download(new_file.clone());
+
             if (command_data.command == "add_model") {
-                saved_state.models.push(new_file.clone());
+                upsert_file(&mut saved_state.models, new_file.clone());
 
                 println!("Updating signal...");
 
@@ -96,9 +136,11 @@ impl ezsockets::ClientExt for WebSocketClient {
                 state_helper.save_current_saved_state();
                 drop(state_helper);
 
+                self.resume_pipelines();
+
                 println!("Model Finished!");
             } else if (command_data.command == "add_concept") {
-                saved_state.concepts.push(new_file.clone());
+                upsert_file(&mut saved_state.concepts, new_file.clone());
 
                 println!("Updating signal...");
 
@@ -125,40 +167,66 @@ impl ezsockets::ClientExt for WebSocketClient {
                 state_helper.save_current_saved_state();
                 drop(state_helper);
 
+                self.resume_pipelines();
+
                 println!("Concept Finished!");
             } else if (command_data.command == "add_landscape_heightmap") {
-                let mut landscape = saved_state
-                    .landscapes
-                    .as_mut()
-                    .expect("Couldn't get landscape data")
-                    .iter_mut()
-                    .find(|l| l.id == command_data.parent_id)
-                    .expect("Couldn't get associated landscape");
-                landscape.heightmap = Some(new_file);
+                match find_landscape(&mut saved_state, &command_data.parent_id) {
+                    Some(landscape) => landscape.heightmap = Some(new_file),
+                    None => {
+                        println!(
+                            "Skipping {}, no landscape {}",
+                            command_data.command, command_data.parent_id
+                        );
+                        return Ok(());
+                    }
+                }
+
+                drop(saved_state);
+                drop(state_helper);
+
+                let mut state_helper = self.state_helper.lock().unwrap();
+                state_helper.save_current_saved_state();
             } else if (command_data.command == "add_landscape_rockmap") {
-                let mut landscape = saved_state
-                    .landscapes
-                    .as_mut()
-                    .expect("Couldn't get landscape data")
-                    .iter_mut()
-                    .find(|l| l.id == command_data.parent_id)
-                    .expect("Couldn't get associated landscape");
-                landscape.rockmap = Some(new_file);
+                match find_landscape(&mut saved_state, &command_data.parent_id) {
+                    Some(landscape) => landscape.rockmap = Some(new_file),
+                    None => {
+                        println!(
+                            "Skipping {}, no landscape {}",
+                            command_data.command, command_data.parent_id
+                        );
+                        return Ok(());
+                    }
+                }
+
+                drop(saved_state);
+                drop(state_helper);
+
+                let mut state_helper = self.state_helper.lock().unwrap();
+                state_helper.save_current_saved_state();
             } else if (command_data.command == "add_landscape_soil") {
-                let mut landscape = saved_state
-                    .landscapes
-                    .as_mut()
-                    .expect("Couldn't get landscape data")
-                    .iter_mut()
-                    .find(|l| l.id == command_data.parent_id)
-                    .expect("Couldn't get associated landscape");
-                landscape.soil = Some(new_file);
+                match find_landscape(&mut saved_state, &command_data.parent_id) {
+                    Some(landscape) => landscape.soil = Some(new_file),
+                    None => {
+                        println!(
+                            "Skipping {}, no landscape {}",
+                            command_data.command, command_data.parent_id
+                        );
+                        return Ok(());
+                    }
+                }
+
+                drop(saved_state);
+                drop(state_helper);
+
+                let mut state_helper = self.state_helper.lock().unwrap();
+                state_helper.save_current_saved_state();
             } else if (command_data.command == "add_texture") {
                 println!("adding texture... {:?}", new_file);
-                saved_state
-                    .textures
-                    .get_or_insert_with(Vec::new)
-                    .push(new_file.clone());
+                upsert_file(
+                    saved_state.textures.get_or_insert_with(Vec::new),
+                    new_file.clone(),
+                );
 
                 println!("Updating signal...");
 
@@ -203,7 +271,49 @@ impl ezsockets::ClientExt for WebSocketClient {
     }
 
     async fn on_binary(&mut self, bytes: Vec<u8>) -> Result<(), Error> {
-        println!("Received binary message: {:?}", bytes);
+        let (header, payload) = match parse_transfer_frame(&bytes) {
+            Ok(frame) => frame,
+            Err(e) => {
+                println!("Ignoring binary message: {}", e);
+                return Ok(());
+            }
+        };
+
+        let project_id = self.current_project_id();
+        let project_id = match project_id {
+            Some(project_id) => project_id,
+            None => {
+                println!("No project open, ignoring transfer of {}", header.asset_id);
+                return Ok(());
+            }
+        };
+
+        match write_chunk(&project_id, &header, payload) {
+            Ok(ChunkOutcome::InProgress(received)) => {
+                println!(
+                    "Transfer {}: {} / {} bytes",
+                    header.asset_id, received, header.total_size
+                );
+            }
+            Ok(ChunkOutcome::Resume(offset)) => {
+                println!(
+                    "Transfer {} out of order, resuming from {}",
+                    header.asset_id, offset
+                );
+                self.handle.text(resume_message(&header.asset_id, offset))?;
+            }
+            Ok(ChunkOutcome::ChecksumMismatch) => {
+                self.handle.text(resume_message(&header.asset_id, 0))?;
+            }
+            Ok(ChunkOutcome::Complete(new_file)) => {
+                println!("Transfer {} verified", header.asset_id);
+                self.register_transferred_asset(&header.kind, new_file);
+            }
+            Err(e) => {
+                println!("Transfer {} failed: {}", header.asset_id, e);
+            }
+        }
+
         Ok(())
     }
 
@@ -232,9 +342,15 @@ impl ezsockets::ClientExt for WebSocketClient {
                 println!("Joining group: {}", project_id);
                 self.handle.text(payload.to_string())?;
 
+                // collaborators from a previous project no longer apply
+                clear_peers();
+                notify_presence(&state_helper.file_signals);
+                state_helper.broadcast_presence();
+
                 drop(state_helper);
             }
             Call::Disconnect => {
+                self.handle.text(presence_leave_message())?;
                 self.handle.close(Some(CloseFrame {
                     code: CloseCode::Normal,
                     reason: "Client disconnecting".to_string(),
@@ -252,6 +368,35 @@ impl ezsockets::ClientExt for WebSocketClient {
         // Automatically join group upon connection
         // may want to do this upon opening a project, or perhaps start WebSocketManager after opening project
         // self.handle.call(Call::JoinGroup)?;
+
+        // on reconnect, rejoin the project group and send anything queued while offline
+        let state_helper = self.state_helper.lock().unwrap();
+        let project_id = state_helper
+            .renderer_state
+            .as_ref()
+            .and_then(|renderer_state| renderer_state.lock().unwrap().project_selected);
+
+        if let Some(project_id) = project_id {
+            let _ = self.handle.call(Call::JoinGroup);
+
+            // pick up any transfers the disconnect interrupted
+            for progress in pending_transfers(&project_id.to_string()) {
+                println!("Resuming transfer {}", progress.header.asset_id);
+                let _ = self.handle.call(Call::SendMessage(resume_message(
+                    &progress.header.asset_id,
+                    progress.received,
+                )));
+            }
+
+            spawn_outbox_flush(
+                Arc::clone(&self.state_helper),
+                state_helper.auth_token.clone(),
+                project_id.to_string(),
+            );
+        }
+
+        drop(state_helper);
+
         Ok(())
     }
 
@@ -261,6 +406,126 @@ impl ezsockets::ClientExt for WebSocketClient {
     // }
 }
 
+// a file already registered from the project folder gets the upload's details instead of a second entry
+fn upsert_file(files: &mut Vec<File>, new_file: File) {
+    match files.iter_mut().find(|f| f.fileName == new_file.fileName) {
+        Some(existing) => *existing = new_file,
+        None => files.push(new_file),
+    }
+}
+
+// map uploads name the landscape by the id the editor sent with `generateLandscape`
+fn find_landscape<'a>(
+    saved_state: &'a mut SavedState,
+    landscape_id: &str,
+) -> Option<&'a mut LandscapeData> {
+    saved_state
+        .landscapes
+        .as_mut()?
+        .iter_mut()
+        .find(|l| l.id == landscape_id)
+}
+
+impl WebSocketClient {
+    fn current_project_id(&self) -> Option<String> {
+        let state_helper = self.state_helper.lock().unwrap();
+        state_helper
+            .renderer_state
+            .as_ref()
+            .and_then(|renderer_state| renderer_state.lock().unwrap().project_selected)
+            .map(|project_id| project_id.to_string())
+    }
+
+    /// Lets concept-to-scene jobs continue once the upload they were waiting on has arrived
+    fn resume_pipelines(&self) {
+        if let Some(project_id) = self.current_project_id() {
+            resume_waiting_pipelines(Arc::clone(&self.state_helper), project_id);
+        }
+    }
+
+    /// Saves a remote edit, then hands it to the UI thread to update the renderer and panels
+    fn apply_remote_scene_operation(&self, operation: SceneOperation) {
+        let mut state_helper = self.state_helper.lock().unwrap();
+        let file_signals = Arc::clone(&state_helper.file_signals);
+
+        let saved_state = match state_helper.saved_state.as_ref() {
+            Some(saved_state) => Arc::clone(saved_state),
+            None => return,
+        };
+        let mut saved_state = saved_state.lock().unwrap();
+
+        apply_to_saved_state(&mut saved_state, &operation);
+
+        let components = saved_state
+            .levels
+            .as_ref()
+            .and_then(|levels| levels.get(0))
+            .and_then(|level| level.components.clone())
+            .unwrap_or_default();
+
+        drop(saved_state);
+
+        state_helper.save_current_saved_state();
+
+        let signals = file_signals.lock().unwrap();
+        if let Some(tx) = signals.get("scene") {
+            let _ = tx.send(UIMessage::SceneOperation(operation));
+        }
+        if let Some(tx) = signals.get("component_browser") {
+            let _ = tx.send(UIMessage::UpdateComponents(components));
+        }
+    }
+
+    /// Adds a verified transfer to the saved state and lets the matching browser know
+    fn register_transferred_asset(&self, kind: &str, new_file: File) {
+        let mut state_helper = self.state_helper.lock().unwrap();
+        let file_signals = Arc::clone(&state_helper.file_signals);
+
+        let mut saved_state = state_helper
+            .saved_state
+            .as_mut()
+            .expect("Couldn't get Saved State")
+            .lock()
+            .unwrap();
+
+        let (files, signal_name, message) = match kind {
+            "concept" => (
+                &mut saved_state.concepts,
+                "concept_browser",
+                UIMessage::AddConcept(new_file.clone()),
+            ),
+            "texture" => (
+                saved_state.textures.get_or_insert_with(Vec::new),
+                "texture_browser",
+                UIMessage::AddTexture(new_file.clone()),
+            ),
+            "model" => (
+                &mut saved_state.models,
+                "model_browser",
+                UIMessage::AddModel(new_file.clone()),
+            ),
+            _ => {
+                println!("Unhandled transfer kind {:?}", kind);
+                return;
+            }
+        };
+
+        // a resent transfer shouldn't show up twice
+        if files.iter().any(|f| f.id == new_file.id) {
+            return;
+        }
+        files.push(new_file);
+
+        drop(saved_state);
+
+        if let Some(tx) = file_signals.lock().unwrap().get(signal_name).cloned() {
+            let _ = tx.send(message);
+        }
+
+        state_helper.save_current_saved_state();
+    }
+}
+
 pub struct WebSocketManager {
     handle: Option<ezsockets::Client<WebSocketClient>>,
 }
@@ -275,7 +540,7 @@ impl WebSocketManager {
         state_helper: Arc<Mutex<StateHelper>>,
         on_message: impl Fn(String, String, Vec<File>) + Send + Sync + 'static,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let url = Url::parse("ws://localhost:4000")?;
+        let url = Url::parse(&get_websocket_endpoint())?;
         let config = ClientConfig::new(url);
 
         // let state_helper = Arc::new(Mutex::new(state_helper));
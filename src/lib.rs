@@ -1,4 +1,5 @@
 pub mod editor_state;
 pub mod gql;
 pub mod helpers;
+pub mod stand_in;
 pub mod views;
@@ -4,7 +4,10 @@ use std::sync::{Arc, Mutex, MutexGuard};
 
 use bytemuck::Contiguous;
 use editor_state::{EditorState, ObjectEdit, StateHelper, UIMessage};
-use helpers::auth::read_auth_token;
+use helpers::auth::{check_auth, read_stored_auth, refresh_stored_auth, AuthStatus};
+use helpers::collaboration::{apply_to_saved_state, transform_operations};
+use helpers::presence::spawn_presence_heartbeat;
+use helpers::utilities::get_backend_address;
 use helpers::websocket::{Call, WebSocketManager};
 use midpoint_engine::core::Rays::{create_ray_debug_mesh, create_ray_from_mouse};
 use midpoint_engine::core::RendererState::{Point, RendererState, WindowSize};
@@ -32,9 +35,11 @@ use midpoint_engine::floem::{Application, CustomRenderCallback};
 use midpoint_engine::floem::{GpuHelper, View, WindowHandle};
 use undo::{Edit, Record};
 
+pub mod control;
 pub mod editor_state;
 pub mod gql;
 pub mod helpers;
+pub mod stand_in;
 pub mod views;
 
 type RenderCallback<'a> = dyn for<'b> Fn(
@@ -578,6 +583,7 @@ fn handle_cursor_moved(
 }
 
 fn handle_mouse_input(
+    state_helper: Arc<Mutex<StateHelper>>,
     mut editor_state: Arc<Mutex<EditorState>>,
     gpu_resources: std::sync::Arc<GpuResources>,
     viewport: std::sync::Arc<Mutex<Viewport>>,
@@ -598,18 +604,44 @@ fn handle_mouse_input(
                 ElementState::Released => renderer_state.mouse_state.right_mouse_pressed = false,
             };
         }
+        let mut dragged_component = None;
+
         if button == MouseButton::Left {
             let edit_config = match state {
                 ElementState::Pressed => {
                     renderer_state.mouse_state.drag_started = true;
                 }
                 ElementState::Released => {
+                    if renderer_state.dragging_gizmo {
+                        dragged_component = renderer_state.object_selected_data.clone();
+                    }
                     renderer_state.mouse_state.is_dragging = false;
                     renderer_state.dragging_gizmo = false;
                 }
             };
         }
 
+        // dragging only moves the renderer, so save and share where the component ended up
+        if let Some(component) = dragged_component {
+            drop(renderer_state);
+            drop(editor_state);
+
+            let operations = transform_operations(&component);
+
+            let mut state_helper = state_helper.lock().unwrap();
+            if let Some(saved_state) = state_helper.saved_state.as_ref() {
+                let mut saved_state = saved_state.lock().unwrap();
+                for operation in operations.iter() {
+                    apply_to_saved_state(&mut saved_state, operation);
+                }
+            }
+            state_helper.save_current_saved_state();
+            for operation in operations {
+                state_helper.broadcast_scene_operation(operation);
+            }
+            return;
+        }
+
         // let mut editor_orig = Arc::clone(&editor);
         // let mut editor = editor.lock().unwrap();
         // let viewport = viewport.lock().unwrap();
@@ -793,10 +825,30 @@ async fn main() {
     // Initialize logging
     // tracing::fmt::init();
 
-    let auth_token = read_auth_token();
+    // run against the in-process stand-in backend instead of commonos-server
+    if std::env::var("MIDPOINT_STAND_IN").is_ok() {
+        stand_in::start_stand_in_server(&get_backend_address())
+            .await
+            .expect("Couldn't start stand-in backend");
+    }
+
+    let mut stored_auth = read_stored_auth();
 
-    // TODO: show alert if auth_token is empty
-    println!("auth_token {:?}", auth_token);
+    // try to refresh an expired token before asking the user to sign in again
+    if check_auth(&stored_auth) == AuthStatus::Expired {
+        if let Some(expired) = stored_auth.clone() {
+            match refresh_stored_auth(expired).await {
+                Ok(refreshed) => stored_auth = Some(refreshed),
+                Err(e) => println!("Couldn't refresh auth token: {}", e),
+            }
+        }
+    }
+
+    // the sign in dialog is shown for anything other than SignedIn
+    let auth_status = check_auth(&stored_auth);
+    let auth_token = stored_auth.map(|a| a.token).unwrap_or_default();
+
+    println!("auth_status {:?}", auth_status);
 
     let app = Application::new();
 
@@ -814,7 +866,7 @@ async fn main() {
     };
 
     let mut gpu_helper = Arc::new(Mutex::new(GpuHelper::new()));
-    let mut state_helper = Arc::new(Mutex::new(StateHelper::new(auth_token)));
+    let mut state_helper = Arc::new(Mutex::new(StateHelper::new(auth_token, auth_status)));
 
     let state_2 = Arc::clone(&state_helper);
     let state_3 = Arc::clone(&state_helper);
@@ -859,6 +911,28 @@ async fn main() {
 
     let manager = Arc::new(manager);
 
+    // scene edits are broadcast to the project group through the same connection
+    state_helper.lock().unwrap().websocket = Some(Arc::clone(&manager));
+
+    spawn_presence_heartbeat(Arc::clone(&state_helper));
+
+    // lets tools on this machine drive the editor
+    if std::env::var("MIDPOINT_CONTROL").is_ok() {
+        match control::control_address() {
+            Ok(address) => match TcpListener::bind(address).await {
+                Ok(listener) => {
+                    if let Err(e) =
+                        control::start_control_server(listener, Arc::clone(&state_helper))
+                    {
+                        println!("Couldn't start control API: {}", e);
+                    }
+                }
+                Err(e) => println!("Couldn't listen on {}: {}", address, e),
+            },
+            Err(e) => println!("Control API not started: {}", e),
+        }
+    }
+
     // // Disconnect when done
     // manager.disconnect();
 
@@ -1267,6 +1341,7 @@ async fn main() {
                     viewport_3.clone(),
                 );
                 window_handle.handle_mouse_input = handle_mouse_input(
+                    state_5.clone(),
                     editor_state.clone(),
                     gpu_resources.clone(),
                     viewport_4.clone(),
@@ -0,0 +1,187 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Cursor;
+
+use base64::encode;
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+
+pub const STAND_IN_IMAGE_SIZE: u32 = 512;
+
+// seeds procedural output from the prompt so the same prompt always gives the same asset
+pub fn prompt_seed(prompt: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    prompt.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn seed_color(seed: u64, shift: u32) -> [u8; 3] {
+    let bits = seed.rotate_left(shift);
+    [
+        (bits & 0xff) as u8,
+        ((bits >> 8) & 0xff) as u8,
+        ((bits >> 16) & 0xff) as u8,
+    ]
+}
+
+/// Diagonal gradient with a checker overlay, tinted by the seed
+pub fn procedural_image(seed: u64, width: u32, height: u32) -> RgbaImage {
+    let from = seed_color(seed, 0);
+    let to = seed_color(seed, 24);
+    let tile = 16 + (seed % 48) as u32;
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let t = (x + y) as f32 / (width + height) as f32;
+        let checker = if ((x / tile) + (y / tile)) % 2 == 0 {
+            1.0
+        } else {
+            0.8
+        };
+
+        let mix = |a: u8, b: u8| ((a as f32 * (1.0 - t) + b as f32 * t) * checker) as u8;
+
+        Rgba([
+            mix(from[0], to[0]),
+            mix(from[1], to[1]),
+            mix(from[2], to[2]),
+            255,
+        ])
+    })
+}
+
+pub fn encode_png(image: RgbaImage) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    DynamicImage::ImageRgba8(image)
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .expect("Couldn't encode stand-in image");
+    bytes
+}
+
+pub fn procedural_png_data_url(seed: u64, width: u32, height: u32) -> String {
+    let png = encode_png(procedural_image(seed, width, height));
+    format!("data:image/png;base64,{}", encode(png))
+}
+
+/// Unit cube with positions, normals, texcoords and u16 indices packed into a GLB
+pub fn cube_glb() -> Vec<u8> {
+    let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+        // normal, u axis, v axis
+        ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ];
+    let corners: [(f32, f32); 4] = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut texcoords: Vec<[f32; 2]> = Vec::new();
+    let mut indices: Vec<u16> = Vec::new();
+
+    for (normal, u_axis, v_axis) in faces.iter() {
+        let base = positions.len() as u16;
+        for (u, v) in corners.iter() {
+            positions.push([
+                0.5 * (normal[0] + u * u_axis[0] + v * v_axis[0]),
+                0.5 * (normal[1] + u * u_axis[1] + v * v_axis[1]),
+                0.5 * (normal[2] + u * u_axis[2] + v * v_axis[2]),
+            ]);
+            normals.push(*normal);
+            texcoords.push([(u + 1.0) * 0.5, 1.0 - (v + 1.0) * 0.5]);
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    mesh_glb("StandInCube", &positions, &normals, &texcoords, &indices)
+}
+
+/// Packs a single untextured mesh into a GLB
+pub fn mesh_glb(
+    name: &str,
+    positions: &[[f32; 3]],
+    normals: &[[f32; 3]],
+    texcoords: &[[f32; 2]],
+    indices: &[u16],
+) -> Vec<u8> {
+    // glTF requires bounds on the position accessor
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for position in positions.iter() {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(position[axis]);
+            max[axis] = max[axis].max(position[axis]);
+        }
+    }
+
+    let mut bin: Vec<u8> = Vec::new();
+    bin.extend_from_slice(bytemuck::cast_slice(positions));
+    let normals_offset = bin.len();
+    bin.extend_from_slice(bytemuck::cast_slice(normals));
+    let texcoords_offset = bin.len();
+    bin.extend_from_slice(bytemuck::cast_slice(texcoords));
+    let indices_offset = bin.len();
+    bin.extend_from_slice(bytemuck::cast_slice(indices));
+    while bin.len() % 4 != 0 {
+        bin.push(0);
+    }
+
+    let json = serde_json::json!({
+        "asset": { "version": "2.0", "generator": "midpoint stand-in" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0, "name": name }],
+        "meshes": [{
+            "primitives": [{
+                "attributes": { "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2 },
+                "indices": 3,
+                "material": 0
+            }]
+        }],
+        "materials": [{
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [0.8, 0.8, 0.8, 1.0],
+                "metallicFactor": 0.0,
+                "roughnessFactor": 1.0
+            }
+        }],
+        "buffers": [{ "byteLength": bin.len() }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": normals_offset, "target": 34962 },
+            { "buffer": 0, "byteOffset": normals_offset, "byteLength": texcoords_offset - normals_offset, "target": 34962 },
+            { "buffer": 0, "byteOffset": texcoords_offset, "byteLength": indices_offset - texcoords_offset, "target": 34962 },
+            { "buffer": 0, "byteOffset": indices_offset, "byteLength": indices.len() * 2, "target": 34963 }
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": positions.len(), "type": "VEC3",
+              "min": min, "max": max },
+            { "bufferView": 1, "componentType": 5126, "count": normals.len(), "type": "VEC3" },
+            { "bufferView": 2, "componentType": 5126, "count": texcoords.len(), "type": "VEC2" },
+            { "bufferView": 3, "componentType": 5123, "count": indices.len(), "type": "SCALAR" }
+        ]
+    });
+
+    let mut json_bytes = serde_json::to_vec(&json).expect("Couldn't serialize stand-in glTF");
+    while json_bytes.len() % 4 != 0 {
+        json_bytes.push(b' ');
+    }
+
+    let total_length = 12 + 8 + json_bytes.len() + 8 + bin.len();
+
+    let mut glb: Vec<u8> = Vec::with_capacity(total_length);
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(total_length as u32).to_le_bytes());
+    glb.extend_from_slice(&(json_bytes.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json_bytes);
+    glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"BIN\0");
+    glb.extend_from_slice(&bin);
+
+    glb
+}
+
+pub fn cube_glb_data_url() -> String {
+    format!("data:model/gltf-binary;base64,{}", encode(cube_glb()))
+}
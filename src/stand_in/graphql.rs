@@ -0,0 +1,238 @@
+use chrono::{Local, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::helpers::generation::{output_dimensions, DEFAULT_RESOLUTION};
+use crate::helpers::landscapes::LandscapeParams;
+use crate::helpers::local_provider::landscape_maps;
+
+use super::assets::{cube_glb_data_url, procedural_png_data_url, prompt_seed, STAND_IN_IMAGE_SIZE};
+use super::StandInState;
+
+const STAND_IN_TOKEN_LIFETIME_SECS: i64 = 60 * 60;
+
+// made up prices, so the usage ledger has something to add up
+const IMAGE_CREDITS: f64 = 1.0;
+const MODEL_CREDITS: f64 = 4.0;
+const LANDSCAPE_CREDITS: f64 = 2.0;
+
+#[derive(Debug, Deserialize)]
+pub struct GraphQLRequest {
+    pub query: String,
+    #[serde(default)]
+    pub variables: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MdProjectRecord {
+    pub id: String,
+    pub title: String,
+    pub context: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
+
+impl GraphQLRequest {
+    /// The first field of the operation's selection set, which names what is being resolved
+    pub fn root_field(&self) -> Option<&str> {
+        root_field(&self.query)
+    }
+
+    // signing in is the only thing allowed without a bearer token
+    pub fn is_public(&self) -> bool {
+        matches!(
+            self.root_field(),
+            Some("signIn") | Some("refreshToken") | Some("backendVersion")
+        )
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Skips the operation type, name and variable definitions, then reads the first field,
+/// looking past an alias like `projects: getMdProjects`
+pub fn root_field(query: &str) -> Option<&str> {
+    // comments run to the end of the line and may mention any field
+    let mut depth = 0;
+    let mut selection_start = None;
+    let mut in_comment = false;
+    for (i, c) in query.char_indices() {
+        match c {
+            '\n' if in_comment => in_comment = false,
+            _ if in_comment => {}
+            '#' => in_comment = true,
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '{' if depth == 0 => {
+                selection_start = Some(i + 1);
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let read_name = |rest: &str| -> Option<(usize, usize)> {
+        let start = rest.len() - rest.trim_start().len();
+        let len = rest[start..]
+            .find(|c: char| !is_name_char(c))
+            .unwrap_or(rest.len() - start);
+        (len > 0).then_some((start, start + len))
+    };
+
+    let selection = &query[selection_start?..];
+    let (start, end) = read_name(selection)?;
+    let after = selection[end..].trim_start();
+
+    match after.strip_prefix(':') {
+        Some(aliased) => {
+            let (alias_start, alias_end) = read_name(aliased)?;
+            Some(&aliased[alias_start..alias_end])
+        }
+        None => Some(&selection[start..end]),
+    }
+}
+
+fn variable(variables: &Value, name: &str) -> String {
+    variables
+        .get(name)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// A single image sized by the resolution and aspect ratio variables, like the real backend
+fn generated_image(variables: &Value) -> String {
+    let prompt = variable(variables, "prompt");
+    let seed = variables
+        .get("seed")
+        .and_then(|v| v.as_i64())
+        .map(|seed| seed as u64)
+        .unwrap_or_else(|| prompt_seed(&prompt));
+    let resolution = variables
+        .get("resolution")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(DEFAULT_RESOLUTION as i64)
+        // keep stand-in responses small
+        .min(STAND_IN_IMAGE_SIZE as i64) as i32;
+    let aspect_ratio = variables
+        .get("aspectRatio")
+        .and_then(|v| v.as_str())
+        .unwrap_or("1:1");
+
+    let (width, height) = output_dimensions(resolution, aspect_ratio);
+
+    procedural_png_data_url(seed, width, height)
+}
+
+/// Procedural maps from the same generator the local provider uses
+fn generated_landscape(variables: &Value) -> Value {
+    let params: LandscapeParams = serde_json::from_value(variables.clone()).unwrap_or_default();
+    let params = LandscapeParams {
+        // keep stand-in responses small
+        size: params.size.min(STAND_IN_IMAGE_SIZE as i32),
+        ..params.resolved()
+    };
+    let maps = landscape_maps(&params);
+
+    json!({
+        "heightmap": format!("data:image/png;base64,{}", maps.heightmap),
+        "rockmap": format!("data:image/png;base64,{}", maps.rockmap),
+        "soil": format!("data:image/png;base64,{}", maps.soil),
+    })
+}
+
+fn graphql_error(message: &str) -> Value {
+    json!({ "data": null, "errors": [{ "message": message }] })
+}
+
+// the cost rides along in the response extensions, where the real backend reports it
+fn charged(root_field: &str, payload: Value, credits: f64) -> Value {
+    json!({ "data": { root_field: payload }, "extensions": { "credits": credits } })
+}
+
+/// Resolves one GraphQL operation, matching on the root field named in the query
+pub fn handle_graphql(state: &mut StandInState, request: GraphQLRequest) -> Value {
+    let variables = &request.variables;
+
+    match request.root_field().unwrap_or_default() {
+        root_field @ ("signIn" | "refreshToken") => {
+            let payload = json!({
+                "token": format!("stand-in-{}", Uuid::new_v4()),
+                "refreshToken": format!("stand-in-refresh-{}", Uuid::new_v4()),
+                "expiresAt": Utc::now().timestamp() + STAND_IN_TOKEN_LIFETIME_SECS,
+            });
+            json!({ "data": { root_field: payload } })
+        }
+        "backendVersion" => {
+            json!({ "data": { "backendVersion": format!("stand-in {}", env!("CARGO_PKG_VERSION")) } })
+        }
+        "generateConcept" => charged(
+            "generateConcept",
+            json!(generated_image(variables)),
+            IMAGE_CREDITS,
+        ),
+        "generateTexture" => charged(
+            "generateTexture",
+            json!(generated_image(variables)),
+            IMAGE_CREDITS,
+        ),
+        "generateLandscape" => charged(
+            "generateLandscape",
+            generated_landscape(variables),
+            LANDSCAPE_CREDITS,
+        ),
+        "generateModel" => charged("generateModel", json!(cube_glb_data_url()), MODEL_CREDITS),
+        "createMdProject" => {
+            let now = Local::now().to_rfc3339();
+            let project = MdProjectRecord {
+                id: Uuid::new_v4().to_string(),
+                title: "New Project".to_string(),
+                context: None,
+                created_at: now.clone(),
+                updated_at: now,
+            };
+            state.projects.insert(project.id.clone(), project.clone());
+            json!({ "data": { "createMdProject": project } })
+        }
+        "updateMdProject" => {
+            let project_id = variable(variables, "projectId");
+            match state.projects.get_mut(&project_id) {
+                Some(project) => {
+                    if let Some(title) = variables.get("title").and_then(|v| v.as_str()) {
+                        project.title = title.to_string();
+                    }
+                    if let Some(context) = variables.get("context").and_then(|v| v.as_str()) {
+                        project.context = Some(context.to_string());
+                    }
+                    project.updated_at = Local::now().to_rfc3339();
+                    json!({ "data": { "updateMdProject": project } })
+                }
+                None => graphql_error("Project not found"),
+            }
+        }
+        "deleteMdProject" => {
+            let project_id = variable(variables, "projectId");
+            match state.projects.remove(&project_id) {
+                Some(_) => json!({ "data": { "deleteMdProject": "success" } }),
+                None => graphql_error("Project not found"),
+            }
+        }
+        "getMdProjects" => {
+            let projects: Vec<&MdProjectRecord> = state.projects.values().collect();
+            json!({ "data": { "getMdProjects": projects } })
+        }
+        "getMdProject" => {
+            let project_id = variable(variables, "projectId");
+            match state.projects.get(&project_id) {
+                Some(project) => json!({ "data": { "getMdProject": project } }),
+                None => graphql_error("Project not found"),
+            }
+        }
+        _ => graphql_error("Operation not supported by stand-in server"),
+    }
+}
@@ -0,0 +1,507 @@
+pub mod assets;
+pub mod graphql;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_tungstenite::accept_async;
+use tokio_tungstenite::tungstenite::Message;
+use uuid::Uuid;
+
+use crate::helpers::utilities::{get_common_os_dir, get_project_dir};
+
+use self::graphql::{handle_graphql, GraphQLRequest, MdProjectRecord};
+
+type StandInError = Box<dyn std::error::Error + Send + Sync>;
+
+// a request head any client of ours sends fits comfortably in this
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+// asset folders the sync emulation watches, with the command the real backend sends for each
+const SYNCED_KINDS: [(&str, &str); 3] = [
+    ("concepts", "add_concept"),
+    ("textures", "add_texture"),
+    ("models", "add_model"),
+];
+
+pub struct GroupMember {
+    pub client_id: Uuid,
+    pub tx: UnboundedSender<Message>,
+}
+
+/// In-memory state of the stand-in backend, shared by every connection
+pub struct StandInState {
+    pub address: String,
+    pub projects: HashMap<String, MdProjectRecord>,
+    pub groups: HashMap<String, Vec<GroupMember>>,
+    pub seen_files: HashSet<PathBuf>,
+}
+
+impl StandInState {
+    pub fn new(address: String) -> Self {
+        Self {
+            address,
+            projects: HashMap::new(),
+            groups: HashMap::new(),
+            seen_files: HashSet::new(),
+        }
+    }
+
+    pub fn broadcast(&self, group_id: &str, message: Message, except: Option<Uuid>) {
+        if let Some(members) = self.groups.get(group_id) {
+            for member in members.iter() {
+                if Some(member.client_id) != except {
+                    let _ = member.tx.send(message.clone());
+                }
+            }
+        }
+    }
+}
+
+fn list_files(dir: &PathBuf) -> Vec<PathBuf> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn has_bearer_token(authorization: &str) -> bool {
+    authorization
+        .strip_prefix("Bearer ")
+        .map(|token| !token.trim().is_empty())
+        .unwrap_or(false)
+}
+
+async fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> Result<(), StandInError> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Everything up to and including the blank line that ends the request head,
+/// `None` if the client hung up before sending one
+async fn read_head(stream: &mut TcpStream) -> Result<Option<(Vec<u8>, usize)>, StandInError> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        // the terminator can straddle two reads
+        let search_from = buffer.len().saturating_sub(3);
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(position) = find_subsequence(&buffer[search_from..], b"\r\n\r\n") {
+            return Ok(Some((buffer, search_from + position + 4)));
+        }
+        if buffer.len() > MAX_HEAD_BYTES {
+            return Err("Request head is too large".into());
+        }
+    }
+}
+
+/// Method, path and lowercased headers of a request head
+fn parse_head(head: &[u8]) -> (String, String, HashMap<String, String>) {
+    let head = String::from_utf8_lossy(head).to_string();
+    let mut lines = head.lines();
+    let request_line = lines.next().unwrap_or_default();
+    let mut request_parts = request_line.split_whitespace();
+    let method = request_parts.next().unwrap_or_default().to_string();
+    let path = request_parts.next().unwrap_or_default().to_string();
+
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    (method, path, headers)
+}
+
+/// Replays bytes already read off the socket before reading from it again,
+/// so the WebSocket handshake sees the request head it was sent
+struct PrefixedStream {
+    prefix: Vec<u8>,
+    position: usize,
+    stream: TcpStream,
+}
+
+impl AsyncRead for PrefixedStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        if self.position < self.prefix.len() {
+            let remaining = &self.prefix[self.position..];
+            let count = remaining.len().min(buf.remaining());
+            buf.put_slice(&remaining[..count]);
+            self.position += count;
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for PrefixedStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+async fn handle_http(
+    mut stream: TcpStream,
+    mut buffer: Vec<u8>,
+    header_end: usize,
+    state: Arc<Mutex<StandInState>>,
+) -> Result<(), StandInError> {
+    let mut chunk = [0u8; 4096];
+    let (method, path, headers) = parse_head(&buffer[..header_end]);
+
+    let content_length = headers
+        .get("content-length")
+        .and_then(|length| length.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    while buffer.len() < header_end + content_length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    let body_end = (header_end + content_length).min(buffer.len());
+    let body = &buffer[header_end..body_end];
+
+    if method == "POST" && path == "/graphql" {
+        let request = match serde_json::from_slice::<GraphQLRequest>(body) {
+            Ok(request) => request,
+            Err(e) => {
+                let invalid =
+                    json!({ "errors": [{ "message": format!("Invalid request: {}", e) }] });
+                return write_response(
+                    &mut stream,
+                    "400 Bad Request",
+                    "application/json",
+                    invalid.to_string().as_bytes(),
+                )
+                .await;
+            }
+        };
+
+        let authorization = headers.get("authorization").cloned().unwrap_or_default();
+        if !request.is_public() && !has_bearer_token(&authorization) {
+            let unauthorized = json!({ "errors": [{ "message": "Unauthorized" }] });
+            return write_response(
+                &mut stream,
+                "401 Unauthorized",
+                "application/json",
+                unauthorized.to_string().as_bytes(),
+            )
+            .await;
+        }
+
+        let response = {
+            let mut state = state.lock().unwrap();
+            handle_graphql(&mut state, request)
+        };
+
+        write_response(
+            &mut stream,
+            "200 OK",
+            "application/json",
+            response.to_string().as_bytes(),
+        )
+        .await
+    } else if method == "GET" && path.starts_with("/files/") {
+        // /files/{project_id}/{kind}/{filename}
+        let relative = path.trim_start_matches("/files/");
+        if relative
+            .split('/')
+            .any(|part| part == ".." || part.is_empty())
+        {
+            return write_response(&mut stream, "400 Bad Request", "text/plain", b"Bad path").await;
+        }
+
+        let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
+        let file_path = sync_dir.join("midpoint").join("projects").join(relative);
+
+        match fs::read(&file_path) {
+            Ok(bytes) => {
+                write_response(&mut stream, "200 OK", "application/octet-stream", &bytes).await
+            }
+            Err(_) => {
+                write_response(&mut stream, "404 Not Found", "text/plain", b"Not found").await
+            }
+        }
+    } else {
+        write_response(&mut stream, "404 Not Found", "text/plain", b"Not found").await
+    }
+}
+
+fn handle_ws_text(
+    state: &Arc<Mutex<StandInState>>,
+    client_id: Uuid,
+    tx: &UnboundedSender<Message>,
+    joined_group: &mut Option<String>,
+    text: String,
+) {
+    let parsed: Value = match serde_json::from_str(&text) {
+        Ok(parsed) => parsed,
+        Err(_) => {
+            println!("Stand-in ignoring non-JSON message");
+            return;
+        }
+    };
+
+    let mut state = state.lock().unwrap();
+
+    if parsed.get("event").and_then(|e| e.as_str()) == Some("join") {
+        let authorization = parsed
+            .get("Authorization")
+            .and_then(|a| a.as_str())
+            .unwrap_or_default();
+        let group_id = parsed
+            .get("payload")
+            .and_then(|p| p.get("group_id"))
+            .and_then(|g| g.as_str())
+            .unwrap_or_default()
+            .to_string();
+
+        if !has_bearer_token(authorization) || group_id.is_empty() {
+            let rejection = json!({ "event": "join_rejected", "reason": "Unauthorized" });
+            let _ = tx.send(Message::Text(rejection.to_string()));
+            return;
+        }
+
+        // files already on disk when the first client joins count as synced
+        if !state.groups.contains_key(&group_id) {
+            let project_dir = get_project_dir(&group_id);
+            for (kind, _) in SYNCED_KINDS.iter() {
+                for file in list_files(&project_dir.join(kind)) {
+                    state.seen_files.insert(file);
+                }
+            }
+        }
+
+        state
+            .groups
+            .entry(group_id.clone())
+            .or_insert_with(Vec::new)
+            .retain(|member| member.client_id != client_id);
+        state
+            .groups
+            .get_mut(&group_id)
+            .expect("Couldn't get group")
+            .push(GroupMember {
+                client_id,
+                tx: tx.clone(),
+            });
+
+        println!("Stand-in client {} joined group {}", client_id, group_id);
+        *joined_group = Some(group_id);
+    } else if let Some(group_id) = joined_group.as_ref() {
+        // everything else is relayed to the rest of the project group
+        state.broadcast(group_id, Message::Text(text), Some(client_id));
+    }
+}
+
+async fn handle_websocket(
+    stream: PrefixedStream,
+    state: Arc<Mutex<StandInState>>,
+) -> Result<(), StandInError> {
+    let websocket = accept_async(stream).await?;
+    let (mut sink, mut source) = websocket.split();
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Message>();
+    let client_id = Uuid::new_v4();
+    let mut joined_group: Option<String> = None;
+
+    let writer = tokio::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            if sink.send(message).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(message) = source.next().await {
+        let message = match message {
+            Ok(message) => message,
+            Err(_) => break,
+        };
+
+        match message {
+            Message::Text(text) => {
+                handle_ws_text(&state, client_id, &tx, &mut joined_group, text);
+            }
+            Message::Binary(bytes) => {
+                if let Some(group_id) = joined_group.as_ref() {
+                    let state = state.lock().unwrap();
+                    state.broadcast(group_id, Message::Binary(bytes), Some(client_id));
+                }
+            }
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+
+    if let Some(group_id) = joined_group {
+        let mut state = state.lock().unwrap();
+        if let Some(members) = state.groups.get_mut(&group_id) {
+            members.retain(|member| member.client_id != client_id);
+        }
+    }
+
+    writer.abort();
+
+    Ok(())
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    state: Arc<Mutex<StandInState>>,
+) -> Result<(), StandInError> {
+    let (buffer, header_end) = match read_head(&mut stream).await? {
+        Some(head) => head,
+        None => return Ok(()),
+    };
+    let (_, _, headers) = parse_head(&buffer[..header_end]);
+
+    let upgrade = headers.get("upgrade").map(|u| u.to_lowercase());
+    if upgrade.as_deref() == Some("websocket") {
+        let stream = PrefixedStream {
+            prefix: buffer,
+            position: 0,
+            stream,
+        };
+        handle_websocket(stream, state).await
+    } else {
+        handle_http(stream, buffer, header_end, state).await
+    }
+}
+
+/// Stands in for the CommonOS file manager: new files in a joined project's
+/// asset folders are announced to the group as `add_*` commands
+fn sync_project_files(state: &mut StandInState) {
+    let group_ids: Vec<String> = state.groups.keys().cloned().collect();
+
+    for group_id in group_ids {
+        let project_dir = get_project_dir(&group_id);
+
+        for (kind, command) in SYNCED_KINDS.iter() {
+            for file in list_files(&project_dir.join(kind)) {
+                if state.seen_files.contains(&file) {
+                    continue;
+                }
+
+                let filename = file
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or_default()
+                    .to_string();
+
+                let message = json!({
+                    "command": command,
+                    "parentId": "",
+                    "newId": Uuid::new_v4().to_string(),
+                    "fileName": filename,
+                    "cloudfrontUrl": format!(
+                        "http://{}/files/{}/{}/{}",
+                        state.address, group_id, kind, filename
+                    ),
+                    "normalFilePath": format!("midpoint/projects/{}/{}", group_id, kind),
+                });
+
+                println!("Stand-in syncing {}", file.display());
+                state.broadcast(&group_id, Message::Text(message.to_string()), None);
+                state.seen_files.insert(file);
+            }
+        }
+    }
+}
+
+/// Binds the stand-in backend and serves GraphQL, files and WebSocket groups in the background
+pub async fn start_stand_in_server(address: &str) -> std::io::Result<Arc<Mutex<StandInState>>> {
+    let listener = TcpListener::bind(address).await?;
+    let state = Arc::new(Mutex::new(StandInState::new(address.to_string())));
+
+    println!("Stand-in backend listening on {}", address);
+
+    tokio::spawn({
+        let state = Arc::clone(&state);
+        async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let state = Arc::clone(&state);
+                        tokio::spawn(async move {
+                            if let Err(e) = handle_connection(stream, state).await {
+                                println!("Stand-in connection error: {:?}", e);
+                            }
+                        });
+                    }
+                    Err(e) => println!("Stand-in accept error: {:?}", e),
+                }
+            }
+        }
+    });
+
+    tokio::spawn({
+        let state = Arc::clone(&state);
+        async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                let mut state = state.lock().unwrap();
+                sync_project_files(&mut state);
+            }
+        }
+    });
+
+    Ok(state)
+}
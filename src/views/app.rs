@@ -1,8 +1,11 @@
 use midpoint_engine::core::RendererState::ObjectConfig;
 use midpoint_engine::core::Viewport::Viewport;
+use midpoint_engine::floem::ext_event::create_signal_from_tokio_channel;
 use midpoint_engine::floem::reactive::create_effect;
 use midpoint_engine::floem::reactive::create_rw_signal;
 use midpoint_engine::floem::reactive::SignalGet;
+use midpoint_engine::floem::reactive::SignalUpdate;
+use midpoint_engine::floem::views::Decorators;
 use midpoint_engine::floem::views::{
     container, dyn_container, empty, label, scroll, stack, tab, text_input, virtual_stack,
     VirtualDirection, VirtualItemSize,
@@ -10,6 +13,7 @@ use midpoint_engine::floem::views::{
 use midpoint_engine::helpers::saved_data::ComponentData;
 use midpoint_engine::helpers::saved_data::ComponentKind;
 use midpoint_engine::helpers::saved_data::GenericProperties;
+use midpoint_engine::startup::restore_renderer_from_saved;
 use std::sync::{Arc, Mutex, MutexGuard};
 use uuid::Uuid;
 use wgpu::util::DeviceExt;
@@ -17,12 +21,19 @@ use wgpu::util::DeviceExt;
 use midpoint_engine::floem::GpuHelper;
 use midpoint_engine::floem::IntoView;
 
-use crate::editor_state::StateHelper;
+use crate::editor_state::{StateHelper, UIMessage};
+use crate::helpers::auth::{read_stored_auth, refresh_stored_auth, AuthStatus};
+use crate::helpers::collaboration::{apply_to_renderer, SceneOperation};
+use crate::helpers::outbox::spawn_outbox_flush;
+use crate::helpers::pipeline::place_pipeline_model;
 use crate::helpers::websocket::WebSocketManager;
 
 use super::aside::project_tab_interface;
 use super::aside::welcome_tab_interface;
+use super::outbox::outbox_panel;
+use super::project_browser::open_project;
 use super::properties_panel::properties_view;
+use super::sign_in::sign_in_dialog;
 
 pub fn project_view(
     state_helper: Arc<Mutex<StateHelper>>,
@@ -52,6 +63,17 @@ pub fn project_view(
     });
 
     let state_2 = Arc::clone(&state_helper);
+    let state_3 = Arc::clone(&state_helper);
+    let state_4 = Arc::clone(&state_helper);
+    let state_5 = Arc::clone(&state_helper);
+    let state_6 = Arc::clone(&state_helper);
+    let gpu_2 = Arc::clone(&gpu_helper);
+    let gpu_3 = Arc::clone(&gpu_helper);
+    let gpu_4 = Arc::clone(&gpu_helper);
+
+    let (scene_tx, scene_rx) = tokio::sync::mpsc::unbounded_channel();
+    let scene_tx = Arc::new(scene_tx);
+    let scene_update_signal = create_signal_from_tokio_channel(scene_rx);
 
     create_effect(move |_| {
         let state_helper = state_2.clone();
@@ -59,6 +81,114 @@ pub fn project_view(
         state_helper.object_selected_signal = Some(object_selected_signal);
         state_helper.selected_object_id_signal = Some(selected_object_id_signal);
         state_helper.selected_object_data_signal = Some(selected_object_data_signal);
+        state_helper.register_file_signal("scene".to_string(), scene_tx.clone());
+    });
+
+    // remote scene edits, already saved by the WebSocket client
+    create_effect(move |_| {
+        if let Some(UIMessage::SceneOperation(operation)) = scene_update_signal.get() {
+            let state_helper = state_3.lock().unwrap();
+            let renderer_state = state_helper
+                .renderer_state
+                .as_ref()
+                .expect("Couldn't get RendererState")
+                .clone();
+            let saved_state = state_helper
+                .saved_state
+                .as_ref()
+                .expect("Couldn't get saved state")
+                .lock()
+                .unwrap()
+                .clone();
+
+            let gpu_helper = gpu_2.lock().unwrap();
+            let gpu_resources = gpu_helper
+                .gpu_resources
+                .as_ref()
+                .expect("Couldn't get gpu resources");
+
+            apply_to_renderer(
+                renderer_state,
+                &gpu_resources.device,
+                &gpu_resources.queue,
+                &saved_state,
+                &operation,
+            );
+
+            // keep the properties panel in step when someone edits what we have selected
+            let component_id = operation.component_id().to_string();
+            if selected_object_id_signal.get_untracked().to_string() == component_id {
+                match operation {
+                    SceneOperation::Remove { .. } => {
+                        object_selected_signal.set(false);
+                        selected_object_id_signal.set(Uuid::nil());
+                    }
+                    _ => {
+                        let component = saved_state
+                            .levels
+                            .as_ref()
+                            .and_then(|levels| levels.get(0))
+                            .and_then(|level| level.components.as_ref())
+                            .and_then(|components| {
+                                components.iter().find(|c| c.id == component_id).cloned()
+                            });
+
+                        if let Some(component) = component {
+                            selected_object_data_signal.set(component);
+                        }
+                    }
+                }
+            }
+        }
+    });
+
+    // concept-to-scene jobs place their model here, where the GPU is available
+    create_effect(move |_| {
+        if let Some(UIMessage::PlacePipelineModel {
+            job_id,
+            model,
+            placement,
+        }) = scene_update_signal.get()
+        {
+            let state_helper = state_4.lock().unwrap();
+            let gpu_helper = gpu_3.lock().unwrap();
+            let gpu_resources = gpu_helper
+                .gpu_resources
+                .as_ref()
+                .expect("Couldn't get gpu resources");
+
+            place_pipeline_model(
+                state_helper,
+                &gpu_resources.device,
+                &gpu_resources.queue,
+                &job_id,
+                model,
+                placement,
+            );
+        }
+    });
+
+    // midpoint.json was reloaded from disk, so the scene is rebuilt from it
+    create_effect(move |_| {
+        if let Some(UIMessage::ReloadScene(project_id)) = scene_update_signal.get() {
+            let state_helper = state_6.lock().unwrap();
+            let renderer_state = state_helper
+                .renderer_state
+                .as_ref()
+                .expect("Couldn't get RendererState")
+                .clone();
+            let saved_state = state_helper
+                .saved_state
+                .as_ref()
+                .expect("Couldn't get saved state")
+                .clone();
+
+            // whatever was selected may not be in the reloaded file
+            object_selected_signal.set(false);
+            selected_object_id_signal.set(Uuid::nil());
+
+            restore_renderer_from_saved(gpu_4.clone(), project_id, saved_state, renderer_state);
+        }
     });
 
     container((
@@ -87,6 +217,8 @@ pub fn project_view(
                 }
             },
         ),
+        // every view that generates queues into the same outbox, so it's shown over all of them
+        outbox_panel(state_5).style(|s| s.absolute().inset_bottom(20.0).inset_right(20.0)),
     ))
 }
 
@@ -111,28 +243,118 @@ pub fn app_view(
     manager: Arc<WebSocketManager>,
 ) -> impl IntoView {
     let project_selected = create_rw_signal(Uuid::nil());
+    let auth_status = create_rw_signal(state_helper.lock().unwrap().auth_status);
 
     let state_2 = Arc::clone(&state_helper);
+    let state_3 = Arc::clone(&state_helper);
+    let state_4 = Arc::clone(&state_helper);
+    let state_5 = Arc::clone(&state_helper);
+    let gpu_2 = Arc::clone(&gpu_helper);
+    let manager_2 = Arc::clone(&manager);
+    let manager_3 = Arc::clone(&manager);
 
+    let (auth_tx, auth_rx) = tokio::sync::mpsc::unbounded_channel();
+    let auth_tx = Arc::new(auth_tx);
+    let auth_update_signal = create_signal_from_tokio_channel(auth_rx);
+
+    let (control_tx, control_rx) = tokio::sync::mpsc::unbounded_channel();
+    let control_tx = Arc::new(control_tx);
+    let control_signal = create_signal_from_tokio_channel(control_rx);
+
+    create_effect({
+        let auth_tx = auth_tx.clone();
+        move |_| {
+            let mut state_helper = state_2.lock().unwrap();
+            state_helper.project_selected_signal = Some(project_selected);
+            state_helper.register_file_signal("auth".to_string(), auth_tx.clone());
+            state_helper.register_file_signal("control".to_string(), control_tx.clone());
+        }
+    });
+
+    // projects opened through the control API, which needs the UI thread for the GPU and signals
     create_effect(move |_| {
-        let mut state_helper = state_2.lock().unwrap();
-        state_helper.project_selected_signal = Some(project_selected);
+        if let Some(UIMessage::OpenProject(project_id)) = control_signal.get() {
+            open_project(
+                state_5.clone(),
+                gpu_2.clone(),
+                manager_3.clone(),
+                project_id,
+            );
+        }
     });
 
-    dyn_container(
-        move || project_selected.get(),
-        move |project_selected_real| {
-            if project_selected_real != Uuid::nil() {
-                project_view(state_helper.clone(), gpu_helper.clone(), viewport.clone()).into_any()
-            } else {
-                selection_view(
-                    state_helper.clone(),
-                    gpu_helper.clone(),
-                    viewport.clone(),
-                    manager.clone(),
-                )
-                .into_any()
+    // token failures from GraphQL or the WebSocket arrive here, as do fresh tokens
+    create_effect(move |_| {
+        if let Some(msg) = auth_update_signal.get() {
+            match msg {
+                UIMessage::AuthRequired(status) => {
+                    println!("Auth required: {:?}", status);
+
+                    let mut state_helper = state_3.lock().unwrap();
+                    state_helper.auth_status = status;
+                    auth_status.set(status);
+
+                    // a refresh token lets us recover without the dialog
+                    if let Some(stored) = read_stored_auth().filter(|a| a.refresh_token.is_some()) {
+                        let auth_tx = auth_tx.clone();
+                        tokio::runtime::Handle::current().spawn(async move {
+                            match refresh_stored_auth(stored).await {
+                                Ok(refreshed) => {
+                                    auth_tx
+                                        .send(UIMessage::AuthUpdated(refreshed.token))
+                                        .unwrap();
+                                }
+                                Err(e) => println!("Couldn't refresh auth token: {}", e),
+                            }
+                        });
+                    }
+                }
+                UIMessage::AuthUpdated(token) => {
+                    let mut state_helper = state_3.lock().unwrap();
+                    state_helper.auth_token = token.clone();
+                    state_helper.auth_status = AuthStatus::SignedIn;
+                    auth_status.set(AuthStatus::SignedIn);
+
+                    // rejoin with the new token and send anything that failed meanwhile
+                    let project_id = project_selected.get_untracked();
+                    if project_id != Uuid::nil() {
+                        manager_2.join_group();
+                        spawn_outbox_flush(state_3.clone(), token, project_id.to_string());
+                    }
+                }
+                _ => return,
             }
-        },
-    )
+        }
+    });
+
+    container((
+        dyn_container(
+            move || project_selected.get(),
+            move |project_selected_real| {
+                if project_selected_real != Uuid::nil() {
+                    project_view(state_helper.clone(), gpu_helper.clone(), viewport.clone())
+                        .into_any()
+                } else {
+                    selection_view(
+                        state_helper.clone(),
+                        gpu_helper.clone(),
+                        viewport.clone(),
+                        manager.clone(),
+                    )
+                    .into_any()
+                }
+            },
+        ),
+        dyn_container(
+            move || auth_status.get() == AuthStatus::SignedIn,
+            move |signed_in| {
+                if signed_in {
+                    empty().into_any()
+                } else {
+                    sign_in_dialog(state_4.clone(), auth_status).into_any()
+                }
+            },
+        ),
+    ))
+    .style(|s| s.width_full().height_full())
 }
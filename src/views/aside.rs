@@ -226,9 +226,12 @@ pub fn project_tab_interface(
                             "Performance" => {
                                 performance_view(gpu_helper.clone(), viewport.clone()).into_any()
                             }
-                            "Settings" => {
-                                project_settings(gpu_helper.clone(), viewport.clone()).into_any()
-                            }
+                            "Settings" => project_settings(
+                                state_2.clone(),
+                                gpu_helper.clone(),
+                                viewport.clone(),
+                            )
+                            .into_any(),
                             _ => label(|| "Not implemented".to_owned()).into_any(),
                         },
                     )
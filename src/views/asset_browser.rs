@@ -0,0 +1,747 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use midpoint_engine::floem::common::small_button;
+use midpoint_engine::floem::peniko::Color;
+use midpoint_engine::floem::reactive::{
+    create_effect, create_rw_signal, RwSignal, SignalGet, SignalUpdate, SignalWith,
+};
+use midpoint_engine::floem::taffy::{FlexDirection, FlexWrap};
+use midpoint_engine::floem::views::Decorators;
+use midpoint_engine::floem::views::{
+    dyn_container, dyn_stack, empty, h_stack, label, scroll, text_input, v_stack, virtual_stack,
+    VirtualDirection, VirtualItemSize,
+};
+use midpoint_engine::floem::{AnyView, IntoView, View};
+
+use crate::editor_state::StateHelper;
+use crate::helpers::asset_labels::{
+    asset_label_key, load_asset_labels, parse_tags, save_asset_label, AssetLabel,
+};
+use crate::helpers::asset_usage::{
+    delete_asset, find_references, rename_asset, AssetReference, ReferenceAction,
+};
+use crate::helpers::generation::load_generations;
+
+use super::generation_options::choice_row;
+
+pub const SORT_ORDERS: [&str; 3] = ["Newest", "Oldest", "Name"];
+pub const LAYOUTS: [&str; 2] = ["Grid", "List"];
+
+const GRID_COLUMNS: usize = 2;
+const CARD_WIDTH: f64 = 120.0;
+const CARD_GAP: f64 = 10.0;
+const LIST_ROW_HEIGHT: f64 = 28.0;
+// the browser's own Details toggle under every card
+const CARD_FOOTER_HEIGHT: f64 = 28.0;
+const BROWSER_HEIGHT: f64 = 520.0;
+
+/// What the shared browser needs to know about an item to search, sort and label it
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetEntry {
+    pub id: String,
+    pub name: String,
+    // generated files can be searched by the prompt they came from
+    pub file_name: Option<String>,
+}
+
+// one line of the virtual list, a row of cards or a single list entry
+#[derive(Clone)]
+struct BrowserRow<T: Clone> {
+    key: String,
+    items: Vec<T>,
+}
+
+/// Every whitespace separated word has to match; `#word` only matches tags,
+/// anything else the name, prompt, collection or tags
+fn matches_query(
+    entry: &AssetEntry,
+    label: Option<&AssetLabel>,
+    prompt: &str,
+    query: &str,
+) -> bool {
+    let tags: Vec<String> = label.map(|l| l.tags.clone()).unwrap_or_default();
+    let collection = label
+        .and_then(|l| l.collection.clone())
+        .unwrap_or_default()
+        .to_lowercase();
+    let name = entry.name.to_lowercase();
+    let prompt = prompt.to_lowercase();
+
+    query.split_whitespace().all(|word| {
+        let word = word.to_lowercase();
+        match word.strip_prefix('#') {
+            Some(tag) => tags.iter().any(|t| t == tag),
+            None => {
+                name.contains(&word)
+                    || prompt.contains(&word)
+                    || collection.contains(&word)
+                    || tags.iter().any(|t| t.contains(&word))
+            }
+        }
+    })
+}
+
+fn chip(text: String, active: impl Fn() -> bool + 'static) -> impl View {
+    label(move || text.clone()).style(move |s| {
+        let s = s
+            .font_size(10.0)
+            .padding_horiz(4.0)
+            .padding_vert(2.0)
+            .margin_right(3.0)
+            .margin_bottom(3.0)
+            .border_radius(3.0);
+
+        if active() {
+            s.color(Color::WHITE).background(Color::rgb8(70, 150, 230))
+        } else {
+            s.background(Color::rgb8(220, 220, 220))
+        }
+    })
+}
+
+// the Details buttons open the panel for their asset, or close it if it's already open
+fn toggle_selected(selected: RwSignal<Option<String>>, id: &str) {
+    selected.update(|selected| {
+        *selected = match selected {
+            Some(current) if current == id => None,
+            _ => Some(id.to_string()),
+        }
+    })
+}
+
+fn find_asset_references(
+    state_helper: &Arc<Mutex<StateHelper>>,
+    asset_id: &str,
+) -> Vec<AssetReference> {
+    let state_helper = state_helper.lock().unwrap();
+    match state_helper.saved_state.as_ref() {
+        Some(saved_state) => find_references(&saved_state.lock().unwrap(), asset_id),
+        None => Vec::new(),
+    }
+}
+
+/// Rename, where used and delete for the selected asset. Deleting something that's still used
+/// asks first whether its references are removed or pointed at one of `others`.
+fn manage_asset(
+    state_helper: Arc<Mutex<StateHelper>>,
+    kind_dir: &'static str,
+    entry: AssetEntry,
+    others: Vec<AssetEntry>,
+    selected: RwSignal<Option<String>>,
+) -> impl View {
+    let references = find_asset_references(&state_helper, &entry.id);
+    let used_by: Vec<String> = references.iter().map(|r| r.description()).collect();
+    let in_use = !used_by.is_empty();
+
+    let name_field = create_rw_signal(entry.name.clone());
+    let status = create_rw_signal("".to_string());
+    let confirming = create_rw_signal(false);
+    let replacement: RwSignal<Option<String>> = create_rw_signal(None);
+    let rename_active = create_rw_signal(false);
+    let delete_active = create_rw_signal(false);
+    let remove_active = create_rw_signal(false);
+    let replace_active = create_rw_signal(false);
+
+    let rename_state = Arc::clone(&state_helper);
+    let rename_id = entry.id.clone();
+
+    // deletes with the chosen action and closes the panel, or says why it couldn't
+    let delete = {
+        let asset_id = entry.id.clone();
+        Rc::new(move |action: Option<ReferenceAction>| {
+            let project_id = match state_helper.lock().unwrap().selected_project_id() {
+                Some(project_id) => project_id,
+                None => return,
+            };
+            let mut state_helper = state_helper.lock().unwrap();
+
+            match delete_asset(&mut state_helper, &project_id, kind_dir, &asset_id, action) {
+                Ok(()) => selected.set(None),
+                Err(e) => status.set(e),
+            }
+        })
+    };
+    let delete_unused = Rc::clone(&delete);
+    let delete_removing = Rc::clone(&delete);
+    let delete_replacing = Rc::clone(&delete);
+
+    v_stack((
+        h_stack((
+            text_input(name_field).style(|s| s.width(170.0).margin_right(5.0)),
+            small_button(
+                "Rename",
+                "plus",
+                move |_| {
+                    let project_id = match rename_state.lock().unwrap().selected_project_id() {
+                        Some(project_id) => project_id,
+                        None => return,
+                    };
+                    let mut state_helper = rename_state.lock().unwrap();
+
+                    match rename_asset(
+                        &mut state_helper,
+                        &project_id,
+                        kind_dir,
+                        &rename_id,
+                        &name_field.get(),
+                    ) {
+                        Ok(name) => status.set(format!("Renamed to {}", name)),
+                        Err(e) => status.set(e),
+                    }
+                },
+                rename_active,
+            ),
+        ))
+        .style(|s| s.items_center().margin_bottom(5.0)),
+        label(|| "Where Used").style(|s| s.font_size(10.0).margin_bottom(1.0)),
+        if in_use {
+            dyn_stack(
+                move || used_by.clone(),
+                move |description| description.clone(),
+                move |description| label(move || description.clone()).style(|s| s.font_size(10.0)),
+            )
+            .style(|s| s.flex_direction(FlexDirection::Column).margin_bottom(5.0))
+            .into_any()
+        } else {
+            label(|| "Not used yet")
+                .style(|s| s.font_size(10.0).margin_bottom(5.0))
+                .into_any()
+        },
+        small_button(
+            "Delete",
+            "plus",
+            move |_| {
+                if in_use {
+                    confirming.set(true);
+                } else {
+                    delete_unused(None);
+                }
+            },
+            delete_active,
+        ),
+        dyn_container(
+            move || confirming.get(),
+            move |confirming| {
+                if !confirming {
+                    return empty().into_any();
+                }
+
+                let delete_removing = Rc::clone(&delete_removing);
+                let delete_replacing = Rc::clone(&delete_replacing);
+                let others = others.clone();
+
+                v_stack((
+                    label(|| "Still in use, remove its references or replace them with")
+                        .style(|s| s.font_size(10.0).margin_vert(3.0)),
+                    dyn_stack(
+                        move || others.clone(),
+                        move |other| other.id.clone(),
+                        move |other| {
+                            let active = {
+                                let id = other.id.clone();
+                                move || replacement.get().as_ref() == Some(&id)
+                            };
+                            chip(other.name.clone(), active)
+                                .on_click_stop(move |_| replacement.set(Some(other.id.clone())))
+                        },
+                    )
+                    .style(|s| {
+                        s.flex_direction(FlexDirection::Row)
+                            .flex_wrap(FlexWrap::Wrap)
+                    }),
+                    h_stack((
+                        small_button(
+                            "Remove References",
+                            "plus",
+                            move |_| delete_removing(Some(ReferenceAction::Remove)),
+                            remove_active,
+                        ),
+                        small_button(
+                            "Replace",
+                            "plus",
+                            move |_| match replacement.get() {
+                                Some(new_id) => {
+                                    delete_replacing(Some(ReferenceAction::Replace(new_id)))
+                                }
+                                None => status.set("Choose the asset to use instead".to_string()),
+                            },
+                            replace_active,
+                        ),
+                    )),
+                ))
+                .into_any()
+            },
+        ),
+        label(move || status.get()).style(|s| s.font_size(10.0).margin_top(3.0)),
+    ))
+    .style(|s| s.margin_top(5.0))
+}
+
+/// Lists the assets with search, tags, collections, sorting and a grid or list layout.
+/// Only the rows in view are built, so `card_height` has to fit the tallest card.
+pub fn asset_browser<T: Clone + 'static>(
+    state_helper: Arc<Mutex<StateHelper>>,
+    kind_dir: &'static str,
+    items: RwSignal<Vec<T>>,
+    card_height: f64,
+    describe: impl Fn(&T) -> AssetEntry + 'static,
+    card: impl Fn(T) -> AnyView + 'static,
+    details: Option<Box<dyn Fn(&T) -> AnyView>>,
+) -> impl View {
+    let describe = Rc::new(describe);
+    let card = Rc::new(card);
+    let details = Rc::new(details);
+
+    let query = create_rw_signal("".to_string());
+    let sort = create_rw_signal(SORT_ORDERS[0].to_string());
+    let layout = create_rw_signal(LAYOUTS[0].to_string());
+    let collection: RwSignal<Option<String>> = create_rw_signal(None);
+    let selected: RwSignal<Option<String>> = create_rw_signal(None);
+
+    let labels: RwSignal<HashMap<String, AssetLabel>> = create_rw_signal(HashMap::new());
+    // prompts of generated files in this kind, by file name
+    let prompts: RwSignal<HashMap<String, String>> = create_rw_signal(HashMap::new());
+
+    let state_2 = Arc::clone(&state_helper);
+    create_effect(move |_| {
+        // read again whenever assets come or go
+        items.with(|_| ());
+
+        let project_id = match state_2.lock().unwrap().selected_project_id() {
+            Some(project_id) => project_id,
+            None => return,
+        };
+
+        labels.set(load_asset_labels(&project_id));
+
+        let prefix = format!("{}/", kind_dir);
+        prompts.set(
+            load_generations(&project_id)
+                .into_iter()
+                .filter_map(|(key, record)| {
+                    key.strip_prefix(&prefix)
+                        .map(|file_name| (file_name.to_string(), record.prompt))
+                })
+                .collect(),
+        );
+    });
+
+    let visible = {
+        let describe = Rc::clone(&describe);
+        move || -> Vec<(AssetEntry, T)> {
+            let query = query.get();
+            let collection = collection.get();
+            let labels = labels.get();
+            let prompts = prompts.get();
+
+            let mut visible: Vec<(AssetEntry, T)> = items
+                .get()
+                .into_iter()
+                .map(|item| (describe(&item), item))
+                .filter(|(entry, _)| {
+                    let label = labels.get(&asset_label_key(kind_dir, &entry.id));
+                    let in_collection = match &collection {
+                        Some(collection) => {
+                            label.and_then(|l| l.collection.as_ref()) == Some(collection)
+                        }
+                        None => true,
+                    };
+                    let prompt = entry
+                        .file_name
+                        .as_ref()
+                        .and_then(|file_name| prompts.get(file_name))
+                        .map(|prompt| prompt.as_str())
+                        .unwrap_or_default();
+
+                    in_collection && matches_query(entry, label, prompt, &query)
+                })
+                .collect();
+
+            // assets are appended as they're made, so list order is age
+            match sort.get().as_str() {
+                "Newest" => visible.reverse(),
+                "Name" => visible.sort_by_key(|(entry, _)| entry.name.to_lowercase()),
+                _ => {}
+            }
+
+            visible
+        }
+    };
+
+    let rows = move || -> im::Vector<BrowserRow<T>> {
+        let visible = visible();
+        let layout = layout.get();
+        let per_row = if layout == "List" { 1 } else { GRID_COLUMNS };
+
+        visible
+            .chunks(per_row)
+            .map(|chunk| BrowserRow {
+                // includes the layout, so rows are built again when it's switched
+                key: format!(
+                    "{}:{}",
+                    layout,
+                    chunk
+                        .iter()
+                        .map(|(entry, _)| entry.id.clone())
+                        .collect::<Vec<String>>()
+                        .join(",")
+                ),
+                items: chunk.iter().map(|(_, item)| item.clone()).collect(),
+            })
+            .collect()
+    };
+
+    let collections = move || -> Vec<String> {
+        let mut collections: Vec<String> = labels
+            .get()
+            .iter()
+            .filter(|(key, _)| key.starts_with(&format!("{}/", kind_dir)))
+            .filter_map(|(_, label)| label.collection.clone())
+            .collect();
+        collections.sort();
+        collections.dedup();
+        collections
+    };
+
+    let row_describe = Rc::clone(&describe);
+    let row_card = Rc::clone(&card);
+    let panel_describe = Rc::clone(&describe);
+    let panel_card = Rc::clone(&card);
+
+    v_stack((
+        text_input(query)
+            .placeholder("Search names and prompts, #tag".to_string())
+            .style(|s| s.width(260.0).margin_bottom(5.0)),
+        h_stack((
+            choice_row("Sort", &SORT_ORDERS, sort),
+            choice_row("View", &LAYOUTS, layout).style(|s| s.margin_left(10.0)),
+        )),
+        dyn_container(collections, move |collections| {
+            if collections.is_empty() {
+                return empty().into_any();
+            }
+
+            let mut choices: Vec<Option<String>> = vec![None];
+            choices.extend(collections.into_iter().map(Some));
+
+            v_stack((
+                label(|| "Collection").style(|s| s.font_size(10.0).margin_bottom(1.0)),
+                dyn_stack(
+                    move || choices.clone(),
+                    move |choice| choice.clone(),
+                    move |choice| {
+                        let text = choice.clone().unwrap_or_else(|| "All".to_string());
+                        let active = {
+                            let choice = choice.clone();
+                            move || collection.get() == choice
+                        };
+                        chip(text, active).on_click_stop(move |_| collection.set(choice.clone()))
+                    },
+                )
+                .style(|s| {
+                    s.flex_direction(FlexDirection::Row)
+                        .flex_wrap(FlexWrap::Wrap)
+                }),
+            ))
+            .style(|s| s.margin_bottom(5.0))
+            .into_any()
+        }),
+        dyn_container(
+            move || selected.get(),
+            move |selected_id| {
+                let item = selected_id.as_ref().and_then(|selected_id| {
+                    items
+                        .get_untracked()
+                        .into_iter()
+                        .find(|item| panel_describe(item).id == *selected_id)
+                });
+                let item = match item {
+                    Some(item) => item,
+                    None => return empty().into_any(),
+                };
+
+                let entry = panel_describe(&item);
+                let name = entry.name.clone();
+                let others: Vec<AssetEntry> = items
+                    .get_untracked()
+                    .iter()
+                    .map(|other| panel_describe(other))
+                    .filter(|other| other.id != entry.id)
+                    .collect();
+                let manage = manage_asset(
+                    Arc::clone(&state_helper),
+                    kind_dir,
+                    entry.clone(),
+                    others,
+                    selected,
+                );
+                let label_key = asset_label_key(kind_dir, &entry.id);
+                let current = labels
+                    .get_untracked()
+                    .get(&label_key)
+                    .cloned()
+                    .unwrap_or_default();
+                let tags_field = create_rw_signal(current.tags.join(", "));
+                let collection_field =
+                    create_rw_signal(current.collection.clone().unwrap_or_default());
+                let save_active = create_rw_signal(false);
+                let close_active = create_rw_signal(false);
+                let save_state = Arc::clone(&state_helper);
+
+                v_stack((
+                    label(move || name.clone()).style(|s| s.font_size(12.0)),
+                    // in the list the card isn't shown anywhere else
+                    if layout.get_untracked() == "List" {
+                        panel_card(item.clone())
+                    } else {
+                        empty().into_any()
+                    },
+                    text_input(tags_field)
+                        .placeholder("Tags, ex. rock, mossy".to_string())
+                        .style(|s| s.width(260.0).margin_bottom(5.0)),
+                    text_input(collection_field)
+                        .placeholder("Collection, ex. Cliffs".to_string())
+                        .style(|s| s.width(260.0).margin_bottom(5.0)),
+                    h_stack((
+                        small_button(
+                            "Save Labels",
+                            "plus",
+                            move |_| {
+                                let project_id =
+                                    match save_state.lock().unwrap().selected_project_id() {
+                                        Some(project_id) => project_id,
+                                        None => return,
+                                    };
+                                let collection_name = collection_field.get().trim().to_string();
+                                let label = AssetLabel {
+                                    tags: parse_tags(&tags_field.get()),
+                                    collection: Some(collection_name)
+                                        .filter(|name| !name.is_empty()),
+                                };
+
+                                match save_asset_label(
+                                    &project_id,
+                                    kind_dir,
+                                    &entry.id,
+                                    label.clone(),
+                                ) {
+                                    Ok(()) => labels.update(|labels| {
+                                        if label.is_empty() {
+                                            labels.remove(&label_key);
+                                        } else {
+                                            labels.insert(label_key.clone(), label);
+                                        }
+                                    }),
+                                    Err(e) => println!("{}", e),
+                                }
+                            },
+                            save_active,
+                        ),
+                        small_button("Close", "plus", move |_| selected.set(None), close_active),
+                    )),
+                    match details.as_ref() {
+                        Some(details) => details(&item),
+                        None => empty().into_any(),
+                    },
+                    manage,
+                ))
+                .style(|s| {
+                    s.width(260.0)
+                        .margin_bottom(7.0)
+                        .padding(5.0)
+                        .border_radius(5.0)
+                        .background(Color::rgb8(240, 240, 240))
+                })
+                .into_any()
+            },
+        ),
+        scroll(
+            virtual_stack(
+                VirtualDirection::Vertical,
+                VirtualItemSize::Fixed(Box::new(move || {
+                    if layout.get() == "List" {
+                        LIST_ROW_HEIGHT
+                    } else {
+                        card_height + CARD_FOOTER_HEIGHT + CARD_GAP
+                    }
+                })),
+                rows,
+                move |row| row.key.clone(),
+                move |row| {
+                    let list = row.key.starts_with("List:");
+                    let cells: Vec<(AssetEntry, T)> = row
+                        .items
+                        .into_iter()
+                        .map(|item| (row_describe(&item), item))
+                        .collect();
+                    let row_card = Rc::clone(&row_card);
+
+                    dyn_stack(
+                        move || cells.clone(),
+                        move |(entry, _)| entry.id.clone(),
+                        move |(entry, item)| {
+                            let details_active = create_rw_signal(false);
+                            let id = entry.id.clone();
+
+                            if list {
+                                let label_key = asset_label_key(kind_dir, &entry.id);
+                                let tags = move || {
+                                    labels.with(|labels| {
+                                        labels
+                                            .get(&label_key)
+                                            .map(|l| {
+                                                l.tags
+                                                    .iter()
+                                                    .map(|t| format!("#{}", t))
+                                                    .collect::<Vec<String>>()
+                                                    .join(" ")
+                                            })
+                                            .unwrap_or_default()
+                                    })
+                                };
+                                let name = entry.name.clone();
+
+                                h_stack((
+                                    label(move || name.clone())
+                                        .style(|s| s.width(130.0).font_size(11.0)),
+                                    label(tags).style(|s| s.width(70.0).font_size(10.0)),
+                                    small_button(
+                                        "Details",
+                                        "plus",
+                                        move |_| toggle_selected(selected, &id),
+                                        details_active,
+                                    ),
+                                ))
+                                .style(|s| s.height(LIST_ROW_HEIGHT).items_center())
+                                .into_any()
+                            } else {
+                                v_stack((
+                                    row_card(item),
+                                    small_button(
+                                        "Details",
+                                        "plus",
+                                        move |_| toggle_selected(selected, &id),
+                                        details_active,
+                                    ),
+                                ))
+                                .style(move |s| {
+                                    s.width(CARD_WIDTH)
+                                        .height(card_height + CARD_FOOTER_HEIGHT)
+                                        .margin_right(CARD_GAP)
+                                })
+                                .into_any()
+                            }
+                        },
+                    )
+                    .style(|s| s.flex_direction(FlexDirection::Row))
+                },
+            )
+            .style(|s| s.flex_direction(FlexDirection::Column).width(260.0)),
+        )
+        .style(|s| s.width(260.0).height(BROWSER_HEIGHT)),
+    ))
+    .style(|s| s.width(260.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str) -> AssetEntry {
+        AssetEntry {
+            id: "1".to_string(),
+            name: name.to_string(),
+            file_name: Some(name.to_string()),
+        }
+    }
+
+    fn labelled(tags: &[&str], collection: Option<&str>) -> AssetLabel {
+        AssetLabel {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            collection: collection.map(|c| c.to_string()),
+        }
+    }
+
+    #[test]
+    fn an_empty_query_matches_everything() {
+        assert!(matches_query(&entry("Barrel.glb"), None, "", ""));
+        assert!(matches_query(&entry("Barrel.glb"), None, "", "   "));
+    }
+
+    #[test]
+    fn words_match_name_prompt_collection_and_tags_ignoring_case() {
+        let label = labelled(&["wooden"], Some("Props"));
+
+        assert!(matches_query(&entry("Barrel.glb"), None, "", "barrel"));
+        assert!(matches_query(
+            &entry("Barrel.glb"),
+            None,
+            "An old CASK",
+            "cask"
+        ));
+        assert!(matches_query(
+            &entry("Barrel.glb"),
+            Some(&label),
+            "",
+            "PROPS"
+        ));
+        assert!(matches_query(
+            &entry("Barrel.glb"),
+            Some(&label),
+            "",
+            "wood"
+        ));
+        assert!(!matches_query(
+            &entry("Barrel.glb"),
+            Some(&label),
+            "",
+            "stone"
+        ));
+    }
+
+    #[test]
+    fn every_word_has_to_match() {
+        let label = labelled(&["wooden"], None);
+
+        assert!(matches_query(
+            &entry("Barrel.glb"),
+            Some(&label),
+            "",
+            "barrel wooden"
+        ));
+        assert!(!matches_query(
+            &entry("Barrel.glb"),
+            Some(&label),
+            "",
+            "barrel iron"
+        ));
+    }
+
+    #[test]
+    fn hash_words_only_match_whole_tags() {
+        let label = labelled(&["wooden"], Some("wood"));
+
+        assert!(matches_query(
+            &entry("Barrel.glb"),
+            Some(&label),
+            "",
+            "#Wooden"
+        ));
+        // neither part of a tag nor the collection or name counts
+        assert!(!matches_query(
+            &entry("Barrel.glb"),
+            Some(&label),
+            "",
+            "#wood"
+        ));
+        assert!(!matches_query(
+            &entry("Barrel.glb"),
+            Some(&label),
+            "",
+            "#barrel"
+        ));
+        assert!(!matches_query(&entry("Barrel.glb"), None, "", "#wooden"));
+    }
+}
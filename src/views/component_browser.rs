@@ -6,11 +6,13 @@ use std::sync::{Arc, Mutex, MutexGuard};
 use super::shared::dynamic_img;
 use midpoint_engine::core::Viewport::Viewport;
 use midpoint_engine::floem::common::small_button;
+use midpoint_engine::floem::ext_event::create_signal_from_tokio_channel;
+use midpoint_engine::floem::peniko::Color;
 use midpoint_engine::floem::reactive::SignalGet;
 use midpoint_engine::floem::reactive::{create_effect, create_rw_signal, RwSignal, SignalUpdate};
 use midpoint_engine::floem::taffy::{FlexDirection, FlexWrap};
 use midpoint_engine::floem::views::{
-    button, container, dyn_container, dyn_stack, empty, label, scroll, v_stack,
+    button, container, dyn_container, dyn_stack, empty, h_stack, label, scroll, v_stack,
 };
 use midpoint_engine::floem::IntoView;
 use midpoint_engine::floem_renderer::gpu_resources;
@@ -22,18 +24,52 @@ use wgpu::util::DeviceExt;
 use midpoint_engine::floem::views::Decorators;
 use midpoint_engine::floem::{GpuHelper, View, WindowHandle};
 
-use crate::editor_state::{EditorState, StateHelper};
+use crate::editor_state::{EditorState, StateHelper, UIMessage};
+use crate::helpers::presence::{current_peers, PresencePeer};
+
+pub fn presence_badge(peer: PresencePeer) -> impl View {
+    let (r, g, b) = peer.color();
+
+    label(move || peer.name.clone()).style(move |s| {
+        s.font_size(10.0)
+            .padding_horiz(4.0)
+            .padding_vert(1.0)
+            .margin_right(3.0)
+            .margin_bottom(3.0)
+            .border_radius(3.0)
+            .color(Color::WHITE)
+            .background(Color::rgb8(r, g, b))
+    })
+}
 
 pub fn component_item(
     state_helper: Arc<Mutex<StateHelper>>,
     gpu_helper: Arc<Mutex<GpuHelper>>,
     label_text: String,
     component_data: ComponentData,
+    presence: RwSignal<Vec<PresencePeer>>,
 ) -> impl View {
     let active = create_rw_signal(false);
+    let component_id = component_data.id.clone();
 
     v_stack((
         label(move || label_text.clone()),
+        // collaborators who have this component selected
+        dyn_stack(
+            move || {
+                presence
+                    .get()
+                    .into_iter()
+                    .filter(|peer| peer.selected_component.as_ref() == Some(&component_id))
+                    .collect::<Vec<_>>()
+            },
+            move |peer| peer.client_id.clone(),
+            move |peer| presence_badge(peer),
+        )
+        .style(|s| {
+            s.flex_direction(FlexDirection::Row)
+                .flex_wrap(FlexWrap::Wrap)
+        }),
         small_button(
             "Select Component",
             "plus",
@@ -82,6 +118,11 @@ pub fn component_item(
                         // arrows,
                         component_data.generic_properties.position,
                     );
+
+                    drop(renderer_state);
+
+                    // let collaborators see what we're working on
+                    state_helper.broadcast_presence();
                 }
             },
             active,
@@ -96,12 +137,32 @@ pub fn component_browser(
     viewport: Arc<Mutex<Viewport>>,
 ) -> impl View {
     let component_data: RwSignal<Vec<ComponentData>> = create_rw_signal(Vec::new());
+    let presence: RwSignal<Vec<PresencePeer>> = create_rw_signal(Vec::new());
 
     let state_2 = Arc::clone(&state_helper);
     let gpu_2 = Arc::clone(&gpu_helper);
 
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let tx = Arc::new(tx);
+    let update_signal = create_signal_from_tokio_channel(rx);
+
+    // components added, renamed or removed by collaborators
+    create_effect(move |_| {
+        if let Some(msg) = update_signal.get() {
+            match msg {
+                UIMessage::UpdateComponents(components) => component_data.set(components),
+                UIMessage::UpdatePresence(peers) => presence.set(peers),
+                _ => return,
+            }
+        }
+    });
+
     create_effect(move |_| {
         let mut state_helper = state_helper.lock().unwrap();
+
+        state_helper.register_file_signal("component_browser".to_string(), tx.clone());
+        presence.set(current_peers());
+
         let mut saved_state = state_helper
             .saved_state
             .as_mut()
@@ -122,25 +183,46 @@ pub fn component_browser(
         );
     });
 
-    container((scroll(
+    v_stack((
+        label(move || {
+            if presence.get().is_empty() {
+                "Only you are here".to_string()
+            } else {
+                "Also here:".to_string()
+            }
+        })
+        .style(|s| s.font_size(10.0).margin_bottom(3.0)),
         dyn_stack(
-            move || component_data.get(),
-            move |component_data| component_data.id.clone(),
-            move |component_data| {
-                component_item(
-                    state_2.clone(),
-                    gpu_2.clone(),
-                    component_data.generic_properties.name.clone(),
-                    component_data,
-                )
-            },
+            move || presence.get(),
+            move |peer| peer.client_id.clone(),
+            move |peer| presence_badge(peer),
         )
         .style(|s| {
             s.flex_direction(FlexDirection::Row)
                 .flex_wrap(FlexWrap::Wrap)
-        })
-        .style(|s| s.width(260.0))
-        .into_view(),
-    ),))
-    .style(|s| s.width(260.0))
+                .margin_bottom(7.0)
+        }),
+        container((scroll(
+            dyn_stack(
+                move || component_data.get(),
+                move |component_data| component_data.id.clone(),
+                move |component_data| {
+                    component_item(
+                        state_2.clone(),
+                        gpu_2.clone(),
+                        component_data.generic_properties.name.clone(),
+                        component_data,
+                        presence,
+                    )
+                },
+            )
+            .style(|s| {
+                s.flex_direction(FlexDirection::Row)
+                    .flex_wrap(FlexWrap::Wrap)
+            })
+            .style(|s| s.width(260.0))
+            .into_view(),
+        ),))
+        .style(|s| s.width(260.0)),
+    ))
 }
@@ -25,15 +25,18 @@ use midpoint_engine::floem::{GpuHelper, View, WindowHandle};
 
 use crate::editor_state::UIMessage;
 use crate::editor_state::{EditorState, StateHelper};
-use crate::gql::generateConcept::generate_concept;
-use crate::gql::generateModel::generate_model;
-use crate::gql::generateTexture::generate_texture;
-use crate::helpers::auth::read_auth_token;
-use crate::helpers::concepts::save_concept;
-use crate::helpers::models::save_model;
-use crate::helpers::textures::save_texture;
-use crate::helpers::utilities::change_extension_to_glb;
-use crate::helpers::utilities::get_filename;
+use crate::helpers::asset_store::AssetKind;
+use crate::helpers::generation::{find_generation, random_seed, GenerationOptions, MAX_VARIATIONS};
+use crate::helpers::outbox::{submit_request, GenerationRequest};
+
+use super::asset_browser::{asset_browser, AssetEntry};
+use super::generation_options::{generation_options_view, GenerationForm};
+use super::image_import::image_import_view;
+use super::pipeline::pipeline_panel;
+use super::provenance::{provenance_inspector, provenance_search};
+
+// image, name and three buttons
+const CONCEPT_CARD_HEIGHT: f64 = 225.0;
 
 pub fn concept_item(
     state_helper: Arc<Mutex<StateHelper>>,
@@ -43,10 +46,14 @@ pub fn concept_item(
 ) -> impl View {
     let select_active = create_rw_signal(false);
     let generate_active = create_rw_signal(false);
+    let variations_active = create_rw_signal(false);
     let generate_disabled = create_rw_signal(false);
 
+    let state_2 = Arc::clone(&state_helper);
+
     let (btn_disabled_tx, btn_disabled_rx) = tokio::sync::mpsc::unbounded_channel();
     let btn_disabled_tx = Arc::new(btn_disabled_tx);
+    let btn_disabled_tx_2 = Arc::clone(&btn_disabled_tx);
     let btn_disabled_update_signal = create_signal_from_tokio_channel(btn_disabled_rx);
 
     create_effect(move |_| {
@@ -81,6 +88,7 @@ pub fn concept_item(
                         let image_path = image_path.clone();
                         let label_text = label_text.clone();
                         let image_url = image_url.clone();
+                        let task_state = Arc::clone(&state_helper);
                         let state_helper = state_helper.lock().unwrap();
                         let btn_disabled_tx = btn_disabled_tx.clone();
 
@@ -101,23 +109,20 @@ pub fn concept_item(
                             .expect("Couldn't get current project")
                             .to_string();
 
+                        let auth_token = state_helper.auth_token.clone();
+
                         // Use the runtime handle to spawn
                         tokio::runtime::Handle::current().spawn(async move {
-                            // Now we can safely use generate_field inside async block
-                            let auth_token = read_auth_token();
-
                             println!("Generating model... {:?}", auth_token,);
 
-                            let model_data = generate_model(auth_token, image_url).await;
-                            let modelBase64 = model_data
-                                .expect("Couldn't unwrap model data")
-                                .generateModel;
-
-                            println!("Saving...");
-                            // save texture to sync directory (to be uploaded to S3)
-                            let modelFilename = change_extension_to_glb(&label_text);
+                            let request = GenerationRequest::Model {
+                                image_url,
+                                filename: label_text,
+                            };
 
-                            save_model(selected_project_id, modelBase64, modelFilename);
+                            // saved to sync directory (to be uploaded to S3), or queued while offline
+                            submit_request(task_state, auth_token, selected_project_id, request)
+                                .await;
 
                             // Update saved state - rather update on websocket, its the only way to get cloudfrontUrl
                             println!("Syncing...");
@@ -129,6 +134,53 @@ pub fn concept_item(
                 generate_active,
             )
             .disabled(move || generate_disabled.get()),
+            small_button(
+                "Generate Variations",
+                "plus",
+                {
+                    let image_url = concept_data_real.cloudfrontUrl.clone();
+                    let file_name = concept_data_real.fileName.clone();
+
+                    move |_| {
+                        let state_helper = state_2.lock().unwrap();
+                        let btn_disabled_tx = btn_disabled_tx_2.clone();
+
+                        generate_disabled.set(true);
+
+                        let selected_project_id = state_helper.get_project_id();
+
+                        // start from what produced this concept, if we know
+                        let (prompt, options) =
+                            match find_generation(&selected_project_id, "concepts", &file_name) {
+                                Some(record) => (record.prompt, record.options),
+                                None => (file_name.clone(), GenerationOptions::default()),
+                            };
+                        let options = GenerationOptions {
+                            seed: Some(random_seed()),
+                            variations: Some(MAX_VARIATIONS),
+                            source_image_url: Some(image_url.clone()),
+                            ..options
+                        };
+
+                        let task_state = Arc::clone(&state_2);
+                        let auth_token = state_helper.auth_token.clone();
+
+                        tokio::runtime::Handle::current().spawn(async move {
+                            println!("Generating variations of {:?}...", prompt);
+
+                            let request = GenerationRequest::Concept { prompt, options };
+
+                            // saved to sync directory (to be uploaded to S3), or queued while offline
+                            submit_request(task_state, auth_token, selected_project_id, request)
+                                .await;
+
+                            btn_disabled_tx.send(false).unwrap();
+                        });
+                    }
+                },
+                variations_active,
+            )
+            .disabled(move || generate_disabled.get()),
             small_button(
                 "Inspect Concept",
                 "plus",
@@ -154,8 +206,14 @@ pub fn concepts_view(
 ) -> impl View {
     let state_2 = Arc::clone(&state_helper);
     let state_3 = Arc::clone(&state_helper);
+    let state_5 = Arc::clone(&state_helper);
+    let state_6 = Arc::clone(&state_helper);
+    let state_7 = Arc::clone(&state_helper);
+    let state_8 = Arc::clone(&state_helper);
+    let state_9 = Arc::clone(&state_helper);
 
     let generate_field = create_rw_signal("".to_string());
+    let generation_form = GenerationForm::new();
     let generate_active = create_rw_signal(false);
     let generate_disabled = create_rw_signal(false);
     let selected_concept: RwSignal<Option<File>> = create_rw_signal(None);
@@ -248,35 +306,32 @@ pub fn concepts_view(
                                     .to_string();
 
                                 let generated_field_val = generate_field.get();
+                                let options = generation_form.options();
+
+                                let task_state = Arc::clone(&state_2);
+                                let auth_token = state_helper.auth_token.clone();
 
                                 // Use the runtime handle to spawn
                                 tokio::runtime::Handle::current().spawn(async move {
-                                    // Now we can safely use generate_field inside async block
-                                    let auth_token = read_auth_token();
-
                                     println!(
                                         "Generating... {:?} {:?}",
                                         auth_token,
                                         generated_field_val.clone()
                                     );
 
-                                    let concept_data =
-                                        generate_concept(auth_token, generated_field_val.clone())
-                                            .await;
-                                    let conceptBase64 = concept_data
-                                        .expect("Couldn't unwrap concept data")
-                                        .generateConcept;
-
-                                    println!("Saving...");
-                                    // save texture to sync directory (to be uploaded to S3)
-                                    let conceptFilename = get_filename(generated_field_val.clone());
-                                    let conceptFilename = conceptFilename + ".png";
+                                    let request = GenerationRequest::Concept {
+                                        prompt: generated_field_val.clone(),
+                                        options,
+                                    };
 
-                                    save_concept(
+                                    // saved to sync directory (to be uploaded to S3), or queued while offline
+                                    submit_request(
+                                        task_state,
+                                        auth_token,
                                         selected_project_id,
-                                        conceptBase64,
-                                        conceptFilename,
-                                    );
+                                        request,
+                                    )
+                                    .await;
 
                                     // Update saved state - rather update on websocket, its the only way to get cloudfrontUrl
                                     println!("Syncing...");
@@ -290,27 +345,31 @@ pub fn concepts_view(
                     .disabled(move || generate_disabled.get()),
                 ))
                 .style(|s| s.margin_bottom(7.0)),
-                scroll(
-                    dyn_stack(
-                        move || concept_data.get(),
-                        move |concept_data| concept_data.id.clone(),
-                        move |concept_data_real| {
-                            let current_concepts = concept_data.get(); // Add this to ensure reactivity
-                            concept_item(
-                                state_3.clone(),
-                                gpu_helper.clone(),
-                                concept_data_real, // for retrieval
-                                selected_concept,  // for updating value
-                            )
-                        },
-                    )
-                    .into_view()
-                    .style(|s| {
-                        s.width(260.0)
-                            .flex_direction(FlexDirection::Row)
-                            .flex_wrap(FlexWrap::Wrap)
-                            .gap(10.0)
-                    }),
+                generation_options_view(generation_form),
+                image_import_view(state_8.clone(), AssetKind::Concept),
+                pipeline_panel(state_7.clone(), generation_form),
+                provenance_search(state_6.clone()),
+                asset_browser(
+                    state_9,
+                    "concepts",
+                    concept_data,
+                    CONCEPT_CARD_HEIGHT,
+                    |concept: &File| AssetEntry {
+                        id: concept.id.clone(),
+                        name: concept.fileName.clone(),
+                        file_name: Some(concept.fileName.clone()),
+                    },
+                    move |concept: File| {
+                        concept_item(
+                            state_3.clone(),
+                            gpu_helper.clone(),
+                            concept,          // for retrieval
+                            selected_concept, // for updating value
+                        )
+                        .into_any()
+                    },
+                    // provenance is shown beside the concept once it's inspected
+                    None,
                 ),
             ))
             .style(|s| s.width(260.0)),
@@ -321,13 +380,21 @@ pub fn concepts_view(
             move || selected_concept.get(),
             move |selected_concept| selected_concept.id.clone(),
             move |selected_concept_real| {
-                container((dynamic_img(
-                    selected_concept_real.normalFilePath.clone(),
-                    selected_concept_real.fileName.clone(),
-                    1024.0,
-                    1024.0,
-                )
-                .style(|s| s.width(1024.0).height(1024.0).margin_left(50.0)),))
+                v_stack((
+                    dynamic_img(
+                        selected_concept_real.normalFilePath.clone(),
+                        selected_concept_real.fileName.clone(),
+                        1024.0,
+                        1024.0,
+                    )
+                    .style(|s| s.width(1024.0).height(1024.0)),
+                    provenance_inspector(
+                        state_5.clone(),
+                        "concepts",
+                        &selected_concept_real.fileName,
+                    ),
+                ))
+                .style(|s| s.margin_left(50.0))
             },
         ),
     ))
@@ -0,0 +1,112 @@
+use std::sync::{Arc, Mutex};
+
+use midpoint_engine::floem::common::small_button;
+use midpoint_engine::floem::ext_event::create_signal_from_tokio_channel;
+use midpoint_engine::floem::peniko::Color;
+use midpoint_engine::floem::reactive::SignalGet;
+use midpoint_engine::floem::reactive::SignalUpdate;
+use midpoint_engine::floem::reactive::{create_effect, create_rw_signal, RwSignal};
+use midpoint_engine::floem::views::{dyn_container, dyn_stack, empty, h_stack, label, v_stack};
+use midpoint_engine::floem::IntoView;
+
+use midpoint_engine::floem::views::Decorators;
+use midpoint_engine::floem::View;
+
+use crate::editor_state::{StateHelper, UIMessage};
+use crate::helpers::watcher::{apply_change, dismiss_change, pending_changes, ExternalChange};
+
+pub fn external_change_item(
+    state_helper: Arc<Mutex<StateHelper>>,
+    change: ExternalChange,
+) -> impl View {
+    let apply_active = create_rw_signal(false);
+    let ignore_active = create_rw_signal(false);
+    let error_text = create_rw_signal("".to_string());
+
+    let description = change.description();
+    let action_label = change.action_label();
+    let ignored_change = change.clone();
+    let state_2 = Arc::clone(&state_helper);
+
+    v_stack((
+        label(move || description.clone()).style(|s| s.font_size(10.0)),
+        h_stack((
+            small_button(
+                action_label,
+                "plus",
+                move |_| {
+                    let project_id = state_helper.lock().unwrap().get_project_id();
+
+                    if let Err(e) = apply_change(state_helper.clone(), &project_id, &change) {
+                        error_text.set(e);
+                    }
+                },
+                apply_active,
+            ),
+            small_button(
+                "Ignore",
+                "plus",
+                move |_| {
+                    let file_signals = Arc::clone(&state_2.lock().unwrap().file_signals);
+                    dismiss_change(&file_signals, &ignored_change);
+                },
+                ignore_active,
+            ),
+        )),
+        label(move || error_text.get())
+            .style(|s| s.font_size(10.0).color(Color::rgb8(180, 60, 60))),
+    ))
+    .style(|s| s.width(260.0).margin_bottom(7.0))
+}
+
+/// Files other programs added, removed or changed in the project folder, waiting on a decision
+pub fn external_changes_panel(state_helper: Arc<Mutex<StateHelper>>) -> impl View {
+    let state_2 = Arc::clone(&state_helper);
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let tx = Arc::new(tx);
+    let changes_data: RwSignal<Vec<ExternalChange>> = create_rw_signal(pending_changes());
+
+    let update_signal = create_signal_from_tokio_channel(rx);
+
+    create_effect(move |_| {
+        if let Some(msg) = update_signal.get() {
+            match msg {
+                UIMessage::UpdateExternalChanges(changes) => changes_data.set(changes),
+                _ => return,
+            }
+        }
+    });
+
+    create_effect({
+        let tx = tx.clone();
+        move |_| {
+            let mut state_helper = state_helper.lock().unwrap();
+            state_helper.register_file_signal("external_changes".to_string(), tx.clone());
+        }
+    });
+
+    dyn_container(
+        move || changes_data.get().is_empty(),
+        move |no_changes| {
+            if no_changes {
+                return empty().into_any();
+            }
+
+            let state_2 = state_2.clone();
+
+            v_stack((
+                label(move || format!("Changed Outside the Editor ({})", changes_data.get().len()))
+                    .style(|s| s.margin_bottom(5.0)),
+                dyn_stack(
+                    move || changes_data.get(),
+                    move |change| change.key(),
+                    move |change| external_change_item(state_2.clone(), change),
+                )
+                .style(|s| s.flex_col()),
+            ))
+            .style(|s| s.margin_bottom(7.0))
+            .into_any()
+        },
+    )
+}
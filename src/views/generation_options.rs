@@ -0,0 +1,130 @@
+use midpoint_engine::floem::peniko::Color;
+use midpoint_engine::floem::reactive::SignalGet;
+use midpoint_engine::floem::reactive::SignalUpdate;
+use midpoint_engine::floem::reactive::{create_rw_signal, RwSignal};
+use midpoint_engine::floem::taffy::{FlexDirection, FlexWrap};
+use midpoint_engine::floem::views::{dyn_stack, h_stack, label, text_input, v_stack};
+
+use midpoint_engine::floem::views::Decorators;
+use midpoint_engine::floem::View;
+
+use crate::helpers::generation::{
+    GenerationOptions, ASPECT_RATIOS, DEFAULT_RESOLUTION, MAX_VARIATIONS, STYLE_PRESETS,
+};
+
+/// The signals behind the generation options form, shared with the Generate button
+#[derive(Clone, Copy)]
+pub struct GenerationForm {
+    pub negative_prompt: RwSignal<String>,
+    // blank means a random seed
+    pub seed: RwSignal<String>,
+    pub resolution: RwSignal<String>,
+    pub variations: RwSignal<String>,
+    pub aspect_ratio: RwSignal<String>,
+    pub style_preset: RwSignal<String>,
+}
+
+impl GenerationForm {
+    pub fn new() -> GenerationForm {
+        GenerationForm {
+            negative_prompt: create_rw_signal("".to_string()),
+            seed: create_rw_signal("".to_string()),
+            resolution: create_rw_signal(DEFAULT_RESOLUTION.to_string()),
+            variations: create_rw_signal("1".to_string()),
+            aspect_ratio: create_rw_signal(ASPECT_RATIOS[0].to_string()),
+            style_preset: create_rw_signal(STYLE_PRESETS[0].to_string()),
+        }
+    }
+
+    pub fn options(&self) -> GenerationOptions {
+        let negative_prompt = self.negative_prompt.get().trim().to_string();
+        let style_preset = self.style_preset.get();
+
+        GenerationOptions {
+            negative_prompt: Some(negative_prompt).filter(|p| !p.is_empty()),
+            seed: self.seed.get().trim().parse::<i32>().ok(),
+            resolution: self
+                .resolution
+                .get()
+                .trim()
+                .parse::<i32>()
+                .ok()
+                .filter(|r| *r > 0),
+            aspect_ratio: Some(self.aspect_ratio.get()),
+            style_preset: Some(style_preset).filter(|s| s != STYLE_PRESETS[0]),
+            variations: self
+                .variations
+                .get()
+                .trim()
+                .parse::<i32>()
+                .ok()
+                .map(|v| v.clamp(1, MAX_VARIATIONS)),
+            source_image_url: None,
+        }
+    }
+}
+
+pub fn choice_row(
+    title: &'static str,
+    choices: &'static [&'static str],
+    selected: RwSignal<String>,
+) -> impl View {
+    v_stack((
+        label(move || title).style(|s| s.font_size(10.0).margin_bottom(1.0)),
+        dyn_stack(
+            move || choices.to_vec(),
+            move |choice| choice.to_string(),
+            move |choice| {
+                label(move || choice)
+                    .on_click_stop(move |_| selected.set(choice.to_string()))
+                    .style(move |s| {
+                        let s = s
+                            .font_size(10.0)
+                            .padding_horiz(4.0)
+                            .padding_vert(2.0)
+                            .margin_right(3.0)
+                            .margin_bottom(3.0)
+                            .border_radius(3.0);
+
+                        if selected.get() == choice {
+                            s.color(Color::WHITE).background(Color::rgb8(70, 150, 230))
+                        } else {
+                            s.background(Color::rgb8(220, 220, 220))
+                        }
+                    })
+            },
+        )
+        .style(|s| {
+            s.flex_direction(FlexDirection::Row)
+                .flex_wrap(FlexWrap::Wrap)
+        }),
+    ))
+    .style(|s| s.margin_bottom(5.0))
+}
+
+pub fn numeric_field(title: &'static str, value: RwSignal<String>, placeholder: &str) -> impl View {
+    v_stack((
+        label(move || title).style(|s| s.font_size(10.0).margin_bottom(1.0)),
+        text_input(value)
+            .placeholder(placeholder.to_string())
+            .style(|s| s.width(80.0)),
+    ))
+    .style(|s| s.margin_right(5.0))
+}
+
+pub fn generation_options_view(form: GenerationForm) -> impl View {
+    v_stack((
+        text_input(form.negative_prompt)
+            .placeholder("Negative prompt, ex. blurry, text".to_string())
+            .style(|s| s.width(260.0).margin_bottom(5.0)),
+        h_stack((
+            numeric_field("Seed", form.seed, "Random"),
+            numeric_field("Resolution", form.resolution, "1024"),
+            numeric_field("Variations", form.variations, "1"),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        choice_row("Aspect Ratio", &ASPECT_RATIOS, form.aspect_ratio),
+        choice_row("Style", &STYLE_PRESETS, form.style_preset),
+    ))
+    .style(|s| s.width(260.0).margin_bottom(7.0))
+}
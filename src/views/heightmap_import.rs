@@ -0,0 +1,77 @@
+use std::sync::{Arc, Mutex};
+
+use midpoint_engine::floem::common::small_button;
+use midpoint_engine::floem::reactive::{create_rw_signal, SignalGet, SignalUpdate};
+use midpoint_engine::floem::views::{h_stack, label, text_input, v_stack};
+
+use midpoint_engine::floem::views::Decorators;
+use midpoint_engine::floem::View;
+
+use crate::editor_state::StateHelper;
+use crate::helpers::heightmap_import::{
+    import_heightmap, HeightmapImportOptions, BYTE_ORDERS, NORMALIZATIONS,
+};
+
+use super::generation_options::{choice_row, numeric_field};
+
+/// Brings a 16-bit PNG, TIFF or RAW heightmap from disk into the project as a landscape
+pub fn heightmap_import_view(state_helper: Arc<Mutex<StateHelper>>) -> impl View {
+    let import_active = create_rw_signal(false);
+    let path_field = create_rw_signal("".to_string());
+    let raw_width = create_rw_signal("".to_string());
+    let raw_height = create_rw_signal("".to_string());
+    let byte_order = create_rw_signal(BYTE_ORDERS[0].to_string());
+    let normalization = create_rw_signal(NORMALIZATIONS[0].to_string());
+    let world_scale = create_rw_signal("".to_string());
+    let vertical_scale = create_rw_signal("".to_string());
+    let status = create_rw_signal("".to_string());
+
+    v_stack((
+        text_input(path_field)
+            .placeholder("Heightmap path, ex. C:/terrain/valley.r16".to_string())
+            .style(|s| s.width(260.0).margin_bottom(5.0)),
+        // only RAW files need these, the others carry their size
+        h_stack((
+            numeric_field("RAW Width", raw_width, "Square"),
+            numeric_field("RAW Height", raw_height, "Square"),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        choice_row("Byte Order", &BYTE_ORDERS, byte_order),
+        choice_row("Normalize", &NORMALIZATIONS, normalization),
+        h_stack((
+            numeric_field("World Scale", world_scale, "1"),
+            numeric_field("Vertical Scale", vertical_scale, "1"),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        small_button(
+            "Import Heightmap…",
+            "plus",
+            move |_| {
+                let project_id = state_helper.lock().unwrap().get_project_id();
+                let options = HeightmapImportOptions::from_choices(
+                    &raw_width.get(),
+                    &raw_height.get(),
+                    &byte_order.get(),
+                    &normalization.get(),
+                    &world_scale.get(),
+                    &vertical_scale.get(),
+                );
+
+                match import_heightmap(&state_helper, &project_id, &path_field.get(), &options) {
+                    Ok(landscape) => {
+                        let name = landscape
+                            .heightmap
+                            .map(|heightmap| heightmap.fileName)
+                            .unwrap_or(landscape.id);
+                        status.set(format!("Imported {}", name));
+                        path_field.set("".to_string());
+                    }
+                    Err(e) => status.set(e),
+                }
+            },
+            import_active,
+        ),
+        label(move || status.get()).style(|s| s.font_size(10.0)),
+    ))
+    .style(|s| s.width(260.0).margin_bottom(7.0))
+}
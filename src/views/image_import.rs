@@ -0,0 +1,106 @@
+use std::sync::{Arc, Mutex};
+
+use midpoint_engine::floem::common::small_button;
+use midpoint_engine::floem::ext_event::create_signal_from_tokio_channel;
+use midpoint_engine::floem::reactive::{create_effect, create_rw_signal, SignalGet, SignalUpdate};
+use midpoint_engine::floem::views::{h_stack, label, text_input, v_stack};
+
+use midpoint_engine::floem::views::Decorators;
+use midpoint_engine::floem::View;
+
+use crate::editor_state::{StateHelper, UIMessage};
+use crate::helpers::asset_store::AssetKind;
+use crate::helpers::imports::{
+    import_image, ImageImportOptions, IMAGE_EXTENSIONS, IMAGE_FORMATS, IMAGE_SIZES,
+};
+
+use super::generation_options::choice_row;
+
+/// Brings a PNG, JPEG, WebP or TIFF from disk into the project as a texture or concept
+pub fn image_import_view(state_helper: Arc<Mutex<StateHelper>>, kind: AssetKind) -> impl View {
+    let browse_active = create_rw_signal(false);
+    let import_active = create_rw_signal(false);
+    let importing = create_rw_signal(false);
+    let path_field = create_rw_signal("".to_string());
+    let size = create_rw_signal(IMAGE_SIZES[0].to_string());
+    let format = create_rw_signal(IMAGE_FORMATS[0].to_string());
+    let status = create_rw_signal("".to_string());
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let tx = Arc::new(tx);
+
+    // decoding and resizing large images happens off the UI thread, which hears back here
+    let update_signal = create_signal_from_tokio_channel(rx);
+    create_effect(move |_| {
+        if let Some(msg) = update_signal.get() {
+            match msg {
+                UIMessage::ImportFinished(file) => {
+                    importing.set(false);
+                    status.set(format!("Imported {}", file.fileName));
+                    path_field.set("".to_string());
+                }
+                UIMessage::ImportFailed(e) => {
+                    importing.set(false);
+                    status.set(e);
+                }
+                _ => return,
+            }
+        }
+    });
+
+    v_stack((
+        h_stack((
+            text_input(path_field)
+                .placeholder("Image path, ex. C:/textures/bark.png".to_string())
+                .style(|s| s.width(180.0)),
+            small_button(
+                "Browse…",
+                "plus",
+                move |_| {
+                    let picked = rfd::FileDialog::new()
+                        .add_filter("Images", &IMAGE_EXTENSIONS)
+                        .pick_file();
+                    if let Some(path) = picked {
+                        path_field.set(path.to_string_lossy().to_string());
+                    }
+                },
+                browse_active,
+            ),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        choice_row("Resize To", &IMAGE_SIZES, size),
+        choice_row("Convert To", &IMAGE_FORMATS, format),
+        small_button(
+            "Import…",
+            "plus",
+            move |_| {
+                if importing.get() {
+                    return;
+                }
+
+                let project_id = state_helper.lock().unwrap().get_project_id();
+                let options = ImageImportOptions::from_choices(&size.get(), &format.get());
+                let path = path_field.get();
+                let state_helper = Arc::clone(&state_helper);
+                let kind = kind.clone();
+                let tx = tx.clone();
+
+                importing.set(true);
+                status.set("Importing...".to_string());
+
+                tokio::runtime::Handle::current().spawn_blocking(move || {
+                    let message =
+                        match import_image(&state_helper, &project_id, &kind, &path, &options) {
+                            Ok(file) => UIMessage::ImportFinished(file),
+                            Err(e) => UIMessage::ImportFailed(e),
+                        };
+
+                    let _ = tx.send(message);
+                });
+            },
+            import_active,
+        ),
+        label(move || status.get()).style(|s| s.font_size(10.0)),
+    ))
+    .style(|s| s.width(260.0).margin_bottom(7.0))
+}
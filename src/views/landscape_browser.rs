@@ -4,11 +4,12 @@ use super::shared::dynamic_img;
 use midpoint_engine::core::RendererState::ObjectConfig;
 use midpoint_engine::core::Viewport::Viewport;
 use midpoint_engine::floem::common::small_button;
+use midpoint_engine::floem::ext_event::create_signal_from_tokio_channel;
 use midpoint_engine::floem::reactive::SignalGet;
 use midpoint_engine::floem::reactive::{create_effect, create_rw_signal, RwSignal, SignalUpdate};
 use midpoint_engine::floem::taffy::{FlexDirection, FlexWrap};
 use midpoint_engine::floem::views::{
-    container, dyn_container, dyn_stack, empty, label, scroll, v_stack,
+    container, dyn_container, dyn_stack, empty, h_stack, label, scroll, v_stack,
 };
 use midpoint_engine::floem::IntoView;
 use midpoint_engine::floem_renderer::gpu_resources;
@@ -16,13 +17,26 @@ use midpoint_engine::handlers::handle_add_landscape;
 use midpoint_engine::helpers::saved_data::{
     ComponentData, ComponentKind, File, GenericProperties, LandscapeData, LandscapeProperties,
 };
+use tokio::sync::mpsc::UnboundedSender;
 use uuid::Uuid;
 use wgpu::util::DeviceExt;
 
 use midpoint_engine::floem::views::Decorators;
 use midpoint_engine::floem::{GpuHelper, View, WindowHandle};
 
-use crate::editor_state::{EditorState, StateHelper};
+use crate::editor_state::{EditorState, StateHelper, UIMessage};
+use crate::helpers::collaboration::{SceneOperation, TransformProperty};
+use crate::helpers::landscapes::{
+    placed_landscape_scale, request_landscape, LandscapeParams, BIOMES, LANDSCAPE_SIZES,
+};
+
+use super::asset_browser::{asset_browser, AssetEntry};
+use super::generation_options::{choice_row, numeric_field};
+use super::heightmap_import::heightmap_import_view;
+use super::terrain_generator::terrain_generator_view;
+
+// rockmap, three map names and Add to Scene
+const LANDSCAPE_CARD_HEIGHT: f64 = 205.0;
 
 pub fn landscape_item(
     state_helper: Arc<Mutex<StateHelper>>,
@@ -99,6 +113,8 @@ pub fn landscape_item(
                         .expect("Couldn't get RendererState")
                         .lock()
                         .unwrap();
+                    // imported heightmaps can be laid out larger than the engine's default
+                    let scale = placed_landscape_scale(&saved_state, &landscape.id);
 
                     // add to `levels.components` in SavedContext
                     let landscape_component = ComponentData {
@@ -109,7 +125,7 @@ pub fn landscape_item(
                             name: "New Landscape Component".to_string(),
                             position: [0.0, 0.0, 0.0],
                             rotation: [0.0, 0.0, 0.0],
-                            scale: [1.0, 1.0, 1.0],
+                            scale,
                         },
                         landscape_properties: Some(LandscapeProperties {
                             // these are the visible texture ids, not the map ids, so are added after adding
@@ -134,6 +150,10 @@ pub fn landscape_item(
 
                     state_helper.save_saved_state(project_id, saved_state);
 
+                    state_helper.broadcast_scene_operation(SceneOperation::Add {
+                        component: landscape_component.clone(),
+                    });
+
                     // drop(saved_state);
 
                     let mut renderer_state = state_helper
@@ -179,6 +199,18 @@ pub fn landscape_item(
                         [0.0, 0.0, 0.0],
                     );
 
+                    if scale != [1.0, 1.0, 1.0] {
+                        let operation = SceneOperation::Transform {
+                            component_id: landscapeComponentId.to_string(),
+                            property: TransformProperty::Scale,
+                            value: scale,
+                        };
+                        state_helper.broadcast_scene_operation(operation.clone());
+                        if let Some(tx) = state_helper.file_signals.lock().unwrap().get("scene") {
+                            let _ = tx.send(UIMessage::SceneOperation(operation));
+                        }
+                    }
+
                     // update selected_component_id in signal
                     let object_selected_signal = state_helper
                         .object_selected_signal
@@ -205,45 +237,142 @@ pub fn landscape_item(
     .style(|s| s.width(120.0))
 }
 
+/// Biome, size, roughness and seed for a new landscape, sent to the project's provider
+fn landscape_generator(
+    state_helper: Arc<Mutex<StateHelper>>,
+    tx: Arc<UnboundedSender<UIMessage>>,
+    generate_status: RwSignal<String>,
+    generate_disabled: RwSignal<bool>,
+) -> impl View {
+    let biome = create_rw_signal(BIOMES[1].to_string());
+    let size = create_rw_signal(LANDSCAPE_SIZES[0].to_string());
+    let roughness = create_rw_signal("".to_string());
+    let seed = create_rw_signal("".to_string());
+    let generate_active = create_rw_signal(false);
+
+    v_stack((
+        choice_row("Biome", &BIOMES, biome),
+        choice_row("Size", &LANDSCAPE_SIZES, size),
+        h_stack((
+            numeric_field("Roughness", roughness, "0.5"),
+            numeric_field("Seed", seed, "Random"),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        small_button(
+            "Generate",
+            "plus",
+            move |_| {
+                let defaults = LandscapeParams::default();
+                let params = LandscapeParams {
+                    size: size.get().parse().unwrap_or(defaults.size),
+                    biome: biome.get(),
+                    roughness: roughness.get().trim().parse().unwrap_or(defaults.roughness),
+                    seed: seed.get().trim().parse().ok(),
+                }
+                .resolved();
+
+                let project_id = state_helper.lock().unwrap().get_project_id();
+                let state_helper = state_helper.clone();
+                let tx = tx.clone();
+
+                generate_disabled.set(true);
+                generate_status.set(format!("Generating {} landscape...", params.biome));
+
+                tokio::runtime::Handle::current().spawn(async move {
+                    let message = match request_landscape(state_helper, project_id, params).await {
+                        Ok(landscape) => UIMessage::AddLandscape(landscape),
+                        Err(e) => UIMessage::LandscapeFailed(e),
+                    };
+
+                    let _ = tx.send(message);
+                });
+            },
+            generate_active,
+        )
+        .disabled(move || generate_disabled.get()),
+        label(move || generate_status.get()).style(|s| s.font_size(10.0)),
+    ))
+    .style(|s| s.width(260.0).margin_bottom(7.0))
+}
+
 pub fn landscape_browser(
     state_helper: Arc<Mutex<StateHelper>>,
     gpu_helper: Arc<Mutex<GpuHelper>>,
     viewport: Arc<Mutex<Viewport>>,
 ) -> impl View {
     let landscape_data: RwSignal<Vec<LandscapeData>> = create_rw_signal(Vec::new());
+    let generate_status = create_rw_signal("".to_string());
+    let generate_disabled = create_rw_signal(false);
 
     let state_2 = Arc::clone(&state_helper);
+    let state_3 = Arc::clone(&state_helper);
+    let state_4 = Arc::clone(&state_helper);
+    let state_5 = Arc::clone(&state_helper);
+    let state_6 = Arc::clone(&state_helper);
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let tx = Arc::new(tx);
+
+    let update_signal = create_signal_from_tokio_channel(rx);
 
     create_effect(move |_| {
-        let state_helper = state_helper.lock().unwrap();
-        let saved_state = state_helper
-            .saved_state
-            .as_ref()
-            .expect("Couldn't get saved state")
-            .lock()
-            .unwrap();
-        landscape_data.set(
-            saved_state
-                .landscapes
+        if let Some(msg) = update_signal.get() {
+            match msg {
+                UIMessage::UpdateLandscapes(landscapes) => landscape_data.set(landscapes),
+                UIMessage::AddLandscape(landscape) => {
+                    generate_disabled.set(false);
+                    generate_status.set("Landscape added".to_string());
+                    landscape_data.update(|landscapes| landscapes.push(landscape));
+                }
+                UIMessage::LandscapeFailed(e) => {
+                    generate_disabled.set(false);
+                    generate_status.set(format!("Couldn't generate landscape: {}", e));
+                }
+                _ => return,
+            }
+        }
+    });
+
+    create_effect({
+        let tx = tx.clone();
+        move |_| {
+            let mut state_helper = state_helper.lock().unwrap();
+
+            state_helper.register_file_signal("landscape_browser".to_string(), tx.clone());
+
+            let saved_state = state_helper
+                .saved_state
                 .as_ref()
-                .expect("Couldn't get landscape data")
-                .clone(),
-        );
+                .expect("Couldn't get saved state")
+                .lock()
+                .unwrap();
+            landscape_data.set(saved_state.landscapes.clone().unwrap_or_default());
+        }
     });
 
-    container((scroll(
-        dyn_stack(
-            move || landscape_data.get(),
-            move |landscape_data| landscape_data.id.clone(),
-            move |landscape_data| {
-                landscape_item(state_2.clone(), gpu_helper.clone(), landscape_data)
+    v_stack((
+        landscape_generator(state_3, tx, generate_status, generate_disabled),
+        terrain_generator_view(state_6),
+        heightmap_import_view(state_5),
+        asset_browser(
+            state_4,
+            "landscapes",
+            landscape_data,
+            LANDSCAPE_CARD_HEIGHT,
+            |landscape: &LandscapeData| AssetEntry {
+                id: landscape.id.clone(),
+                // landscapes have no name of their own, their heightmap's is the closest
+                name: landscape
+                    .heightmap
+                    .as_ref()
+                    .map(|heightmap| heightmap.fileName.clone())
+                    .unwrap_or_else(|| landscape.id.clone()),
+                file_name: None,
             },
-        )
-        .into_view(),
-    ),))
-    .style(|s| {
-        s.flex_direction(FlexDirection::Row)
-            .flex_wrap(FlexWrap::Wrap)
-    })
-    .style(|s| s.width(260.0))
+            move |landscape: LandscapeData| {
+                landscape_item(state_2.clone(), gpu_helper.clone(), landscape).into_any()
+            },
+            None,
+        ),
+    ))
 }
@@ -0,0 +1,288 @@
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use image::DynamicImage;
+use midpoint_engine::floem::common::small_button;
+use midpoint_engine::floem::ext_event::create_signal_from_tokio_channel;
+use midpoint_engine::floem::peniko::Color;
+use midpoint_engine::floem::reactive::{
+    create_effect, create_rw_signal, RwSignal, SignalGet, SignalUpdate,
+};
+use midpoint_engine::floem::views::{h_stack, img_dynamic, label, v_stack};
+use midpoint_engine::handlers::handle_add_landscape_texture;
+use midpoint_engine::helpers::saved_data::{ComponentData, LandscapeData};
+
+use midpoint_engine::floem::views::Decorators;
+use midpoint_engine::floem::{GpuHelper, View};
+
+use crate::editor_state::{StateHelper, UIMessage};
+use crate::helpers::downloads::get_asset_path;
+use crate::helpers::heightfield::{load_heightfield, Heightfield};
+use crate::helpers::landscapes::{
+    has_synced_masks, load_mask_params, preview_masks, regenerate_masks, MaskParams,
+};
+
+use super::generation_options::numeric_field;
+
+const MASK_PREVIEW_WIDTH: f64 = 120.0;
+
+fn find_landscape(
+    state_helper: &MutexGuard<StateHelper>,
+    landscape_id: &str,
+) -> Option<LandscapeData> {
+    state_helper
+        .saved_state
+        .as_ref()?
+        .lock()
+        .unwrap()
+        .landscapes
+        .iter()
+        .flatten()
+        .find(|l| l.id == landscape_id)
+        .cloned()
+}
+
+fn load_landscape_heights(
+    state_helper: &MutexGuard<StateHelper>,
+    landscape_id: &str,
+) -> Option<Heightfield> {
+    let heightmap = find_landscape(state_helper, landscape_id)?.heightmap?;
+
+    load_heightfield(&get_asset_path(
+        &heightmap.normalFilePath,
+        &heightmap.fileName,
+    ))
+    .map_err(|e| println!("{}", e))
+    .ok()
+}
+
+/// The renderer reads a landscape's masks when a texture is applied to it,
+/// so the component's textures are applied again to show the new masks
+fn reload_mask_textures(
+    state_helper: &MutexGuard<StateHelper>,
+    gpu_helper: &Arc<Mutex<GpuHelper>>,
+    project_id: &str,
+    component_id: &str,
+    landscape: &LandscapeData,
+) {
+    let (textures, component) = {
+        let saved_state = match state_helper.saved_state.as_ref() {
+            Some(saved_state) => saved_state.lock().unwrap(),
+            None => return,
+        };
+        let component = saved_state
+            .levels
+            .iter()
+            .flatten()
+            .flat_map(|level| level.components.iter().flatten())
+            .find(|c| c.id == component_id)
+            .cloned();
+        (saved_state.textures.clone().unwrap_or_default(), component)
+    };
+
+    let properties = match component
+        .as_ref()
+        .and_then(|c| c.landscape_properties.as_ref())
+    {
+        Some(properties) => properties,
+        None => return,
+    };
+    let renderer_state = match state_helper.renderer_state.as_ref() {
+        Some(renderer_state) => renderer_state,
+        None => return,
+    };
+    let gpu_helper = gpu_helper.lock().unwrap();
+    let gpu_resources = match gpu_helper.gpu_resources.as_ref() {
+        Some(gpu_resources) => gpu_resources,
+        None => return,
+    };
+
+    for (kind, texture_id, mask) in [
+        (
+            "Rockmap",
+            &properties.rockmap_texture_id,
+            &landscape.rockmap,
+        ),
+        ("Soil", &properties.soil_texture_id, &landscape.soil),
+    ] {
+        let texture = texture_id
+            .as_ref()
+            .and_then(|texture_id| textures.iter().find(|t| t.id == *texture_id));
+
+        if let (Some(texture), Some(mask)) = (texture, mask) {
+            handle_add_landscape_texture(
+                renderer_state.clone(),
+                &gpu_resources.device,
+                &gpu_resources.queue,
+                project_id.to_string(),
+                component_id.to_string(),
+                landscape.id.clone(),
+                texture.fileName.clone(),
+                kind.to_string(),
+                mask.fileName.clone(),
+            );
+        }
+    }
+}
+
+fn mask_preview(title: &'static str, mask: RwSignal<Option<Rc<DynamicImage>>>) -> impl View {
+    v_stack((
+        label(move || title).style(|s| s.font_size(10.0).margin_bottom(1.0)),
+        img_dynamic(move || mask.get()).style(|s| {
+            s.width(MASK_PREVIEW_WIDTH)
+                .height(MASK_PREVIEW_WIDTH)
+                .background(Color::rgb8(200, 200, 200))
+                .border_radius(5.0)
+        }),
+    ))
+    .style(|s| s.margin_right(5.0))
+}
+
+/// Slope and height thresholds for the landscape's rockmap and soil masks, previewed as they
+/// change and written to the project on Regenerate
+pub fn landscape_masks_view(
+    state_helper: Arc<Mutex<StateHelper>>,
+    gpu_helper: Arc<Mutex<GpuHelper>>,
+    component: ComponentData,
+) -> impl View {
+    let (project_id, heightfield) = {
+        let state_helper = state_helper.lock().unwrap();
+        (
+            state_helper.get_project_id(),
+            load_landscape_heights(&state_helper, &component.asset_id),
+        )
+    };
+    let saved = load_mask_params(&project_id, &component.asset_id);
+
+    let rock_slope = create_rw_signal(saved.rock_slope.to_string());
+    let soil_slope = create_rw_signal(saved.soil_slope.to_string());
+    let soil_height = create_rw_signal(saved.soil_height.to_string());
+    let blur = create_rw_signal(saved.blur.to_string());
+    let rock_preview: RwSignal<Option<Rc<DynamicImage>>> = create_rw_signal(None);
+    let soil_preview: RwSignal<Option<Rc<DynamicImage>>> = create_rw_signal(None);
+    let status = create_rw_signal(match heightfield {
+        Some(_) => "".to_string(),
+        None => "Heightmap isn't downloaded yet".to_string(),
+    });
+    let regenerate_active = create_rw_signal(false);
+    let regenerating = create_rw_signal(false);
+    // synced masks are only replaced on a second click
+    let replace_synced = create_rw_signal(false);
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let tx = Arc::new(tx);
+
+    // full size masks are made off the UI thread, the textures are applied again back here
+    let update_signal = create_signal_from_tokio_channel(rx);
+    {
+        let state_helper = Arc::clone(&state_helper);
+        let project_id = project_id.clone();
+        let component_id = component.id.clone();
+
+        create_effect(move |_| {
+            if let Some(msg) = update_signal.get() {
+                match msg {
+                    UIMessage::MasksRegenerated(landscape) => {
+                        regenerating.set(false);
+                        replace_synced.set(false);
+                        reload_mask_textures(
+                            &state_helper.lock().unwrap(),
+                            &gpu_helper,
+                            &project_id,
+                            &component_id,
+                            &landscape,
+                        );
+                        status.set("Masks regenerated".to_string());
+                    }
+                    UIMessage::LandscapeFailed(e) => {
+                        regenerating.set(false);
+                        status.set(e);
+                    }
+                    _ => return,
+                }
+            }
+        });
+    }
+
+    let params = move || MaskParams {
+        rock_slope: rock_slope.get().trim().parse().unwrap_or(saved.rock_slope),
+        soil_slope: soil_slope.get().trim().parse().unwrap_or(saved.soil_slope),
+        soil_height: soil_height
+            .get()
+            .trim()
+            .parse()
+            .unwrap_or(saved.soil_height),
+        blur: blur.get().trim().parse().unwrap_or(saved.blur),
+    };
+
+    // drawn again whenever a threshold changes
+    create_effect(move |_| {
+        if let Some(heightfield) = heightfield.as_ref() {
+            let (rock, soil) = preview_masks(heightfield, &params());
+            rock_preview.set(Some(Rc::new(DynamicImage::ImageLuma8(rock))));
+            soil_preview.set(Some(Rc::new(DynamicImage::ImageLuma8(soil))));
+        }
+    });
+
+    v_stack((
+        label(|| "Rock and Soil Masks").style(|s| s.margin_top(5.0).margin_bottom(3.0)),
+        h_stack((
+            numeric_field("Rock Slope", rock_slope, "0.35"),
+            numeric_field("Soil Slope", soil_slope, "0.25"),
+            numeric_field("Soil Height", soil_height, "0.45"),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        numeric_field("Blur", blur, "2").style(|s| s.margin_bottom(5.0)),
+        h_stack((
+            mask_preview("Rock", rock_preview),
+            mask_preview("Soil", soil_preview),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        small_button(
+            "Regenerate Masks",
+            "plus",
+            move |_| {
+                if regenerating.get() {
+                    return;
+                }
+
+                let synced = find_landscape(&state_helper.lock().unwrap(), &component.asset_id)
+                    .map_or(false, |landscape| has_synced_masks(&landscape));
+                if synced && !replace_synced.get() {
+                    replace_synced.set(true);
+                    status.set(
+                        "These masks are synced, regenerate again to replace them".to_string(),
+                    );
+                    return;
+                }
+
+                let state_helper = Arc::clone(&state_helper);
+                let project_id = project_id.clone();
+                let landscape_id = component.asset_id.clone();
+                let params = params();
+                let replace = replace_synced.get();
+                let tx = tx.clone();
+
+                regenerating.set(true);
+                status.set("Regenerating...".to_string());
+
+                tokio::runtime::Handle::current().spawn_blocking(move || {
+                    let message = match regenerate_masks(
+                        &state_helper,
+                        &project_id,
+                        &landscape_id,
+                        &params,
+                        replace,
+                    ) {
+                        Ok(landscape) => UIMessage::MasksRegenerated(landscape),
+                        Err(e) => UIMessage::LandscapeFailed(e),
+                    };
+
+                    let _ = tx.send(message);
+                });
+            },
+            regenerate_active,
+        ),
+        label(move || status.get()).style(|s| s.font_size(10.0)),
+    ))
+}
@@ -1,27 +1,40 @@
 pub mod animations;
 pub mod app;
 pub mod aside;
+pub mod asset_browser;
 pub mod audio;
 pub mod component_browser;
 pub mod concepts;
 pub mod editor_settings;
+pub mod external_changes;
+pub mod generation_options;
+pub mod heightmap_import;
+pub mod image_import;
 pub mod inputs;
 pub mod keyframe_timeline;
 pub mod landscape_browser;
+pub mod landscape_masks;
 pub mod level_browser;
 pub mod map;
 pub mod model_browser;
+pub mod model_import;
 pub mod nodes;
+pub mod outbox;
 pub mod part_browser;
 pub mod part_properties;
 pub mod performance;
+pub mod pipeline;
 pub mod project_browser;
 pub mod project_settings;
 pub mod properties_panel;
+pub mod provenance;
 pub mod scene;
 pub mod shared;
+pub mod sign_in;
 pub mod skeleton_browser;
 pub mod skeleton_properties;
 pub mod story;
+pub mod terrain_generator;
 pub mod texture_browser;
 pub mod topographic_map;
+pub mod usage;
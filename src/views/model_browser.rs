@@ -1,16 +1,23 @@
+use std::cell::RefCell;
 use std::future::Future;
+use std::path::PathBuf;
 use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, MutexGuard};
 
-use super::shared::dynamic_img;
+use super::shared::{asset_status_signal, download_status_text, dynamic_img};
+use image::DynamicImage;
 use midpoint_engine::core::Viewport::Viewport;
 use midpoint_engine::floem::common::small_button;
+use midpoint_engine::floem::event::{EventListener, EventPropagation};
 use midpoint_engine::floem::ext_event::create_signal_from_tokio_channel;
+use midpoint_engine::floem::peniko::Color;
 use midpoint_engine::floem::reactive::SignalGet;
 use midpoint_engine::floem::reactive::{create_effect, create_rw_signal, RwSignal, SignalUpdate};
 use midpoint_engine::floem::taffy::{FlexDirection, FlexWrap};
 use midpoint_engine::floem::views::{
-    button, container, dyn_container, dyn_stack, empty, label, scroll, v_stack,
+    button, container, dyn_container, dyn_stack, empty, img_dynamic, label, scroll, v_stack,
 };
 use midpoint_engine::floem::IntoView;
 use midpoint_engine::floem_renderer::gpu_resources;
@@ -24,19 +31,121 @@ use midpoint_engine::floem::views::Decorators;
 use midpoint_engine::floem::{GpuHelper, View, WindowHandle};
 
 use crate::editor_state::{EditorState, StateHelper, UIMessage};
+use crate::helpers::collaboration::SceneOperation;
+use crate::helpers::downloads::{get_asset_path, DownloadStatus};
+use crate::helpers::model_previews::{request_model_preview, spawn_turntable};
+
+use super::asset_browser::{asset_browser, AssetEntry};
+use super::model_import::model_import_view;
+use super::provenance::provenance_inspector;
 
 // type BoxedAsyncFn = Box<dyn Fn() -> Pin<Box<dyn Future<Output = String> + Send>> + Send + Sync>;
 
+// preview, name and Add to Scene
+const MODEL_CARD_HEIGHT: f64 = 175.0;
+
+/// The model's rendered preview, turning while the pointer is over it
+fn model_preview(model_path: PathBuf, status: RwSignal<DownloadStatus>) -> impl IntoView {
+    let preview: RwSignal<Option<Rc<DynamicImage>>> = create_rw_signal(None);
+    let still: RwSignal<Option<Rc<DynamicImage>>> = create_rw_signal(None);
+    let preview_failed = create_rw_signal(false);
+    // cleared when the pointer leaves, which stops that hover's turntable
+    let turntable: Rc<RefCell<Option<Arc<AtomicBool>>>> = Rc::new(RefCell::new(None));
+
+    let (still_tx, still_rx) = tokio::sync::mpsc::unbounded_channel();
+    let still_signal = create_signal_from_tokio_channel(still_rx);
+    let (frame_tx, frame_rx) = tokio::sync::mpsc::unbounded_channel();
+    let frame_signal = create_signal_from_tokio_channel(frame_rx);
+
+    let still_path = model_path.clone();
+    create_effect(move |_| {
+        // rendered once the model lands in the project folder
+        if status.get() == DownloadStatus::Ready {
+            preview_failed.set(false);
+            request_model_preview(still_path.clone(), still_tx.clone());
+        }
+    });
+
+    create_effect(move |_| {
+        if let Some(image) = still_signal.get() {
+            preview_failed.set(image.is_none());
+            still.set(image.map(Rc::new));
+            preview.set(still.get_untracked());
+        }
+    });
+
+    let frame_turntable = Rc::clone(&turntable);
+    create_effect(move |_| {
+        if let Some(Some(frame)) = frame_signal.get() {
+            // a frame can still arrive just after the pointer has left
+            if frame_turntable.borrow().is_some() {
+                preview.set(Some(Rc::new(frame)));
+            }
+        }
+    });
+
+    let enter_turntable = Rc::clone(&turntable);
+    let leave_turntable = Rc::clone(&turntable);
+
+    dyn_container(
+        move || preview.get().is_some(),
+        move |has_preview| {
+            if has_preview {
+                img_dynamic(move || preview.get())
+                    .style(|s| s.width(120.0).height(120.0).border_radius(5.0))
+                    .into_any()
+            } else {
+                label(move || match status.get() {
+                    DownloadStatus::Ready if preview_failed.get() => "No preview".to_string(),
+                    DownloadStatus::Ready => "Rendering...".to_string(),
+                    status => download_status_text(&status),
+                })
+                .into_any()
+            }
+        },
+    )
+    .style(|s| {
+        s.width(120.0)
+            .height(120.0)
+            .items_center()
+            .justify_center()
+            .background(Color::rgb8(200, 200, 200))
+            .border_radius(5.0)
+    })
+    .on_event(EventListener::PointerEnter, move |_| {
+        let mut current = enter_turntable.borrow_mut();
+        if current.is_none()
+            && status.get_untracked() == DownloadStatus::Ready
+            && !preview_failed.get_untracked()
+        {
+            let playing = Arc::new(AtomicBool::new(true));
+            spawn_turntable(model_path.clone(), Arc::clone(&playing), frame_tx.clone());
+            *current = Some(playing);
+        }
+        EventPropagation::Continue
+    })
+    .on_event(EventListener::PointerLeave, move |_| {
+        if let Some(playing) = leave_turntable.borrow_mut().take() {
+            playing.store(false, Ordering::Relaxed);
+        }
+        preview.set(still.get_untracked());
+        EventPropagation::Continue
+    })
+}
+
 pub fn model_item(
     state_helper: Arc<Mutex<StateHelper>>,
     gpu_helper: Arc<Mutex<GpuHelper>>,
     label_text: String,
+    model_path: String,
     filename: String,
     model_id: String,
 ) -> impl View {
     let active = create_rw_signal(false);
+    let status = asset_status_signal(model_path.clone(), filename.clone());
 
     v_stack((
+        model_preview(get_asset_path(&model_path, &filename), status),
         label(move || label_text.clone()),
         small_button(
             "Add to Scene",
@@ -106,7 +215,7 @@ pub fn model_item(
                         .expect("Couldn't get first level")
                         .components
                         .get_or_insert_with(Vec::new)
-                        .push(model_component);
+                        .push(model_component.clone());
 
                     let project_id = state_helper
                         .project_selected_signal
@@ -114,10 +223,16 @@ pub fn model_item(
                         .get();
 
                     state_helper.save_saved_state(project_id, saved_state);
+
+                    state_helper.broadcast_scene_operation(SceneOperation::Add {
+                        component: model_component,
+                    });
                 }
             },
             active,
-        ),
+        )
+        // models can't be loaded until they're downloaded
+        .disabled(move || status.get() != DownloadStatus::Ready),
     ))
     .style(|s| s.width(120.0))
 }
@@ -128,6 +243,9 @@ pub fn model_browser(
     viewport: Arc<Mutex<Viewport>>,
 ) -> impl View {
     let state_2 = Arc::clone(&state_helper);
+    let state_3 = Arc::clone(&state_helper);
+    let state_4 = Arc::clone(&state_helper);
+    let state_5 = Arc::clone(&state_helper);
     let gpu_2 = Arc::clone(&gpu_helper);
 
     let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
@@ -165,25 +283,33 @@ pub fn model_browser(
         }
     });
 
-    container((scroll(
-        dyn_stack(
-            move || model_data.get(),
-            move |model_data| model_data.id.clone(),
-            move |model_data| {
+    v_stack((
+        model_import_view(state_3),
+        asset_browser(
+            state_4,
+            "models",
+            model_data,
+            MODEL_CARD_HEIGHT,
+            |model: &File| AssetEntry {
+                id: model.id.clone(),
+                name: model.fileName.clone(),
+                file_name: Some(model.fileName.clone()),
+            },
+            move |model: File| {
                 model_item(
                     state_2.clone(),
                     gpu_2.clone(),
-                    model_data.fileName.clone(),
-                    model_data.fileName.clone(),
-                    model_data.id.clone(),
+                    model.fileName.clone(),
+                    model.normalFilePath.clone(),
+                    model.fileName.clone(),
+                    model.id.clone(),
                 )
+                .into_any()
             },
-        )
-        .into_view(),
-    ),))
-    .style(|s| {
-        s.flex_direction(FlexDirection::Row)
-            .flex_wrap(FlexWrap::Wrap)
-    })
+            Some(Box::new(move |model: &File| {
+                provenance_inspector(state_5.clone(), "models", &model.fileName).into_any()
+            })),
+        ),
+    ))
     .style(|s| s.width(260.0))
 }
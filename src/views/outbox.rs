@@ -0,0 +1,161 @@
+use std::sync::{Arc, Mutex};
+
+use midpoint_engine::floem::common::small_button;
+use midpoint_engine::floem::ext_event::create_signal_from_tokio_channel;
+use midpoint_engine::floem::peniko::Color;
+use midpoint_engine::floem::reactive::SignalGet;
+use midpoint_engine::floem::reactive::SignalUpdate;
+use midpoint_engine::floem::reactive::{create_effect, create_rw_signal, RwSignal};
+use midpoint_engine::floem::views::{dyn_container, dyn_stack, empty, h_stack, label, v_stack};
+use midpoint_engine::floem::IntoView;
+
+use midpoint_engine::floem::views::Decorators;
+use midpoint_engine::floem::View;
+
+use crate::editor_state::{StateHelper, UIMessage};
+use crate::helpers::outbox::{discard_request, load_outbox, spawn_outbox_flush, OutboxEntry};
+
+pub fn outbox_item(
+    state_helper: Arc<Mutex<StateHelper>>,
+    entry: OutboxEntry,
+    outbox_data: RwSignal<Vec<OutboxEntry>>,
+) -> impl View {
+    let discard_active = create_rw_signal(false);
+
+    let kind_text = entry.request.kind_label();
+    let summary_text = entry.request.summary();
+    let attempts_text = if entry.failed {
+        format!("Failed after {} attempts, won't be retried", entry.attempts)
+    } else {
+        format!("Attempts: {}", entry.attempts)
+    };
+    let error_text = entry.last_error.clone().unwrap_or_default();
+
+    v_stack((
+        label(move || format!("{}: {}", kind_text, summary_text)),
+        label(move || attempts_text.clone()).style(|s| s.font_size(10.0)),
+        label(move || error_text.clone())
+            .style(|s| s.font_size(10.0).color(Color::rgb8(180, 60, 60))),
+        small_button(
+            "Discard",
+            "plus",
+            {
+                let entry_id = entry.id.clone();
+
+                move |_| {
+                    let state_helper = state_helper.lock().unwrap();
+                    let project_id = state_helper.get_project_id();
+
+                    match discard_request(&project_id, &entry_id) {
+                        Ok(entries) => outbox_data.set(entries),
+                        Err(e) => println!("{}", e),
+                    }
+                }
+            },
+            discard_active,
+        ),
+    ))
+    .style(|s| s.width(260.0).margin_bottom(7.0))
+}
+
+pub fn outbox_panel(state_helper: Arc<Mutex<StateHelper>>) -> impl View {
+    let state_2 = Arc::clone(&state_helper);
+    let state_3 = Arc::clone(&state_helper);
+
+    let retry_active = create_rw_signal(false);
+    let dismiss_active = create_rw_signal(false);
+    // the last generation that failed outright, rather than waiting here
+    let failure: RwSignal<Option<String>> = create_rw_signal(None);
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let tx = Arc::new(tx);
+    let outbox_data: RwSignal<Vec<OutboxEntry>> = create_rw_signal(Vec::new());
+
+    // Create signal from channel
+    let update_signal = create_signal_from_tokio_channel(rx);
+
+    // Handle updates in UI thread
+    create_effect(move |_| {
+        if let Some(msg) = update_signal.get() {
+            match msg {
+                UIMessage::UpdateOutbox(entries) => outbox_data.set(entries),
+                UIMessage::GenerationFailed(error) => failure.set(Some(error)),
+                _ => return,
+            }
+        }
+    });
+
+    create_effect({
+        let tx = tx.clone();
+        move |_| {
+            let tx = tx.clone();
+            let mut state_helper = state_helper.lock().unwrap();
+
+            state_helper.register_file_signal("outbox".to_string(), tx);
+
+            let project_id = state_helper.get_project_id();
+
+            outbox_data.set(load_outbox(&project_id));
+        }
+    });
+
+    v_stack((
+        dyn_container(
+            move || failure.get(),
+            move |failure_text| match failure_text {
+                Some(failure_text) => v_stack((
+                    label(move || format!("Generation failed: {}", failure_text))
+                        .style(|s| s.font_size(10.0).color(Color::rgb8(180, 60, 60))),
+                    small_button(
+                        "Dismiss",
+                        "plus",
+                        move |_| failure.set(None),
+                        dismiss_active,
+                    ),
+                ))
+                .style(|s| s.margin_bottom(7.0))
+                .into_any(),
+                None => empty().into_any(),
+            },
+        ),
+        dyn_container(
+            move || outbox_data.get().is_empty(),
+            move |outbox_empty| {
+                if outbox_empty {
+                    return empty().into_any();
+                }
+
+                let state_2 = state_2.clone();
+                let state_3 = state_3.clone();
+
+                v_stack((
+                    h_stack((
+                        label(move || format!("Pending ({})", outbox_data.get().len())),
+                        small_button(
+                            "Retry Now",
+                            "plus",
+                            move |_| {
+                                let state_helper = state_2.lock().unwrap();
+                                let project_id = state_helper.get_project_id();
+                                let auth_token = state_helper.auth_token.clone();
+
+                                spawn_outbox_flush(state_2.clone(), auth_token, project_id);
+                            },
+                            retry_active,
+                        ),
+                    ))
+                    .style(|s| s.margin_bottom(7.0)),
+                    dyn_stack(
+                        move || outbox_data.get(),
+                        move |entry| entry.id.clone(),
+                        move |entry| outbox_item(state_3.clone(), entry, outbox_data),
+                    )
+                    .style(|s| s.flex_col()),
+                ))
+                .style(|s| s.margin_bottom(7.0))
+                .into_any()
+            },
+        ),
+    ))
+    .style(|s| s.width(280.0).background(Color::rgba8(255, 255, 255, 230)))
+}
@@ -26,10 +26,13 @@ use midpoint_engine::floem::{GpuHelper, View, WindowHandle};
 use crate::editor_state::UIMessage;
 use crate::editor_state::{EditorState, StateHelper};
 use crate::gql::generateTexture::generate_texture;
-use crate::helpers::auth::read_auth_token;
-use crate::helpers::textures::save_texture;
 use crate::helpers::utilities::get_filename;
 
+use super::asset_browser::{asset_browser, AssetEntry};
+
+// name and Edit Joints
+const PART_CARD_HEIGHT: f64 = 50.0;
+
 pub fn part_item(
     part_id: String,
     label_text: String,
@@ -65,6 +68,7 @@ pub fn part_browser(
     selected_part_id_signal: RwSignal<String>,
 ) -> impl View {
     let state_2 = Arc::clone(&state_helper);
+    let state_3 = Arc::clone(&state_helper);
 
     let generate_field = create_rw_signal("".to_string());
     let generate_active = create_rw_signal(false);
@@ -178,27 +182,26 @@ pub fn part_browser(
             .disabled(move || generate_disabled.get()),
         ))
         .style(|s| s.margin_bottom(7.0)),
-        scroll(
-            dyn_stack(
-                move || part_data.get(),
-                move |part_data| part_data.id.clone(),
-                move |part_data_real| {
-                    let current_textures = part_data.get(); // Add this to ensure reactivity
-                    part_item(
-                        part_data_real.id,
-                        part_data_real.name,
-                        part_selected_signal,
-                        selected_part_id_signal,
-                    )
-                },
-            )
-            .into_view()
-            .style(|s| {
-                s.width(260.0)
-                    .flex_direction(FlexDirection::Row)
-                    .flex_wrap(FlexWrap::Wrap)
-                    .gap(10.0)
-            }),
+        asset_browser(
+            state_3,
+            "parts",
+            part_data,
+            PART_CARD_HEIGHT,
+            |part: &SkeletonPart| AssetEntry {
+                id: part.id.clone(),
+                name: part.name.clone(),
+                file_name: None,
+            },
+            move |part: SkeletonPart| {
+                part_item(
+                    part.id,
+                    part.name,
+                    part_selected_signal,
+                    selected_part_id_signal,
+                )
+                .into_any()
+            },
+            None,
         ),
     ))
     .style(|s| s.width(260.0))
@@ -0,0 +1,247 @@
+use std::sync::{Arc, Mutex};
+
+use midpoint_engine::floem::common::small_button;
+use midpoint_engine::floem::ext_event::create_signal_from_tokio_channel;
+use midpoint_engine::floem::peniko::Color;
+use midpoint_engine::floem::reactive::SignalGet;
+use midpoint_engine::floem::reactive::SignalUpdate;
+use midpoint_engine::floem::reactive::{create_effect, create_rw_signal, RwSignal};
+use midpoint_engine::floem::taffy::{FlexDirection, FlexWrap};
+use midpoint_engine::floem::views::{dyn_stack, empty, h_stack, label, text_input, v_stack};
+use midpoint_engine::floem::IntoView;
+
+use midpoint_engine::floem::views::Decorators;
+use midpoint_engine::floem::View;
+
+use crate::editor_state::{StateHelper, UIMessage};
+use crate::helpers::pipeline::{
+    choose_concept, create_job, dismiss_job, load_pipelines, spawn_pipeline, PipelineJob,
+    PipelineStage, PipelineStatus, Placement,
+};
+
+use super::generation_options::GenerationForm;
+use super::shared::dynamic_img;
+
+// blank coordinates mean the centre of the viewport
+fn parse_placement(x: &str, y: &str, z: &str) -> Placement {
+    match (
+        x.trim().parse::<f32>(),
+        y.trim().parse::<f32>(),
+        z.trim().parse::<f32>(),
+    ) {
+        (Ok(x), Ok(y), Ok(z)) => Placement::Position([x, y, z]),
+        _ => Placement::ViewportCentre,
+    }
+}
+
+fn concept_choice(
+    state_helper: Arc<Mutex<StateHelper>>,
+    project_id: String,
+    job_id: String,
+    file_name: String,
+    pipeline_data: RwSignal<Vec<PipelineJob>>,
+) -> impl View {
+    let use_active = create_rw_signal(false);
+
+    v_stack((
+        dynamic_img(
+            format!("midpoint/projects/{}/concepts", project_id),
+            file_name.clone(),
+            60.0,
+            60.0,
+        )
+        .style(|s| s.width(60.0).height(60.0).border_radius(5.0)),
+        small_button(
+            "Use",
+            "plus",
+            move |_| {
+                let jobs = choose_concept(&project_id, &job_id, &file_name);
+                pipeline_data.set(jobs);
+
+                spawn_pipeline(state_helper.clone(), project_id.clone(), job_id.clone());
+            },
+            use_active,
+        ),
+    ))
+    .style(|s| s.width(60.0).margin_right(5.0).margin_bottom(5.0))
+}
+
+pub fn pipeline_item(
+    state_helper: Arc<Mutex<StateHelper>>,
+    job: PipelineJob,
+    pipeline_data: RwSignal<Vec<PipelineJob>>,
+) -> impl View {
+    let retry_active = create_rw_signal(false);
+    let dismiss_active = create_rw_signal(false);
+
+    let project_id = state_helper.lock().unwrap().get_project_id();
+
+    let prompt_text = job.prompt.clone();
+    let status_text = job.status_text();
+    let failed = matches!(job.status, PipelineStatus::Failed(_));
+    let can_retry = failed
+        || (matches!(job.status, PipelineStatus::Waiting(_))
+            && matches!(job.stage, PipelineStage::Model | PipelineStage::Place));
+    let can_dismiss = failed || job.stage == PipelineStage::Done;
+
+    let choices = if job.stage == PipelineStage::Choose {
+        job.concept_files.clone()
+    } else {
+        Vec::new()
+    };
+
+    // picks up again at the stage that failed
+    let retry_button = if can_retry {
+        let state_helper = Arc::clone(&state_helper);
+        let project_id = project_id.clone();
+        let job_id = job.id.clone();
+
+        small_button(
+            "Retry",
+            "plus",
+            move |_| spawn_pipeline(state_helper.clone(), project_id.clone(), job_id.clone()),
+            retry_active,
+        )
+        .into_any()
+    } else {
+        empty().into_any()
+    };
+
+    let dismiss_button = if can_dismiss {
+        let project_id = project_id.clone();
+        let job_id = job.id.clone();
+
+        small_button(
+            "Dismiss",
+            "plus",
+            move |_| pipeline_data.set(dismiss_job(&project_id, &job_id)),
+            dismiss_active,
+        )
+        .into_any()
+    } else {
+        empty().into_any()
+    };
+
+    v_stack((
+        label(move || prompt_text.clone()),
+        label(move || status_text.clone()).style(move |s| {
+            let s = s.font_size(10.0);
+            if failed {
+                s.color(Color::rgb8(180, 60, 60))
+            } else {
+                s
+            }
+        }),
+        dyn_stack(
+            move || choices.clone(),
+            move |file_name| file_name.clone(),
+            move |file_name| {
+                concept_choice(
+                    state_helper.clone(),
+                    project_id.clone(),
+                    job.id.clone(),
+                    file_name,
+                    pipeline_data,
+                )
+            },
+        )
+        .style(|s| {
+            s.flex_direction(FlexDirection::Row)
+                .flex_wrap(FlexWrap::Wrap)
+        }),
+        h_stack((retry_button, dismiss_button)),
+    ))
+    .style(|s| s.width(260.0).margin_bottom(7.0))
+}
+
+/// Prompt to placed component in one go, using the generation options above it
+pub fn pipeline_panel(
+    state_helper: Arc<Mutex<StateHelper>>,
+    generation_form: GenerationForm,
+) -> impl View {
+    let state_2 = Arc::clone(&state_helper);
+    let state_3 = Arc::clone(&state_helper);
+
+    let prompt_field = create_rw_signal("".to_string());
+    let position_x = create_rw_signal("".to_string());
+    let position_y = create_rw_signal("".to_string());
+    let position_z = create_rw_signal("".to_string());
+    let start_active = create_rw_signal(false);
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let tx = Arc::new(tx);
+    let pipeline_data: RwSignal<Vec<PipelineJob>> = create_rw_signal(Vec::new());
+
+    let update_signal = create_signal_from_tokio_channel(rx);
+
+    create_effect(move |_| {
+        if let Some(msg) = update_signal.get() {
+            match msg {
+                UIMessage::UpdatePipelines(jobs) => pipeline_data.set(jobs),
+                _ => return,
+            }
+        }
+    });
+
+    create_effect({
+        let tx = tx.clone();
+        move |_| {
+            let tx = tx.clone();
+            let mut state_helper = state_helper.lock().unwrap();
+
+            state_helper.register_file_signal("pipeline".to_string(), tx);
+
+            let project_id = state_helper.get_project_id();
+
+            pipeline_data.set(load_pipelines(&project_id));
+        }
+    });
+
+    v_stack((
+        label(|| "Concept to Scene"),
+        text_input(prompt_field)
+            .placeholder("Ex. Wooden Barrel".to_string())
+            .style(|s| s.width(260.0).margin_bottom(5.0)),
+        h_stack((
+            text_input(position_x)
+                .placeholder("X (centre)".to_string())
+                .style(|s| s.width(80.0).margin_right(5.0)),
+            text_input(position_y)
+                .placeholder("Y".to_string())
+                .style(|s| s.width(80.0).margin_right(5.0)),
+            text_input(position_z)
+                .placeholder("Z".to_string())
+                .style(|s| s.width(80.0)),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        small_button(
+            "Generate and Place",
+            "plus",
+            move |_| {
+                let prompt = prompt_field.get();
+                if prompt.trim().is_empty() {
+                    return;
+                }
+
+                let placement =
+                    parse_placement(&position_x.get(), &position_y.get(), &position_z.get());
+                let project_id = state_2.lock().unwrap().get_project_id();
+
+                let job = create_job(&project_id, prompt, generation_form.options(), placement);
+                pipeline_data.set(load_pipelines(&project_id));
+
+                spawn_pipeline(state_2.clone(), project_id, job.id);
+            },
+            start_active,
+        )
+        .style(|s| s.margin_bottom(7.0)),
+        dyn_stack(
+            move || pipeline_data.get(),
+            // keyed on progress too, so an item redraws as its job moves along
+            move |job| format!("{}-{}", job.id, job.status_text()),
+            move |job| pipeline_item(state_3.clone(), job, pipeline_data),
+        )
+        .style(|s| s.flex_col()),
+    ))
+    .style(|s| s.margin_bottom(7.0))
+}
@@ -32,7 +32,11 @@ use midpoint_engine::floem::IntoView;
 use midpoint_engine::floem::{GpuHelper, View, WindowHandle};
 
 use crate::editor_state::StateHelper;
+use crate::helpers::downloads::queue_missing_downloads;
+use crate::helpers::outbox::spawn_outbox_flush;
+use crate::helpers::pipeline::resume_pipelines;
 use crate::helpers::projects::{get_projects, ProjectInfo};
+use crate::helpers::watcher::spawn_project_watcher;
 use crate::helpers::websocket::WebSocketManager;
 
 pub fn project_item(
@@ -72,6 +76,70 @@ pub fn project_item(
     // })
 }
 
+/// Loads the project's saved state into the editor and starts its background work
+pub fn open_project(
+    state_helper: Arc<Mutex<StateHelper>>,
+    gpu_helper: Arc<Mutex<GpuHelper>>,
+    manager: Arc<WebSocketManager>,
+    project_id: String,
+) {
+    // join the WebSocket group for this project
+    manager.join_group(); // locks and drops the state_helper
+
+    let state_2 = state_helper.clone();
+    let mut state_helper = state_helper.lock().unwrap();
+
+    // retrieve saved state of project and set on helper
+    let saved_state = load_project_state(&project_id).expect("Couldn't get project saved state");
+    queue_missing_downloads(&saved_state);
+    let saved_state = Arc::new(Mutex::new(saved_state));
+    state_helper.saved_state = Some(saved_state.clone());
+
+    // update the UI signal
+    let project_selected = state_helper
+        .project_selected_signal
+        .expect("Couldn't get project selection signal");
+    let uuid = Uuid::from_str(&project_id.clone()).expect("Couldn't convert project name to id");
+    project_selected.set(uuid.clone());
+
+    // update renderer_state with project_selected (and current_view if necessary)
+    let mut renderer_state = state_helper
+        .renderer_state
+        .as_mut()
+        .expect("Couldn't find RendererState")
+        .lock()
+        .unwrap();
+    renderer_state.project_selected = Some(uuid.clone());
+    renderer_state.current_view = "scene".to_string();
+
+    drop(renderer_state);
+
+    // restore the saved state to the rendererstate
+    restore_renderer_from_saved(
+        gpu_helper.clone(),
+        uuid.clone().to_string(),
+        saved_state.clone(),
+        state_helper
+            .renderer_state
+            .as_ref()
+            .cloned()
+            .expect("Couldn't get RendererState"),
+    );
+
+    // send any generation requests left over from an offline session
+    spawn_outbox_flush(
+        state_2.clone(),
+        state_helper.auth_token.clone(),
+        project_id.clone(),
+    );
+
+    // pick up concept-to-scene jobs that were waiting on uploads
+    resume_pipelines(state_2.clone(), project_id.clone());
+
+    // notice files other programs drop into the project folder
+    spawn_project_watcher(state_2.clone(), project_id.clone());
+}
+
 pub fn project_browser(
     state_helper: Arc<Mutex<StateHelper>>,
     gpu_helper: Arc<Mutex<GpuHelper>>,
@@ -126,47 +194,11 @@ pub fn project_browser(
 
                             loading_project.set(true);
 
-                            // join the WebSocket group for this project
-                            manager.join_group(); // locks and drops the state_helper
-
-                            let mut state_helper = state_helper.lock().unwrap();
-
-                            // retrieve saved state of project and set on helper
-                            let saved_state = load_project_state(&project.name)
-                                .expect("Couldn't get project saved state");
-                            let saved_state = Arc::new(Mutex::new(saved_state));
-                            state_helper.saved_state = Some(saved_state.clone());
-
-                            // update the UI signal
-                            let project_selected = state_helper
-                                .project_selected_signal
-                                .expect("Couldn't get project selection signal");
-                            let uuid = Uuid::from_str(&project.name.clone())
-                                .expect("Couldn't convert project name to id");
-                            project_selected.set(uuid.clone());
-
-                            // update renderer_state with project_selected (and current_view if necessary)
-                            let mut renderer_state = state_helper
-                                .renderer_state
-                                .as_mut()
-                                .expect("Couldn't find RendererState")
-                                .lock()
-                                .unwrap();
-                            renderer_state.project_selected = Some(uuid.clone());
-                            renderer_state.current_view = "scene".to_string();
-
-                            drop(renderer_state);
-
-                            // restore the saved state to the rendererstate
-                            restore_renderer_from_saved(
+                            open_project(
+                                state_helper.clone(),
                                 gpu_2.clone(),
-                                uuid.clone().to_string(),
-                                saved_state.clone(),
-                                state_helper
-                                    .renderer_state
-                                    .as_ref()
-                                    .cloned()
-                                    .expect("Couldn't get RendererState"),
+                                manager.clone(),
+                                project.name.clone(),
                             );
 
                             println!("Project selected {:?}", project.name.clone());
@@ -1,18 +1,70 @@
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::{Arc, Mutex};
 
-use midpoint_engine::floem::common::card_styles;
-use midpoint_engine::floem::views::{container, dyn_container, empty, label, v_stack};
 use midpoint_engine::core::Viewport::Viewport;
-use wgpu::util::DeviceExt;
+use midpoint_engine::floem::common::card_styles;
+use midpoint_engine::floem::peniko::Color;
+use midpoint_engine::floem::reactive::{create_rw_signal, SignalGet, SignalUpdate};
+use midpoint_engine::floem::taffy::{FlexDirection, FlexWrap};
+use midpoint_engine::floem::views::{dyn_stack, label, v_stack};
 
 use midpoint_engine::floem::views::Decorators;
-use midpoint_engine::floem::{GpuHelper, View, WindowHandle};
+use midpoint_engine::floem::{GpuHelper, View};
+
+use crate::editor_state::StateHelper;
+use crate::helpers::project_settings::{
+    load_project_settings, save_project_settings, ProviderKind,
+};
+
+use super::usage::usage_panel;
 
 pub fn project_settings(
+    state_helper: Arc<Mutex<StateHelper>>,
     gpu_helper: Arc<Mutex<GpuHelper>>,
     viewport: Arc<Mutex<Viewport>>,
 ) -> impl View {
-    v_stack(((label(|| "Project Settings"),)))
-        .style(|s| card_styles(s))
-        .style(|s| s.width(300.0))
+    let project_id = state_helper.lock().unwrap().get_project_id();
+    let settings = create_rw_signal(load_project_settings(&project_id));
+
+    v_stack((
+        label(|| "Project Settings"),
+        label(|| "Generation Provider").style(|s| s.font_size(10.0).margin_top(7.0)),
+        dyn_stack(
+            move || ProviderKind::ALL.to_vec(),
+            move |kind| *kind,
+            move |kind| {
+                let project_id = project_id.clone();
+
+                label(move || kind.label())
+                    .on_click_stop(move |_| {
+                        // reloaded so limits saved from the usage panel are kept
+                        let mut current = load_project_settings(&project_id);
+                        current.generation_provider = kind;
+                        save_project_settings(&project_id, &current);
+                        settings.set(current);
+                    })
+                    .style(move |s| {
+                        let s = s
+                            .font_size(10.0)
+                            .padding_horiz(4.0)
+                            .padding_vert(2.0)
+                            .margin_right(3.0)
+                            .border_radius(3.0);
+
+                        if settings.get().generation_provider == kind {
+                            s.color(Color::WHITE).background(Color::rgb8(70, 150, 230))
+                        } else {
+                            s.background(Color::rgb8(220, 220, 220))
+                        }
+                    })
+            },
+        )
+        .style(|s| {
+            s.flex_direction(FlexDirection::Row)
+                .flex_wrap(FlexWrap::Wrap)
+                .margin_top(3.0)
+        }),
+        usage_panel(state_helper),
+    ))
+    .style(|s| card_styles(s))
+    .style(|s| s.width(300.0))
 }
@@ -29,12 +29,15 @@ use midpoint_engine::floem::IntoView;
 
 use crate::editor_state::EditorState;
 use crate::editor_state::StateHelper;
+use crate::editor_state::UIMessage;
+use crate::helpers::collaboration::{remove_from_renderer, SceneOperation, TransformProperty};
 use crate::helpers::landscapes::save_landscape_texture;
 use crate::helpers::utilities::parse_string_to_float;
 
 use super::inputs::create_dropdown;
 use super::inputs::styled_input;
 use super::inputs::DropdownOption;
+use super::landscape_masks::landscape_masks_view;
 
 pub fn update_position(
     // state_helper: Arc<Mutex<StateHelper>>,
@@ -172,6 +175,127 @@ pub fn update_position(
 
     // Save the updated state
     state_helper.save_saved_state(project_id, saved_state);
+
+    state_helper.broadcast_scene_operation(SceneOperation::Transform {
+        component_id: selected_component.id.clone(),
+        property: TransformProperty::Position,
+        value: new_position,
+    });
+}
+
+pub fn update_name(
+    mut state_helper: MutexGuard<StateHelper>,
+    selected_object_data: RwSignal<ComponentData>,
+    value: String,
+) {
+    let name = value.trim().to_string();
+
+    if name.is_empty() {
+        println!("invalid name");
+        return;
+    }
+
+    let mut new_data = selected_object_data.get();
+    new_data.generic_properties.name = name.clone();
+    selected_object_data.set(new_data.clone());
+
+    let mut saved_state = state_helper
+        .saved_state
+        .as_mut()
+        .expect("Couldn't get saved state")
+        .lock()
+        .unwrap();
+
+    let components = saved_state
+        .levels
+        .as_mut()
+        .expect("Couldn't get level")
+        .get_mut(0)
+        .expect("Couldn't get first level")
+        .components
+        .get_or_insert_with(Vec::new);
+
+    if let Some(component) = components.iter_mut().find(|c| c.id == new_data.id) {
+        component.generic_properties.name = name.clone();
+    }
+
+    let components = components.clone();
+
+    drop(saved_state);
+
+    state_helper.save_current_saved_state();
+
+    if let Some(tx) = state_helper
+        .file_signals
+        .lock()
+        .unwrap()
+        .get("component_browser")
+    {
+        let _ = tx.send(UIMessage::UpdateComponents(components));
+    }
+
+    state_helper.broadcast_scene_operation(SceneOperation::Rename {
+        component_id: new_data.id.clone(),
+        name,
+    });
+}
+
+pub fn remove_component(
+    mut state_helper: MutexGuard<StateHelper>,
+    object_selected_signal: RwSignal<bool>,
+    selected_object_id_signal: RwSignal<Uuid>,
+) {
+    let component_id = selected_object_id_signal.get().to_string();
+
+    let mut saved_state = state_helper
+        .saved_state
+        .as_mut()
+        .expect("Couldn't get saved state")
+        .lock()
+        .unwrap();
+
+    let components = saved_state
+        .levels
+        .as_mut()
+        .expect("Couldn't get level")
+        .get_mut(0)
+        .expect("Couldn't get first level")
+        .components
+        .get_or_insert_with(Vec::new);
+
+    components.retain(|c| c.id != component_id);
+
+    let components = components.clone();
+
+    drop(saved_state);
+
+    state_helper.save_current_saved_state();
+
+    let mut renderer_state = state_helper
+        .renderer_state
+        .as_mut()
+        .expect("Couldn't get RendererState")
+        .lock()
+        .unwrap();
+    remove_from_renderer(&mut renderer_state, &component_id);
+    renderer_state.object_selected = None;
+    renderer_state.object_selected_data = None;
+
+    drop(renderer_state);
+
+    object_selected_signal.set(false);
+    selected_object_id_signal.set(Uuid::nil());
+
+    if let Some(tx) = state_helper
+        .file_signals
+        .lock()
+        .unwrap()
+        .get("component_browser")
+    {
+        let _ = tx.send(UIMessage::UpdateComponents(components));
+    }
+
+    state_helper.broadcast_scene_operation(SceneOperation::Remove { component_id });
 }
 
 pub fn properties_view(
@@ -193,9 +317,11 @@ pub fn properties_view(
     let state_8 = Arc::clone(&state_helper);
     let state_9 = Arc::clone(&state_helper);
     let state_10 = Arc::clone(&state_helper);
+    let state_11 = Arc::clone(&state_helper);
 
     let gpu_2 = Arc::clone(&gpu_helper);
     let gpu_3 = Arc::clone(&gpu_helper);
+    let gpu_4 = Arc::clone(&gpu_helper);
 
     let aside_width = 260.0;
     let quarters = (aside_width / 4.0) + (5.0 * 4.0);
@@ -203,6 +329,7 @@ pub fn properties_view(
     let halfs = (aside_width / 2.0) + (5.0 * 2.0);
 
     let back_active = RwSignal::new(false);
+    let remove_active = RwSignal::new(false);
     let texture_options: RwSignal<Vec<DropdownOption>> = create_rw_signal(Vec::new());
     let initial_rockmap = create_rw_signal("".to_string());
     let initial_soil = create_rw_signal("".to_string());
@@ -523,6 +650,7 @@ pub fn properties_view(
                     }
                 },
             ),
+            landscape_masks_view(state_11, gpu_4, selected_object_data.get()),
         ))
         .style(move |s| s.width(aside_width))
         .into_any()
@@ -564,6 +692,24 @@ pub fn properties_view(
             label(|| "Properties").style(|s| s.font_size(24.0).font_weight(Weight::THIN)),
         ))
         .style(|s| s.margin_bottom(12.0)),
+        styled_input(
+            "Name:".to_string(),
+            &selected_object_data
+                .read()
+                .borrow()
+                .generic_properties
+                .name
+                .clone(),
+            "Component Name",
+            Box::new({
+                move |mut state_helper_passed, value| {
+                    update_name(state_helper_passed, selected_object_data, value)
+                }
+            }),
+            state_9,
+            "name".to_string(),
+        )
+        .style(move |s| s.width(aside_width).margin_bottom(5.0)),
         h_stack((
             styled_input(
                 "X:".to_string(),
@@ -642,6 +788,20 @@ pub fn properties_view(
         ))
         .style(move |s| s.width(aside_width)),
         landscape_property_list,
+        small_button(
+            "Remove from Scene",
+            "plus",
+            move |_| {
+                let state_helper = state_10.lock().unwrap();
+                remove_component(
+                    state_helper,
+                    object_selected_signal,
+                    selected_object_id_signal,
+                );
+            },
+            remove_active,
+        )
+        .style(|s| s.margin_top(12.0)),
     ))
     .style(|s| card_styles(s))
     .style(|s| {
@@ -0,0 +1,161 @@
+use std::sync::{Arc, Mutex};
+
+use midpoint_engine::floem::peniko::Color;
+use midpoint_engine::floem::reactive::SignalGet;
+use midpoint_engine::floem::reactive::SignalUpdate;
+use midpoint_engine::floem::reactive::{create_effect, create_rw_signal, RwSignal};
+use midpoint_engine::floem::views::{dyn_container, dyn_stack, empty, label, text_input, v_stack};
+use midpoint_engine::floem::IntoView;
+
+use midpoint_engine::floem::views::Decorators;
+use midpoint_engine::floem::View;
+
+use crate::editor_state::StateHelper;
+use crate::helpers::generation::{find_generation, search_generations, GenerationRecord};
+
+// keep the search list short, it reads the whole record file on every keystroke
+const MAX_SEARCH_RESULTS: usize = 30;
+
+/// A readable summary of where a generated file came from
+pub fn provenance_summary(record: &GenerationRecord) -> String {
+    let options = &record.options;
+    let mut lines = vec![format!("Prompt: {}", record.prompt)];
+
+    if let Some(negative_prompt) = options.negative_prompt.as_ref() {
+        lines.push(format!("Negative prompt: {}", negative_prompt));
+    }
+    if let Some(seed) = options.seed {
+        lines.push(format!("Seed: {}", seed));
+    }
+    if let Some(resolution) = options.resolution {
+        lines.push(format!("Resolution: {}", resolution));
+    }
+    if let Some(aspect_ratio) = options.aspect_ratio.as_ref() {
+        lines.push(format!("Aspect ratio: {}", aspect_ratio));
+    }
+    if let Some(style_preset) = options.style_preset.as_ref() {
+        lines.push(format!("Style: {}", style_preset));
+    }
+    if options.source_image_url.is_some() {
+        lines.push("Variation of another concept".to_string());
+    }
+    if let Some(source_concept) = record.source_concept.as_ref() {
+        lines.push(format!("Source concept: {}", source_concept));
+    }
+    if let Some(requested) = record.requested.as_ref() {
+        lines.push(format!("Requested: {}", requested));
+    }
+    lines.push(format!("Created: {}", record.created));
+    if let Some(backend_version) = record.backend_version.as_ref() {
+        lines.push(format!("Backend: {}", backend_version));
+    }
+    if let Some(editor_version) = record.editor_version.as_ref() {
+        lines.push(format!("Editor: {}", editor_version));
+    }
+
+    lines.join("\n")
+}
+
+/// Shows the prompt and parameters behind one concept, texture or model
+pub fn provenance_inspector(
+    state_helper: Arc<Mutex<StateHelper>>,
+    kind_dir: &str,
+    file_name: &str,
+) -> impl View {
+    let provenance_text = {
+        let state_helper = state_helper.lock().unwrap();
+        let project_id = state_helper.get_project_id();
+
+        find_generation(&project_id, kind_dir, file_name)
+            .map(|record| provenance_summary(&record))
+            .unwrap_or_else(|| "No generation details recorded".to_string())
+    };
+
+    label(move || provenance_text.clone()).style(|s| s.font_size(10.0).margin_top(7.0))
+}
+
+fn search_result(
+    kind_dir: String,
+    file_name: String,
+    record: GenerationRecord,
+    selected_result: RwSignal<Option<String>>,
+) -> impl View {
+    let result_key = format!("{}/{}", kind_dir, file_name);
+    let prompt_text = record.prompt.clone();
+    let summary_text = provenance_summary(&record);
+
+    v_stack((
+        label(move || format!("{}: {}", kind_dir, file_name)),
+        label(move || prompt_text.clone()).style(|s| s.font_size(10.0)),
+        dyn_container(
+            {
+                let result_key = result_key.clone();
+                move || selected_result.get() == Some(result_key.clone())
+            },
+            move |is_selected| {
+                if is_selected {
+                    let summary_text = summary_text.clone();
+                    label(move || summary_text.clone())
+                        .style(|s| s.font_size(10.0).margin_top(3.0))
+                        .into_any()
+                } else {
+                    empty().into_any()
+                }
+            },
+        ),
+    ))
+    .on_click_stop(move |_| {
+        // clicking the open result closes it again
+        if selected_result.get_untracked() == Some(result_key.clone()) {
+            selected_result.set(None);
+        } else {
+            selected_result.set(Some(result_key.clone()));
+        }
+    })
+    .style(|s| {
+        s.width(260.0)
+            .padding(5.0)
+            .margin_bottom(5.0)
+            .border_radius(5.0)
+            .background(Color::rgb8(240, 240, 240))
+    })
+}
+
+/// Finds generated assets by prompt, negative prompt, style, source concept or file name
+pub fn provenance_search(state_helper: Arc<Mutex<StateHelper>>) -> impl View {
+    let search_field = create_rw_signal("".to_string());
+    let selected_result: RwSignal<Option<String>> = create_rw_signal(None);
+    let results: RwSignal<Vec<(String, String, GenerationRecord)>> = create_rw_signal(Vec::new());
+
+    create_effect(move |_| {
+        let query = search_field.get();
+
+        if query.trim().is_empty() {
+            results.set(Vec::new());
+            return;
+        }
+
+        let state_helper = state_helper.lock().unwrap();
+        let project_id = state_helper.get_project_id();
+
+        let mut matches = search_generations(&project_id, &query);
+        matches.truncate(MAX_SEARCH_RESULTS);
+        results.set(matches);
+    });
+
+    v_stack((
+        text_input(search_field)
+            .placeholder("Search by prompt".to_string())
+            .style(|s| s.width(260.0).margin_bottom(5.0)),
+        dyn_stack(
+            move || results.get(),
+            move |(kind_dir, file_name, _)| format!("{}/{}", kind_dir, file_name),
+            move |(kind_dir, file_name, record)| {
+                search_result(kind_dir, file_name, record, selected_result)
+            },
+        )
+        .into_view()
+        .style(|s| s.flex_col()),
+    ))
+    .style(|s| s.margin_bottom(7.0))
+}
@@ -24,6 +24,7 @@ use midpoint_engine::floem::{GpuHelper, View, WindowHandle};
 use crate::editor_state::StateHelper;
 
 use super::component_browser::component_browser;
+use super::external_changes::external_changes_panel;
 use super::landscape_browser::landscape_browser;
 use super::level_browser::level_browser;
 use super::model_browser::model_browser;
@@ -35,6 +36,7 @@ pub fn scene_view(
     viewport: Arc<Mutex<Viewport>>,
 ) -> impl View {
     let state_2 = Arc::clone(&state_helper);
+    let state_3 = Arc::clone(&state_helper);
 
     let tabs: im::Vector<&str> = vec!["Levels", "Components", "Models", "Landscapes", "Textures"]
         .into_iter()
@@ -128,6 +130,7 @@ pub fn scene_view(
     .style(|s| s.height(55.0).width(260.0));
 
     v_stack((
+        external_changes_panel(state_3),
         list, // tab list
         tab(
             // active tab
@@ -1,14 +1,14 @@
 use image::DynamicImage;
-use image::GenericImageView;
+use midpoint_engine::floem::ext_event::create_signal_from_tokio_channel;
 use midpoint_engine::floem::peniko::Color;
 use midpoint_engine::floem::reactive::create_effect;
 use midpoint_engine::floem::reactive::SignalGet;
 use midpoint_engine::floem::reactive::SignalUpdate;
 use midpoint_engine::floem::views::dyn_container;
-use midpoint_engine::floem::views::empty;
 use midpoint_engine::floem::views::img;
 use midpoint_engine::floem::views::img_dynamic; // Note: using img_dynamic instead of img
 use midpoint_engine::floem::views::Decorators;
+use midpoint_engine::floem::views::{container, label};
 use midpoint_engine::floem::{
     reactive::{create_rw_signal, RwSignal},
     IntoView,
@@ -16,25 +16,81 @@ use midpoint_engine::floem::{
 use std::fs;
 use std::rc::Rc;
 
-use crate::helpers::utilities::get_common_os_dir;
+use crate::helpers::downloads::{get_asset_path, get_download_status, watch_asset, DownloadStatus};
+use crate::helpers::thumbnails::request_thumbnail;
+
+/// Tracks whether an asset is on disk yet, following its download if there is one
+pub fn asset_status_signal(image_path: String, filename: String) -> RwSignal<DownloadStatus> {
+    let asset_path = get_asset_path(&image_path, &filename);
+    let status = create_rw_signal(get_download_status(&asset_path));
+
+    if status.get_untracked() != DownloadStatus::Ready {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let status_update_signal = create_signal_from_tokio_channel(rx);
+
+        watch_asset(asset_path.clone(), tx);
+
+        // the download may have finished before we started watching
+        if asset_path.exists() {
+            status.set(DownloadStatus::Ready);
+        }
+
+        create_effect(move |_| {
+            if let Some(new_status) = status_update_signal.get() {
+                status.set(new_status);
+            }
+        });
+    }
+
+    status
+}
+
+pub fn download_status_text(status: &DownloadStatus) -> String {
+    match status {
+        DownloadStatus::Missing => "Missing".to_string(),
+        DownloadStatus::Queued => "Waiting to download...".to_string(),
+        DownloadStatus::Progress {
+            received,
+            total: Some(total),
+        } if *total > 0 => format!("Downloading {}%", received * 100 / total),
+        DownloadStatus::Progress { received, .. } => {
+            format!("Downloading {} KB", received / 1024)
+        }
+        DownloadStatus::Ready => "".to_string(),
+        DownloadStatus::Failed(_) => "Download failed".to_string(),
+    }
+}
 
 pub fn dynamic_img(image_path: String, filename: String, width: f32, height: f32) -> impl IntoView {
     let image_signal: RwSignal<Option<Rc<DynamicImage>>> = create_rw_signal(None); // fix?
+    let thumbnail_failed = create_rw_signal(false);
+    let status = asset_status_signal(image_path.clone(), filename.clone());
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let thumbnail_signal = create_signal_from_tokio_channel(rx);
+
+    // twice the display size, so thumbnails stay sharp on high density screens
+    let thumbnail_size = (width.max(height) * 2.0).ceil() as u32;
 
     create_effect(move |_| {
-        let sync_dir = get_common_os_dir().expect("Couldn't get CommonOS directory");
-        let target_dir = sync_dir.join(&image_path);
-        let target_file = target_dir.join(&filename);
-
-        match image::open(&target_file) {
-            Ok(img) => {
-                println!("Loaded image dimensions: {:?}", img.dimensions());
-                image_signal.set(Some(Rc::new(img)));
-            }
-            Err(e) => {
-                println!("Error loading image: {}", e);
-                image_signal.set(None);
-            }
+        // reload once the file lands in the project folder
+        if status.get() != DownloadStatus::Ready {
+            image_signal.set(None);
+            return;
+        }
+
+        thumbnail_failed.set(false);
+        request_thumbnail(
+            get_asset_path(&image_path, &filename),
+            thumbnail_size,
+            tx.clone(),
+        );
+    });
+
+    create_effect(move |_| {
+        if let Some(thumbnail) = thumbnail_signal.get() {
+            thumbnail_failed.set(thumbnail.is_none());
+            image_signal.set(thumbnail.map(Rc::new));
         }
     });
 
@@ -61,7 +117,23 @@ pub fn dynamic_img(image_path: String, filename: String, width: f32, height: f32
                     )
                     .into_any()
             } else {
-                empty().into_any()
+                // placeholder until the image is downloaded and its thumbnail is ready
+                container(label(move || match status.get() {
+                    DownloadStatus::Ready if thumbnail_failed.get() => {
+                        "Couldn't read image".to_string()
+                    }
+                    DownloadStatus::Ready => "Loading...".to_string(),
+                    status => download_status_text(&status),
+                }))
+                .style(move |s| {
+                    s.width(width)
+                        .height(height)
+                        .items_center()
+                        .justify_center()
+                        .background(Color::rgb8(200, 200, 200))
+                        .border_radius(5.0)
+                })
+                .into_any()
             }
         },
     )
@@ -0,0 +1,144 @@
+use std::sync::{Arc, Mutex};
+
+use midpoint_engine::floem::common::{card_styles, small_button};
+use midpoint_engine::floem::ext_event::create_signal_from_tokio_channel;
+use midpoint_engine::floem::peniko::Color;
+use midpoint_engine::floem::reactive::SignalGet;
+use midpoint_engine::floem::reactive::SignalUpdate;
+use midpoint_engine::floem::reactive::{create_effect, create_rw_signal, RwSignal};
+use midpoint_engine::floem::views::{container, label, text_input, v_stack};
+
+use midpoint_engine::floem::views::Decorators;
+use midpoint_engine::floem::View;
+
+use crate::editor_state::{StateHelper, UIMessage};
+use crate::helpers::auth::{sign_in_with_password, AuthStatus};
+
+const MASK: char = '•';
+
+/// A text input that shows bullets while `password` holds what was typed,
+/// editing at the end of the field the way passwords are entered
+fn password_input(password: RwSignal<String>, placeholder: &str) -> impl View {
+    let masked = create_rw_signal("".to_string());
+
+    create_effect(move |_| {
+        let shown = masked.get();
+        let kept = shown.chars().take_while(|c| *c == MASK).count();
+        let typed: String = shown.chars().filter(|c| *c != MASK).collect();
+
+        let current = password.get_untracked();
+        let updated: String = current.chars().take(kept).chain(typed.chars()).collect();
+        if updated != current {
+            password.set(updated.clone());
+        }
+
+        let remasked: String = std::iter::repeat(MASK)
+            .take(updated.chars().count())
+            .collect();
+        if remasked != shown {
+            masked.set(remasked);
+        }
+    });
+
+    // cleared from outside, like after signing in
+    create_effect(move |_| {
+        if password.get().is_empty() && !masked.get_untracked().is_empty() {
+            masked.set("".to_string());
+        }
+    });
+
+    text_input(masked).placeholder(placeholder.to_string())
+}
+
+fn status_message(status: AuthStatus) -> &'static str {
+    match status {
+        AuthStatus::SignedIn => "Signed in",
+        AuthStatus::Missing => "Sign in to generate assets and sync your projects",
+        AuthStatus::Expired => "Your session has expired, please sign in again",
+        AuthStatus::Rejected => "The server rejected your session, please sign in again",
+    }
+}
+
+pub fn sign_in_dialog(
+    state_helper: Arc<Mutex<StateHelper>>,
+    auth_status: RwSignal<AuthStatus>,
+) -> impl View {
+    let email_field = create_rw_signal("".to_string());
+    let password_field = create_rw_signal("".to_string());
+    let error_message = create_rw_signal("".to_string());
+    let sign_in_active = create_rw_signal(false);
+    let sign_in_disabled = create_rw_signal(false);
+
+    let (error_tx, error_rx) = tokio::sync::mpsc::unbounded_channel();
+    let error_update_signal = create_signal_from_tokio_channel(error_rx);
+
+    create_effect(move |_| {
+        if let Some(error) = error_update_signal.get() {
+            error_message.set(error);
+            sign_in_disabled.set(false);
+        }
+    });
+
+    container(
+        v_stack((
+            label(|| "Sign In").style(|s| s.font_size(18.0).margin_bottom(7.0)),
+            label(move || status_message(auth_status.get()).to_string())
+                .style(|s| s.margin_bottom(7.0)),
+            text_input(email_field)
+                .placeholder("Email".to_string())
+                .style(|s| s.width(260.0).margin_bottom(7.0)),
+            password_input(password_field, "Password").style(|s| s.width(260.0).margin_bottom(7.0)),
+            label(move || error_message.get())
+                .style(|s| s.color(Color::rgb8(180, 60, 60)).margin_bottom(7.0)),
+            small_button(
+                "Sign In",
+                "plus",
+                move |_| {
+                    let state_helper = state_helper.lock().unwrap();
+                    let auth_signal = state_helper
+                        .file_signals
+                        .lock()
+                        .unwrap()
+                        .get("auth")
+                        .cloned()
+                        .expect("Couldn't get auth signal");
+                    let error_tx = error_tx.clone();
+
+                    let email = email_field.get();
+                    let password = password_field.get();
+
+                    sign_in_disabled.set(true);
+                    error_message.set("".to_string());
+
+                    tokio::runtime::Handle::current().spawn(async move {
+                        match sign_in_with_password(email, password).await {
+                            Ok(stored) => {
+                                auth_signal
+                                    .send(UIMessage::AuthUpdated(stored.token))
+                                    .unwrap();
+                            }
+                            Err(e) => {
+                                println!("Sign in failed: {}", e);
+                                error_tx
+                                    .send("Couldn't sign in, check your details".to_string())
+                                    .unwrap();
+                            }
+                        }
+                    });
+                },
+                sign_in_active,
+            )
+            .disabled(move || sign_in_disabled.get()),
+        ))
+        .style(|s| card_styles(s))
+        .style(|s| s.width(300.0)),
+    )
+    .style(|s| {
+        s.absolute()
+            .width_full()
+            .height_full()
+            .items_center()
+            .justify_center()
+            .background(Color::rgba8(0, 0, 0, 120))
+    })
+}
@@ -26,10 +26,13 @@ use midpoint_engine::floem::{GpuHelper, View, WindowHandle};
 use crate::editor_state::UIMessage;
 use crate::editor_state::{EditorState, StateHelper};
 use crate::gql::generateTexture::generate_texture;
-use crate::helpers::auth::read_auth_token;
-use crate::helpers::textures::save_texture;
 use crate::helpers::utilities::get_filename;
 
+use super::asset_browser::{asset_browser, AssetEntry};
+
+// name and Edit Assembly
+const SKELETON_CARD_HEIGHT: f64 = 50.0;
+
 pub fn skeleton_item(label_text: String) -> impl View {
     let active_btn = create_rw_signal(false);
 
@@ -52,6 +55,7 @@ pub fn skeleton_browser(
     selected_skeleton_id_signal: RwSignal<String>,
 ) -> impl View {
     let state_2 = Arc::clone(&state_helper);
+    let state_3 = Arc::clone(&state_helper);
 
     let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
     let tx = Arc::new(tx);
@@ -90,22 +94,18 @@ pub fn skeleton_browser(
         }
     });
 
-    v_stack((scroll(
-        dyn_stack(
-            move || skeleton_data.get(),
-            move |skeleton_data| skeleton_data.id.clone(),
-            move |skeleton_data_real| {
-                let current_textures = skeleton_data.get(); // Add this to ensure reactivity
-                skeleton_item(skeleton_data_real.name)
-            },
-        )
-        .into_view()
-        .style(|s| {
-            s.width(260.0)
-                .flex_direction(FlexDirection::Row)
-                .flex_wrap(FlexWrap::Wrap)
-                .gap(10.0)
-        }),
+    v_stack((asset_browser(
+        state_3,
+        "skeletons",
+        skeleton_data,
+        SKELETON_CARD_HEIGHT,
+        |skeleton: &SkeletonAssemblyConfig| AssetEntry {
+            id: skeleton.id.clone(),
+            name: skeleton.name.clone(),
+            file_name: None,
+        },
+        |skeleton: SkeletonAssemblyConfig| skeleton_item(skeleton.name).into_any(),
+        None,
     ),))
     .style(|s| s.width(260.0))
 }
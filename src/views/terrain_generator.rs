@@ -0,0 +1,215 @@
+use std::sync::{Arc, Mutex};
+
+use midpoint_engine::floem::common::small_button;
+use midpoint_engine::floem::ext_event::create_signal_from_tokio_channel;
+use midpoint_engine::floem::reactive::{
+    create_effect, create_rw_signal, RwSignal, SignalGet, SignalUpdate,
+};
+use midpoint_engine::floem::views::{dyn_container, empty, h_stack, label, v_stack};
+use midpoint_engine::floem::IntoView;
+
+use midpoint_engine::floem::views::Decorators;
+use midpoint_engine::floem::View;
+use nalgebra as na;
+
+use crate::editor_state::{StateHelper, UIMessage};
+use crate::helpers::generation::random_seed;
+use crate::helpers::landscapes::LANDSCAPE_SIZES;
+use crate::helpers::terrain::{
+    generate_terrain, save_terrain, NoiseLayer, TerrainParams, NOISE_KINDS, TERRAIN_PREVIEW_SIZE,
+};
+
+use super::generation_options::{choice_row, numeric_field};
+use super::topographic_map::topographic_preview;
+
+const PREVIEW_WIDTH: f64 = 240.0;
+
+/// The fields of one noise layer, blank ones fall back to the layer's defaults
+#[derive(Clone, Copy)]
+struct LayerForm {
+    kind: RwSignal<String>,
+    octaves: RwSignal<String>,
+    frequency: RwSignal<String>,
+    amplitude: RwSignal<String>,
+}
+
+impl LayerForm {
+    fn new(defaults: &NoiseLayer) -> LayerForm {
+        LayerForm {
+            kind: create_rw_signal(defaults.kind.clone()),
+            octaves: create_rw_signal("".to_string()),
+            frequency: create_rw_signal("".to_string()),
+            amplitude: create_rw_signal("".to_string()),
+        }
+    }
+
+    fn to_layer(&self, defaults: &NoiseLayer) -> NoiseLayer {
+        NoiseLayer {
+            kind: self.kind.get(),
+            octaves: self
+                .octaves
+                .get()
+                .trim()
+                .parse()
+                .unwrap_or(defaults.octaves),
+            frequency: self
+                .frequency
+                .get()
+                .trim()
+                .parse()
+                .unwrap_or(defaults.frequency),
+            amplitude: self
+                .amplitude
+                .get()
+                .trim()
+                .parse()
+                .unwrap_or(defaults.amplitude),
+        }
+    }
+}
+
+fn layer_fields(title: &'static str, form: LayerForm, defaults: &NoiseLayer) -> impl View {
+    v_stack((
+        choice_row(title, &NOISE_KINDS, form.kind),
+        h_stack((
+            numeric_field("Octaves", form.octaves, &defaults.octaves.to_string()),
+            numeric_field("Frequency", form.frequency, &defaults.frequency.to_string()),
+            numeric_field("Amplitude", form.amplitude, &defaults.amplitude.to_string()),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+    ))
+}
+
+/// Heights from layered noise, previewed as a contour map before they're saved as a landscape
+pub fn terrain_generator_view(state_helper: Arc<Mutex<StateHelper>>) -> impl View {
+    let defaults = TerrainParams::default();
+    let base_defaults = defaults.layers[0].clone();
+    let detail_defaults = defaults.layers[1].clone();
+
+    let size = create_rw_signal(LANDSCAPE_SIZES[0].to_string());
+    let seed = create_rw_signal(random_seed().to_string());
+    let terraces = create_rw_signal("".to_string());
+    let island_falloff = create_rw_signal("".to_string());
+    let base = LayerForm::new(&base_defaults);
+    let detail = LayerForm::new(&detail_defaults);
+    let preview: RwSignal<Option<na::DMatrix<f32>>> = create_rw_signal(None);
+    let status = create_rw_signal("".to_string());
+    let seed_active = create_rw_signal(false);
+    let create_active = create_rw_signal(false);
+    let creating = create_rw_signal(false);
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let tx = Arc::new(tx);
+
+    // a full size terrain takes a while to generate and encode, so it's made off the UI thread
+    let update_signal = create_signal_from_tokio_channel(rx);
+    create_effect(move |_| {
+        if let Some(msg) = update_signal.get() {
+            match msg {
+                UIMessage::AddLandscape(landscape) => {
+                    creating.set(false);
+                    status.set(format!(
+                        "Created {}",
+                        landscape
+                            .heightmap
+                            .map(|heightmap| heightmap.fileName)
+                            .unwrap_or(landscape.id)
+                    ));
+                }
+                UIMessage::LandscapeFailed(e) => {
+                    creating.set(false);
+                    status.set(e);
+                }
+                _ => return,
+            }
+        }
+    });
+
+    let params = {
+        let (base_defaults, detail_defaults) = (base_defaults.clone(), detail_defaults.clone());
+        move || TerrainParams {
+            seed: seed.get().trim().parse().unwrap_or_default(),
+            layers: vec![
+                base.to_layer(&base_defaults),
+                detail.to_layer(&detail_defaults),
+            ],
+            terraces: terraces.get().trim().parse().unwrap_or(defaults.terraces),
+            island_falloff: island_falloff
+                .get()
+                .trim()
+                .parse()
+                .unwrap_or(defaults.island_falloff),
+        }
+    };
+    let preview_params = params.clone();
+
+    // drawn again whenever a field changes
+    create_effect(move |_| {
+        let terrain = generate_terrain(&preview_params(), TERRAIN_PREVIEW_SIZE);
+        preview.set(Some(na::DMatrix::from_row_slice(
+            terrain.height as usize,
+            terrain.width as usize,
+            &terrain.heights,
+        )));
+    });
+
+    v_stack((
+        choice_row("Terrain Size", &LANDSCAPE_SIZES, size),
+        h_stack((
+            numeric_field("Seed", seed, "0"),
+            numeric_field("Terraces", terraces, "0"),
+            numeric_field("Island", island_falloff, "0"),
+        ))
+        .style(|s| s.margin_bottom(5.0)),
+        layer_fields("Base Noise", base, &base_defaults),
+        layer_fields("Detail Noise", detail, &detail_defaults),
+        dyn_container(
+            move || preview.get(),
+            move |heights| match heights {
+                Some(heights) => topographic_preview(heights, PREVIEW_WIDTH, PREVIEW_WIDTH)
+                    .style(|s| s.margin_bottom(5.0))
+                    .into_any(),
+                None => empty().into_any(),
+            },
+        ),
+        h_stack((
+            small_button(
+                "New Seed",
+                "plus",
+                move |_| seed.set(random_seed().to_string()),
+                seed_active,
+            ),
+            small_button(
+                "Create Landscape",
+                "plus",
+                move |_| {
+                    if creating.get() {
+                        return;
+                    }
+
+                    let project_id = state_helper.lock().unwrap().get_project_id();
+                    let size: u32 = size.get().parse().unwrap_or(256);
+                    let params = params();
+                    let state_helper = Arc::clone(&state_helper);
+                    let tx = tx.clone();
+
+                    creating.set(true);
+                    status.set("Creating...".to_string());
+
+                    tokio::runtime::Handle::current().spawn_blocking(move || {
+                        let message = match save_terrain(&state_helper, &project_id, &params, size)
+                        {
+                            Ok(landscape) => UIMessage::AddLandscape(landscape),
+                            Err(e) => UIMessage::LandscapeFailed(e),
+                        };
+
+                        let _ = tx.send(message);
+                    });
+                },
+                create_active,
+            ),
+        )),
+        label(move || status.get()).style(|s| s.font_size(10.0)),
+    ))
+    .style(|s| s.width(260.0).margin_bottom(7.0))
+}
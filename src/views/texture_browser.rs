@@ -24,10 +24,16 @@ use midpoint_engine::floem::{GpuHelper, View, WindowHandle};
 
 use crate::editor_state::UIMessage;
 use crate::editor_state::{EditorState, StateHelper};
-use crate::gql::generateTexture::generate_texture;
-use crate::helpers::auth::read_auth_token;
-use crate::helpers::textures::save_texture;
-use crate::helpers::utilities::get_filename;
+use crate::helpers::asset_store::AssetKind;
+use crate::helpers::outbox::{submit_request, GenerationRequest};
+
+use super::asset_browser::{asset_browser, AssetEntry};
+use super::generation_options::{generation_options_view, GenerationForm};
+use super::image_import::image_import_view;
+use super::provenance::provenance_inspector;
+
+// image and name
+const TEXTURE_CARD_HEIGHT: f64 = 145.0;
 
 pub fn texture_item(image_path: String, label_text: String) -> impl View {
     v_stack(
@@ -48,10 +54,14 @@ pub fn texture_browser(
     // let texture_data: RwSignal<Vec<File>> = create_rw_signal(Vec::new());
 
     let state_2 = Arc::clone(&state_helper);
+    let state_3 = Arc::clone(&state_helper);
+    let state_4 = Arc::clone(&state_helper);
+    let state_5 = Arc::clone(&state_helper);
 
     let generate_field = create_rw_signal("".to_string());
     let generate_active = create_rw_signal(false);
     let generate_disabled = create_rw_signal(false);
+    let generation_form = GenerationForm::new();
 
     let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
     let tx = Arc::new(tx);
@@ -140,30 +150,27 @@ pub fn texture_browser(
                             .to_string();
 
                         let generated_field_val = generate_field.get();
+                        let options = generation_form.options();
+
+                        let task_state = Arc::clone(&state_2);
+                        let auth_token = state_helper.auth_token.clone();
 
                         // Use the runtime handle to spawn
                         tokio::runtime::Handle::current().spawn(async move {
-                            // Now we can safely use generate_field inside async block
-                            let auth_token = read_auth_token();
-
                             println!(
                                 "Generating... {:?} {:?}",
                                 auth_token,
                                 generated_field_val.clone()
                             );
 
-                            let texture_data =
-                                generate_texture(auth_token, generated_field_val.clone()).await;
-                            let textureBase64 = texture_data
-                                .expect("Couldn't unwrap texture data")
-                                .generateTexture;
+                            let request = GenerationRequest::Texture {
+                                prompt: generated_field_val.clone(),
+                                options,
+                            };
 
-                            println!("Saving...");
-                            // save texture to sync directory (to be uploaded to S3)
-                            let textureFilename = get_filename(generated_field_val.clone());
-                            let textureFilename = textureFilename + ".png";
-
-                            save_texture(selected_project_id, textureBase64, textureFilename);
+                            // saved to sync directory (to be uploaded to S3), or queued while offline
+                            submit_request(task_state, auth_token, selected_project_id, request)
+                                .await;
 
                             // Update saved state - rather update on websocket, its the only way to get cloudfrontUrl
                             println!("Syncing...");
@@ -175,22 +182,22 @@ pub fn texture_browser(
             .disabled(move || generate_disabled.get()),
         ))
         .style(|s| s.margin_bottom(7.0)),
-        scroll(
-            dyn_stack(
-                move || texture_data.get(),
-                move |texture_data| texture_data.id.clone(),
-                move |texture_data_real| {
-                    let current_textures = texture_data.get(); // Add this to ensure reactivity
-                    texture_item(texture_data_real.normalFilePath, texture_data_real.fileName)
-                },
-            )
-            .into_view()
-            .style(|s| {
-                s.width(260.0)
-                    .flex_direction(FlexDirection::Row)
-                    .flex_wrap(FlexWrap::Wrap)
-                    .gap(10.0)
-            }),
+        generation_options_view(generation_form),
+        image_import_view(state_4, AssetKind::Texture),
+        asset_browser(
+            state_3,
+            "textures",
+            texture_data,
+            TEXTURE_CARD_HEIGHT,
+            |texture: &File| AssetEntry {
+                id: texture.id.clone(),
+                name: texture.fileName.clone(),
+                file_name: Some(texture.fileName.clone()),
+            },
+            |texture: File| texture_item(texture.normalFilePath, texture.fileName).into_any(),
+            Some(Box::new(move |texture: &File| {
+                provenance_inspector(state_5.clone(), "textures", &texture.fileName).into_any()
+            })),
         ),
     ))
     .style(|s| s.width(260.0))
@@ -289,6 +289,22 @@ impl View for TopographicMapView {
     }
 }
 
+/// A small map of heights in 0..1 at a fixed size, for previews
+pub fn topographic_preview(heights: na::DMatrix<f32>, width: f64, height: f64) -> impl View {
+    let config = TopographicConfig {
+        width,
+        height,
+        contour_interval: 0.05,
+        major_interval: 0.25,
+        offset_x: 0.0,
+        offset_y: 0.0,
+        ..Default::default()
+    };
+
+    container(TopographicMapView::new(heights, Some(config)))
+        .style(move |s| s.width(width).height(height))
+}
+
 pub fn create_topographic_map(heights: na::DMatrix<f32>) -> impl View {
     let config = TopographicConfig {
         width: 1200.0,
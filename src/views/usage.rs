@@ -0,0 +1,108 @@
+use std::sync::{Arc, Mutex};
+
+use midpoint_engine::floem::common::small_button;
+use midpoint_engine::floem::reactive::{create_rw_signal, RwSignal, SignalGet, SignalUpdate};
+use midpoint_engine::floem::views::{dyn_stack, h_stack, label, v_stack};
+
+use midpoint_engine::floem::views::Decorators;
+use midpoint_engine::floem::View;
+
+use crate::editor_state::StateHelper;
+use crate::helpers::project_settings::{load_project_settings, save_project_settings};
+use crate::helpers::usage::{load_usage, total_credits, usage_totals, UsageEntry, UsageTotal};
+
+use super::generation_options::numeric_field;
+
+fn limit_text(limit: Option<f64>) -> String {
+    limit.map(|limit| limit.to_string()).unwrap_or_default()
+}
+
+fn total_text(total: &UsageTotal) -> String {
+    let mut text = format!(
+        "{} calls, {} outputs, {:.1} credits",
+        total.calls, total.outputs, total.credits
+    );
+    if total.unreported > 0 {
+        text.push_str(&format!(" ({} unreported)", total.unreported));
+    }
+    text
+}
+
+fn totals_list(
+    title: &'static str,
+    usage_data: RwSignal<Vec<UsageEntry>>,
+    key: fn(&UsageEntry) -> String,
+) -> impl View {
+    v_stack((
+        label(move || title).style(|s| s.font_size(10.0).margin_top(5.0)),
+        dyn_stack(
+            move || usage_totals(&usage_data.get(), key),
+            move |(name, total)| format!("{}-{}", name, total_text(total)),
+            move |(name, total)| {
+                label(move || format!("{}: {}", name, total_text(&total)))
+                    .style(|s| s.font_size(10.0))
+            },
+        )
+        .style(|s| s.flex_col()),
+    ))
+}
+
+/// The project's generation ledger, with totals and the limits that stop generation
+pub fn usage_panel(state_helper: Arc<Mutex<StateHelper>>) -> impl View {
+    let refresh_active = create_rw_signal(false);
+    let save_active = create_rw_signal(false);
+
+    let project_id = state_helper.lock().unwrap().get_project_id();
+    let settings = load_project_settings(&project_id);
+
+    let usage_data = create_rw_signal(load_usage(&project_id));
+    let credit_limit = create_rw_signal(limit_text(settings.credit_limit));
+    let daily_credit_limit = create_rw_signal(limit_text(settings.daily_credit_limit));
+    let limit_status = create_rw_signal("".to_string());
+
+    let refresh_project_id = project_id.clone();
+
+    v_stack((
+        label(|| "Usage").style(|s| s.margin_top(10.0)),
+        label(move || {
+            let entries = usage_data.get();
+            format!(
+                "{} generations, {:.1} credits",
+                entries.len(),
+                total_credits(&entries)
+            )
+        })
+        .style(|s| s.font_size(10.0)),
+        small_button(
+            "Refresh",
+            "plus",
+            move |_| usage_data.set(load_usage(&refresh_project_id)),
+            refresh_active,
+        ),
+        totals_list("By Day", usage_data, |entry| entry.day()),
+        totals_list("By User", usage_data, |entry| entry.user.clone()),
+        totals_list("By Kind", usage_data, |entry| entry.kind.clone()),
+        h_stack((
+            numeric_field("Credit Limit", credit_limit, "None"),
+            numeric_field("Daily Limit", daily_credit_limit, "None"),
+        ))
+        .style(|s| s.margin_top(7.0).margin_bottom(5.0)),
+        small_button(
+            "Save Limits",
+            "plus",
+            move |_| {
+                let mut settings = load_project_settings(&project_id);
+                // blank or unreadable limits are cleared
+                settings.credit_limit = credit_limit.get().trim().parse().ok();
+                settings.daily_credit_limit = daily_credit_limit.get().trim().parse().ok();
+                save_project_settings(&project_id, &settings);
+
+                credit_limit.set(limit_text(settings.credit_limit));
+                daily_credit_limit.set(limit_text(settings.daily_credit_limit));
+                limit_status.set("Limits saved".to_string());
+            },
+            save_active,
+        ),
+        label(move || limit_status.get()).style(|s| s.font_size(10.0)),
+    ))
+}
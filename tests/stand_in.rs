@@ -0,0 +1,228 @@
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+use midpoint_editor::stand_in::graphql::root_field;
+use midpoint_editor::stand_in::start_stand_in_server;
+
+/// Starts a stand-in on a free local port and returns its address
+async fn start() -> String {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("Couldn't find a free port")
+        .port();
+    let address = format!("127.0.0.1:{}", port);
+    start_stand_in_server(&address)
+        .await
+        .expect("Couldn't start stand-in");
+    address
+}
+
+async fn post(address: &str, token: Option<&str>, query: &str, variables: Value) -> (u16, Value) {
+    let mut request = reqwest::Client::new()
+        .post(format!("http://{}/graphql", address))
+        .json(&json!({ "query": query, "variables": variables }));
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await.expect("Couldn't reach stand-in");
+    let status = response.status().as_u16();
+    (status, response.json().await.expect("Response wasn't JSON"))
+}
+
+#[test]
+fn root_field_skips_operation_name_variables_aliases_and_comments() {
+    assert_eq!(
+        root_field("query GetMdProjects { getMdProjects { id } }"),
+        Some("getMdProjects")
+    );
+    assert_eq!(
+        root_field("query GetMdProject($projectId: String!) { getMdProject(projectId: $projectId) { id } }"),
+        Some("getMdProject")
+    );
+    assert_eq!(
+        root_field("# getMdProjects {\n{ projects: getMdProject(projectId: \"a\") { id } }"),
+        Some("getMdProject")
+    );
+    assert_eq!(
+        root_field("mutation M($input: In = {a: 1}) { signIn(email: \"\") { token } }"),
+        Some("signIn")
+    );
+    assert_eq!(root_field("query Nothing"), None);
+}
+
+#[tokio::test]
+async fn graphql_requires_a_token_except_for_public_operations() {
+    let address = start().await;
+
+    let (status, body) = post(
+        &address,
+        None,
+        "query GetMdProjects { getMdProjects { id } }",
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, 401);
+    assert_eq!(body["errors"][0]["message"], "Unauthorized");
+
+    let (status, body) = post(
+        &address,
+        None,
+        "query BackendVersion { backendVersion }",
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert!(body["data"]["backendVersion"]
+        .as_str()
+        .unwrap()
+        .starts_with("stand-in"));
+
+    // a public field mentioned inside a private operation doesn't open it up
+    let (status, _) = post(
+        &address,
+        None,
+        "query GetMdProjects { getMdProjects { id backendVersion } }",
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, 401);
+}
+
+#[tokio::test]
+async fn project_queries_dispatch_on_the_exact_root_field() {
+    let address = start().await;
+    let token = Some("stand-in-test");
+
+    let (_, created) = post(
+        &address,
+        token,
+        "mutation CreateMdProject { createMdProject { id title } }",
+        json!({}),
+    )
+    .await;
+    let project_id = created["data"]["createMdProject"]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let (_, single) = post(
+        &address,
+        token,
+        "query GetMdProject($projectId: String!) { getMdProject(projectId: $projectId) { id } }",
+        json!({ "projectId": project_id }),
+    )
+    .await;
+    assert_eq!(single["data"]["getMdProject"]["id"], project_id.as_str());
+
+    // getMdProject's name is a prefix of getMdProjects, and the operation name mentions both
+    let (_, list) = post(
+        &address,
+        token,
+        "query GetMdProjectsNotGetMdProject { getMdProjects { id } }",
+        json!({}),
+    )
+    .await;
+    assert_eq!(list["data"]["getMdProjects"].as_array().unwrap().len(), 1);
+
+    let (_, missing) = post(
+        &address,
+        token,
+        "query GetMdProject($projectId: String!) { getMdProject(projectId: $projectId) { id } }",
+        json!({ "projectId": "missing" }),
+    )
+    .await;
+    assert_eq!(missing["errors"][0]["message"], "Project not found");
+}
+
+#[tokio::test]
+async fn generate_concept_returns_a_single_image_for_its_seed() {
+    let address = start().await;
+    let query = "mutation GenerateConcept($prompt: String!, $seed: Int) { generateConcept(prompt: $prompt, seed: $seed) }";
+
+    let mut images = Vec::new();
+    for seed in [7, 7, 8] {
+        let (status, body) = post(
+            &address,
+            Some("stand-in-test"),
+            query,
+            json!({ "prompt": "a red barn", "seed": seed }),
+        )
+        .await;
+        assert_eq!(status, 200);
+
+        assert_eq!(body["extensions"]["credits"], 1.0);
+
+        let image = body["data"]["generateConcept"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(image.starts_with("data:image/png;base64,"));
+        images.push(image);
+    }
+
+    // the seed alone decides the output, so a recorded seed reproduces it
+    assert_eq!(images[0], images[1]);
+    assert_ne!(images[0], images[2]);
+}
+
+#[tokio::test]
+async fn unknown_operations_are_reported() {
+    let address = start().await;
+
+    let (_, body) = post(
+        &address,
+        Some("stand-in-test"),
+        "query Whatever { notAField }",
+        json!({}),
+    )
+    .await;
+    assert_eq!(
+        body["errors"][0]["message"],
+        "Operation not supported by stand-in server"
+    );
+}
+
+#[tokio::test]
+async fn websocket_join_without_a_token_is_rejected() {
+    let address = start().await;
+
+    let (mut socket, _) = connect_async(format!("ws://{}/", address))
+        .await
+        .expect("Couldn't open WebSocket");
+    socket
+        .send(Message::Text(
+            json!({ "event": "join", "payload": { "group_id": "project" } }).to_string(),
+        ))
+        .await
+        .unwrap();
+
+    let reply = match socket.next().await {
+        Some(Ok(Message::Text(text))) => serde_json::from_str::<Value>(&text).unwrap(),
+        other => panic!("Expected a text reply, got {:?}", other),
+    };
+    assert_eq!(reply["event"], "join_rejected");
+}
+
+#[tokio::test]
+async fn websocket_and_http_share_the_port() {
+    let address = start().await;
+
+    // a WebSocket handshake doesn't keep plain requests from being answered, and vice versa
+    let (socket, response) = connect_async(format!("ws://{}/", address))
+        .await
+        .expect("Couldn't open WebSocket");
+    assert_eq!(response.status().as_u16(), 101);
+
+    let (status, _) = post(
+        &address,
+        None,
+        "query BackendVersion { backendVersion }",
+        json!({}),
+    )
+    .await;
+    assert_eq!(status, 200);
+
+    drop(socket);
+}